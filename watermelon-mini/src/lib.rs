@@ -1,12 +1,13 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use rustls_platform_verifier::Verifier;
-use tokio::net::TcpStream;
+use tokio::{net::TcpStream, time};
+pub use tokio_rustls::rustls;
 use tokio_rustls::{
-    rustls::{self, crypto::CryptoProvider, version::TLS13, ClientConfig},
+    rustls::{crypto::CryptoProvider, version::TLS13, ClientConfig},
     TlsConnector,
 };
-use watermelon_net::Connection;
+use watermelon_net::{Connection, TcpConnectOptions};
 use watermelon_proto::{ServerAddr, ServerInfo};
 
 #[cfg(feature = "non-standard-zstd")]
@@ -15,20 +16,74 @@ use self::proto::connect;
 pub use self::proto::{
     AuthenticationMethod, ConnectError, ConnectionCompression, ConnectionSecurity,
 };
+pub use self::tls::{TlsClientCert, TlsClientCertError};
 
 #[cfg(feature = "non-standard-zstd")]
 pub(crate) mod non_standard_zstd;
 mod proto;
+mod tls;
 mod util;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ConnectFlags {
     pub echo: bool,
+    /// Whether the server should acknowledge every command with `+OK`
+    ///
+    /// Defaults to `true`.
+    pub verbose: bool,
+    /// Whether the server should strictly validate subjects and protocol commands
+    pub pedantic: bool,
+    pub tcp: TcpConnectOptions,
+    /// The maximum length of a control line received from the server
+    ///
+    /// Defaults to 16 KiB if `None`.
+    pub max_head_len: Option<usize>,
+    /// Whether received messages keep their header block alongside the parsed headers
+    ///
+    /// Defaults to `false`.
+    pub retain_raw_headers: bool,
+    /// The length below which a payload is copied instead of being written as its own chunk
+    ///
+    /// Defaults to 4 KiB if `None`.
+    pub write_flatten_threshold: Option<usize>,
+    /// The maximum amount of time the whole connect sequence may take
+    ///
+    /// Covers resolving the hostname, establishing the TCP connection,
+    /// the TLS handshake and the `INFO` and `CONNECT` exchange.
+    /// [`ConnectError::TimedOut`] is returned once it expires.
+    ///
+    /// Defaults to no timeout if `None`.
+    pub timeout: Option<Duration>,
+    /// Whether websocket connections offer the `permessage-deflate` extension to the server
+    ///
+    /// Defaults to `false`.
+    #[cfg(feature = "websocket-deflate")]
+    pub websocket_deflate: bool,
     #[cfg(feature = "non-standard-zstd")]
     pub zstd: bool,
 }
 
+impl Default for ConnectFlags {
+    fn default() -> Self {
+        Self {
+            echo: false,
+            verbose: true,
+            pedantic: false,
+            tcp: TcpConnectOptions::default(),
+            max_head_len: None,
+            retain_raw_headers: false,
+            write_flatten_threshold: None,
+            timeout: None,
+            #[cfg(feature = "websocket-deflate")]
+            websocket_deflate: false,
+            #[cfg(feature = "non-standard-zstd")]
+            zstd: false,
+        }
+    }
+}
+
 /// Connect to a given address with some reasonable presets.
 ///
 /// The function is going to establish a TLS 1.3 connection, without the support of the client
@@ -39,7 +94,7 @@ pub struct ConnectFlags {
 /// This returns an error in case the connection fails.
 #[expect(
     clippy::missing_panics_doc,
-    reason = "the crypto_provider function always returns a provider that supports TLS 1.3"
+    reason = "a configuration without client authentication is always valid"
 )]
 pub async fn easy_connect(
     addr: &ServerAddr,
@@ -55,17 +110,112 @@ pub async fn easy_connect(
     ),
     ConnectError,
 > {
-    let provider = Arc::new(crypto_provider());
-    let connector = TlsConnector::from(Arc::new(
-        ClientConfig::builder_with_provider(Arc::clone(&provider))
-            .with_protocol_versions(&[&TLS13])
-            .unwrap()
-            .dangerous()
-            .with_custom_certificate_verifier(Arc::new(Verifier::new().with_provider(provider)))
-            .with_no_client_auth(),
-    ));
+    let tls_config = default_tls_config(None).expect("build default TLS configuration");
+    connect_with_tls_config(addr, auth, flags, tls_config).await
+}
+
+/// Connect to a given address with some reasonable presets, using a custom crypto provider.
+///
+/// This is like [`easy_connect`], but uses `provider` instead of the one
+/// selected via the `aws-lc-rs` or `ring` features, both for the TLS
+/// connection and for verifying the server certificate.
+///
+/// # Errors
+///
+/// This returns an error in case `provider` doesn't support TLS 1.3 or the connection fails.
+pub async fn easy_connect_with_provider(
+    addr: &ServerAddr,
+    auth: Option<&AuthenticationMethod>,
+    flags: ConnectFlags,
+    provider: Arc<CryptoProvider>,
+) -> Result<
+    (
+        Connection<
+            ConnectionCompression<ConnectionSecurity<TcpStream>>,
+            ConnectionSecurity<TcpStream>,
+        >,
+        Box<ServerInfo>,
+    ),
+    ConnectError,
+> {
+    let tls_config = tls_config_with_provider(provider, None).map_err(ConnectError::TlsConfig)?;
+    connect_with_tls_config(addr, auth, flags, tls_config).await
+}
+
+/// Build the TLS configuration used by [`easy_connect`]
+///
+/// The configuration only allows TLS 1.3 and verifies the server certificate
+/// using the platform certificate verifier. If `client_cert` is provided it is
+/// presented to the server for mutual TLS authentication.
+///
+/// # Errors
+///
+/// It returns an error if the private key of `client_cert` is not supported.
+pub fn default_tls_config(
+    client_cert: Option<TlsClientCert>,
+) -> Result<Arc<ClientConfig>, rustls::Error> {
+    tls_config_with_provider(Arc::new(crypto_provider()), client_cert)
+}
+
+/// Build the TLS configuration used by [`easy_connect_with_provider`]
+///
+/// This is like [`default_tls_config`], but uses `provider` both for the
+/// TLS connection and for the platform certificate verifier.
+///
+/// # Errors
+///
+/// It returns an error if `provider` doesn't support TLS 1.3 or if
+/// the private key of `client_cert` is not supported.
+pub fn tls_config_with_provider(
+    provider: Arc<CryptoProvider>,
+    client_cert: Option<TlsClientCert>,
+) -> Result<Arc<ClientConfig>, rustls::Error> {
+    let builder = ClientConfig::builder_with_provider(Arc::clone(&provider))
+        .with_protocol_versions(&[&TLS13])?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(Verifier::new().with_provider(provider)));
+    let tls_config = match client_cert {
+        Some(TlsClientCert { cert_chain, key }) => {
+            builder.with_client_auth_cert(cert_chain, key)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Arc::new(tls_config))
+}
+
+/// Connect to a given address using a custom TLS configuration.
+///
+/// This is like [`easy_connect`], but allows customizing the TLS client
+/// configuration in order to use mutual TLS, a custom root store or pinned certificates.
+///
+/// # Errors
+///
+/// This returns an error in case the connection fails.
+pub async fn connect_with_tls_config(
+    addr: &ServerAddr,
+    auth: Option<&AuthenticationMethod>,
+    flags: ConnectFlags,
+    tls_config: Arc<ClientConfig>,
+) -> Result<
+    (
+        Connection<
+            ConnectionCompression<ConnectionSecurity<TcpStream>>,
+            ConnectionSecurity<TcpStream>,
+        >,
+        Box<ServerInfo>,
+    ),
+    ConnectError,
+> {
+    let connector = TlsConnector::from(tls_config);
 
-    let (conn, info) = connect(&connector, addr, "watermelon".to_owned(), auth, flags).await?;
+    let timeout = flags.timeout;
+    let connect = connect(&connector, addr, "watermelon".to_owned(), auth, flags);
+    let (conn, info) = match timeout {
+        Some(timeout) => time::timeout(timeout, connect)
+            .await
+            .map_err(|_| ConnectError::TimedOut)??,
+        None => connect.await?,
+    };
     Ok((conn, info))
 }
 
@@ -26,6 +26,16 @@ where
             encoder: ZstdEncoder::new(MaybeConnection(None)),
         }
     }
+
+    pub fn get_ref(&self) -> &S {
+        if let Some(stream) = &self.encoder.get_ref().0 {
+            stream
+        } else if let Some(stream) = &self.decoder.get_ref().get_ref().0 {
+            stream
+        } else {
+            unreachable!()
+        }
+    }
 }
 
 impl<S> AsyncRead for ZstdStream<S>
@@ -33,6 +33,14 @@ where
     pub fn is_zstd_compressed(&self) -> bool {
         matches!(self, Self::Zstd(_))
     }
+
+    pub fn get_ref(&self) -> &S {
+        match self {
+            Self::Plain(socket) => socket,
+            #[cfg(feature = "non-standard-zstd")]
+            Self::Zstd(socket) => socket.get_ref(),
+        }
+    }
 }
 
 impl<S> AsyncRead for ConnectionCompression<S>
@@ -34,6 +34,10 @@ where
         let conn = connector.connect(domain, conn).await?;
         Ok(Self::Tls(conn))
     }
+
+    pub fn is_tls(&self) -> bool {
+        matches!(self, Self::Tls(_))
+    }
 }
 
 impl<S> AsyncRead for ConnectionSecurity<S>
@@ -1,4 +1,4 @@
-use std::io;
+use std::{borrow::Cow, io};
 
 use tokio::net::TcpStream;
 use tokio_rustls::{
@@ -12,9 +12,8 @@ use watermelon_net::{
 };
 #[cfg(feature = "websocket")]
 use watermelon_net::{error::WebsocketReadError, WebsocketConnection};
-#[cfg(feature = "websocket")]
-use watermelon_proto::proto::error::FrameDecoderError;
 use watermelon_proto::{
+    error::ServerAddrError,
     proto::{error::DecoderError, ServerOp},
     Connect, Host, NonStandardConnect, Protocol, ServerAddr, ServerInfo, Transport,
 };
@@ -30,8 +29,12 @@ use super::{
 pub enum ConnectError {
     #[error("io error")]
     Io(#[source] io::Error),
+    #[error("invalid server address")]
+    InvalidServerAddr(#[source] ServerAddrError),
     #[error("invalid DNS name")]
     InvalidDnsName(#[source] InvalidDnsNameError),
+    #[error("invalid TLS configuration")]
+    TlsConfig(#[source] tokio_rustls::rustls::Error),
     #[error("websocket not supported")]
     WebsocketUnsupported,
     #[error("unexpected ServerOp")]
@@ -42,6 +45,10 @@ pub enum ConnectError {
     Authentication(#[source] AuthenticationError),
     #[error("connect")]
     Connect(#[source] watermelon_net::error::ConnectError),
+    #[error("connection closed")]
+    ConnectionClosed,
+    #[error("timed out")]
+    TimedOut,
 }
 
 #[expect(clippy::too_many_lines)]
@@ -61,8 +68,9 @@ pub(crate) async fn connect(
     ),
     ConnectError,
 > {
-    let conn = connect_tcp(addr).await.map_err(ConnectError::Io)?;
-    conn.set_nodelay(true).map_err(ConnectError::Io)?;
+    let conn = connect_tcp(addr, &flags.tcp)
+        .await
+        .map_err(ConnectError::Io)?;
     let mut conn = ConnectionSecurity::Plain(conn);
 
     if matches!(addr.protocol(), Protocol::TLS) {
@@ -78,11 +86,15 @@ pub(crate) async fn connect(
         #[cfg(feature = "websocket")]
         Transport::Websocket => {
             let uri = addr.to_string().parse().unwrap();
-            Connection::Websocket(
-                WebsocketConnection::new(uri, conn)
-                    .await
-                    .map_err(ConnectError::Io)?,
-            )
+            #[cfg(feature = "websocket-deflate")]
+            let conn = if flags.websocket_deflate {
+                WebsocketConnection::new_with_deflate(uri, conn).await
+            } else {
+                WebsocketConnection::new(uri, conn).await
+            };
+            #[cfg(not(feature = "websocket-deflate"))]
+            let conn = WebsocketConnection::new(uri, conn).await;
+            Connection::Websocket(conn.map_err(ConnectError::Io)?)
         }
         #[cfg(not(feature = "websocket"))]
         Transport::Websocket => return Err(ConnectError::WebsocketUnsupported),
@@ -101,18 +113,23 @@ pub(crate) async fn connect(
             return Err(ConnectError::Io(err))
         }
         #[cfg(feature = "websocket")]
-        Err(ConnectionReadError::Websocket(WebsocketReadError::Decoder(
-            FrameDecoderError::Decoder(err),
-        ))) => return Err(ConnectError::Decoder(err)),
+        Err(ConnectionReadError::Websocket(WebsocketReadError::Decoder(err))) => {
+            return Err(ConnectError::Decoder(err))
+        }
         #[cfg(feature = "websocket")]
-        Err(ConnectionReadError::Websocket(WebsocketReadError::Decoder(
-            FrameDecoderError::IncompleteFrame,
-        ))) => todo!(),
+        Err(ConnectionReadError::Websocket(WebsocketReadError::Closed)) => {
+            return Err(ConnectError::ConnectionClosed)
+        }
         #[cfg(feature = "websocket")]
-        Err(ConnectionReadError::Websocket(WebsocketReadError::Closed)) => todo!(),
+        Err(ConnectionReadError::Websocket(err @ WebsocketReadError::FrameTooLarge { .. })) => {
+            return Err(ConnectError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                err,
+            )))
+        }
     };
 
-    let conn = match conn {
+    let mut conn = match conn {
         Connection::Streaming(streaming) => Connection::Streaming(
             if matches!(
                 (addr.protocol(), info.tls_required),
@@ -133,6 +150,13 @@ pub(crate) async fn connect(
         ),
         Connection::Websocket(websocket) => Connection::Websocket(websocket),
     };
+    if let Some(max_head_len) = flags.max_head_len {
+        conn.set_max_head_len(max_head_len);
+    }
+    conn.set_retain_raw_headers(flags.retain_raw_headers);
+    if let Some(write_flatten_threshold) = flags.write_flatten_threshold {
+        conn.set_flatten_threshold(write_flatten_threshold);
+    }
 
     let auth;
     let auth_method = if let Some(auth_method) = auth_method {
@@ -152,15 +176,15 @@ pub(crate) async fn connect(
     }
 
     let mut connect = Connect {
-        verbose: true,
-        pedantic: false,
+        verbose: flags.verbose,
+        pedantic: flags.pedantic,
         require_tls: false,
         auth_token: None,
         username: None,
         password: None,
         client_name: Some(client_name),
-        client_lang: "rust-watermelon",
-        client_version: env!("CARGO_PKG_VERSION"),
+        client_lang: Cow::Borrowed("rust-watermelon"),
+        client_version: Cow::Borrowed(env!("CARGO_PKG_VERSION")),
         protocol: 1,
         echo: flags.echo,
         signature: None,
@@ -6,8 +6,6 @@ use std::{
 };
 
 use tokio::io::{AsyncRead, AsyncWrite};
-#[cfg(feature = "websocket")]
-use watermelon_proto::proto::error::FrameDecoderError;
 use watermelon_proto::{
     error::ServerError,
     proto::{error::DecoderError, ClientOp, ServerOp},
@@ -15,14 +13,25 @@ use watermelon_proto::{
 };
 
 pub use self::streaming::{StreamingConnection, StreamingReadError};
+#[cfg(feature = "capture")]
+pub use self::tap::Tap;
 #[cfg(feature = "websocket")]
 pub use self::websocket::{WebsocketConnection, WebsocketReadError};
 
 mod streaming;
+#[cfg(feature = "capture")]
+mod tap;
 #[cfg(feature = "websocket")]
 mod websocket;
 
 #[derive(Debug)]
+#[cfg_attr(
+    not(feature = "websocket"),
+    expect(
+        clippy::large_enum_variant,
+        reason = "the connection is long lived and is already stored on the heap by most users"
+    )
+)]
 pub enum Connection<S1, S2> {
     Streaming(StreamingConnection<S1>),
     Websocket(WebsocketConnection<S2>),
@@ -118,6 +127,78 @@ where
         }
     }
 
+    /// The number of bytes that have been enqueued but not written to the socket yet
+    ///
+    /// Websocket connections don't expose their write buffer, so `0` is returned for them.
+    pub fn buffered_write_bytes(&self) -> usize {
+        match self {
+            Self::Streaming(streaming) => streaming.buffered_write_bytes(),
+            #[cfg(feature = "websocket")]
+            Self::Websocket(_websocket) => 0,
+            #[cfg(not(feature = "websocket"))]
+            Self::Websocket(_) => unreachable!(),
+        }
+    }
+
+    /// The number of bytes that have been read from the socket but not decoded yet
+    pub fn buffered_read_bytes(&self) -> usize {
+        match self {
+            Self::Streaming(streaming) => streaming.buffered_read_bytes(),
+            #[cfg(feature = "websocket")]
+            Self::Websocket(websocket) => websocket.buffered_read_bytes(),
+            #[cfg(not(feature = "websocket"))]
+            Self::Websocket(_) => unreachable!(),
+        }
+    }
+
+    /// Discard the bytes which have been read but not decoded yet and reset the decoder
+    ///
+    /// See [`StreamDecoder::reset`].
+    ///
+    /// [`StreamDecoder::reset`]: watermelon_proto::proto::StreamDecoder::reset
+    pub fn reset_decoder(&mut self) {
+        match self {
+            Self::Streaming(streaming) => streaming.reset_decoder(),
+            #[cfg(feature = "websocket")]
+            Self::Websocket(websocket) => websocket.reset_decoder(),
+            #[cfg(not(feature = "websocket"))]
+            Self::Websocket(_) => unreachable!(),
+        }
+    }
+
+    /// Set the maximum length of a control line received from the server
+    pub fn set_max_head_len(&mut self, max_head_len: usize) {
+        match self {
+            Self::Streaming(streaming) => streaming.set_max_head_len(max_head_len),
+            #[cfg(feature = "websocket")]
+            Self::Websocket(websocket) => websocket.set_max_head_len(max_head_len),
+            #[cfg(not(feature = "websocket"))]
+            Self::Websocket(_) => unreachable!(),
+        }
+    }
+
+    /// Keep the header block of every received message alongside its parsed headers
+    pub fn set_retain_raw_headers(&mut self, retain_raw_headers: bool) {
+        match self {
+            Self::Streaming(streaming) => streaming.set_retain_raw_headers(retain_raw_headers),
+            #[cfg(feature = "websocket")]
+            Self::Websocket(websocket) => websocket.set_retain_raw_headers(retain_raw_headers),
+            #[cfg(not(feature = "websocket"))]
+            Self::Websocket(_) => unreachable!(),
+        }
+    }
+
+    /// Set the length below which a payload is copied instead of being written as its own chunk
+    ///
+    /// Websocket connections always copy payloads into the frame being written,
+    /// so this only affects streaming connections.
+    pub fn set_flatten_threshold(&mut self, flatten_threshold: usize) {
+        match self {
+            Self::Streaming(streaming) => streaming.set_flatten_threshold(flatten_threshold),
+            Self::Websocket(_) => {}
+        }
+    }
+
     pub fn enqueue_write_op(&mut self, item: &ClientOp) {
         match self {
             Self::Streaming(streaming) => streaming.enqueue_write_op(item),
@@ -195,6 +276,8 @@ pub enum ConnectError {
     Io(#[source] io::Error),
     #[error("unexpected ServerOp")]
     UnexpectedOp,
+    #[error("connection closed")]
+    ConnectionClosed,
 }
 
 /// Send the `CONNECT` command to a pre-establised connection `conn`.
@@ -246,19 +329,24 @@ where
                 return Err(ConnectError::Io(err))
             }
             #[cfg(feature = "websocket")]
-            Err(ConnectionReadError::Websocket(WebsocketReadError::Decoder(
-                FrameDecoderError::Decoder(err),
-            ))) => return Err(ConnectError::Proto(err)),
-            #[cfg(feature = "websocket")]
-            Err(ConnectionReadError::Websocket(WebsocketReadError::Decoder(
-                FrameDecoderError::IncompleteFrame,
-            ))) => todo!(),
+            Err(ConnectionReadError::Websocket(WebsocketReadError::Decoder(err))) => {
+                return Err(ConnectError::Proto(err))
+            }
             #[cfg(feature = "websocket")]
             Err(ConnectionReadError::Websocket(WebsocketReadError::Io(err))) => {
                 return Err(ConnectError::Io(err))
             }
             #[cfg(feature = "websocket")]
-            Err(ConnectionReadError::Websocket(WebsocketReadError::Closed)) => todo!(),
+            Err(ConnectionReadError::Websocket(WebsocketReadError::Closed)) => {
+                return Err(ConnectError::ConnectionClosed)
+            }
+            #[cfg(feature = "websocket")]
+            Err(ConnectionReadError::Websocket(err @ WebsocketReadError::FrameTooLarge { .. })) => {
+                return Err(ConnectError::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    err,
+                )))
+            }
         }
     }
 }
@@ -1,3 +1,5 @@
+#[cfg(feature = "capture")]
+use std::io::Write;
 use std::{
     future::{self, Future},
     io,
@@ -11,6 +13,9 @@ use watermelon_proto::proto::{
     error::DecoderError, ClientOp, ServerOp, StreamDecoder, StreamEncoder,
 };
 
+#[cfg(feature = "capture")]
+use super::Tap;
+
 #[derive(Debug)]
 pub struct StreamingConnection<S> {
     socket: S,
@@ -79,6 +84,44 @@ where
         self.encoder.remaining() < 8_290_304
     }
 
+    /// The number of bytes that have been enqueued but not written to the socket yet
+    pub fn buffered_write_bytes(&self) -> usize {
+        self.encoder.buffered_len()
+    }
+
+    /// Discard the bytes which have been read but not decoded yet and reset the decoder
+    ///
+    /// See [`StreamDecoder::reset`].
+    pub fn reset_decoder(&mut self) {
+        self.decoder.reset();
+    }
+
+    /// The number of bytes that have been read from the socket but not decoded yet
+    pub fn buffered_read_bytes(&self) -> usize {
+        self.decoder.buffered_len()
+    }
+
+    /// Set the maximum length of a control line received from the server
+    ///
+    /// See [`StreamDecoder::with_max_head_len`].
+    pub fn set_max_head_len(&mut self, max_head_len: usize) {
+        self.decoder.set_max_head_len(max_head_len);
+    }
+
+    /// Keep the header block of every received message alongside its parsed headers
+    ///
+    /// See [`StreamDecoder::with_retain_raw_headers`].
+    pub fn set_retain_raw_headers(&mut self, retain_raw_headers: bool) {
+        self.decoder.set_retain_raw_headers(retain_raw_headers);
+    }
+
+    /// Set the length below which a payload is copied instead of being written as its own chunk
+    ///
+    /// See [`StreamEncoder::with_flatten_threshold`].
+    pub fn set_flatten_threshold(&mut self, flatten_threshold: usize) {
+        self.encoder.set_flatten_threshold(flatten_threshold);
+    }
+
     pub fn enqueue_write_op(&mut self, item: &ClientOp) {
         self.encoder.enqueue_write_op(item);
     }
@@ -173,6 +216,19 @@ where
         }
     }
 
+    /// Copy every byte read from and written to the socket into `reader_sink` and `writer_sink`
+    ///
+    /// This produces a transcript of the raw protocol traffic, which is useful
+    /// to diagnose interoperability issues. See [`Tap`].
+    #[cfg(feature = "capture")]
+    pub fn with_tap<R, W>(self, reader_sink: R, writer_sink: W) -> StreamingConnection<Tap<S, R, W>>
+    where
+        R: Write,
+        W: Write,
+    {
+        self.replace_socket(|socket| Tap::new(socket, reader_sink, writer_sink))
+    }
+
     pub fn into_inner(self) -> S {
         self.socket
     }
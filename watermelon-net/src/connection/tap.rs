@@ -0,0 +1,170 @@
+use std::{
+    io::{self, Write},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+pin_project! {
+    /// A socket which copies every byte read from and written to it into two sinks
+    ///
+    /// Obtained from [`StreamingConnection::with_tap`]. Copying to the sinks is
+    /// best effort: errors returned by them are ignored, so that a failing sink
+    /// never interrupts the connection.
+    ///
+    /// [`StreamingConnection::with_tap`]: super::StreamingConnection::with_tap
+    #[derive(Debug)]
+    pub struct Tap<S, R, W> {
+        #[pin]
+        socket: S,
+        reader_sink: R,
+        writer_sink: W,
+    }
+}
+
+impl<S, R, W> Tap<S, R, W> {
+    /// Wrap `socket`, copying read bytes into `reader_sink` and written bytes into `writer_sink`
+    pub fn new(socket: S, reader_sink: R, writer_sink: W) -> Self {
+        Self {
+            socket,
+            reader_sink,
+            writer_sink,
+        }
+    }
+
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+
+    pub fn socket_mut(&mut self) -> &mut S {
+        &mut self.socket
+    }
+
+    pub fn reader_sink(&self) -> &R {
+        &self.reader_sink
+    }
+
+    pub fn writer_sink(&self) -> &W {
+        &self.writer_sink
+    }
+
+    /// Returns the socket, the reader sink and the writer sink
+    pub fn into_inner(self) -> (S, R, W) {
+        (self.socket, self.reader_sink, self.writer_sink)
+    }
+}
+
+impl<S, R, W> AsyncRead for Tap<S, R, W>
+where
+    S: AsyncRead,
+    R: Write,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+
+        let filled = buf.filled().len();
+        let result = this.socket.poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = &result {
+            let _ = this.reader_sink.write_all(&buf.filled()[filled..]);
+        }
+        result
+    }
+}
+
+impl<S, R, W> AsyncWrite for Tap<S, R, W>
+where
+    S: AsyncWrite,
+    W: Write,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+
+        let result = this.socket.poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = &result {
+            let _ = this.writer_sink.write_all(&buf[..*n]);
+        }
+        result
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+
+        let result = this.socket.poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(n)) = &result {
+            let mut remaining = *n;
+            for buf in bufs {
+                if remaining == 0 {
+                    break;
+                }
+
+                let len = buf.len().min(remaining);
+                let _ = this.writer_sink.write_all(&buf[..len]);
+                remaining -= len;
+            }
+        }
+        result
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.socket.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+
+        let result = this.socket.poll_flush(cx);
+        if let Poll::Ready(Ok(())) = &result {
+            let _ = this.writer_sink.flush();
+        }
+        result
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().socket.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_matches;
+    use tokio::io::{self, AsyncReadExt as _, AsyncWriteExt as _};
+    use watermelon_proto::proto::{ClientOp, ServerOp};
+
+    use crate::StreamingConnection;
+
+    #[tokio::test]
+    async fn transcript() {
+        let (socket, mut conn) = io::duplex(1024);
+
+        let mut client = StreamingConnection::new(socket).with_tap(Vec::new(), Vec::new());
+
+        client.enqueue_write_op(&ClientOp::Ping);
+        assert_eq!(6, client.write_next().await.unwrap());
+        client.flush().await.unwrap();
+
+        let mut buf = [0; 6];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"PING\r\n", &buf);
+
+        conn.write_all(b"PONG\r\n").await.unwrap();
+        assert_matches!(client.read_next().await, Ok(ServerOp::Pong));
+
+        let (_socket, reader_sink, writer_sink) = client.into_inner().into_inner();
+        assert_eq!(b"PONG\r\n", reader_sink.as_slice());
+        assert_eq!(b"PING\r\n", writer_sink.as_slice());
+    }
+}
@@ -4,21 +4,39 @@ use std::{
     task::{Context, Poll},
 };
 
-use bytes::Bytes;
+use bytes::{BufMut as _, Bytes};
 use futures_sink::Sink;
 use futures_util::{task::noop_waker_ref, Stream};
 use http::Uri;
 use tokio::io::{AsyncRead, AsyncWrite};
-use tokio_websockets::{ClientBuilder, Message, WebSocketStream};
+#[cfg(not(feature = "websocket-deflate"))]
+use tokio_websockets::WebSocketStream;
+use tokio_websockets::{ClientBuilder, Message};
 use watermelon_proto::proto::{
-    decode_frame, error::FrameDecoderError, ClientOp, FramedEncoder, ServerOp,
+    error::DecoderError, ClientOp, FramedEncoder, ServerOp, StreamDecoder,
 };
 
+#[cfg(feature = "websocket-deflate")]
+use self::deflate::Socket;
+
+#[cfg(feature = "websocket-deflate")]
+mod deflate;
+
+#[cfg(not(feature = "websocket-deflate"))]
+type Socket<S> = WebSocketStream<S>;
+
+/// The default maximum size of an operation reassembled from websocket messages
+///
+/// Fits the biggest `max_payload` a NATS server can be configured with,
+/// plus room for the control line and the headers.
+const DEFAULT_MAX_FRAME_BYTES: usize = 64 * 1024 * 1024 + 64 * 1024;
+
 #[derive(Debug)]
 pub struct WebsocketConnection<S> {
-    socket: WebSocketStream<S>,
+    socket: Socket<S>,
     encoder: FramedEncoder,
-    residual_frame: Bytes,
+    decoder: StreamDecoder,
+    max_frame_bytes: usize,
     should_flush: bool,
 }
 
@@ -36,12 +54,37 @@ where
             .connect_on(socket)
             .await
             .map_err(websockets_error_to_io)?;
-        Ok(Self {
+        #[cfg(feature = "websocket-deflate")]
+        let socket = Socket::Plain(socket);
+        Ok(Self::from_socket(socket))
+    }
+
+    /// Construct a websocket stream to a pre-established connection `socket`,
+    /// offering the `permessage-deflate` extension during the handshake.
+    ///
+    /// Messages are compressed in both directions if the server accepts the extension,
+    /// otherwise the connection falls back to uncompressed messages.
+    /// See [`WebsocketConnection::is_deflate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the websocket handshake fails.
+    #[cfg(feature = "websocket-deflate")]
+    pub async fn new_with_deflate(uri: Uri, socket: S) -> io::Result<Self> {
+        let socket = Socket::connect(&uri, socket)
+            .await
+            .map_err(websockets_error_to_io)?;
+        Ok(Self::from_socket(socket))
+    }
+
+    fn from_socket(socket: Socket<S>) -> Self {
+        Self {
             socket,
             encoder: FramedEncoder::new(),
-            residual_frame: Bytes::new(),
+            decoder: StreamDecoder::new(),
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
             should_flush: false,
-        })
+        }
     }
 
     pub fn poll_read_next(
@@ -49,16 +92,27 @@ where
         cx: &mut Context<'_>,
     ) -> Poll<Result<ServerOp, WebsocketReadError>> {
         loop {
-            if !self.residual_frame.is_empty() {
-                return Poll::Ready(
-                    decode_frame(&mut self.residual_frame).map_err(WebsocketReadError::Decoder),
-                );
+            // A server operation may be split across multiple websocket messages,
+            // so every message is buffered until a complete operation is available
+            match self.decoder.decode() {
+                Ok(Some(server_op)) => return Poll::Ready(Ok(server_op)),
+                Ok(None) => {
+                    let len = self.decoder.buffered_len();
+                    if len > self.max_frame_bytes {
+                        return Poll::Ready(Err(WebsocketReadError::FrameTooLarge {
+                            len,
+                            max: self.max_frame_bytes,
+                        }));
+                    }
+                }
+                Err(err) => return Poll::Ready(Err(WebsocketReadError::Decoder(err))),
             }
 
             match Pin::new(&mut self.socket).poll_next(cx) {
                 Poll::Pending => return Poll::Pending,
                 Poll::Ready(Some(Ok(message))) if message.is_binary() => {
-                    self.residual_frame = message.into_payload().into();
+                    let payload: Bytes = message.into_payload().into();
+                    self.decoder.read_buf().put(payload);
                 }
                 Poll::Ready(Some(Ok(_message))) => {}
                 Poll::Ready(Some(Err(err))) => {
@@ -82,6 +136,55 @@ where
         self.should_flush
     }
 
+    /// Discard the bytes which have been read but not decoded yet and reset the decoder
+    ///
+    /// See [`StreamDecoder::reset`].
+    pub fn reset_decoder(&mut self) {
+        self.decoder.reset();
+    }
+
+    /// The number of bytes that have been received but not decoded yet
+    pub fn buffered_read_bytes(&self) -> usize {
+        self.decoder.buffered_len()
+    }
+
+    /// Set the maximum length of a control line received from the server
+    ///
+    /// See [`StreamDecoder::with_max_head_len`].
+    pub fn set_max_head_len(&mut self, max_head_len: usize) {
+        self.decoder.set_max_head_len(max_head_len);
+    }
+
+    /// Keep the header block of every received message alongside its parsed headers
+    ///
+    /// See [`StreamDecoder::with_retain_raw_headers`].
+    pub fn set_retain_raw_headers(&mut self, retain_raw_headers: bool) {
+        self.decoder.set_retain_raw_headers(retain_raw_headers);
+    }
+
+    /// Set the maximum number of bytes an incomplete operation can span across websocket messages
+    ///
+    /// A server sending an operation bigger than this fails reading
+    /// with [`WebsocketReadError::FrameTooLarge`], instead of having
+    /// the connection buffer an unbounded amount of bytes.
+    ///
+    /// Default: 64 MiB + 64 KiB.
+    pub fn set_max_frame_bytes(&mut self, max_frame_bytes: usize) {
+        self.max_frame_bytes = max_frame_bytes;
+    }
+
+    pub fn socket(&self) -> &S {
+        self.socket.get_ref()
+    }
+
+    /// Returns `true` if the server accepted the `permessage-deflate` extension
+    ///
+    /// See [`WebsocketConnection::new_with_deflate`].
+    #[cfg(feature = "websocket-deflate")]
+    pub fn is_deflate(&self) -> bool {
+        self.socket.is_deflate()
+    }
+
     pub fn may_enqueue_more_ops(&mut self) -> bool {
         let mut cx = Context::from_waker(noop_waker_ref());
         Pin::new(&mut self.socket).poll_ready(&mut cx).is_ready()
@@ -128,16 +231,97 @@ where
 #[derive(Debug, thiserror::Error)]
 pub enum WebsocketReadError {
     #[error("decoder")]
-    Decoder(#[source] FrameDecoderError),
+    Decoder(#[source] DecoderError),
     #[error("io")]
     Io(#[source] io::Error),
     #[error("closed")]
     Closed,
+    #[error("operation too large, len={len} max={max}")]
+    FrameTooLarge { len: usize, max: usize },
 }
 
 fn websockets_error_to_io(err: tokio_websockets::Error) -> io::Error {
     match err {
         tokio_websockets::Error::Io(err) => err,
-        err => io::Error::new(io::ErrorKind::Other, err),
+        err => io::Error::other(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_matches;
+    use futures_util::SinkExt as _;
+    use tokio::io::{self, DuplexStream};
+    use tokio_websockets::{Message, ServerBuilder, WebSocketStream};
+    use watermelon_proto::proto::ServerOp;
+
+    use super::{WebsocketConnection, WebsocketReadError};
+
+    async fn connect() -> (
+        WebsocketConnection<DuplexStream>,
+        WebSocketStream<DuplexStream>,
+    ) {
+        let (socket, server_socket) = io::duplex(1024);
+        let server = ServerBuilder::new();
+        let (client, server) = tokio::join!(
+            WebsocketConnection::new("ws://127.0.0.1/".parse().unwrap(), socket),
+            server.accept(server_socket)
+        );
+        (client.unwrap(), server.unwrap().1)
+    }
+
+    #[tokio::test]
+    async fn reassemble_fragments() {
+        let (mut client, mut server) = connect().await;
+
+        server.send(Message::binary("PI")).await.unwrap();
+        server
+            .send(Message::binary("NG\r\nMSG foo 1 5\r\nhe"))
+            .await
+            .unwrap();
+        server.send(Message::binary("l")).await.unwrap();
+        server.send(Message::binary("lo\r\n")).await.unwrap();
+
+        assert_matches!(client.read_next().await, Ok(ServerOp::Ping));
+        let Ok(ServerOp::Message { message }) = client.read_next().await else {
+            unreachable!()
+        };
+        assert_eq!("foo", message.base.subject.as_str());
+        assert_eq!(b"hello".as_slice(), message.base.payload);
+        assert_eq!(0, client.buffered_read_bytes());
+    }
+
+    #[tokio::test]
+    async fn frame_too_large() {
+        let (mut client, mut server) = connect().await;
+        client.set_max_frame_bytes(16);
+
+        server
+            .send(Message::binary("MSG foo 1 32\r\n"))
+            .await
+            .unwrap();
+        server
+            .send(Message::binary("01234567890123456789"))
+            .await
+            .unwrap();
+
+        assert_matches!(
+            client.read_next().await,
+            Err(WebsocketReadError::FrameTooLarge { len: 20, max: 16 })
+        );
+    }
+
+    #[tokio::test]
+    async fn closed() {
+        let (mut client, mut server) = connect().await;
+
+        // The incomplete operation is discarded along with the connection
+        server
+            .send(Message::binary("MSG foo 1 5\r\nhe"))
+            .await
+            .unwrap();
+        let (closed, read) = tokio::join!(server.close(), client.read_next());
+        closed.unwrap();
+        assert_matches!(read, Err(WebsocketReadError::Closed));
     }
 }
@@ -0,0 +1,800 @@
+//! The `permessage-deflate` websocket extension, as specified by RFC 7692
+//!
+//! `tokio-websockets` rejects frames with the reserved bits set, which the extension
+//! uses to mark compressed messages, so connections negotiating it are handled
+//! by the minimal client implemented here. Connections to servers declining
+//! the extension keep using `tokio-websockets`.
+
+use std::{
+    fmt,
+    future::Future as _,
+    io,
+    pin::{pin, Pin},
+    str,
+    task::{ready, Context, Poll},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use bytes::{Buf as _, BufMut as _, Bytes, BytesMut};
+use futures_sink::Sink;
+use futures_util::Stream;
+use http::Uri;
+use miniz_oxide::{
+    deflate::core::{
+        compress_to_output, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush,
+        TDEFLStatus,
+    },
+    inflate::decompress_to_vec_with_limit,
+};
+use sha1_smol::Sha1;
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use tokio_websockets::{
+    proto::ProtocolError, upgrade, ClientBuilder, Error, Message, WebSocketStream,
+};
+
+const EXTENSION: &str = "permessage-deflate";
+/// The extension offered to the server
+///
+/// Without context takeover every message is compressed on its own,
+/// so neither side has to keep a window around between messages.
+const EXTENSION_OFFER: &str =
+    "permessage-deflate; client_no_context_takeover; server_no_context_takeover";
+const ACCEPT_GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const SWITCHING_PROTOCOLS: u16 = 101;
+const MAX_RESPONSE_LEN: usize = 16 * 1024;
+
+/// The maximum length of a received message, both before and after decompression
+///
+/// Matches the limit `tokio-websockets` applies to uncompressed connections.
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+/// Messages shorter than this are sent uncompressed
+const MIN_COMPRESSED_LEN: usize = 64;
+const COMPRESSION_LEVEL: i32 = 1;
+/// The amount of buffered writes after which the stream stops accepting messages until flushed
+const FLUSH_THRESHOLD: usize = 8 * 1024;
+/// The trailer left by a sync flush, which senders strip from compressed messages
+const SYNC_FLUSH_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+/// An empty final block, ending the deflate stream of a message
+const FINAL_BLOCK: [u8; 5] = [0x01, 0x00, 0x00, 0xff, 0xff];
+const NORMAL_CLOSURE: [u8; 2] = 1000_u16.to_be_bytes();
+
+const FIN: u8 = 0x80;
+const RSV1: u8 = 0x40;
+const RSV: u8 = 0x70;
+const OPCODE: u8 = 0x0f;
+const MASKED: u8 = 0x80;
+/// The payload lengths announcing a 16 and a 64 bit extended length
+const LEN_16: u8 = 0x7e;
+const LEN_64: u8 = 0x7f;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xa;
+
+/// A websocket stream which may have negotiated the `permessage-deflate` extension
+#[derive(Debug)]
+pub(super) enum Socket<S> {
+    Plain(WebSocketStream<S>),
+    Deflate(DeflateStream<S>),
+}
+
+/// A client websocket stream compressing messages with `permessage-deflate`
+pub(super) struct DeflateStream<S> {
+    socket: S,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+    /// The opcode, compression and payload of the fragmented message being received
+    message: Option<(u8, bool, BytesMut)>,
+    compressor: Box<CompressorOxide>,
+    close_sent: bool,
+    close_received: bool,
+}
+
+impl<S> Socket<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Perform the websocket handshake on `socket`, offering the `permessage-deflate` extension
+    ///
+    /// Falls back to an uncompressed stream if the server doesn't accept the extension.
+    pub(super) async fn connect(uri: &Uri, mut socket: S) -> Result<Self, Error> {
+        Ok(if handshake(uri, &mut socket).await? {
+            Self::Deflate(DeflateStream::new(socket))
+        } else {
+            Self::Plain(ClientBuilder::new().take_over(socket))
+        })
+    }
+
+    pub(super) fn get_ref(&self) -> &S {
+        match self {
+            Self::Plain(socket) => socket.get_ref(),
+            Self::Deflate(socket) => &socket.socket,
+        }
+    }
+
+    pub(super) fn is_deflate(&self) -> bool {
+        matches!(self, Self::Deflate(_))
+    }
+}
+
+impl<S> Stream for Socket<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Item = Result<Message, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).poll_next(cx),
+            Self::Deflate(socket) => Pin::new(socket).poll_next(cx),
+        }
+    }
+}
+
+impl<S> Sink<Message> for Socket<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).poll_ready(cx),
+            Self::Deflate(socket) => Pin::new(socket).poll_ready(cx),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).start_send(item),
+            Self::Deflate(socket) => Pin::new(socket).start_send(item),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).poll_flush(cx),
+            Self::Deflate(socket) => Pin::new(socket).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).poll_close(cx),
+            Self::Deflate(socket) => Pin::new(socket).poll_close(cx),
+        }
+    }
+}
+
+impl<S> DeflateStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn new(socket: S) -> Self {
+        Self {
+            socket,
+            read_buf: BytesMut::new(),
+            write_buf: BytesMut::new(),
+            message: None,
+            compressor: compressor(),
+            close_sent: false,
+            close_received: false,
+        }
+    }
+
+    /// Handle a frame received from the server, returning the message it completes
+    fn handle_frame(&mut self, head: u8, payload: BytesMut) -> Result<Option<Message>, Error> {
+        let fin = head & FIN != 0;
+        let rsv = head & RSV;
+        match head & OPCODE {
+            opcode @ (OP_TEXT | OP_BINARY) => {
+                if self.message.is_some() {
+                    return Err(Error::Protocol(ProtocolError::InvalidOpcode));
+                }
+                if rsv & !RSV1 != 0 {
+                    return Err(Error::Protocol(ProtocolError::InvalidRsv));
+                }
+
+                let compressed = rsv == RSV1;
+                if fin {
+                    return finish_message(opcode, compressed, payload).map(Some);
+                }
+                self.message = Some((opcode, compressed, payload));
+                Ok(None)
+            }
+            OP_CONTINUATION => {
+                let Some((_opcode, _compressed, message)) = &mut self.message else {
+                    return Err(Error::Protocol(ProtocolError::InvalidOpcode));
+                };
+                if rsv != 0 {
+                    return Err(Error::Protocol(ProtocolError::InvalidRsv));
+                }
+                let len = message.len() + payload.len();
+                if len > MAX_MESSAGE_LEN {
+                    return Err(Error::PayloadTooLong {
+                        len,
+                        max_len: MAX_MESSAGE_LEN,
+                    });
+                }
+
+                message.unsplit(payload);
+                if !fin {
+                    return Ok(None);
+                }
+                let (opcode, compressed, message) = self.message.take().unwrap();
+                finish_message(opcode, compressed, message).map(Some)
+            }
+            opcode @ (OP_CLOSE | OP_PING | OP_PONG) => {
+                if !fin {
+                    return Err(Error::Protocol(ProtocolError::FragmentedControlFrame));
+                }
+                if rsv != 0 {
+                    return Err(Error::Protocol(ProtocolError::InvalidRsv));
+                }
+
+                match opcode {
+                    OP_CLOSE => {
+                        self.close_received = true;
+                        if !self.close_sent {
+                            self.close_sent = true;
+                            let code = payload.get(..2).unwrap_or(&NORMAL_CLOSURE);
+                            self.enqueue_frame(FIN | OP_CLOSE, code);
+                        }
+                    }
+                    OP_PING if !self.close_sent => self.enqueue_frame(FIN | OP_PONG, &payload),
+                    _ => {}
+                }
+                Ok(None)
+            }
+            _ => Err(Error::Protocol(ProtocolError::InvalidOpcode)),
+        }
+    }
+
+    fn enqueue_frame(&mut self, head: u8, payload: &[u8]) {
+        encode_frame(&mut self.write_buf, head, payload, Some(rand::random()));
+    }
+
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        while !self.write_buf.is_empty() {
+            match ready!(Pin::new(&mut self.socket).poll_write(cx, &self.write_buf))? {
+                0 => return Poll::Ready(Err(Error::Io(io::ErrorKind::WriteZero.into()))),
+                n => self.write_buf.advance(n),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> Stream for DeflateStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Item = Result<Message, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            // Replies to pings and to the close frame are written as soon as possible
+            if let Poll::Ready(Err(err)) = this.poll_write_buf(cx) {
+                return Poll::Ready(Some(Err(err)));
+            }
+            if this.close_received {
+                return Poll::Ready(None);
+            }
+
+            match decode_frame(&mut this.read_buf, false) {
+                Ok(Some((head, payload))) => match this.handle_frame(head, payload) {
+                    Ok(Some(message)) => return Poll::Ready(Some(Ok(message))),
+                    Ok(None) => continue,
+                    Err(err) => return Poll::Ready(Some(Err(err))),
+                },
+                Ok(None) => {}
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+
+            let read_buf_fut = pin!(this.socket.read_buf(&mut this.read_buf));
+            match ready!(read_buf_fut.poll(cx)) {
+                Ok(1..) => {}
+                Ok(0) => return Poll::Ready(None),
+                Err(err) => return Poll::Ready(Some(Err(Error::Io(err)))),
+            }
+        }
+    }
+}
+
+impl<S> Sink<Message> for DeflateStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.write_buf.len() >= FLUSH_THRESHOLD {
+            ready!(this.poll_write_buf(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        if this.close_sent {
+            return Err(Error::AlreadyClosed);
+        }
+
+        let opcode = if item.is_binary() {
+            OP_BINARY
+        } else if item.is_text() {
+            OP_TEXT
+        } else if item.is_ping() {
+            OP_PING
+        } else if item.is_pong() {
+            OP_PONG
+        } else {
+            this.close_sent = true;
+            OP_CLOSE
+        };
+        let payload = Bytes::from(item.into_payload());
+
+        if matches!(opcode, OP_BINARY | OP_TEXT) && payload.len() >= MIN_COMPRESSED_LEN {
+            let compressed = compress(&mut this.compressor, &payload);
+            // Sending the message uncompressed is allowed and cheaper to decode
+            if compressed.len() < payload.len() {
+                this.enqueue_frame(FIN | RSV1 | opcode, &compressed);
+                return Ok(());
+            }
+        }
+        this.enqueue_frame(FIN | opcode, &payload);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.socket).poll_flush(cx).map_err(Error::Io)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if !self.close_sent {
+            self.close_sent = true;
+            self.enqueue_frame(FIN | OP_CLOSE, &NORMAL_CLOSURE);
+        }
+        ready!(self.as_mut().poll_flush(cx))?;
+
+        // Wait for the server to acknowledge the close frame
+        while let Some(result) = ready!(self.as_mut().poll_next(cx)) {
+            result?;
+        }
+        Pin::new(&mut self.socket)
+            .poll_shutdown(cx)
+            .map_err(Error::Io)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for DeflateStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeflateStream")
+            .field("socket", &self.socket)
+            .field("close_sent", &self.close_sent)
+            .field("close_received", &self.close_received)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Send the upgrade request and read the response of the server
+///
+/// Returns whether the server accepted the `permessage-deflate` extension.
+/// The response is read one byte at a time, so that the frames which may
+/// immediately follow it are left unread in `socket`.
+async fn handshake<S>(uri: &Uri, socket: &mut S) -> Result<bool, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let key = STANDARD.encode(rand::random::<[u8; 16]>());
+    socket.write_all(&upgrade_request(uri, &key)).await?;
+    socket.flush().await?;
+
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() == MAX_RESPONSE_LEN {
+            return Err(Error::Upgrade(upgrade::Error::Parsing(
+                httparse::Error::TooManyHeaders,
+            )));
+        }
+        response.push(socket.read_u8().await?);
+    }
+
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut parsed = httparse::Response::new(&mut headers);
+    parsed
+        .parse(&response)
+        .map_err(|err| Error::Upgrade(upgrade::Error::Parsing(err)))?;
+    let code = parsed.code.unwrap_or_default();
+    if code != SWITCHING_PROTOCOLS {
+        return Err(Error::Upgrade(upgrade::Error::DidNotSwitchProtocols(code)));
+    }
+
+    let accept = parsed
+        .headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("Sec-WebSocket-Accept"))
+        .ok_or(Error::Upgrade(upgrade::Error::MissingHeader(
+            "Sec-WebSocket-Accept",
+        )))?;
+    if accept.value != accept_key(&key).as_bytes() {
+        return Err(Error::Upgrade(upgrade::Error::WrongWebSocketAccept));
+    }
+
+    accepts_extension(parsed.headers)
+}
+
+fn upgrade_request(uri: &Uri, key: &str) -> Vec<u8> {
+    let path = match uri.path_and_query() {
+        Some(path) if !path.as_str().is_empty() => path.as_str(),
+        _ => "/",
+    };
+    let host = uri.host().unwrap_or_default();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("wss") {
+            443
+        } else {
+            80
+        });
+
+    format!(
+        "GET {path} HTTP/1.1\r\n\
+         Host: {host}:{port}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\n\
+         Sec-WebSocket-Version: 13\r\n\
+         Sec-WebSocket-Extensions: {EXTENSION_OFFER}\r\n\
+         \r\n"
+    )
+    .into_bytes()
+}
+
+fn accept_key(key: &str) -> String {
+    let mut sha1 = Sha1::new();
+    sha1.update(key.as_bytes());
+    sha1.update(ACCEPT_GUID);
+    STANDARD.encode(sha1.digest().bytes())
+}
+
+/// Whether the server accepted the offered extension
+///
+/// # Errors
+///
+/// Returns an error if the server responded with extensions or parameters
+/// that weren't offered, or without `server_no_context_takeover`.
+fn accepts_extension(headers: &[httparse::Header<'_>]) -> Result<bool, Error> {
+    let invalid = || {
+        Error::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid Sec-WebSocket-Extensions response",
+        ))
+    };
+
+    let mut accepted = false;
+    for header in headers
+        .iter()
+        .filter(|header| header.name.eq_ignore_ascii_case("Sec-WebSocket-Extensions"))
+    {
+        let value = str::from_utf8(header.value).map_err(|_| invalid())?;
+        for extension in value.split(',') {
+            let mut params = extension.split(';').map(str::trim);
+            if accepted || params.next() != Some(EXTENSION) {
+                return Err(invalid());
+            }
+
+            let mut server_no_context_takeover = false;
+            for param in params {
+                match param
+                    .split_once('=')
+                    .map_or(param, |(name, _)| name.trim_end())
+                {
+                    "server_no_context_takeover" => server_no_context_takeover = true,
+                    // A smaller window than the one offered is still decompressed fine
+                    "client_no_context_takeover" | "server_max_window_bits" => {}
+                    _ => return Err(invalid()),
+                }
+            }
+            if !server_no_context_takeover {
+                return Err(invalid());
+            }
+            accepted = true;
+        }
+    }
+    Ok(accepted)
+}
+
+/// Decode the next frame from `buf`, returning its first byte and its unmasked payload
+fn decode_frame(buf: &mut BytesMut, masked: bool) -> Result<Option<(u8, BytesMut)>, Error> {
+    let Some(&[head, len_byte]) = buf.get(..2) else {
+        return Ok(None);
+    };
+    match (len_byte & MASKED != 0, masked) {
+        (true, false) => return Err(Error::Protocol(ProtocolError::UnexpectedMaskedFrame)),
+        (false, true) => return Err(Error::Protocol(ProtocolError::UnexpectedUnmaskedFrame)),
+        (true, true) | (false, false) => {}
+    }
+
+    let (len, mut offset) = match len_byte & !MASKED {
+        LEN_16 => {
+            let Some(len) = buf.get(2..4) else {
+                return Ok(None);
+            };
+            (u64::from(u16::from_be_bytes(len.try_into().unwrap())), 4)
+        }
+        LEN_64 => {
+            let Some(len) = buf.get(2..10) else {
+                return Ok(None);
+            };
+            (u64::from_be_bytes(len.try_into().unwrap()), 10)
+        }
+        len => (u64::from(len), 2),
+    };
+    if head & OPCODE >= OP_CLOSE && len > 125 {
+        return Err(Error::Protocol(ProtocolError::InvalidPayloadLength));
+    }
+    let len = usize::try_from(len).unwrap_or(usize::MAX);
+    if len > MAX_MESSAGE_LEN {
+        return Err(Error::PayloadTooLong {
+            len,
+            max_len: MAX_MESSAGE_LEN,
+        });
+    }
+
+    let mask = if masked {
+        let Some(mask) = buf.get(offset..offset + 4) else {
+            return Ok(None);
+        };
+        offset += 4;
+        Some(<[u8; 4]>::try_from(mask).unwrap())
+    } else {
+        None
+    };
+    if buf.len() < offset + len {
+        buf.reserve(offset + len - buf.len());
+        return Ok(None);
+    }
+
+    buf.advance(offset);
+    let mut payload = buf.split_to(len);
+    if let Some(mask) = mask {
+        apply_mask(&mut payload, mask);
+    }
+    Ok(Some((head, payload)))
+}
+
+/// Encode a frame starting with `head` into `buf`, masking `payload` with `mask`
+fn encode_frame(buf: &mut BytesMut, head: u8, payload: &[u8], mask: Option<[u8; 4]>) {
+    let masked = if mask.is_some() { MASKED } else { 0 };
+    buf.reserve(14 + payload.len());
+    buf.put_u8(head);
+    match (u8::try_from(payload.len()), u16::try_from(payload.len())) {
+        (Ok(len @ ..=125), _) => buf.put_u8(masked | len),
+        (_, Ok(len)) => {
+            buf.put_u8(masked | LEN_16);
+            buf.put_u16(len);
+        }
+        (_, Err(_)) => {
+            buf.put_u8(masked | LEN_64);
+            buf.put_u64(payload.len() as u64);
+        }
+    }
+
+    let start = buf.len();
+    if let Some(mask) = mask {
+        buf.put_slice(&mask);
+    }
+    buf.put_slice(payload);
+    if let Some(mask) = mask {
+        apply_mask(&mut buf[start + 4..], mask);
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for chunk in payload.chunks_mut(4) {
+        for (byte, mask) in chunk.iter_mut().zip(mask) {
+            *byte ^= mask;
+        }
+    }
+}
+
+fn finish_message(opcode: u8, compressed: bool, payload: BytesMut) -> Result<Message, Error> {
+    let payload = if compressed {
+        decompress(&payload)?
+    } else {
+        payload.freeze()
+    };
+
+    if opcode == OP_TEXT {
+        let text = String::from_utf8(payload.into())
+            .map_err(|_| Error::Protocol(ProtocolError::InvalidUtf8))?;
+        Ok(Message::text(text))
+    } else {
+        Ok(Message::binary(payload))
+    }
+}
+
+fn compressor() -> Box<CompressorOxide> {
+    Box::new(CompressorOxide::new(create_comp_flags_from_zip_params(
+        COMPRESSION_LEVEL,
+        0,
+        0,
+    )))
+}
+
+/// Compress the payload of a message, without the trailer of the final sync flush
+fn compress(compressor: &mut CompressorOxide, payload: &[u8]) -> Vec<u8> {
+    compressor.reset();
+
+    let mut compressed = Vec::with_capacity(payload.len() / 2);
+    let (status, _) = compress_to_output(compressor, payload, TDEFLFlush::Sync, |chunk| {
+        compressed.extend_from_slice(chunk);
+        true
+    });
+    debug_assert_eq!(TDEFLStatus::Okay, status);
+
+    if compressed.ends_with(&SYNC_FLUSH_TRAILER) {
+        compressed.truncate(compressed.len() - SYNC_FLUSH_TRAILER.len());
+    }
+    compressed
+}
+
+/// Decompress the payload of a message
+///
+/// The stripped sync flush trailer is restored and followed by an empty final block,
+/// so that the payload forms a complete deflate stream.
+fn decompress(payload: &[u8]) -> Result<Bytes, Error> {
+    let mut stream =
+        Vec::with_capacity(payload.len() + SYNC_FLUSH_TRAILER.len() + FINAL_BLOCK.len());
+    stream.extend_from_slice(payload);
+    stream.extend_from_slice(&SYNC_FLUSH_TRAILER);
+    stream.extend_from_slice(&FINAL_BLOCK);
+
+    decompress_to_vec_with_limit(&stream, MAX_MESSAGE_LEN)
+        .map(Bytes::from)
+        .map_err(|err| Error::Io(io::Error::new(io::ErrorKind::InvalidData, err.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+    use claims::assert_matches;
+    use futures_util::{SinkExt as _, StreamExt as _};
+    use tokio::io::{self, AsyncReadExt as _, AsyncWriteExt as _, DuplexStream};
+    use tokio_websockets::{Message, ServerBuilder};
+    use watermelon_proto::proto::{ClientOp, ServerOp};
+
+    use super::{
+        accept_key, compress, compressor, decode_frame, decompress, encode_frame, Socket,
+        EXTENSION_OFFER, FIN, OPCODE, OP_CLOSE, RSV1,
+    };
+    use crate::connection::WebsocketConnection;
+
+    /// Accept the extension and echo every message compressed, split into two frames
+    ///
+    /// Returns whether each of the received messages was compressed.
+    async fn deflating_echo(mut socket: DuplexStream) -> Vec<bool> {
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(socket.read_u8().await.unwrap());
+        }
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.contains(EXTENSION_OFFER));
+        let key = request
+            .lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+            .unwrap();
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\
+             Sec-WebSocket-Extensions: permessage-deflate; server_no_context_takeover; client_no_context_takeover\r\n\
+             \r\n",
+            accept_key(key)
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+
+        let mut compressor = compressor();
+        let mut read_buf = BytesMut::new();
+        let mut compressed = Vec::new();
+        loop {
+            let (head, payload) = loop {
+                if let Some(frame) = decode_frame(&mut read_buf, true).unwrap() {
+                    break frame;
+                }
+                assert_ne!(0, socket.read_buf(&mut read_buf).await.unwrap());
+            };
+            if head & OPCODE == OP_CLOSE {
+                let mut close = BytesMut::new();
+                encode_frame(&mut close, FIN | OP_CLOSE, &payload, None);
+                socket.write_all(&close).await.unwrap();
+                return compressed;
+            }
+
+            compressed.push(head & RSV1 != 0);
+            let payload = if head & RSV1 != 0 {
+                decompress(&payload).unwrap()
+            } else {
+                payload.freeze()
+            };
+            let payload = compress(&mut compressor, &payload);
+            let (first, second) = payload.split_at(payload.len() / 2);
+
+            let mut echo = BytesMut::new();
+            encode_frame(&mut echo, RSV1 | (head & OPCODE), first, None);
+            encode_frame(&mut echo, FIN, second, None);
+            socket.write_all(&echo).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let (socket, server_socket) = io::duplex(1024);
+        let server = tokio::spawn(deflating_echo(server_socket));
+        let mut client = Socket::connect(&"ws://127.0.0.1/".parse().unwrap(), socket)
+            .await
+            .unwrap();
+        assert!(client.is_deflate());
+
+        let large = Bytes::from("MSG foo 1 5\r\nhello\r\n".repeat(1024));
+        for payload in [large, Bytes::from_static(b"PING\r\n")] {
+            client.send(Message::binary(payload.clone())).await.unwrap();
+            let message = client.next().await.unwrap().unwrap();
+            assert!(message.is_binary());
+            assert_eq!(payload, Bytes::from(message.into_payload()));
+        }
+
+        client.close().await.unwrap();
+        assert_eq!(vec![true, false], server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn connection_round_trip() {
+        let (socket, server_socket) = io::duplex(1024);
+        let server = tokio::spawn(deflating_echo(server_socket));
+        let mut client =
+            WebsocketConnection::new_with_deflate("ws://127.0.0.1/".parse().unwrap(), socket)
+                .await
+                .unwrap();
+        assert!(client.is_deflate());
+
+        client.enqueue_write_op(&ClientOp::Ping);
+        client.flush().await.unwrap();
+        assert_matches!(client.read_next().await, Ok(ServerOp::Ping));
+
+        client.shutdown().await.unwrap();
+        assert_eq!(vec![false], server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn fallback() {
+        let (socket, server_socket) = io::duplex(1024);
+        let uri = "ws://127.0.0.1/".parse().unwrap();
+        let server = ServerBuilder::new();
+        let (client, server) =
+            tokio::join!(Socket::connect(&uri, socket), server.accept(server_socket));
+        let mut client = client.unwrap();
+        let mut server = server.unwrap().1;
+        assert!(!client.is_deflate());
+
+        let large = Bytes::from("PUB foo 5\r\nhello\r\n".repeat(1024));
+        let (sent, message) =
+            tokio::join!(client.send(Message::binary(large.clone())), server.next());
+        sent.unwrap();
+        let message = message.unwrap().unwrap();
+        assert_eq!(large, Bytes::from(message.into_payload()));
+
+        server.send(Message::binary("PING\r\n")).await.unwrap();
+        let message = client.next().await.unwrap().unwrap();
+        assert_eq!(b"PING\r\n".as_slice(), Bytes::from(message.into_payload()));
+    }
+}
@@ -1,15 +1,18 @@
 use std::{
+    fmt::Debug,
     future::Future,
     io,
     net::SocketAddr,
     pin::{pin, Pin},
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
 
 use futures_util::{
+    future::BoxFuture,
     stream::{self, FusedStream, FuturesUnordered},
-    Stream, StreamExt,
+    FutureExt as _, Stream, StreamExt,
 };
 use pin_project_lite::pin_project;
 use tokio::{
@@ -18,8 +21,68 @@ use tokio::{
 };
 use watermelon_proto::{Host, ServerAddr};
 
+use crate::SocketOptions;
+
 const CONN_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
 
+/// Options for [`connect`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TcpConnectOptions {
+    /// How long to wait for a connection attempt to succeed before starting the next one
+    ///
+    /// Default: 250 milliseconds.
+    pub attempt_delay: Duration,
+    /// How long to wait for the whole connection process, including DNS resolution
+    ///
+    /// Default: no timeout.
+    pub timeout: Option<Duration>,
+    /// Which IP version to try first when the host resolves to both
+    ///
+    /// Default: [`IpPreference::Ipv6`].
+    pub ip_preference: IpPreference,
+    /// The resolver used to lookup the IPs of hostnames
+    ///
+    /// Default: [`SystemResolver`].
+    pub resolver: Arc<dyn Resolver>,
+    /// The options applied to the socket once connected
+    ///
+    /// Default: [`SocketOptions::default`].
+    pub socket: SocketOptions,
+}
+
+/// A DNS resolver used by [`connect`]
+pub trait Resolver: Debug + Send + Sync {
+    /// Resolve `host` into the socket addresses to connect to
+    ///
+    /// The returned addresses are attempted by the [Happy Eyeballs] algorithm.
+    ///
+    /// [Happy Eyeballs]: https://en.wikipedia.org/wiki/Happy_Eyeballs
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if `host` cannot be resolved.
+    fn resolve<'a>(
+        &'a self,
+        host: &'a str,
+        port: u16,
+    ) -> BoxFuture<'a, io::Result<Vec<SocketAddr>>>;
+}
+
+/// A [`Resolver`] using the resolver of the operating system
+///
+/// Backed by [`tokio::net::lookup_host`].
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemResolver;
+
+/// The IP version that is attempted first by [`connect`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    #[default]
+    Ipv6,
+    Ipv4,
+}
+
 /// Connects to an address and returns a [`TcpStream`].
 ///
 /// If the given address is an ip, this just uses [`TcpStream::connect`]. Otherwise, if a host is
@@ -29,15 +92,32 @@ const CONN_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
 ///
 /// # Errors
 ///
-/// It returns an error if it is not possible to connect to any host.
-pub async fn connect(addr: &ServerAddr) -> io::Result<TcpStream> {
+/// It returns an error if it is not possible to connect to any host,
+/// if connecting takes longer than [`TcpConnectOptions::timeout`]
+/// or if [`TcpConnectOptions::socket`] can't be applied.
+pub async fn connect(addr: &ServerAddr, options: &TcpConnectOptions) -> io::Result<TcpStream> {
+    let stream = match options.timeout {
+        Some(timeout) => time::timeout(timeout, connect_inner(addr, options))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connect timed out"))??,
+        None => connect_inner(addr, options).await?,
+    };
+    options.socket.apply(&stream)?;
+    Ok(stream)
+}
+
+async fn connect_inner(addr: &ServerAddr, options: &TcpConnectOptions) -> io::Result<TcpStream> {
     match addr.host() {
         Host::Ip(ip) => TcpStream::connect(SocketAddr::new(*ip, addr.port())).await,
         Host::Dns(host) => {
             let host = <_ as AsRef<str>>::as_ref(host);
-            let addrs = net::lookup_host(format!("{}:{}", host, addr.port())).await?;
+            let addrs = options.resolver.resolve(host, addr.port()).await?;
 
-            let mut happy_eyeballs = pin!(HappyEyeballs::new(stream::iter(addrs)));
+            let mut happy_eyeballs = pin!(HappyEyeballs::new(
+                stream::iter(addrs),
+                options.attempt_delay,
+                options.ip_preference,
+            ));
             let mut last_err = None;
             loop {
                 match happy_eyeballs.next().await {
@@ -66,6 +146,8 @@ pin_project! {
             Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send + Sync + 'static>>,
         >,
         last_attempted: Option<LastAttempted>,
+        first_attempted: LastAttempted,
+        attempt_delay: Duration,
         #[pin]
         next_attempt_delay: Option<Sleep>,
     }
@@ -77,13 +159,40 @@ enum LastAttempted {
     Ipv6,
 }
 
+impl Default for TcpConnectOptions {
+    fn default() -> Self {
+        Self {
+            attempt_delay: CONN_ATTEMPT_DELAY,
+            timeout: None,
+            ip_preference: IpPreference::default(),
+            resolver: Arc::new(SystemResolver),
+            socket: SocketOptions::default(),
+        }
+    }
+}
+
+impl Resolver for SystemResolver {
+    fn resolve<'a>(
+        &'a self,
+        host: &'a str,
+        port: u16,
+    ) -> BoxFuture<'a, io::Result<Vec<SocketAddr>>> {
+        async move { Ok(net::lookup_host((host, port)).await?.collect()) }.boxed()
+    }
+}
+
 impl<D> HappyEyeballs<D> {
-    fn new(dns: D) -> Self {
+    fn new(dns: D, attempt_delay: Duration, ip_preference: IpPreference) -> Self {
         Self {
             dns: Some(dns),
             dns_received: Vec::new(),
             connecting: FuturesUnordered::new(),
             last_attempted: None,
+            first_attempted: match ip_preference {
+                IpPreference::Ipv6 => LastAttempted::Ipv6,
+                IpPreference::Ipv4 => LastAttempted::Ipv4,
+            },
+            attempt_delay,
             next_attempt_delay: None,
         }
     }
@@ -97,7 +206,7 @@ impl<D> HappyEyeballsProj<'_, D> {
 
         let next_kind = self
             .last_attempted
-            .map_or(LastAttempted::Ipv6, LastAttempted::opposite);
+            .map_or(*self.first_attempted, LastAttempted::opposite);
         for i in 0..self.dns_received.len() {
             if LastAttempted::from_addr(self.dns_received[i]) == next_kind {
                 *self.last_attempted = Some(next_kind);
@@ -164,7 +273,7 @@ where
                     let conn_fut = TcpStream::connect(record);
                     this.connecting.push(Box::pin(conn_fut));
                     this.next_attempt_delay
-                        .set(Some(time::sleep(CONN_ATTEMPT_DELAY)));
+                        .set(Some(time::sleep(*this.attempt_delay)));
                 }
                 None => break,
             }
@@ -211,3 +320,45 @@ impl LastAttempted {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{io, net::SocketAddr, sync::Arc};
+
+    use futures_util::{future::BoxFuture, FutureExt as _};
+    use tokio::net::TcpListener;
+    use watermelon_proto::ServerAddr;
+
+    use super::{connect, Resolver, TcpConnectOptions};
+
+    #[derive(Debug)]
+    struct StaticResolver(SocketAddr);
+
+    impl Resolver for StaticResolver {
+        fn resolve<'a>(
+            &'a self,
+            host: &'a str,
+            _port: u16,
+        ) -> BoxFuture<'a, io::Result<Vec<SocketAddr>>> {
+            assert_eq!("nats.invalid", host);
+            async move { Ok(vec![self.0]) }.boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_resolver() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let options = TcpConnectOptions {
+            resolver: Arc::new(StaticResolver(local_addr)),
+            ..TcpConnectOptions::default()
+        };
+        let addr = format!("nats://nats.invalid:{}", local_addr.port())
+            .parse::<ServerAddr>()
+            .unwrap();
+
+        let stream = connect(&addr, &options).await.unwrap();
+        assert_eq!(local_addr, stream.peer_addr().unwrap());
+    }
+}
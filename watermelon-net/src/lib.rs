@@ -1,10 +1,16 @@
+#[cfg(feature = "capture")]
+pub use self::connection::Tap;
 #[cfg(feature = "websocket")]
 pub use self::connection::WebsocketConnection;
 pub use self::connection::{connect as proto_connect, Connection, StreamingConnection};
-pub use self::happy_eyeballs::connect as connect_tcp;
+pub use self::happy_eyeballs::{
+    connect as connect_tcp, IpPreference, Resolver, SystemResolver, TcpConnectOptions,
+};
+pub use self::socket_options::SocketOptions;
 
 mod connection;
 mod happy_eyeballs;
+mod socket_options;
 
 pub mod error {
     #[cfg(feature = "websocket")]
@@ -0,0 +1,127 @@
+use std::{io, time::Duration};
+
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::TcpStream;
+
+/// Options applied to the TCP socket once connected
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SocketOptions {
+    /// Whether to disable Nagle's algorithm via `TCP_NODELAY`
+    ///
+    /// Default: `true`.
+    pub nodelay: bool,
+    /// How long the connection must be idle before TCP keepalive probes are sent
+    ///
+    /// TCP keepalive is only enabled if this is set.
+    ///
+    /// Default: TCP keepalive disabled.
+    pub keepalive_time: Option<Duration>,
+    /// The interval between TCP keepalive probes
+    ///
+    /// Only used if [`SocketOptions::keepalive_time`] is set. Ignored on
+    /// platforms that don't support configuring it.
+    ///
+    /// Default: the operating system default.
+    pub keepalive_interval: Option<Duration>,
+    /// The size of the send buffer of the socket, via `SO_SNDBUF`
+    ///
+    /// Default: the operating system default.
+    pub send_buffer_size: Option<usize>,
+    /// The size of the receive buffer of the socket, via `SO_RCVBUF`
+    ///
+    /// Default: the operating system default.
+    pub recv_buffer_size: Option<usize>,
+}
+
+impl SocketOptions {
+    pub(crate) fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_nodelay(self.nodelay)?;
+
+        let socket = SockRef::from(stream);
+        if let Some(keepalive_time) = self.keepalive_time {
+            #[cfg_attr(
+                not(any(
+                    target_os = "android",
+                    target_os = "freebsd",
+                    target_os = "ios",
+                    target_os = "linux",
+                    target_os = "macos",
+                    target_os = "netbsd",
+                    target_os = "windows",
+                )),
+                expect(unused_mut)
+            )]
+            let mut keepalive = TcpKeepalive::new().with_time(keepalive_time);
+            #[cfg(any(
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "ios",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "netbsd",
+                target_os = "windows",
+            ))]
+            if let Some(keepalive_interval) = self.keepalive_interval {
+                keepalive = keepalive.with_interval(keepalive_interval);
+            }
+            socket.set_tcp_keepalive(&keepalive)?;
+        }
+        if let Some(send_buffer_size) = self.send_buffer_size {
+            socket.set_send_buffer_size(send_buffer_size)?;
+        }
+        if let Some(recv_buffer_size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(recv_buffer_size)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            keepalive_time: None,
+            keepalive_interval: None,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use socket2::SockRef;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::SocketOptions;
+
+    #[tokio::test]
+    async fn apply() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        SocketOptions::default().apply(&stream).unwrap();
+        assert!(stream.nodelay().unwrap());
+        assert!(!SockRef::from(&stream).keepalive().unwrap());
+
+        let options = SocketOptions {
+            nodelay: false,
+            keepalive_time: Some(Duration::from_secs(30)),
+            keepalive_interval: Some(Duration::from_secs(10)),
+            send_buffer_size: Some(256 * 1024),
+            recv_buffer_size: Some(256 * 1024),
+        };
+        options.apply(&stream).unwrap();
+        assert!(!stream.nodelay().unwrap());
+
+        let socket = SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        assert!(socket.send_buffer_size().unwrap() >= 256 * 1024);
+        assert!(socket.recv_buffer_size().unwrap() >= 256 * 1024);
+    }
+}
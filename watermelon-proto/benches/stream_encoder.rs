@@ -0,0 +1,61 @@
+use std::{hint::black_box, io::IoSlice};
+
+use bytes::{Buf, Bytes};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use watermelon_proto::{
+    headers::HeaderMap,
+    proto::{ClientOp, StreamEncoder},
+    MessageBase, Subject,
+};
+
+const MESSAGES: usize = 64;
+const PAYLOAD_LEN: usize = 8 * 1024;
+
+fn encode(flatten_threshold: usize, payload: &Bytes) -> StreamEncoder {
+    let mut encoder = StreamEncoder::new().with_flatten_threshold(flatten_threshold);
+    for _ in 0..MESSAGES {
+        encoder.enqueue_write_op(&ClientOp::Publish {
+            message: MessageBase {
+                subject: Subject::from_static("bench.payload"),
+                reply_subject: None,
+                headers: HeaderMap::new(),
+                payload: payload.clone(),
+            },
+        });
+    }
+    encoder
+}
+
+/// Drain `encoder` as a socket accepting up to 64 chunks per vectored write would
+///
+/// Returns the number of vectored writes needed.
+fn write_vectored(mut encoder: StreamEncoder) -> usize {
+    let mut writes = 0;
+    while encoder.has_remaining() {
+        let mut bufs = [IoSlice::new(&[]); 64];
+        let n = encoder.chunks_vectored(&mut bufs);
+        let len = bufs[..n].iter().map(|buf| buf.len()).sum();
+        encoder.advance(len);
+        writes += 1;
+    }
+    writes
+}
+
+fn stream_encoder(c: &mut Criterion) {
+    let payload = Bytes::from(vec![b'x'; PAYLOAD_LEN]);
+
+    let mut group = c.benchmark_group("stream_encoder");
+    for flatten_threshold in [4 * 1024, 16 * 1024] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(flatten_threshold),
+            &flatten_threshold,
+            |b, &flatten_threshold| {
+                b.iter(|| write_vectored(encode(black_box(flatten_threshold), &payload)));
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, stream_encoder);
+criterion_main!(benches);
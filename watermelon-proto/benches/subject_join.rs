@@ -0,0 +1,56 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use watermelon_proto::Subject;
+
+const STREAM_NAME: &str = "orders";
+const CONSUMER_NAME: &str = "processor";
+
+fn subject_join(c: &mut Criterion) {
+    let prefix = Subject::from_static("$JS.API");
+    let endpoint = Subject::from_static("$JS.API.CONSUMER.MSG.NEXT");
+
+    let mut group = c.benchmark_group("subject_join");
+    group.bench_function("format", |b| {
+        b.iter(|| {
+            Subject::try_from(format!(
+                "{}.CONSUMER.MSG.NEXT.{}.{}",
+                black_box(&prefix),
+                black_box(STREAM_NAME),
+                black_box(CONSUMER_NAME)
+            ))
+            .unwrap()
+        });
+    });
+    group.bench_function("join_tokens", |b| {
+        b.iter(|| {
+            black_box(&prefix)
+                .join_tokens(&[
+                    "CONSUMER",
+                    "MSG",
+                    "NEXT",
+                    black_box(STREAM_NAME),
+                    black_box(CONSUMER_NAME),
+                ])
+                .unwrap()
+        });
+    });
+    group.bench_function("join_tokens_cached", |b| {
+        b.iter(|| {
+            black_box(&endpoint)
+                .join_tokens(&[black_box(STREAM_NAME), black_box(CONSUMER_NAME)])
+                .unwrap()
+        });
+    });
+    group.bench_function("join_display_cached", |b| {
+        b.iter(|| {
+            black_box(&endpoint)
+                .join_display(&[&black_box(STREAM_NAME), &black_box(CONSUMER_NAME)])
+                .unwrap()
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, subject_join);
+criterion_main!(benches);
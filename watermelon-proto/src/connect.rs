@@ -1,13 +1,13 @@
-use alloc::string::String;
+use alloc::{borrow::Cow, string::String};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct Connect {
     pub verbose: bool,
     pub pedantic: bool,
-    #[serde(rename = "tls_required")]
+    #[serde(default, rename = "tls_required")]
     pub require_tls: bool,
     pub auth_token: Option<String>,
     #[serde(rename = "user")]
@@ -16,18 +16,20 @@ pub struct Connect {
     pub password: Option<String>,
     #[serde(rename = "name")]
     pub client_name: Option<String>,
-    #[serde(rename = "lang")]
-    pub client_lang: &'static str,
-    #[serde(rename = "version")]
-    pub client_version: &'static str,
+    #[serde(default, rename = "lang")]
+    pub client_lang: Cow<'static, str>,
+    #[serde(default, rename = "version")]
+    pub client_version: Cow<'static, str>,
+    #[serde(default)]
     pub protocol: u8,
+    #[serde(default = "default_echo")]
     pub echo: bool,
     #[serde(rename = "sig")]
     pub signature: Option<String>,
     pub jwt: Option<String>,
-    #[serde(rename = "no_responders")]
+    #[serde(default, rename = "no_responders")]
     pub supports_no_responders: bool,
-    #[serde(rename = "headers")]
+    #[serde(default, rename = "headers")]
     pub supports_headers: bool,
     pub nkey: Option<String>,
 
@@ -35,11 +37,12 @@ pub struct Connect {
     pub non_standard: NonStandardConnect,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct NonStandardConnect {
     #[cfg(feature = "non-standard-zstd")]
     #[serde(
+        default,
         rename = "m4ss_zstd",
         skip_serializing_if = "skip_serializing_if_false"
     )]
@@ -56,6 +59,10 @@ impl Default for NonStandardConnect {
     }
 }
 
+fn default_echo() -> bool {
+    true
+}
+
 #[cfg(feature = "non-standard-zstd")]
 #[allow(clippy::trivially_copy_pass_by_ref)]
 fn skip_serializing_if_false(val: &bool) -> bool {
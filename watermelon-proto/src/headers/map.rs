@@ -5,15 +5,19 @@ use alloc::{
 };
 use core::{iter, mem, slice};
 
+use bytes::Bytes;
+
 use super::{HeaderName, HeaderValue};
 
 /// A set of NATS headers
 ///
 /// [`HeaderMap`] is a multimap of [`HeaderName`].
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct HeaderMap {
     headers: BTreeMap<HeaderName, OneOrMany>,
     len: usize,
+    // The header block the map was decoded from, if it hasn't been modified since
+    raw: Option<Bytes>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -34,10 +38,12 @@ impl HeaderMap {
         Self {
             headers: BTreeMap::new(),
             len: 0,
+            raw: None,
         }
     }
 
     pub fn insert(&mut self, name: HeaderName, value: HeaderValue) {
+        self.raw = None;
         if let Some(prev) = self.headers.insert(name, OneOrMany::One(value)) {
             self.len -= prev.len();
         }
@@ -45,6 +51,7 @@ impl HeaderMap {
     }
 
     pub fn append(&mut self, name: HeaderName, value: HeaderValue) {
+        self.raw = None;
         match self.headers.entry(name) {
             Entry::Vacant(vacant) => {
                 vacant.insert(OneOrMany::One(value));
@@ -57,11 +64,34 @@ impl HeaderMap {
     }
 
     pub fn remove(&mut self, name: &HeaderName) {
+        self.raw = None;
         if let Some(prev) = self.headers.remove(name) {
             self.len -= prev.len();
         }
     }
 
+    /// Removes `name` from the map, returning all of its values
+    ///
+    /// The values are yielded in the order they were appended.
+    /// Nothing is yielded if `name` was not in the map.
+    pub fn remove_all(&mut self, name: &HeaderName) -> impl Iterator<Item = HeaderValue> {
+        self.raw = None;
+        let prev = self.headers.remove(name);
+        if let Some(prev) = &prev {
+            self.len -= prev.len();
+        }
+        prev.into_iter().flat_map(OneOrMany::into_values)
+    }
+
+    /// Gets the entry for `name`, for in-place manipulation
+    pub fn entry(&mut self, name: HeaderName) -> HeaderEntry<'_> {
+        self.raw = None;
+        HeaderEntry {
+            entry: self.headers.entry(name),
+            len: &mut self.len,
+        }
+    }
+
     /// Returns the number of keys stored in the map
     ///
     /// This number will be less than or equal to [`HeaderMap::len`].
@@ -85,27 +115,175 @@ impl HeaderMap {
         self.headers.is_empty()
     }
 
+    /// Returns the header block this map was decoded from, if it hasn't been modified since
+    ///
+    /// A [`StreamDecoder`] configured via [`StreamDecoder::with_retain_raw_headers`]
+    /// retains it for headers received without a status code, allowing the
+    /// encoder to write it back verbatim when the map is published again,
+    /// as done when relaying messages, instead of re-encoding every header.
+    /// Any modification to the map discards it.
+    ///
+    /// [`StreamDecoder`]: crate::proto::StreamDecoder
+    /// [`StreamDecoder::with_retain_raw_headers`]: crate::proto::StreamDecoder::with_retain_raw_headers
+    #[must_use]
+    pub fn raw(&self) -> Option<&Bytes> {
+        self.raw.as_ref()
+    }
+
+    pub(crate) fn set_raw(&mut self, raw: Bytes) {
+        self.raw = Some(raw);
+    }
+
+    /// Returns the length of the headers section once encoded
+    ///
+    /// This includes the `NATS/1.0` head and the terminating empty line,
+    /// or is zero if the map is empty, in which case no headers section
+    /// is sent.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        if let Some(raw) = &self.raw {
+            return raw.len();
+        }
+
+        let lines = self
+            .iter()
+            .flat_map(|(name, values)| values.map(move |value| (name, value)))
+            .map(|(name, value)| name.len() + ": ".len() + value.len() + "\r\n".len())
+            .sum::<usize>();
+        "NATS/1.0\r\n".len() + lines + "\r\n".len()
+    }
+
     /// Clear the map, removing all key-value pairs. Keeps the allocated memory for reuse
     pub fn clear(&mut self) {
+        self.raw = None;
         self.headers.clear();
         self.len = 0;
     }
 
-    #[cfg(test)]
-    fn keys(&self) -> impl Iterator<Item = &'_ HeaderName> {
+    /// Returns the first value associated with `name`
+    ///
+    /// If `name` has multiple values, the one that was appended first is returned.
+    #[must_use]
+    pub fn get(&self, name: &HeaderName) -> Option<&HeaderValue> {
+        self.headers.get(name).map(OneOrMany::first)
+    }
+
+    /// Returns a mutable reference to the first value associated with `name`
+    ///
+    /// If `name` has multiple values, the one that was appended first is returned.
+    /// Use [`HeaderMap::entry`] to replace or append values instead.
+    #[must_use]
+    pub fn get_mut(&mut self, name: &HeaderName) -> Option<&mut HeaderValue> {
+        self.raw = None;
+        self.headers.get_mut(name).map(OneOrMany::first_mut)
+    }
+
+    /// Returns an iterator over all of the values associated with `name`
+    ///
+    /// The values are yielded in the order they were appended.
+    /// The iterator is empty if `name` is not in the map.
+    #[must_use]
+    pub fn get_all<'a>(
+        &'a self,
+        name: &HeaderName,
+    ) -> impl DoubleEndedIterator<Item = &'a HeaderValue> {
+        self.headers.get(name).into_iter().flat_map(OneOrMany::iter)
+    }
+
+    /// Returns true if the map contains at least one value for `name`
+    #[must_use]
+    pub fn contains_key(&self, name: &HeaderName) -> bool {
+        self.headers.contains_key(name)
+    }
+
+    /// Returns an iterator over the keys of the map
+    ///
+    /// Each key is yielded only once, even if it has multiple values.
+    #[must_use]
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &'_ HeaderName> {
         self.headers.keys()
     }
 
-    pub(crate) fn iter(
+    /// Returns an iterator over the entries of the map
+    ///
+    /// Each key is yielded only once, together with an iterator over all of its values.
+    #[must_use]
+    pub fn iter(
         &self,
-    ) -> impl DoubleEndedIterator<Item = (&'_ HeaderName, impl Iterator<Item = &'_ HeaderValue>)>
-    {
+    ) -> impl DoubleEndedIterator<
+        Item = (
+            &'_ HeaderName,
+            impl DoubleEndedIterator<Item = &'_ HeaderValue>,
+        ),
+    > {
         self.headers
             .iter()
             .map(|(name, value)| (name, value.iter()))
     }
 }
 
+/// An entry of a [`HeaderMap`]
+///
+/// Obtained from [`HeaderMap::entry`].
+#[derive(Debug)]
+pub struct HeaderEntry<'a> {
+    entry: Entry<'a, HeaderName, OneOrMany>,
+    len: &'a mut usize,
+}
+
+impl<'a> HeaderEntry<'a> {
+    /// Returns the name of this entry
+    #[must_use]
+    pub fn key(&self) -> &HeaderName {
+        self.entry.key()
+    }
+
+    /// Returns the first value of this entry, inserting `value` if the entry is empty
+    pub fn or_insert(self, value: HeaderValue) -> &'a mut HeaderValue {
+        match self.entry {
+            Entry::Vacant(vacant) => {
+                *self.len += 1;
+                vacant.insert(OneOrMany::One(value)).first_mut()
+            }
+            Entry::Occupied(occupied) => occupied.into_mut().first_mut(),
+        }
+    }
+
+    /// Replaces all of the values of this entry with `value`
+    ///
+    /// The previous values are returned in the order they were appended.
+    pub fn insert(self, value: HeaderValue) -> Vec<HeaderValue> {
+        *self.len += 1;
+        match self.entry {
+            Entry::Vacant(vacant) => {
+                vacant.insert(OneOrMany::One(value));
+                Vec::new()
+            }
+            Entry::Occupied(mut occupied) => {
+                let prev = occupied.insert(OneOrMany::One(value));
+                *self.len -= prev.len();
+                prev.into_vec()
+            }
+        }
+    }
+
+    /// Appends `value` to the values of this entry
+    pub fn append(self, value: HeaderValue) {
+        *self.len += 1;
+        match self.entry {
+            Entry::Vacant(vacant) => {
+                vacant.insert(OneOrMany::One(value));
+            }
+            Entry::Occupied(mut occupied) => {
+                occupied.get_mut().push(value);
+            }
+        }
+    }
+}
+
 impl FromIterator<(HeaderName, HeaderValue)> for HeaderMap {
     fn from_iter<I: IntoIterator<Item = (HeaderName, HeaderValue)>>(iter: I) -> Self {
         let mut this = Self::new();
@@ -122,6 +300,15 @@ impl Extend<(HeaderName, HeaderValue)> for HeaderMap {
     }
 }
 
+impl PartialEq for HeaderMap {
+    fn eq(&self, other: &Self) -> bool {
+        // `raw` is only a cache of the encoded form of `headers`
+        self.len == other.len && self.headers == other.headers
+    }
+}
+
+impl Eq for HeaderMap {}
+
 impl Default for HeaderMap {
     fn default() -> Self {
         Self::new()
@@ -136,6 +323,35 @@ impl OneOrMany {
         }
     }
 
+    fn first(&self) -> &HeaderValue {
+        match self {
+            Self::One(one) => one,
+            Self::Many(many) => many.first().expect("OneOrMany can't be empty"),
+        }
+    }
+
+    fn first_mut(&mut self) -> &mut HeaderValue {
+        match self {
+            Self::One(one) => one,
+            Self::Many(many) => many.first_mut().expect("OneOrMany can't be empty"),
+        }
+    }
+
+    fn into_values(self) -> impl Iterator<Item = HeaderValue> {
+        let (one, many) = match self {
+            Self::One(one) => (Some(one), Vec::new()),
+            Self::Many(many) => (None, many),
+        };
+        one.into_iter().chain(many)
+    }
+
+    fn into_vec(self) -> Vec<HeaderValue> {
+        match self {
+            Self::One(one) => vec![one],
+            Self::Many(many) => many,
+        }
+    }
+
     fn push(&mut self, item: HeaderValue) {
         match self {
             Self::One(current_item) => {
@@ -150,7 +366,7 @@ impl OneOrMany {
         }
     }
 
-    fn iter(&self) -> impl Iterator<Item = &'_ HeaderValue> {
+    fn iter(&self) -> impl DoubleEndedIterator<Item = &'_ HeaderValue> {
         enum Either<'a> {
             A(iter::Once<&'a HeaderValue>),
             B(slice::Iter<'a, HeaderValue>),
@@ -222,6 +438,31 @@ mod tests {
 
     use super::HeaderMap;
 
+    #[test]
+    fn encoded_len() {
+        assert_eq!(0, HeaderMap::new().encoded_len());
+
+        let headers = [
+            (
+                HeaderName::from_static("Nats-Message-Id"),
+                HeaderValue::from_static("abcd"),
+            ),
+            (
+                HeaderName::from_static("Nats-Sequence"),
+                HeaderValue::from_static("1"),
+            ),
+            (
+                HeaderName::from_static("Nats-Sequence"),
+                HeaderValue::from_static("2"),
+            ),
+        ]
+        .into_iter()
+        .collect::<HeaderMap>();
+        let encoded =
+            "NATS/1.0\r\nNats-Message-Id: abcd\r\nNats-Sequence: 1\r\nNats-Sequence: 2\r\n\r\n";
+        assert_eq!(encoded.len(), headers.encoded_len());
+    }
+
     #[test]
     fn manual() {
         let mut headers = HeaderMap::new();
@@ -268,6 +509,190 @@ mod tests {
         verify_header_map(&headers);
     }
 
+    #[test]
+    fn lookup() {
+        let headers = [
+            (
+                HeaderName::from_static("Nats-Message-Id"),
+                HeaderValue::from_static("abcd"),
+            ),
+            (
+                HeaderName::from_static("Nats-Sequence"),
+                HeaderValue::from_static("1"),
+            ),
+            (
+                HeaderName::from_static("Nats-Message-Id"),
+                HeaderValue::from_static("1234"),
+            ),
+        ]
+        .into_iter()
+        .collect::<HeaderMap>();
+
+        assert!(headers.contains_key(&HeaderName::from_static("nats-message-id")));
+        assert!(!headers.contains_key(&HeaderName::from_static("Nats-Stream")));
+
+        assert_eq!(
+            Some(&HeaderValue::from_static("abcd")),
+            headers.get(&HeaderName::from_static("Nats-Message-Id"))
+        );
+        assert_eq!(
+            Some(&HeaderValue::from_static("1")),
+            headers.get(&HeaderName::from_static("Nats-Sequence"))
+        );
+        assert_eq!(None, headers.get(&HeaderName::from_static("Nats-Stream")));
+
+        assert_eq!(
+            [
+                HeaderValue::from_static("abcd"),
+                HeaderValue::from_static("1234")
+            ]
+            .as_slice(),
+            headers
+                .get_all(&HeaderName::from_static("Nats-Message-Id"))
+                .cloned()
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+        assert_eq!(
+            0,
+            headers
+                .get_all(&HeaderName::from_static("Nats-Stream"))
+                .count()
+        );
+    }
+
+    #[test]
+    fn get_mut() {
+        let name = HeaderName::from_static("Nats-Message-Id");
+        let mut headers = HeaderMap::new();
+        assert_eq!(None, headers.get_mut(&name));
+
+        headers.insert(name.clone(), HeaderValue::from_static("abcd"));
+        *headers.get_mut(&name).unwrap() = HeaderValue::from_static("efgh");
+        assert_eq!(Some(&HeaderValue::from_static("efgh")), headers.get(&name));
+        assert_eq!(1, headers.len());
+
+        // One -> Many
+        headers.append(name.clone(), HeaderValue::from_static("1234"));
+        *headers.get_mut(&name).unwrap() = HeaderValue::from_static("abcd");
+        assert_eq!(
+            [
+                HeaderValue::from_static("abcd"),
+                HeaderValue::from_static("1234")
+            ]
+            .as_slice(),
+            headers
+                .get_all(&name)
+                .cloned()
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+        assert_eq!(2, headers.len());
+    }
+
+    #[test]
+    fn remove() {
+        let id = HeaderName::from_static("Nats-Message-Id");
+        let sequence = HeaderName::from_static("Nats-Sequence");
+        let mut headers = [
+            (id.clone(), HeaderValue::from_static("abcd")),
+            (sequence.clone(), HeaderValue::from_static("1")),
+            (id.clone(), HeaderValue::from_static("1234")),
+        ]
+        .into_iter()
+        .collect::<HeaderMap>();
+
+        headers.remove(&sequence);
+        assert_eq!(2, headers.len());
+        assert_eq!(None, headers.get(&sequence));
+
+        headers.remove(&id);
+        assert!(headers.is_empty());
+        assert_eq!(0, headers.len());
+    }
+
+    #[test]
+    fn remove_all() {
+        let id = HeaderName::from_static("Nats-Message-Id");
+        let sequence = HeaderName::from_static("Nats-Sequence");
+        let mut headers = [
+            (id.clone(), HeaderValue::from_static("abcd")),
+            (sequence.clone(), HeaderValue::from_static("1")),
+            (id.clone(), HeaderValue::from_static("1234")),
+        ]
+        .into_iter()
+        .collect::<HeaderMap>();
+
+        assert_eq!(
+            vec![HeaderValue::from_static("1")],
+            headers.remove_all(&sequence).collect::<Vec<_>>()
+        );
+        assert_eq!(2, headers.len());
+        assert_eq!(
+            vec![
+                HeaderValue::from_static("abcd"),
+                HeaderValue::from_static("1234")
+            ],
+            headers.remove_all(&id).collect::<Vec<_>>()
+        );
+        assert!(headers.remove_all(&id).next().is_none());
+        assert!(headers.is_empty());
+        assert_eq!(0, headers.len());
+    }
+
+    #[test]
+    fn entry() {
+        let name = HeaderName::from_static("Nats-Message-Id");
+        let mut headers = HeaderMap::new();
+
+        assert_eq!(
+            &HeaderValue::from_static("abcd"),
+            headers
+                .entry(name.clone())
+                .or_insert(HeaderValue::from_static("abcd"))
+        );
+        assert_eq!(
+            &HeaderValue::from_static("abcd"),
+            headers
+                .entry(name.clone())
+                .or_insert(HeaderValue::from_static("efgh"))
+        );
+        assert_eq!(1, headers.len());
+
+        // One -> Many
+        headers
+            .entry(name.clone())
+            .append(HeaderValue::from_static("1234"));
+        assert_eq!(2, headers.len());
+        assert_eq!(2, headers.get_all(&name).count());
+
+        // Many -> One
+        assert_eq!(
+            vec![
+                HeaderValue::from_static("abcd"),
+                HeaderValue::from_static("1234")
+            ],
+            headers
+                .entry(name.clone())
+                .insert(HeaderValue::from_static("5678"))
+        );
+        assert_eq!(1, headers.len());
+        assert_eq!(
+            [HeaderValue::from_static("5678")].as_slice(),
+            headers
+                .get_all(&name)
+                .cloned()
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+
+        assert!(headers
+            .entry(HeaderName::from_static("Nats-Sequence"))
+            .insert(HeaderValue::from_static("1"))
+            .is_empty());
+        assert_eq!(2, headers.len());
+    }
+
     fn verify_header_map(headers: &HeaderMap) {
         assert_eq!(
             [
@@ -1,4 +1,4 @@
-pub use self::map::HeaderMap;
+pub use self::map::{HeaderEntry, HeaderMap};
 pub use self::name::HeaderName;
 pub use self::value::HeaderValue;
 
@@ -2,6 +2,7 @@ use alloc::string::String;
 use core::{
     fmt::{self, Display},
     ops::Deref,
+    str::FromStr,
 };
 use unicase::UniCase;
 
@@ -16,8 +17,8 @@ use bytestring::ByteString;
 /// * The value has a length less than or equal to 64 [^2]
 /// * The value does not contain any whitespace characters or `:`
 ///
-/// `HeaderName` can be constructed from [`HeaderName::from_static`]
-/// or any of the `TryFrom` implementations.
+/// `HeaderName` can be constructed from [`HeaderName::from_static`],
+/// [`str::parse`] or any of the `TryFrom` implementations.
 ///
 /// [^1]: Because [`HeaderName::from_dangerous_value`] is safe to call,
 ///       unsafe code must not assume any of the above invariants.
@@ -34,6 +35,11 @@ impl HeaderName {
     pub const EXPECTED_LAST_MESSAGE_ID: Self = Self::new_internal("Nats-Expected-Last-Msg-Id");
     /// Have Jetstream assert that the last sequence ID matches this ID
     pub const EXPECTED_LAST_SEQUENCE: Self = Self::new_internal("Nats-Expected-Last-Sequence");
+    /// Have Jetstream assert that the sequence ID of the last message stored on the same subject matches this ID
+    ///
+    /// A value of `0` asserts that no message has been stored on the subject yet.
+    pub const EXPECTED_LAST_SUBJECT_SEQUENCE: Self =
+        Self::new_internal("Nats-Expected-Last-Subject-Sequence");
     /// Purge all prior messages in the stream (`all` value) or at the subject-level (`sub` value)
     pub const ROLLUP: Self = Self::new_internal("Nats-Rollup");
 
@@ -54,6 +60,11 @@ impl HeaderName {
     /// Size of the message payload in bytes for an headers-only message
     pub const MESSAGE_SIZE: Self = Self::new_internal("Nats-Msg-Size");
 
+    /// W3C Trace Context identifying the trace and the parent span of the message
+    pub const TRACEPARENT: Self = Self::new_internal("traceparent");
+    /// W3C Trace Context vendor-specific trace information
+    pub const TRACESTATE: Self = Self::new_internal("tracestate");
+
     /// Construct `HeaderName` from a static string
     ///
     /// # Panics
@@ -67,7 +78,7 @@ impl HeaderName {
     /// Construct a `HeaderName` from a string, without checking invariants
     ///
     /// This method bypasses invariants checks implemented by [`HeaderName::from_static`]
-    /// and all `TryFrom` implementations.
+    /// and all `FromStr` and `TryFrom` implementations.
     ///
     /// # Security
     ///
@@ -128,6 +139,18 @@ impl TryFrom<String> for HeaderName {
     }
 }
 
+impl_str_eq!(HeaderName, |this: &HeaderName, other: &str| this.0
+    == UniCase::new(other));
+
+impl FromStr for HeaderName {
+    type Err = HeaderNameValidateError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        validate_header_name(value)?;
+        Ok(Self::from_dangerous_value(ByteString::from(value)))
+    }
+}
+
 impl From<HeaderName> for ByteString {
     fn from(value: HeaderName) -> Self {
         value.0.into_inner()
@@ -191,7 +214,7 @@ fn validate_header_name(header_name: &str) -> Result<(), HeaderNameValidateError
 mod tests {
     use core::cmp::Ordering;
 
-    use super::HeaderName;
+    use super::{HeaderName, HeaderNameValidateError};
 
     #[test]
     fn eq() {
@@ -200,4 +223,24 @@ mod tests {
         assert_eq!(cased, lowercase);
         assert_eq!(cased.cmp(&lowercase), Ordering::Equal);
     }
+
+    #[test]
+    fn from_str() {
+        let name = "Nats-Message-Id".parse::<HeaderName>().unwrap();
+        assert_eq!("Nats-Message-Id", name.to_string());
+        assert_eq!(name, name.to_string().parse::<HeaderName>().unwrap());
+        assert!(matches!(
+            "Nats Message Id".parse::<HeaderName>(),
+            Err(HeaderNameValidateError::IllegalCharacter)
+        ));
+    }
+
+    #[test]
+    fn str_eq() {
+        // Compared case-insensitively
+        let name = HeaderName::from_static("Nats-Message-Id");
+        assert!(name == "nats-message-id");
+        assert!("NATS-MESSAGE-ID" == name);
+        assert!(name != "Nats-Message");
+    }
 }
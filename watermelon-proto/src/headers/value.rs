@@ -2,6 +2,7 @@ use alloc::string::String;
 use core::{
     fmt::{self, Display},
     ops::Deref,
+    str::FromStr,
 };
 
 use bytestring::ByteString;
@@ -13,10 +14,10 @@ use bytestring::ByteString;
 ///
 /// * The value is not empty
 /// * The value has a length less than or equal to 1024 [^2]
-/// * The value does not contain any whitespace characters
+/// * The value does not contain any `\r` or `\n` characters
 ///
-/// `HeaderValue` can be constructed from [`HeaderValue::from_static`]
-/// or any of the `TryFrom` implementations.
+/// `HeaderValue` can be constructed from [`HeaderValue::from_static`],
+/// [`str::parse`] or any of the `TryFrom` implementations.
 ///
 /// [^1]: Because [`HeaderValue::from_dangerous_value`] is safe to call,
 ///       unsafe code must not assume any of the above invariants.
@@ -38,7 +39,7 @@ impl HeaderValue {
     /// Construct a `HeaderValue` from a string, without checking invariants
     ///
     /// This method bypasses invariants checks implemented by [`HeaderValue::from_static`]
-    /// and all `TryFrom` implementations.
+    /// and all `FromStr` and `TryFrom` implementations.
     ///
     /// # Security
     ///
@@ -91,6 +92,18 @@ impl TryFrom<String> for HeaderValue {
     }
 }
 
+impl_str_eq!(HeaderValue, |this: &HeaderValue, other: &str| this.as_str()
+    == other);
+
+impl FromStr for HeaderValue {
+    type Err = HeaderValueValidateError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        validate_header_value(value)?;
+        Ok(Self::from_dangerous_value(ByteString::from(value)))
+    }
+}
+
 impl From<HeaderValue> for ByteString {
     fn from(value: HeaderValue) -> Self {
         value.0
@@ -126,8 +139,8 @@ pub enum HeaderValueValidateError {
     /// The value has a length greater than 64
     #[error("HeaderValue is too long")]
     TooLong,
-    /// The value contains an Unicode whitespace character
-    #[error("HeaderValue contained an illegal whitespace character")]
+    /// The value contains a `\r` or `\n` character
+    #[error("HeaderValue contained an illegal line break character")]
     IllegalCharacter,
 }
 
@@ -141,11 +154,59 @@ fn validate_header_value(header_value: &str) -> Result<(), HeaderValueValidateEr
         return Err(HeaderValueValidateError::TooLong);
     }
 
-    if header_value.chars().any(char::is_whitespace) {
-        // The theoretical security limit is just ` `, `\t`, `\r` and `\n`.
-        // Let's be more careful.
+    if header_value.contains(['\r', '\n']) {
+        // Any other character, including spaces, can't be used to inject header lines
         return Err(HeaderValueValidateError::IllegalCharacter);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use bytestring::ByteString;
+
+    use super::HeaderValue;
+
+    #[test]
+    fn valid_header_values() {
+        let values = [
+            "1",
+            "abcd",
+            "some text with spaces",
+            " leading and trailing spaces ",
+            "tab\tseparated",
+        ];
+        for value in values {
+            let header_value = HeaderValue::try_from(ByteString::from_static(value)).unwrap();
+            assert_eq!(value, header_value.as_str());
+        }
+    }
+
+    #[test]
+    fn invalid_header_values() {
+        let values = ["", "line\r\nbreak", "carriage\rreturn", "new\nline"];
+        for value in values {
+            assert!(
+                HeaderValue::try_from(ByteString::from_static(value)).is_err(),
+                "{value:?}"
+            );
+        }
+        assert!(HeaderValue::try_from("a".repeat(1025)).is_err());
+    }
+
+    #[test]
+    fn from_str() {
+        let value = "some text with spaces".parse::<HeaderValue>().unwrap();
+        assert_eq!("some text with spaces", value.to_string());
+        assert_eq!(value, value.to_string().parse::<HeaderValue>().unwrap());
+        assert!("line\r\nbreak".parse::<HeaderValue>().is_err());
+    }
+
+    #[test]
+    fn str_eq() {
+        let value = HeaderValue::from_static("abcd");
+        assert!(value == "abcd");
+        assert!(value != "ABCD");
+    }
+}
@@ -2,10 +2,55 @@
 
 extern crate alloc;
 
+/// Implement `PartialEq` between `$ty` and `str`, `&str` and `String`, in both directions
+///
+/// `$eq` is called with `&$ty` and `&str`.
+macro_rules! impl_str_eq {
+    ($ty:ty, $eq:expr) => {
+        impl PartialEq<str> for $ty {
+            fn eq(&self, other: &str) -> bool {
+                ($eq)(self, other)
+            }
+        }
+
+        impl PartialEq<&str> for $ty {
+            fn eq(&self, other: &&str) -> bool {
+                ($eq)(self, *other)
+            }
+        }
+
+        impl PartialEq<::alloc::string::String> for $ty {
+            fn eq(&self, other: &::alloc::string::String) -> bool {
+                ($eq)(self, other.as_str())
+            }
+        }
+
+        impl PartialEq<$ty> for str {
+            fn eq(&self, other: &$ty) -> bool {
+                ($eq)(other, self)
+            }
+        }
+
+        impl PartialEq<$ty> for &str {
+            fn eq(&self, other: &$ty) -> bool {
+                ($eq)(other, *self)
+            }
+        }
+
+        impl PartialEq<$ty> for ::alloc::string::String {
+            fn eq(&self, other: &$ty) -> bool {
+                ($eq)(other, self.as_str())
+            }
+        }
+    };
+}
+
 pub use self::connect::{Connect, NonStandardConnect};
-pub use self::message::{MessageBase, ServerMessage};
+pub use self::message::{Message, MessageBase, ServerMessage};
 pub use self::queue_group::QueueGroup;
-pub use self::server_addr::{Host, Protocol, ServerAddr, Transport};
+pub use self::server_addr::{
+    Host, Protocol, RedactedServerAddr, ServerAddr, ServerAddrBuilder, Transport,
+};
 pub use self::server_info::{NonStandardServerInfo, ServerInfo};
 pub use self::status_code::StatusCode;
 pub use self::subject::Subject;
@@ -29,7 +74,7 @@ mod util;
 pub mod error {
     pub use super::queue_group::QueueGroupValidateError;
     pub use super::server_addr::ServerAddrError;
-    pub use super::server_error::ServerError;
+    pub use super::server_error::{PermissionOperation, ServerError, ServerErrorKind};
     pub use super::status_code::StatusCodeError;
     pub use super::subject::SubjectValidateError;
     pub use super::util::ParseUintError;
@@ -1,6 +1,10 @@
 use bytes::Bytes;
 
-use crate::{headers::HeaderMap, subscription_id::SubscriptionId, StatusCode, Subject};
+use crate::{
+    headers::{HeaderMap, HeaderName},
+    subscription_id::SubscriptionId,
+    util, StatusCode, Subject,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MessageBase {
@@ -10,9 +14,188 @@ pub struct MessageBase {
     pub payload: Bytes,
 }
 
+/// A message, as seen by application code
+///
+/// Unlike [`ServerMessage`] it doesn't carry the subscription id and the status
+/// code, which are only relevant to the client, and exposes every field directly.
+/// Cloning it doesn't copy the payload, which is reference counted,
+/// but it does deep-clone the headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub subject: Subject,
+    pub reply: Option<Subject>,
+    pub headers: HeaderMap,
+    pub payload: Bytes,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ServerMessage {
     pub status_code: Option<StatusCode>,
     pub subscription_id: SubscriptionId,
     pub base: MessageBase,
 }
+
+impl MessageBase {
+    /// The length of the payload
+    #[must_use]
+    pub fn payload_len(&self) -> usize {
+        self.payload.len()
+    }
+
+    /// The length of the header block as it would be encoded when publishing this message
+    ///
+    /// This is `0` for messages without headers, which are published without a header block.
+    #[must_use]
+    pub fn encoded_header_len(&self) -> usize {
+        self.headers.encoded_len()
+    }
+}
+
+impl ServerMessage {
+    /// The length of the payload
+    #[must_use]
+    pub fn payload_len(&self) -> usize {
+        self.base.payload_len()
+    }
+
+    /// The length of the header block as it would be encoded when re-publishing this message
+    ///
+    /// The status code of the message isn't part of it, so this may be shorter than
+    /// the header block originally sent by the server. It is `0` for messages without headers.
+    /// Together with [`ServerMessage::payload_len`] it can be compared against
+    /// [`ServerInfo::max_payload`] before re-publishing the message.
+    ///
+    /// [`ServerInfo::max_payload`]: crate::ServerInfo::max_payload
+    #[must_use]
+    pub fn encoded_header_len(&self) -> usize {
+        self.base.encoded_header_len()
+    }
+
+    /// Returns `true` if the payload of this message was omitted by the server
+    ///
+    /// This is the case for messages delivered by Jetstream consumers configured
+    /// with `headers_only`, where the size of the original payload is instead
+    /// carried by the [`HeaderName::MESSAGE_SIZE`] header.
+    #[must_use]
+    pub fn is_headers_only(&self) -> bool {
+        self.base.payload.is_empty() && self.base.headers.contains_key(&HeaderName::MESSAGE_SIZE)
+    }
+
+    /// The size of the original payload, as declared by the [`HeaderName::MESSAGE_SIZE`] header
+    ///
+    /// Returns `None` if the header is missing or if it isn't a valid size.
+    #[must_use]
+    pub fn declared_payload_size(&self) -> Option<usize> {
+        let size = self.base.headers.get(&HeaderName::MESSAGE_SIZE)?;
+        util::parse_usize(size.as_str().as_bytes()).ok()
+    }
+}
+
+impl From<MessageBase> for Message {
+    fn from(value: MessageBase) -> Self {
+        let MessageBase {
+            subject,
+            reply_subject,
+            headers,
+            payload,
+        } = value;
+        Self {
+            subject,
+            reply: reply_subject,
+            headers,
+            payload,
+        }
+    }
+}
+
+impl From<ServerMessage> for Message {
+    fn from(value: ServerMessage) -> Self {
+        Self::from(value.base)
+    }
+}
+
+impl From<Message> for MessageBase {
+    fn from(value: Message) -> Self {
+        let Message {
+            subject,
+            reply,
+            headers,
+            payload,
+        } = value;
+        Self {
+            subject,
+            reply_subject: reply,
+            headers,
+            payload,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        headers::{HeaderMap, HeaderName, HeaderValue},
+        MessageBase, StatusCode, Subject, SubscriptionId,
+    };
+
+    use super::{Message, ServerMessage};
+
+    fn message(headers: HeaderMap, payload: Bytes) -> ServerMessage {
+        ServerMessage {
+            status_code: Some(StatusCode::OK),
+            subscription_id: SubscriptionId::from(1),
+            base: MessageBase {
+                subject: Subject::from_static("orders.eu.created"),
+                reply_subject: None,
+                headers,
+                payload,
+            },
+        }
+    }
+
+    #[test]
+    fn headers_only() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::MESSAGE_SIZE, HeaderValue::from_static("1024"));
+
+        let msg = message(headers.clone(), Bytes::new());
+        assert!(msg.is_headers_only());
+        assert_eq!(Some(1024), msg.declared_payload_size());
+
+        let msg = message(headers, Bytes::from_static(b"test"));
+        assert!(!msg.is_headers_only());
+
+        let msg = message(HeaderMap::new(), Bytes::new());
+        assert!(!msg.is_headers_only());
+        assert_eq!(None, msg.declared_payload_size());
+    }
+
+    #[test]
+    fn lengths() {
+        let msg = message(HeaderMap::new(), Bytes::from_static(b"Hello World!"));
+        assert_eq!(12, msg.payload_len());
+        assert_eq!(0, msg.encoded_header_len());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::MESSAGE_ID, HeaderValue::from_static("abcd"));
+        headers.insert(HeaderName::SEQUENCE, HeaderValue::from_static("1"));
+        let msg = message(headers, Bytes::from_static(b"Hello World!"));
+        assert_eq!(12, msg.payload_len());
+        // "NATS/1.0\r\nNats-Msg-Id: abcd\r\nNats-Sequence: 1\r\n\r\n"
+        assert_eq!(49, msg.encoded_header_len());
+    }
+
+    #[test]
+    fn into_message() {
+        let mut msg = message(HeaderMap::new(), Bytes::from_static(b"test"));
+        msg.base.reply_subject = Some(Subject::from_static("_INBOX.abcd"));
+
+        let message = Message::from(msg.clone());
+        assert_eq!(Subject::from_static("orders.eu.created"), message.subject);
+        assert_eq!(Some(Subject::from_static("_INBOX.abcd")), message.reply);
+        assert_eq!(Bytes::from_static(b"test"), message.payload);
+        assert_eq!(msg.base, MessageBase::from(message));
+    }
+}
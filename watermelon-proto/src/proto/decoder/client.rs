@@ -0,0 +1,630 @@
+use alloc::boxed::Box;
+use core::{mem, num::NonZeroU64};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytestring::ByteString;
+
+use crate::{
+    headers::HeaderMap,
+    proto::{error::DecoderError, ClientOp},
+    util, MessageBase, QueueGroup, Subject, SubscriptionId,
+};
+
+use super::{BytesLike, FrameDecoderError, MAX_HEAD_LEN};
+
+const INITIAL_READ_BUF_CAPACITY: usize = 64 * 1024;
+/// The maximum length of a `CONNECT` control line
+///
+/// Larger than [`MAX_HEAD_LEN`], since it may carry long credentials like JWTs.
+const MAX_CONNECT_LEN: usize = 1024 * 1024;
+
+/// A decoder for the operations sent by a client to the server
+///
+/// This is the counterpart of [`StreamDecoder`], useful for
+/// building proxies, recorders and test servers.
+/// Bytes are fed into it via [`ClientOpDecoder::read_buf`] or
+/// [`ClientOpDecoder::push_bytes`] and [`ClientOp`]s are pulled
+/// out of it via [`ClientOpDecoder::decode`].
+///
+/// ```
+/// use watermelon_proto::proto::{ClientOp, ClientOpDecoder};
+///
+/// let mut decoder = ClientOpDecoder::new();
+/// decoder.push_bytes(b"SUB orders.> 1\r\nPUB orders.new 5\r\nhel");
+/// assert!(matches!(decoder.decode(), Ok(Some(ClientOp::Subscribe { .. }))));
+/// assert!(matches!(decoder.decode(), Ok(None)));
+///
+/// decoder.push_bytes(b"lo\r\n");
+/// assert!(matches!(decoder.decode(), Ok(Some(ClientOp::Publish { .. }))));
+/// ```
+///
+/// [`StreamDecoder`]: super::StreamDecoder
+#[derive(Debug)]
+pub struct ClientOpDecoder {
+    read_buf: BytesMut,
+    status: ClientDecoderStatus,
+    retain_raw_headers: bool,
+}
+
+#[derive(Debug)]
+enum ClientDecoderStatus {
+    ControlLine {
+        last_bytes_read: usize,
+    },
+    Payload {
+        subject: Subject,
+        reply_subject: Option<Subject>,
+        header_len: Option<usize>,
+        total_len: usize,
+    },
+    Poisoned,
+}
+
+impl ClientOpDecoder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            read_buf: BytesMut::with_capacity(INITIAL_READ_BUF_CAPACITY),
+            status: ClientDecoderStatus::ControlLine { last_bytes_read: 0 },
+            retain_raw_headers: false,
+        }
+    }
+
+    /// Keep the header block of every decoded publish alongside its parsed headers
+    ///
+    /// See [`StreamDecoder::with_retain_raw_headers`].
+    ///
+    /// Default: `false`.
+    ///
+    /// [`StreamDecoder::with_retain_raw_headers`]: super::StreamDecoder::with_retain_raw_headers
+    #[must_use]
+    pub fn with_retain_raw_headers(mut self, retain_raw_headers: bool) -> Self {
+        self.retain_raw_headers = retain_raw_headers;
+        self
+    }
+
+    /// The number of bytes that have been read but not decoded yet
+    #[must_use]
+    pub fn buffered_len(&self) -> usize {
+        self.read_buf.len()
+    }
+
+    #[must_use]
+    pub fn read_buf(&mut self) -> &mut impl BufMut {
+        &mut self.read_buf
+    }
+
+    /// Append `bytes` to the buffer of bytes to be decoded
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        self.read_buf.extend_from_slice(bytes);
+    }
+
+    /// Decodes the next frame of bytes into a [`ClientOp`].
+    ///
+    /// A `None` variant is returned in case no progress is made,
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if a decoding error occurs.
+    pub fn decode(&mut self) -> Result<Option<ClientOp>, DecoderError> {
+        decode_client(
+            &mut self.status,
+            &mut self.read_buf,
+            self.retain_raw_headers,
+        )
+    }
+}
+
+impl Default for ClientOpDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes a frame of bytes into a [`ClientOp`].
+///
+/// This is the client side counterpart of [`decode_frame`].
+/// Use [`ClientOpDecoder`] for decoding a stream of bytes.
+///
+/// # Errors
+///
+/// It returns [`FrameDecoderError::IncompleteFrame`] if `frame` ends in the middle
+/// of an operation, in which case `frame` isn't modified, or [`FrameDecoderError::Decoder`]
+/// if the operation is invalid.
+///
+/// [`decode_frame`]: super::decode_frame
+pub fn decode_client_op(frame: &mut Bytes) -> Result<ClientOp, FrameDecoderError> {
+    let mut status = ClientDecoderStatus::ControlLine { last_bytes_read: 0 };
+    let mut remaining = frame.clone();
+    match decode_client(&mut status, &mut remaining, false) {
+        Ok(Some(client_op)) => {
+            *frame = remaining;
+            Ok(client_op)
+        }
+        Ok(None) => Err(FrameDecoderError::IncompleteFrame),
+        Err(err) => Err(FrameDecoderError::Decoder(err)),
+    }
+}
+
+fn decode_client(
+    status: &mut ClientDecoderStatus,
+    read_buf: &mut impl BytesLike,
+    retain_raw_headers: bool,
+) -> Result<Option<ClientOp>, DecoderError> {
+    loop {
+        match status {
+            ClientDecoderStatus::ControlLine { last_bytes_read } => {
+                if *last_bytes_read == read_buf.len() {
+                    // No progress has been made
+                    return Ok(None);
+                }
+
+                let Some(control_line_len) = memchr::memmem::find(read_buf, b"\r\n") else {
+                    // The last byte may be the `\r` of the terminating `\r\n`
+                    let min_control_line_len = read_buf.len().saturating_sub("\r".len());
+                    check_head_len(read_buf, min_control_line_len)?;
+
+                    *last_bytes_read = read_buf.len();
+                    return Ok(None);
+                };
+                check_head_len(read_buf, control_line_len)?;
+
+                let mut control_line = read_buf.split_to(control_line_len + "\r\n".len());
+                control_line.truncate(control_line.len() - 2);
+
+                // Operation names are case insensitive
+                let op = split_op(&mut control_line);
+                return if op.eq_ignore_ascii_case(b"PUB") {
+                    *status = decode_pub(control_line)?;
+                    continue;
+                } else if op.eq_ignore_ascii_case(b"HPUB") {
+                    *status = decode_hpub(control_line)?;
+                    continue;
+                } else if op.eq_ignore_ascii_case(b"SUB") {
+                    decode_sub(control_line).map(Some)
+                } else if op.eq_ignore_ascii_case(b"UNSUB") {
+                    decode_unsub(control_line).map(Some)
+                } else if op.eq_ignore_ascii_case(b"PING") && control_line.is_empty() {
+                    Ok(Some(ClientOp::Ping))
+                } else if op.eq_ignore_ascii_case(b"PONG") && control_line.is_empty() {
+                    Ok(Some(ClientOp::Pong))
+                } else if op.eq_ignore_ascii_case(b"CONNECT") {
+                    let connect = serde_json::from_slice(&control_line)
+                        .map_err(DecoderError::InvalidConnect)?;
+                    Ok(Some(ClientOp::Connect {
+                        connect: Box::new(connect),
+                    }))
+                } else {
+                    Err(DecoderError::InvalidCommand)
+                };
+            }
+            ClientDecoderStatus::Payload { total_len, .. } => {
+                if read_buf.len() < *total_len + "\r\n".len() {
+                    return Ok(None);
+                }
+
+                let ClientDecoderStatus::Payload {
+                    subject,
+                    reply_subject,
+                    header_len,
+                    total_len,
+                } = mem::replace(status, ClientDecoderStatus::Poisoned)
+                else {
+                    unreachable!()
+                };
+
+                let mut payload = read_buf.split_to(total_len);
+                read_buf.advance("\r\n".len());
+                let headers = match header_len {
+                    Some(header_len) => {
+                        super::decode_headers_inner(
+                            payload.split_to(header_len),
+                            retain_raw_headers,
+                        )?
+                        .1
+                    }
+                    None => HeaderMap::new(),
+                };
+
+                *status = ClientDecoderStatus::ControlLine { last_bytes_read: 0 };
+                return Ok(Some(ClientOp::Publish {
+                    message: MessageBase {
+                        subject,
+                        reply_subject,
+                        headers,
+                        payload,
+                    },
+                }));
+            }
+            ClientDecoderStatus::Poisoned => return Err(DecoderError::Poisoned),
+        }
+    }
+}
+
+/// Reject control lines longer than [`MAX_HEAD_LEN`]
+///
+/// `CONNECT` is instead limited to [`MAX_CONNECT_LEN`].
+fn check_head_len(read_buf: &impl BytesLike, control_line_len: usize) -> Result<(), DecoderError> {
+    let is_connect = read_buf
+        .get(.."CONNECT ".len())
+        .is_some_and(|head| head.eq_ignore_ascii_case(b"CONNECT "));
+    let max = if is_connect {
+        MAX_CONNECT_LEN
+    } else {
+        MAX_HEAD_LEN
+    };
+    if control_line_len > max {
+        Err(DecoderError::HeadTooLong {
+            len: control_line_len,
+            max,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Split the name of the operation off `control_line`, leaving only its arguments
+fn split_op(control_line: &mut Bytes) -> Bytes {
+    let is_space = |b: &u8| matches!(b, b' ' | b'\t');
+    let op_len = control_line
+        .iter()
+        .position(is_space)
+        .unwrap_or(control_line.len());
+    let op = control_line.split_to(op_len);
+    let spaces_len = control_line
+        .iter()
+        .position(|b| !is_space(b))
+        .unwrap_or(control_line.len());
+    control_line.advance(spaces_len);
+    op
+}
+
+fn decode_pub(control_line: Bytes) -> Result<ClientDecoderStatus, DecoderError> {
+    let mut chunks = util::split_spaces(control_line);
+    let (subject, reply_subject, total_len) = match (chunks.next(), chunks.next(), chunks.next()) {
+        (Some(subject), Some(reply_subject), Some(total_len)) => {
+            (subject, Some(reply_subject), total_len)
+        }
+        (Some(subject), Some(total_len), None) => (subject, None, total_len),
+        _ => return Err(DecoderError::InvalidPubArgsCount),
+    };
+    if chunks.next().is_some() {
+        return Err(DecoderError::InvalidPubArgsCount);
+    }
+
+    let total_len = util::parse_usize(&total_len).map_err(DecoderError::InvalidPayloadLength)?;
+    Ok(ClientDecoderStatus::Payload {
+        subject: decode_subject(subject)?,
+        reply_subject: reply_subject.map(decode_reply_subject).transpose()?,
+        header_len: None,
+        total_len,
+    })
+}
+
+fn decode_hpub(control_line: Bytes) -> Result<ClientDecoderStatus, DecoderError> {
+    let mut chunks = util::split_spaces(control_line);
+    let (subject, reply_subject, header_len, total_len) = match (
+        chunks.next(),
+        chunks.next(),
+        chunks.next(),
+        chunks.next(),
+        chunks.next(),
+    ) {
+        (Some(subject), Some(reply_subject), Some(header_len), Some(total_len), None) => {
+            (subject, Some(reply_subject), header_len, total_len)
+        }
+        (Some(subject), Some(header_len), Some(total_len), None, None) => {
+            (subject, None, header_len, total_len)
+        }
+        _ => return Err(DecoderError::InvalidHpubArgsCount),
+    };
+
+    let header_len = util::parse_usize(&header_len).map_err(DecoderError::InvalidHeaderLength)?;
+    let total_len = util::parse_usize(&total_len).map_err(DecoderError::InvalidPayloadLength)?;
+    if header_len > total_len {
+        return Err(DecoderError::InvalidTotalLength);
+    }
+
+    Ok(ClientDecoderStatus::Payload {
+        subject: decode_subject(subject)?,
+        reply_subject: reply_subject.map(decode_reply_subject).transpose()?,
+        header_len: Some(header_len),
+        total_len,
+    })
+}
+
+fn decode_sub(control_line: Bytes) -> Result<ClientOp, DecoderError> {
+    let mut chunks = util::split_spaces(control_line);
+    let (subject, queue_group, id) =
+        match (chunks.next(), chunks.next(), chunks.next(), chunks.next()) {
+            (Some(subject), Some(queue_group), Some(id), None) => (subject, Some(queue_group), id),
+            (Some(subject), Some(id), None, None) => (subject, None, id),
+            _ => return Err(DecoderError::InvalidSubArgsCount),
+        };
+
+    let queue_group = queue_group
+        .map(|queue_group| {
+            ByteString::try_from(queue_group).map_err(|_| DecoderError::QueueGroupInvalidUtf8)
+        })
+        .transpose()?
+        .map(QueueGroup::from_dangerous_value);
+    Ok(ClientOp::Subscribe {
+        id: SubscriptionId::from_ascii_bytes(&id).map_err(DecoderError::SubscriptionId)?,
+        subject: decode_subject(subject)?,
+        queue_group,
+    })
+}
+
+fn decode_unsub(control_line: Bytes) -> Result<ClientOp, DecoderError> {
+    let mut chunks = util::split_spaces(control_line);
+    let (Some(id), max_messages, None) = (chunks.next(), chunks.next(), chunks.next()) else {
+        return Err(DecoderError::InvalidUnsubArgsCount);
+    };
+
+    let max_messages = max_messages
+        .map(|max_messages| util::parse_u64(&max_messages))
+        .transpose()
+        .map_err(DecoderError::InvalidMaxMessages)?
+        .and_then(NonZeroU64::new);
+    Ok(ClientOp::Unsubscribe {
+        id: SubscriptionId::from_ascii_bytes(&id).map_err(DecoderError::SubscriptionId)?,
+        max_messages,
+    })
+}
+
+fn decode_subject(subject: Bytes) -> Result<Subject, DecoderError> {
+    ByteString::try_from(subject)
+        .map(Subject::from_dangerous_value)
+        .map_err(|_| DecoderError::SubjectInvalidUtf8)
+}
+
+fn decode_reply_subject(reply_subject: Bytes) -> Result<Subject, DecoderError> {
+    ByteString::try_from(reply_subject)
+        .map(Subject::from_dangerous_value)
+        .map_err(|_| DecoderError::ReplySubjectInvalidUtf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU64;
+
+    use bytes::{Buf as _, Bytes};
+
+    use crate::{
+        headers::{HeaderMap, HeaderName, HeaderValue},
+        proto::{
+            error::{DecoderError, FrameDecoderError},
+            ClientOp, StreamEncoder,
+        },
+        MessageBase, QueueGroup, Subject, SubscriptionId,
+    };
+
+    use super::{decode_client_op, ClientOpDecoder, MAX_CONNECT_LEN, MAX_HEAD_LEN};
+
+    fn roundtrip(op: &ClientOp) -> ClientOp {
+        let mut encoder = StreamEncoder::new();
+        encoder.enqueue_write_op(op);
+        let bytes = encoder.copy_to_bytes(encoder.remaining());
+
+        let mut decoder = ClientOpDecoder::new();
+        // Feed the bytes one at a time to exercise partial reads
+        let mut op = None;
+        for byte in &bytes {
+            assert!(op.is_none());
+            decoder.push_bytes(&[*byte]);
+            op = decoder.decode().unwrap();
+        }
+        assert_eq!(0, decoder.buffered_len());
+        op.unwrap()
+    }
+
+    #[test]
+    fn decode_publish() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("Nats-Msg-Id"),
+            HeaderValue::from_static("abcd"),
+        );
+
+        for (reply_subject, headers) in [
+            (None, HeaderMap::new()),
+            (Some(Subject::from_static("_INBOX.1234")), HeaderMap::new()),
+            (None, headers.clone()),
+            (Some(Subject::from_static("_INBOX.1234")), headers),
+        ] {
+            let message = MessageBase {
+                subject: Subject::from_static("orders.new"),
+                reply_subject,
+                headers,
+                payload: Bytes::from_static(b"Hello World!"),
+            };
+            let ClientOp::Publish { message: decoded } = roundtrip(&ClientOp::Publish {
+                message: message.clone(),
+            }) else {
+                unreachable!()
+            };
+            assert_eq!(message, decoded);
+        }
+    }
+
+    #[test]
+    fn decode_subscribe_unsubscribe() {
+        let ClientOp::Subscribe {
+            id,
+            subject,
+            queue_group,
+        } = roundtrip(&ClientOp::Subscribe {
+            id: SubscriptionId::from(7),
+            subject: Subject::from_static("orders.>"),
+            queue_group: Some(QueueGroup::from_static("workers")),
+        })
+        else {
+            unreachable!()
+        };
+        assert_eq!(SubscriptionId::from(7), id);
+        assert_eq!(Subject::from_static("orders.>"), subject);
+        assert_eq!(Some(QueueGroup::from_static("workers")), queue_group);
+
+        let ClientOp::Unsubscribe { id, max_messages } = roundtrip(&ClientOp::Unsubscribe {
+            id: SubscriptionId::from(7),
+            max_messages: NonZeroU64::new(10),
+        }) else {
+            unreachable!()
+        };
+        assert_eq!(SubscriptionId::from(7), id);
+        assert_eq!(NonZeroU64::new(10), max_messages);
+    }
+
+    #[test]
+    fn decode_connect_ping_pong() {
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(
+            b"CONNECT {\"verbose\":false,\"pedantic\":false,\"name\":\"proxy\",\"lang\":\"go\",\"version\":\"1.0.0\"}\r\nPING\r\nPONG\r\n",
+        );
+        let Some(ClientOp::Connect { connect }) = decoder.decode().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(Some("proxy"), connect.client_name.as_deref());
+        assert_eq!("go", connect.client_lang);
+        assert!(connect.echo);
+        assert!(!connect.supports_headers);
+        assert!(matches!(decoder.decode(), Ok(Some(ClientOp::Ping))));
+        assert!(matches!(decoder.decode(), Ok(Some(ClientOp::Pong))));
+        assert!(matches!(decoder.decode(), Ok(None)));
+    }
+
+    #[test]
+    fn decode_lowercase() {
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(
+            b"connect {\"verbose\":false,\"pedantic\":false,\"lang\":\"go\",\"version\":\"1.0.0\"}\r\nsub orders.> 1\r\nPub orders.new 5\r\nhello\r\nunsub 1\r\nping\r\npong\r\n",
+        );
+        assert!(matches!(
+            decoder.decode(),
+            Ok(Some(ClientOp::Connect { .. }))
+        ));
+        assert!(matches!(
+            decoder.decode(),
+            Ok(Some(ClientOp::Subscribe { .. }))
+        ));
+        let Some(ClientOp::Publish { message }) = decoder.decode().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(Subject::from_static("orders.new"), message.subject);
+        assert_eq!(Bytes::from_static(b"hello"), message.payload);
+        assert!(matches!(
+            decoder.decode(),
+            Ok(Some(ClientOp::Unsubscribe { .. }))
+        ));
+        assert!(matches!(decoder.decode(), Ok(Some(ClientOp::Ping))));
+        assert!(matches!(decoder.decode(), Ok(Some(ClientOp::Pong))));
+        assert!(matches!(decoder.decode(), Ok(None)));
+    }
+
+    #[test]
+    fn decode_trailing_garbage() {
+        for line in [
+            &b"PINGX\r\n"[..],
+            b"PING X\r\n",
+            b"PONGPONG\r\n",
+            b"SUBX orders.> 1\r\n",
+        ] {
+            let mut decoder = ClientOpDecoder::new();
+            decoder.push_bytes(line);
+            assert!(matches!(
+                decoder.decode(),
+                Err(DecoderError::InvalidCommand)
+            ));
+        }
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(b"SUB orders.> 1 2 3\r\n");
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::InvalidSubArgsCount)
+        ));
+
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(b"MSG orders.new 1 5\r\n");
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::InvalidCommand)
+        ));
+    }
+
+    #[test]
+    fn decode_head_too_long() {
+        // Rejected before the end of the control line is received
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(b"PUB ");
+        decoder.push_bytes(&[b'a'; MAX_HEAD_LEN]);
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::HeadTooLong { len, max: MAX_HEAD_LEN }) if len == MAX_HEAD_LEN + "PUB ".len() - 1
+        ));
+
+        // Only the control line counts, not the rest of the buffer
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(b"PUB orders.new 5\r\nhello\r\n");
+        decoder.push_bytes(&[b'a'; MAX_HEAD_LEN]);
+        assert!(matches!(
+            decoder.decode(),
+            Ok(Some(ClientOp::Publish { .. }))
+        ));
+
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(b"SUB ");
+        decoder.push_bytes(&[b'a'; MAX_HEAD_LEN]);
+        decoder.push_bytes(b" 1\r\n");
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::HeadTooLong { len, max: MAX_HEAD_LEN }) if len == MAX_HEAD_LEN + "SUB  1".len()
+        ));
+
+        // CONNECT gets a larger, but still bounded, limit
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(b"CONNECT ");
+        decoder.push_bytes(&[b'a'; MAX_HEAD_LEN]);
+        assert!(matches!(decoder.decode(), Ok(None)));
+        decoder.push_bytes(&vec![b'a'; MAX_CONNECT_LEN]);
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::HeadTooLong {
+                max: MAX_CONNECT_LEN,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_empty_header_value() {
+        // Rejected as an invalid header value instead of panicking
+        let mut decoder = ClientOpDecoder::new();
+        decoder.push_bytes(b"HPUB orders.new 16 21\r\nNATS/1.0\r\nA:\r\n\r\nhello\r\n");
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::HeaderValue(_))
+        ));
+    }
+
+    #[test]
+    fn decode_frame() {
+        let mut frame = Bytes::from_static(b"PUB orders.new 5\r\nhello\r\nPING\r\n");
+        let ClientOp::Publish { message } = decode_client_op(&mut frame).unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(Subject::from_static("orders.new"), message.subject);
+        assert_eq!(Bytes::from_static(b"hello"), message.payload);
+        assert!(matches!(decode_client_op(&mut frame), Ok(ClientOp::Ping)));
+        assert!(frame.is_empty());
+
+        let mut frame = Bytes::from_static(b"PUB orders.new 5\r\nhel");
+        assert!(matches!(
+            decode_client_op(&mut frame),
+            Err(FrameDecoderError::IncompleteFrame)
+        ));
+    }
+}
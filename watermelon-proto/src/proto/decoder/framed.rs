@@ -2,26 +2,103 @@ use bytes::Bytes;
 
 use crate::proto::ServerOp;
 
-use super::{DecoderError, DecoderStatus};
+use super::{DecoderError, DecoderStatus, MAX_HEAD_LEN};
 
 /// Decodes a frame of bytes into a [`ServerOp`].
 ///
+/// Meant for transports which take care of framing on their own, like
+/// websocket messages or QUIC datagrams, where `frame` is expected to contain
+/// one or more complete operations. Every call decodes the first operation and
+/// advances `frame` past it, so it can be called until `frame` is empty.
+/// Use [`StreamDecoder`] for decoding a stream of bytes instead.
+///
+/// ```
+/// use bytes::Bytes;
+/// use watermelon_proto::proto::{decode_frame, error::FrameDecoderError, ServerOp};
+///
+/// let mut frame = Bytes::from_static(b"PING\r\nMSG hello 1 5\r\nhello\r\n");
+/// assert!(matches!(decode_frame(&mut frame), Ok(ServerOp::Ping)));
+/// assert!(matches!(decode_frame(&mut frame), Ok(ServerOp::Message { .. })));
+/// assert!(frame.is_empty());
+///
+/// let mut frame = Bytes::from_static(b"MSG hello 1 5\r\nhel");
+/// assert!(matches!(
+///     decode_frame(&mut frame),
+///     Err(FrameDecoderError::IncompleteFrame)
+/// ));
+/// // Incomplete frames are left untouched
+/// assert_eq!(&b"MSG hello 1 5\r\nhel"[..], frame);
+/// ```
+///
 /// # Errors
 ///
-/// It returns an error in case the frame is incomplete or if a decoding error occurs.
+/// It returns [`FrameDecoderError::IncompleteFrame`] if `frame` ends in the middle
+/// of an operation, in which case `frame` isn't modified, or [`FrameDecoderError::Decoder`]
+/// if the operation is invalid.
+///
+/// [`StreamDecoder`]: super::StreamDecoder
 pub fn decode_frame(frame: &mut Bytes) -> Result<ServerOp, FrameDecoderError> {
     let mut status = DecoderStatus::ControlLine { last_bytes_read: 0 };
-    match super::decode(&mut status, frame) {
-        Ok(Some(server_op)) => Ok(server_op),
+    // Cheap clone, so that nothing is consumed if the frame is incomplete
+    let mut remaining = frame.clone();
+    match super::decode(&mut status, &mut remaining, MAX_HEAD_LEN, false) {
+        Ok(Some(server_op)) => {
+            *frame = remaining;
+            Ok(server_op)
+        }
         Ok(None) => Err(FrameDecoderError::IncompleteFrame),
         Err(err) => Err(FrameDecoderError::Decoder(err)),
     }
 }
 
+/// An error encountered while decoding a frame
 #[derive(Debug, thiserror::Error)]
 pub enum FrameDecoderError {
+    /// The frame ends before the operation is complete
     #[error("incomplete frame")]
     IncompleteFrame,
+    /// The operation is invalid
     #[error("decoder error")]
     Decoder(#[source] DecoderError),
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use claims::assert_matches;
+
+    use crate::proto::ServerOp;
+
+    use super::{decode_frame, FrameDecoderError};
+
+    #[test]
+    fn incomplete() {
+        for truncated in [
+            &b"PIN"[..],
+            b"MSG hello 1 5\r\n",
+            b"MSG hello 1 5\r\nhello",
+            b"HMSG hello 1 22 27\r\nNATS/1.0\r\n",
+        ] {
+            let mut frame = Bytes::from_static(truncated);
+            assert_matches!(
+                decode_frame(&mut frame),
+                Err(FrameDecoderError::IncompleteFrame)
+            );
+            assert_eq!(truncated, frame);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let mut frame = Bytes::from_static(b"HELLO\r\n");
+        assert_matches!(decode_frame(&mut frame), Err(FrameDecoderError::Decoder(_)));
+    }
+
+    #[test]
+    fn multiple_ops() {
+        let mut frame = Bytes::from_static(b"+OK\r\nPONG\r\n");
+        assert_matches!(decode_frame(&mut frame), Ok(ServerOp::Success));
+        assert_matches!(decode_frame(&mut frame), Ok(ServerOp::Pong));
+        assert!(frame.is_empty());
+    }
+}
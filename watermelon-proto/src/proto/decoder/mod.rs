@@ -14,15 +14,20 @@ use crate::{
     MessageBase, ServerMessage, StatusCode, Subject, SubscriptionId,
 };
 
+pub use self::client::{decode_client_op, ClientOpDecoder};
 pub use self::framed::{decode_frame, FrameDecoderError};
 pub use self::stream::StreamDecoder;
 
 use super::ServerOp;
 
+mod client;
 mod framed;
 mod stream;
 
+/// The default maximum length of a control line
 const MAX_HEAD_LEN: usize = 16 * 1024;
+/// How many times longer than the maximum length of a control line `INFO` may be
+const INFO_HEAD_LEN_FACTOR: usize = 16;
 
 #[derive(Debug)]
 pub(super) enum DecoderStatus {
@@ -63,6 +68,8 @@ impl BytesLike for BytesMut {}
 pub(super) fn decode(
     status: &mut DecoderStatus,
     read_buf: &mut impl BytesLike,
+    max_head_len: usize,
+    retain_raw_headers: bool,
 ) -> Result<Option<ServerOp>, DecoderError> {
     loop {
         match status {
@@ -73,9 +80,14 @@ pub(super) fn decode(
                 }
 
                 let Some(control_line_len) = memchr::memmem::find(read_buf, b"\r\n") else {
+                    // The last byte may be the `\r` of the terminating `\r\n`
+                    let min_control_line_len = read_buf.len().saturating_sub("\r".len());
+                    check_head_len(read_buf, min_control_line_len, max_head_len)?;
+
                     *last_bytes_read = read_buf.len();
                     return Ok(None);
                 };
+                check_head_len(read_buf, control_line_len, max_head_len)?;
 
                 let mut control_line = read_buf.split_to(control_line_len + "\r\n".len());
                 control_line.truncate(control_line.len() - 2);
@@ -107,10 +119,6 @@ pub(super) fn decode(
                 } else if let Some(info) = control_line.strip_prefix(b"INFO ") {
                     let info = serde_json::from_slice(info).map_err(DecoderError::InvalidInfo)?;
                     Ok(Some(ServerOp::Info { info }))
-                } else if read_buf.len() > MAX_HEAD_LEN {
-                    Err(DecoderError::HeadTooLong {
-                        len: read_buf.len(),
-                    })
                 } else {
                     Err(DecoderError::InvalidCommand)
                 };
@@ -120,7 +128,7 @@ pub(super) fn decode(
                     return Ok(None);
                 }
 
-                decode_headers(read_buf, status)?;
+                decode_hmsg_headers(read_buf, status, retain_raw_headers)?;
             }
             DecoderStatus::Payload { payload_len, .. } => {
                 if read_buf.len() < *payload_len + "\r\n".len() {
@@ -263,9 +271,10 @@ fn decode_hmsg(mut control_line: Bytes) -> Result<DecoderStatus, DecoderError> {
     })
 }
 
-fn decode_headers(
+fn decode_hmsg_headers(
     read_buf: &mut impl BytesLike,
     status: &mut DecoderStatus,
+    retain_raw_headers: bool,
 ) -> Result<(), DecoderError> {
     let DecoderStatus::Headers {
         subscription_id,
@@ -278,26 +287,90 @@ fn decode_headers(
         unreachable!()
     };
 
-    let header = read_buf.split_to(header_len);
+    let (status_code, headers) =
+        decode_headers_inner(read_buf.split_to(header_len), retain_raw_headers)?;
+
+    *status = DecoderStatus::Payload {
+        subscription_id,
+        subject,
+        reply_subject,
+        status_code,
+        headers,
+        payload_len,
+    };
+    Ok(())
+}
+
+/// Reject control lines longer than `max_head_len`
+///
+/// `INFO` is instead limited to [`INFO_HEAD_LEN_FACTOR`] times `max_head_len`,
+/// since it carries the topology of the whole cluster.
+fn check_head_len(
+    read_buf: &impl BytesLike,
+    control_line_len: usize,
+    max_head_len: usize,
+) -> Result<(), DecoderError> {
+    let max = if read_buf.starts_with(b"INFO ") {
+        max_head_len.saturating_mul(INFO_HEAD_LEN_FACTOR)
+    } else {
+        max_head_len
+    };
+    if control_line_len > max {
+        Err(DecoderError::HeadTooLong {
+            len: control_line_len,
+            max,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Decode a NATS headers block
+///
+/// `header` must start with the `NATS/1.0` head line, optionally followed by
+/// a status code, and then contain a `Name: Value` pair for every line.
+/// This is the same format used by the `HMSG` command and by the NATS
+/// Jetstream API when returning stored messages.
+///
+/// # Errors
+///
+/// It returns an error if the head is missing or invalid, or if any of the
+/// header lines can't be parsed.
+pub fn decode_headers(header: Bytes) -> Result<(Option<StatusCode>, HeaderMap), DecoderError> {
+    decode_headers_inner(header, false)
+}
+
+/// Like [`decode_headers`], optionally retaining `header` in the returned map
+///
+/// When `retain_raw` is `true` and the head doesn't carry a status code,
+/// the map keeps `header` so that it can be written back verbatim.
+/// See [`HeaderMap::raw`].
+pub(crate) fn decode_headers_inner(
+    header: Bytes,
+    retain_raw: bool,
+) -> Result<(Option<StatusCode>, HeaderMap), DecoderError> {
+    let raw = header.clone();
     let mut lines = util::lines_iter(header);
     let head = lines.next().ok_or(DecoderError::MissingHead)?;
     let head = head
         .strip_prefix(b"NATS/1.0")
         .ok_or(DecoderError::InvalidHead)?;
+    // Republishing the message must not republish anything following the version
+    let is_plain_head = head.is_empty();
     let status_code = if head.len() >= 4 {
         Some(StatusCode::from_ascii_bytes(&head[1..4]).map_err(DecoderError::StatusCode)?)
     } else {
         None
     };
 
-    let headers = lines
+    let mut headers: HeaderMap = lines
         .filter(|line| !line.is_empty())
         .map(|mut line| {
             let i = memchr::memchr(b':', &line).ok_or(DecoderError::InvalidHeaderLine)?;
 
             let name = line.split_to(i);
             line.advance(":".len());
-            if line[0].is_ascii_whitespace() {
+            if line.first().is_some_and(u8::is_ascii_whitespace) {
                 // The fact that this is allowed sounds like BS to me
                 line.advance(1);
             }
@@ -314,38 +387,45 @@ fn decode_headers(
             Ok((name, value))
         })
         .collect::<Result<_, _>>()?;
+    if retain_raw && is_plain_head {
+        headers.set_raw(raw);
+    }
 
-    *status = DecoderStatus::Payload {
-        subscription_id,
-        subject,
-        reply_subject,
-        status_code,
-        headers,
-        payload_len,
-    };
-    Ok(())
+    Ok((status_code, headers))
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum DecoderError {
-    #[error("The head exceeded the maximum head length (len {len} maximum {MAX_HEAD_LEN}")]
-    HeadTooLong { len: usize },
+    #[error("The head exceeded the maximum head length (len {len} maximum {max})")]
+    HeadTooLong { len: usize, max: usize },
     #[error("Invalid command")]
     InvalidCommand,
     #[error("MSG command has an unexpected number of arguments")]
     InvalidMsgArgsCount,
     #[error("HMSG command has an unexpected number of arguments")]
     InvalidHmsgArgsCount,
+    #[error("PUB command has an unexpected number of arguments")]
+    InvalidPubArgsCount,
+    #[error("HPUB command has an unexpected number of arguments")]
+    InvalidHpubArgsCount,
+    #[error("SUB command has an unexpected number of arguments")]
+    InvalidSubArgsCount,
+    #[error("UNSUB command has an unexpected number of arguments")]
+    InvalidUnsubArgsCount,
     #[error("The subject isn't valid utf-8")]
     SubjectInvalidUtf8,
     #[error("The reply subject isn't valid utf-8")]
     ReplySubjectInvalidUtf8,
+    #[error("The queue group isn't valid utf-8")]
+    QueueGroupInvalidUtf8,
     #[error("Couldn't parse the Subscription ID")]
     SubscriptionId(#[source] ParseUintError),
     #[error("Couldn't parse the length of the header")]
     InvalidHeaderLength(#[source] ParseUintError),
     #[error("Couldn't parse the length of the payload")]
     InvalidPayloadLength(#[source] ParseUintError),
+    #[error("Couldn't parse the maximum number of messages")]
+    InvalidMaxMessages(#[source] ParseUintError),
     #[error("The total length is greater than the header length")]
     InvalidTotalLength,
     #[error("HMSG is missing head")]
@@ -366,6 +446,8 @@ pub enum DecoderError {
     HeaderValue(#[source] HeaderValueValidateError),
     #[error("INFO command JSON payload couldn't be deserialized")]
     InvalidInfo(#[source] serde_json::Error),
+    #[error("CONNECT command JSON payload couldn't be deserialized")]
+    InvalidConnect(#[source] serde_json::Error),
     #[error("-ERR command message couldn't be deserialized")]
     InvalidErrorMessage,
     #[error("The decoder was poisoned")]
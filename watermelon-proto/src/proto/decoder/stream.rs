@@ -2,14 +2,42 @@ use bytes::{BufMut, BytesMut};
 
 use crate::proto::{error::DecoderError, ServerOp};
 
-use super::DecoderStatus;
+use super::{DecoderStatus, MAX_HEAD_LEN};
 
 const INITIAL_READ_BUF_CAPACITY: usize = 64 * 1024;
 
+/// A decoder for the operations sent by the server to a client
+///
+/// Bytes read from the connection are fed into it, either by writing them
+/// directly into [`StreamDecoder::read_buf`] or by copying them via
+/// [`StreamDecoder::push_bytes`]. [`StreamDecoder::decode`] must then be called
+/// until it returns `None`, at which point more bytes need to be read.
+///
+/// ```
+/// use bytes::BufMut as _;
+/// use watermelon_proto::proto::{ServerOp, StreamDecoder};
+///
+/// let mut decoder = StreamDecoder::new();
+/// let mut ops = Vec::new();
+/// for chunk in [&b"PING\r\nMSG hello 1 5\r\nhel"[..], b"lo\r\n"] {
+///     decoder.read_buf().put_slice(chunk);
+///     while let Some(op) = decoder.decode().unwrap() {
+///         ops.push(op);
+///     }
+/// }
+/// assert!(matches!(ops[0], ServerOp::Ping));
+/// assert!(matches!(ops[1], ServerOp::Message { .. }));
+/// ```
+///
+/// See [`ClientOpDecoder`] for decoding the operations sent by a client.
+///
+/// [`ClientOpDecoder`]: super::ClientOpDecoder
 #[derive(Debug)]
 pub struct StreamDecoder {
     read_buf: BytesMut,
     status: DecoderStatus,
+    max_head_len: usize,
+    retain_raw_headers: bool,
 }
 
 impl StreamDecoder {
@@ -18,14 +46,86 @@ impl StreamDecoder {
         Self {
             read_buf: BytesMut::with_capacity(INITIAL_READ_BUF_CAPACITY),
             status: DecoderStatus::ControlLine { last_bytes_read: 0 },
+            max_head_len: MAX_HEAD_LEN,
+            retain_raw_headers: false,
         }
     }
 
+    /// Set the maximum length of a control line, like the head of a `MSG` or `HMSG`
+    ///
+    /// Longer control lines are rejected with [`DecoderError::HeadTooLong`],
+    /// preventing a misbehaving server from having the decoder buffer
+    /// an unbounded amount of bytes. `INFO`, which carries the topology
+    /// of the whole cluster, may be up to 16 times longer.
+    ///
+    /// Default: 16 KiB.
+    #[must_use]
+    pub fn with_max_head_len(mut self, max_head_len: usize) -> Self {
+        self.set_max_head_len(max_head_len);
+        self
+    }
+
+    /// Set the maximum length of a control line
+    ///
+    /// See [`StreamDecoder::with_max_head_len`].
+    pub fn set_max_head_len(&mut self, max_head_len: usize) {
+        self.max_head_len = max_head_len;
+    }
+
+    /// Keep the header block of every decoded message alongside its parsed headers
+    ///
+    /// Allows the headers to be written back verbatim when the message is
+    /// published again, as done when relaying messages, at the cost of keeping
+    /// the block alive for as long as the headers are. See [`HeaderMap::raw`].
+    ///
+    /// Default: `false`.
+    ///
+    /// [`HeaderMap::raw`]: crate::headers::HeaderMap::raw
+    #[must_use]
+    pub fn with_retain_raw_headers(mut self, retain_raw_headers: bool) -> Self {
+        self.set_retain_raw_headers(retain_raw_headers);
+        self
+    }
+
+    /// Keep the header block of every decoded message alongside its parsed headers
+    ///
+    /// See [`StreamDecoder::with_retain_raw_headers`].
+    pub fn set_retain_raw_headers(&mut self, retain_raw_headers: bool) {
+        self.retain_raw_headers = retain_raw_headers;
+    }
+
+    /// The number of bytes that have been read but not decoded yet
+    #[must_use]
+    pub fn buffered_len(&self) -> usize {
+        self.read_buf.len()
+    }
+
     #[must_use]
     pub fn read_buf(&mut self) -> &mut impl BufMut {
         &mut self.read_buf
     }
 
+    /// Append `bytes` to the buffer of bytes to be decoded
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        self.read_buf.extend_from_slice(bytes);
+    }
+
+    /// Discard every buffered byte and go back to expecting a control line
+    ///
+    /// This recovers a decoder which returned an error, including one
+    /// [`DecoderError::Poisoned`] by a previous failure, without having
+    /// to allocate a new one.
+    ///
+    /// The bytes which were buffered but not decoded yet are lost, so this
+    /// is only safe when the rest of the current frame is discarded too:
+    /// either because the connection is being replaced, or because the
+    /// caller accepts that the next bytes read from the connection may
+    /// start in the middle of an operation and fail to decode again.
+    pub fn reset(&mut self) {
+        self.read_buf.clear();
+        self.status = DecoderStatus::ControlLine { last_bytes_read: 0 };
+    }
+
     /// Decodes the next frame of bytes into a [`ServerOp`].
     ///
     /// A `None` variant is returned in case no progress is made,
@@ -34,7 +134,12 @@ impl StreamDecoder {
     ///
     /// It returns an error if a decoding error occurs.
     pub fn decode(&mut self) -> Result<Option<ServerOp>, DecoderError> {
-        super::decode(&mut self.status, &mut self.read_buf)
+        super::decode(
+            &mut self.status,
+            &mut self.read_buf,
+            self.max_head_len,
+            self.retain_raw_headers,
+        )
     }
 }
 
@@ -47,17 +152,18 @@ impl Default for StreamDecoder {
 #[cfg(test)]
 mod tests {
     use bytes::{BufMut as _, Bytes};
+    use bytestring::ByteString;
     use claims::assert_ok_eq;
 
     use crate::{
         error::ServerError,
-        headers::HeaderMap,
+        headers::{HeaderMap, HeaderName, HeaderValue},
         message::{MessageBase, ServerMessage},
         proto::server::ServerOp,
         Subject,
     };
 
-    use super::StreamDecoder;
+    use super::{super::DecoderError, StreamDecoder};
 
     #[test]
     fn decode_ping() {
@@ -75,6 +181,16 @@ mod tests {
         assert_ok_eq!(decoder.decode(), None);
     }
 
+    #[test]
+    fn buffered_len() {
+        let mut decoder = StreamDecoder::new();
+        assert_eq!(0, decoder.buffered_len());
+        decoder.read_buf().put(Bytes::from_static(b"PING\r\nPO"));
+        assert_eq!(8, decoder.buffered_len());
+        assert_ok_eq!(decoder.decode(), Some(ServerOp::Ping));
+        assert_eq!(2, decoder.buffered_len());
+    }
+
     #[test]
     fn decode_ok() {
         let mut decoder = StreamDecoder::new();
@@ -92,7 +208,9 @@ mod tests {
         assert_ok_eq!(
             decoder.decode(),
             Some(ServerOp::Error {
-                error: ServerError::AuthorizationViolation
+                error: ServerError::AuthorizationViolation {
+                    raw_message: ByteString::from_static("Authorization Violation")
+                }
             })
         );
         assert_ok_eq!(decoder.decode(), None);
@@ -122,4 +240,131 @@ mod tests {
         );
         assert_ok_eq!(decoder.decode(), None);
     }
+
+    #[test]
+    fn retain_raw_headers() {
+        let raw = Bytes::from_static(b"NATS/1.0\r\nNats-Message-Id:abcd\r\n\r\n");
+        for retain_raw_headers in [false, true] {
+            let mut decoder = StreamDecoder::new().with_retain_raw_headers(retain_raw_headers);
+            decoder.push_bytes(b"HMSG hello 1 34 36\r\n");
+            decoder.push_bytes(&raw);
+            decoder.push_bytes(b"Hi\r\n");
+
+            let Ok(Some(ServerOp::Message { message })) = decoder.decode() else {
+                unreachable!()
+            };
+            assert_eq!(retain_raw_headers, message.base.headers.raw() == Some(&raw));
+        }
+    }
+
+    #[test]
+    fn decode_hmsg_with_spaces() {
+        let mut decoder = StreamDecoder::new();
+        decoder.read_buf().put(Bytes::from_static(
+            b"HMSG hello.world 1 48 50\r\nNATS/1.0\r\nDescription: some text with spaces\r\n\r\nHi\r\n",
+        ));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("Description"),
+            HeaderValue::from_static("some text with spaces"),
+        );
+        assert_ok_eq!(
+            decoder.decode(),
+            Some(ServerOp::Message {
+                message: ServerMessage {
+                    status_code: None,
+                    subscription_id: 1.into(),
+                    base: MessageBase {
+                        subject: Subject::from_static("hello.world"),
+                        reply_subject: None,
+                        headers,
+                        payload: Bytes::from_static(b"Hi")
+                    }
+                }
+            })
+        );
+        assert_ok_eq!(decoder.decode(), None);
+    }
+
+    #[test]
+    fn head_at_max_len() {
+        let head = b"MSG hello.world 1 2";
+        let mut decoder = StreamDecoder::new().with_max_head_len(head.len());
+        decoder.read_buf().put(&head[..]);
+        decoder.read_buf().put(&b"\r"[..]);
+        assert_ok_eq!(decoder.decode(), None);
+        decoder.read_buf().put(&b"\nHi\r\n"[..]);
+        assert_ok_eq!(
+            decoder.decode(),
+            Some(ServerOp::Message {
+                message: ServerMessage {
+                    status_code: None,
+                    subscription_id: 1.into(),
+                    base: MessageBase {
+                        subject: Subject::from_static("hello.world"),
+                        reply_subject: None,
+                        headers: HeaderMap::new(),
+                        payload: Bytes::from_static(b"Hi")
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn head_over_max_len() {
+        let head = b"MSG hello.world 1 2";
+        let max_head_len = head.len() - 1;
+
+        let mut decoder = StreamDecoder::new().with_max_head_len(max_head_len);
+        decoder.read_buf().put(&head[..]);
+        decoder.read_buf().put(&b"\r\nHi\r\n"[..]);
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::HeadTooLong { len, max }) if len == head.len() && max == max_head_len
+        ));
+
+        // The head is rejected before the terminating `\r\n` arrives
+        let mut decoder = StreamDecoder::new().with_max_head_len(max_head_len);
+        decoder.read_buf().put(&head[..max_head_len]);
+        decoder.read_buf().put(&b"\r"[..]);
+        assert_ok_eq!(decoder.decode(), None);
+        decoder.read_buf().put(&b"2"[..]);
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::HeadTooLong { max, .. }) if max == max_head_len
+        ));
+    }
+
+    #[test]
+    fn info_max_head_len() {
+        let mut decoder = StreamDecoder::new().with_max_head_len(4);
+        decoder.read_buf().put(&b"INFO {\"server_id\""[..]);
+        assert_ok_eq!(decoder.decode(), None);
+
+        // An unterminated `INFO` doesn't grow the buffer forever
+        decoder.read_buf().put(&[b'a'; 64][..]);
+        assert!(matches!(
+            decoder.decode(),
+            Err(DecoderError::HeadTooLong { max: 64, .. })
+        ));
+    }
+
+    #[test]
+    fn reset_poisoned() {
+        let mut decoder = StreamDecoder::new();
+        decoder
+            .read_buf()
+            .put(&b"HMSG hello 1 12 14\r\nXXXX/1.0\r\n\r\nHi\r\nPING\r\n"[..]);
+        assert!(decoder.decode().is_err());
+        assert!(matches!(decoder.decode(), Err(DecoderError::Poisoned)));
+
+        decoder.reset();
+        assert_eq!(0, decoder.buffered_len());
+        assert_ok_eq!(decoder.decode(), None);
+
+        decoder.read_buf().put(&b"PING\r\n"[..]);
+        assert_ok_eq!(decoder.decode(), Some(ServerOp::Ping));
+    }
 }
@@ -97,14 +97,18 @@ pub(super) fn encode<E: FrameEncoder>(encoder: &mut E, item: &ClientOp) {
                 let payload_len = payload.len();
                 small_write!("{payload_len}\r\n");
             } else {
-                let headers_len = encode_headers(headers).fold(0, |len, s| len + s.len());
+                let headers_len = headers.encoded_len();
 
                 let total_len = headers_len + payload.len();
                 small_write!("{headers_len} {total_len}\r\n");
 
-                encode_headers(headers).for_each(|s| {
-                    encoder.small_write(s.as_bytes());
-                });
+                if let Some(raw) = headers.raw() {
+                    encoder.write(IntoBytes(raw));
+                } else {
+                    encode_headers(headers).for_each(|s| {
+                        encoder.small_write(s.as_bytes());
+                    });
+                }
             }
 
             encoder.write(IntoBytes(payload));
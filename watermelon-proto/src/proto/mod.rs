@@ -1,5 +1,7 @@
 pub use self::client::ClientOp;
-pub use self::decoder::{decode_frame, StreamDecoder};
+pub use self::decoder::{
+    decode_client_op, decode_frame, decode_headers, ClientOpDecoder, StreamDecoder,
+};
 pub use self::encoder::{FramedEncoder, StreamEncoder};
 pub use self::server::ServerOp;
 
@@ -2,6 +2,7 @@ use alloc::string::String;
 use core::{
     fmt::{self, Display},
     ops::Deref,
+    str::FromStr,
 };
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
@@ -16,8 +17,8 @@ use bytestring::ByteString;
 /// * The value has a length less than or equal to 64 [^2]
 /// * The value does not contain any whitespace characters or `:`
 ///
-/// `QueueGroup` can be constructed from [`QueueGroup::from_static`]
-/// or any of the `TryFrom` implementations.
+/// `QueueGroup` can be constructed from [`QueueGroup::from_static`],
+/// [`str::parse`] or any of the `TryFrom` implementations.
 ///
 /// [^1]: Because [`QueueGroup::from_dangerous_value`] is safe to call,
 ///       unsafe code must not assume any of the above invariants.
@@ -39,7 +40,7 @@ impl QueueGroup {
     /// Construct a `QueueGroup` from a string, without checking invariants
     ///
     /// This method bypasses invariants checks implemented by [`QueueGroup::from_static`]
-    /// and all `TryFrom` implementations.
+    /// and all `FromStr` and `TryFrom` implementations.
     ///
     /// # Security
     ///
@@ -92,6 +93,18 @@ impl TryFrom<String> for QueueGroup {
     }
 }
 
+impl_str_eq!(QueueGroup, |this: &QueueGroup, other: &str| this.as_str()
+    == other);
+
+impl FromStr for QueueGroup {
+    type Err = QueueGroupValidateError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        validate_queue_group(value)?;
+        Ok(Self::from_dangerous_value(ByteString::from(value)))
+    }
+}
+
 impl From<QueueGroup> for ByteString {
     fn from(value: QueueGroup) -> Self {
         value.0
@@ -250,4 +263,24 @@ mod tests {
             assert_eq!(expected_err, err);
         }
     }
+
+    #[test]
+    fn from_str() {
+        for queue_group in ["importer", "importer.thing"] {
+            let q = queue_group.parse::<QueueGroup>().unwrap();
+            assert_eq!(queue_group, q.to_string());
+            assert_eq!(q, q.to_string().parse::<QueueGroup>().unwrap());
+        }
+        assert_eq!(
+            QueueGroupValidateError::IllegalCharacter,
+            "importer thing".parse::<QueueGroup>().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn str_eq() {
+        let queue_group = QueueGroup::from_static("importer");
+        assert!(queue_group == "importer");
+        assert!(queue_group != "Importer");
+    }
 }
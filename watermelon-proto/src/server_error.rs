@@ -1,110 +1,380 @@
+use core::fmt::{self, Display};
+
 use bytestring::ByteString;
 
+use crate::Subject;
+
+/// An error sent by the server via `-ERR`
+///
+/// Every variant keeps the message exactly as it was sent by the server,
+/// which is also returned by [`ServerError::raw_message`].
+///
+/// The permission violations, previously reported as `PublishPermissionViolation`
+/// and `SubscribePermissionViolation`, are now reported as [`ServerError::PermissionsViolation`],
+/// `AuthorizationTimeout` was renamed to [`ServerError::AuthenticationTimeout`]
+/// and `MaximumPayloadViolation` to [`ServerError::MaximumPayloadExceeded`].
 #[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum ServerError {
     #[error("subject is invalid")]
-    InvalidSubject,
-    #[error("permissions violation for publish")]
-    PublishPermissionViolation,
-    #[error("permissions violation for subscription")]
-    SubscribePermissionViolation,
+    InvalidSubject { raw_message: ByteString },
+    #[error("permissions violation for {operation} to {subject}")]
+    PermissionsViolation {
+        operation: PermissionOperation,
+        subject: Subject,
+        raw_message: ByteString,
+    },
+    /// A permissions violation whose message couldn't be parsed further
+    #[error("permissions violation: {raw_message}")]
+    UnknownPermissionsViolation { raw_message: ByteString },
 
     #[error("unknown protocol operation")]
-    UnknownProtocolOperation,
+    UnknownProtocolOperation { raw_message: ByteString },
 
     #[error("attempted to connect to route port")]
-    ConnectionAttemptedToWrongPort,
+    ConnectionAttemptedToWrongPort { raw_message: ByteString },
 
     #[error("authorization violation")]
-    AuthorizationViolation,
-    #[error("authorization timeout")]
-    AuthorizationTimeout,
+    AuthorizationViolation { raw_message: ByteString },
+    #[error("authentication timeout")]
+    AuthenticationTimeout { raw_message: ByteString },
     #[error("invalid client protocol")]
-    InvalidClientProtocol,
+    InvalidClientProtocol { raw_message: ByteString },
     #[error("maximum control line exceeded")]
-    MaximumControlLineExceeded,
+    MaximumControlLineExceeded { raw_message: ByteString },
     #[error("parser error")]
-    ParseError,
+    ParseError { raw_message: ByteString },
     #[error("secure connection, tls required")]
-    TlsRequired,
+    TlsRequired { raw_message: ByteString },
     #[error("stale connection")]
-    StaleConnection,
+    StaleConnection { raw_message: ByteString },
     #[error("maximum connections exceeded")]
-    MaximumConnectionsExceeded,
+    MaximumConnectionsExceeded { raw_message: ByteString },
     #[error("slow consumer")]
-    SlowConsumer,
-    #[error("maximum payload violation")]
-    MaximumPayloadViolation,
+    SlowConsumer { raw_message: ByteString },
+    #[error("maximum payload exceeded")]
+    MaximumPayloadExceeded { raw_message: ByteString },
 
     #[error("unknown error: {raw_message}")]
     Other { raw_message: ByteString },
 }
 
+/// The operation that was denied by a [`ServerError::PermissionsViolation`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PermissionOperation {
+    Publish,
+    Subscribe,
+}
+
+/// A broad classification of a [`ServerError`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServerErrorKind {
+    /// The credentials were rejected or not presented in time
+    Authentication,
+    /// The user is not allowed to publish or subscribe to a subject
+    Permissions,
+    /// The client sent something the server couldn't understand
+    Protocol,
+    /// A server or connection limit was exceeded
+    Limits,
+    /// The connection itself was rejected or has gone stale
+    Connection,
+    /// The error wasn't recognized
+    Other,
+}
+
 impl ServerError {
+    /// Returns whether the server is going to close the connection after this error
+    ///
+    /// `None` is returned for errors that couldn't be recognized.
+    #[must_use]
     pub fn is_fatal(&self) -> Option<bool> {
         match self {
-            Self::InvalidSubject
-            | Self::PublishPermissionViolation
-            | Self::SubscribePermissionViolation => Some(false),
-
-            Self::UnknownProtocolOperation
-            | Self::ConnectionAttemptedToWrongPort
-            | Self::AuthorizationViolation
-            | Self::AuthorizationTimeout
-            | Self::InvalidClientProtocol
-            | Self::MaximumControlLineExceeded
-            | Self::ParseError
-            | Self::TlsRequired
-            | Self::StaleConnection
-            | Self::MaximumConnectionsExceeded
-            | Self::SlowConsumer
-            | Self::MaximumPayloadViolation => Some(true),
+            Self::InvalidSubject { .. }
+            | Self::PermissionsViolation { .. }
+            | Self::UnknownPermissionsViolation { .. } => Some(false),
+
+            Self::UnknownProtocolOperation { .. }
+            | Self::ConnectionAttemptedToWrongPort { .. }
+            | Self::AuthorizationViolation { .. }
+            | Self::AuthenticationTimeout { .. }
+            | Self::InvalidClientProtocol { .. }
+            | Self::MaximumControlLineExceeded { .. }
+            | Self::ParseError { .. }
+            | Self::TlsRequired { .. }
+            | Self::StaleConnection { .. }
+            | Self::MaximumConnectionsExceeded { .. }
+            | Self::SlowConsumer { .. }
+            | Self::MaximumPayloadExceeded { .. } => Some(true),
 
             Self::Other { .. } => None,
         }
     }
 
+    /// Classify this error
+    #[must_use]
+    pub fn kind(&self) -> ServerErrorKind {
+        match self {
+            Self::AuthorizationViolation { .. } | Self::AuthenticationTimeout { .. } => {
+                ServerErrorKind::Authentication
+            }
+            Self::PermissionsViolation { .. } | Self::UnknownPermissionsViolation { .. } => {
+                ServerErrorKind::Permissions
+            }
+            Self::InvalidSubject { .. }
+            | Self::UnknownProtocolOperation { .. }
+            | Self::InvalidClientProtocol { .. }
+            | Self::MaximumControlLineExceeded { .. }
+            | Self::ParseError { .. } => ServerErrorKind::Protocol,
+            Self::SlowConsumer { .. } | Self::MaximumPayloadExceeded { .. } => {
+                ServerErrorKind::Limits
+            }
+            Self::ConnectionAttemptedToWrongPort { .. }
+            | Self::TlsRequired { .. }
+            | Self::StaleConnection { .. }
+            | Self::MaximumConnectionsExceeded { .. } => ServerErrorKind::Connection,
+            Self::Other { .. } => ServerErrorKind::Other,
+        }
+    }
+
+    /// Returns whether reconnecting with the same configuration is likely to fail again
+    ///
+    /// This is the case for errors caused by the credentials or the
+    /// connection options rather than by the state of the connection.
+    #[must_use]
+    pub fn is_persistent(&self) -> bool {
+        matches!(
+            self,
+            Self::AuthorizationViolation { .. }
+                | Self::ConnectionAttemptedToWrongPort { .. }
+                | Self::TlsRequired { .. }
+                | Self::InvalidClientProtocol { .. }
+        )
+    }
+
+    /// The message exactly as it was sent by the server
+    #[must_use]
+    pub fn raw_message(&self) -> &str {
+        match self {
+            Self::InvalidSubject { raw_message }
+            | Self::PermissionsViolation { raw_message, .. }
+            | Self::UnknownPermissionsViolation { raw_message }
+            | Self::UnknownProtocolOperation { raw_message }
+            | Self::ConnectionAttemptedToWrongPort { raw_message }
+            | Self::AuthorizationViolation { raw_message }
+            | Self::AuthenticationTimeout { raw_message }
+            | Self::InvalidClientProtocol { raw_message }
+            | Self::MaximumControlLineExceeded { raw_message }
+            | Self::ParseError { raw_message }
+            | Self::TlsRequired { raw_message }
+            | Self::StaleConnection { raw_message }
+            | Self::MaximumConnectionsExceeded { raw_message }
+            | Self::SlowConsumer { raw_message }
+            | Self::MaximumPayloadExceeded { raw_message }
+            | Self::Other { raw_message } => raw_message,
+        }
+    }
+
     pub(crate) fn parse(raw_message: ByteString) -> Self {
-        const PUBLISH_PERMISSIONS: &str = "Permissions Violation for Publish";
-        const SUBSCRIPTION_PERMISSIONS: &str = "Permissions Violation for Subscription";
+        const PERMISSIONS: &str = "Permissions Violation";
+        const PUBLISH_PERMISSIONS: &str = "Permissions Violation for Publish to ";
+        const SUBSCRIPTION_PERMISSIONS: &str = "Permissions Violation for Subscription to ";
 
         let m = raw_message.trim();
         if m.eq_ignore_ascii_case("Invalid Subject") {
-            Self::InvalidSubject
-        } else if m.len() > PUBLISH_PERMISSIONS.len()
-            && m[..PUBLISH_PERMISSIONS.len()].eq_ignore_ascii_case(PUBLISH_PERMISSIONS)
+            Self::InvalidSubject { raw_message }
+        } else if let Some((operation, subject)) = Self::parse_permissions(
+            m,
+            PUBLISH_PERMISSIONS,
+            PermissionOperation::Publish,
+        )
+        .or_else(|| {
+            Self::parse_permissions(m, SUBSCRIPTION_PERMISSIONS, PermissionOperation::Subscribe)
+        }) {
+            Self::PermissionsViolation {
+                operation,
+                subject,
+                raw_message,
+            }
+        } else if m
+            .get(..PERMISSIONS.len())
+            .is_some_and(|m| m.eq_ignore_ascii_case(PERMISSIONS))
         {
-            Self::PublishPermissionViolation
-        } else if m.len() > SUBSCRIPTION_PERMISSIONS.len()
-            && m[..SUBSCRIPTION_PERMISSIONS.len()].eq_ignore_ascii_case(SUBSCRIPTION_PERMISSIONS)
-        {
-            Self::SubscribePermissionViolation
+            Self::UnknownPermissionsViolation { raw_message }
         } else if m.eq_ignore_ascii_case("Unknown Protocol Operation") {
-            Self::UnknownProtocolOperation
+            Self::UnknownProtocolOperation { raw_message }
         } else if m.eq_ignore_ascii_case("Attempted To Connect To Route Port") {
-            Self::ConnectionAttemptedToWrongPort
+            Self::ConnectionAttemptedToWrongPort { raw_message }
         } else if m.eq_ignore_ascii_case("Authorization Violation") {
-            Self::AuthorizationViolation
-        } else if m.eq_ignore_ascii_case("Authorization Timeout") {
-            Self::AuthorizationTimeout
+            Self::AuthorizationViolation { raw_message }
+        } else if m.eq_ignore_ascii_case("Authentication Timeout")
+            || m.eq_ignore_ascii_case("Authorization Timeout")
+        {
+            Self::AuthenticationTimeout { raw_message }
         } else if m.eq_ignore_ascii_case("Invalid Client Protocol") {
-            Self::InvalidClientProtocol
+            Self::InvalidClientProtocol { raw_message }
         } else if m.eq_ignore_ascii_case("Maximum Control Line Exceeded") {
-            Self::MaximumControlLineExceeded
+            Self::MaximumControlLineExceeded { raw_message }
         } else if m.eq_ignore_ascii_case("Parser Error") {
-            Self::ParseError
+            Self::ParseError { raw_message }
         } else if m.eq_ignore_ascii_case("Secure Connection - TLS Required") {
-            Self::TlsRequired
+            Self::TlsRequired { raw_message }
         } else if m.eq_ignore_ascii_case("Stale Connection") {
-            Self::StaleConnection
+            Self::StaleConnection { raw_message }
         } else if m.eq_ignore_ascii_case("Maximum Connections Exceeded") {
-            Self::MaximumConnectionsExceeded
+            Self::MaximumConnectionsExceeded { raw_message }
         } else if m.eq_ignore_ascii_case("Slow Consumer") {
-            Self::SlowConsumer
-        } else if m.eq_ignore_ascii_case("Maximum Payload Violation") {
-            Self::MaximumPayloadViolation
+            Self::SlowConsumer { raw_message }
+        } else if m.eq_ignore_ascii_case("Maximum Payload Violation")
+            || m.eq_ignore_ascii_case("Maximum Payload Exceeded")
+        {
+            Self::MaximumPayloadExceeded { raw_message }
         } else {
             Self::Other { raw_message }
         }
     }
+
+    fn parse_permissions(
+        m: &str,
+        prefix: &str,
+        operation: PermissionOperation,
+    ) -> Option<(PermissionOperation, Subject)> {
+        if !m.get(..prefix.len())?.eq_ignore_ascii_case(prefix) {
+            return None;
+        }
+        let rest = &m[prefix.len()..];
+
+        // The subject is quoted and may be followed by ` using queue "..."`
+        let rest = rest.strip_prefix('"')?;
+        let subject = &rest[..rest.find('"')?];
+        let subject = Subject::try_from(ByteString::from(subject)).ok()?;
+        Some((operation, subject))
+    }
+}
+
+impl Display for PermissionOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Publish => "publish",
+            Self::Subscribe => "subscription",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytestring::ByteString;
+
+    use crate::Subject;
+
+    use super::{PermissionOperation, ServerError, ServerErrorKind};
+
+    #[test]
+    fn parse_known() {
+        let parse = |raw| ServerError::parse(ByteString::from_static(raw));
+        assert!(matches!(
+            parse("Authorization Violation"),
+            ServerError::AuthorizationViolation { .. }
+        ));
+        assert!(matches!(
+            parse("Authentication Timeout"),
+            ServerError::AuthenticationTimeout { .. }
+        ));
+        assert!(matches!(
+            parse("authorization timeout"),
+            ServerError::AuthenticationTimeout { .. }
+        ));
+        assert!(matches!(
+            parse("Unknown Protocol Operation"),
+            ServerError::UnknownProtocolOperation { .. }
+        ));
+        assert!(matches!(
+            parse("Maximum Payload Violation"),
+            ServerError::MaximumPayloadExceeded { .. }
+        ));
+
+        let error = parse(" Slow Consumer ");
+        assert_eq!(
+            ServerError::SlowConsumer {
+                raw_message: ByteString::from_static(" Slow Consumer ")
+            },
+            error
+        );
+        assert_eq!(" Slow Consumer ", error.raw_message());
+    }
+
+    #[test]
+    fn parse_permissions() {
+        assert_eq!(
+            ServerError::PermissionsViolation {
+                operation: PermissionOperation::Publish,
+                subject: Subject::from_static("orders.eu.created"),
+                raw_message: ByteString::from_static(
+                    "Permissions Violation for Publish to \"orders.eu.created\""
+                ),
+            },
+            ServerError::parse(ByteString::from_static(
+                "Permissions Violation for Publish to \"orders.eu.created\""
+            ))
+        );
+        assert_eq!(
+            ServerError::PermissionsViolation {
+                operation: PermissionOperation::Subscribe,
+                subject: Subject::from_static("orders.>"),
+                raw_message: ByteString::from_static(
+                    "Permissions Violation for Subscription to \"orders.>\" using queue \"workers\""
+                ),
+            },
+            ServerError::parse(ByteString::from_static(
+                "Permissions Violation for Subscription to \"orders.>\" using queue \"workers\""
+            ))
+        );
+
+        let error = ServerError::parse(ByteString::from_static(
+            "permissions violation for publish to \"orders.eu.created\"",
+        ));
+        assert_eq!(Some(false), error.is_fatal());
+        assert_eq!(ServerErrorKind::Permissions, error.kind());
+    }
+
+    #[test]
+    fn parse_unknown_permissions() {
+        for raw in [
+            "Permissions Violation for Publish with Reply of \"_INBOX.abcd\"",
+            "Permissions Violation for Publish",
+        ] {
+            let raw = ByteString::from_static(raw);
+            let error = ServerError::parse(raw.clone());
+            assert_eq!(
+                ServerError::UnknownPermissionsViolation { raw_message: raw },
+                error
+            );
+            assert_eq!(Some(false), error.is_fatal());
+            assert_eq!(ServerErrorKind::Permissions, error.kind());
+        }
+    }
+
+    #[test]
+    fn parse_other() {
+        let raw = ByteString::from_static("Something New");
+        let error = ServerError::parse(raw.clone());
+        assert_eq!(None, error.is_fatal());
+        assert_eq!(ServerErrorKind::Other, error.kind());
+        assert_eq!(ServerError::Other { raw_message: raw }, error);
+    }
+
+    #[test]
+    fn classification() {
+        let parse = |raw| ServerError::parse(ByteString::from_static(raw));
+        let authorization_violation = parse("Authorization Violation");
+        let slow_consumer = parse("Slow Consumer");
+        assert_eq!(
+            ServerErrorKind::Authentication,
+            authorization_violation.kind()
+        );
+        assert!(authorization_violation.is_persistent());
+        assert!(!parse("Stale Connection").is_persistent());
+        assert_eq!(Some(true), slow_consumer.is_fatal());
+        assert_eq!(ServerErrorKind::Limits, slow_consumer.kind());
+    }
 }
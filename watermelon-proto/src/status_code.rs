@@ -26,13 +26,62 @@ impl StatusCode {
     pub const IDLE_HEARTBEAT: StatusCode = Self::new_internal(100);
     /// The request has successfully been sent
     pub const OK: StatusCode = Self::new_internal(200);
+    /// The Jetstream request was malformed
+    pub const BAD_REQUEST: StatusCode = Self::new_internal(400);
     /// The requested Jetstream resource doesn't exist
+    ///
+    /// Also sent to terminate a `no_wait` pull consumer batch when there are no messages.
     pub const NOT_FOUND: StatusCode = Self::new_internal(404);
     /// The pull consumer batch reached the timeout
     pub const TIMEOUT: StatusCode = Self::new_internal(408);
+    /// The pull consumer batch was terminated by the server
+    ///
+    /// The description of the status tells the reason, for example
+    /// `Consumer Deleted`, `Message Size Exceeds MaxBytes` or `Exceeded MaxWaiting`.
+    pub const CONFLICT: StatusCode = Self::new_internal(409);
     /// The request was sent to a subject that does not appear to have any subscribers listening
     pub const NO_RESPONDERS: StatusCode = Self::new_internal(503);
 
+    /// Returns `true` if the status code is in the `100..200` range
+    ///
+    /// Informational statuses, like [`StatusCode::IDLE_HEARTBEAT`], don't carry a message.
+    #[must_use]
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0.get())
+    }
+
+    /// Returns `true` if the status code is in the `200..300` range
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0.get())
+    }
+
+    /// Returns `true` if the status code is `400` or above
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.0.get() >= 400
+    }
+
+    /// Returns `true` if a pull consumer batch was terminated because there are no messages
+    #[must_use]
+    pub fn is_no_messages(&self) -> bool {
+        *self == Self::NOT_FOUND
+    }
+
+    /// Returns `true` if a pull consumer batch was terminated because it reached the timeout
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        *self == Self::TIMEOUT
+    }
+
+    /// Returns `true` if a pull consumer batch was terminated by the server
+    ///
+    /// See [`StatusCode::CONFLICT`].
+    #[must_use]
+    pub fn is_conflict(&self) -> bool {
+        *self == Self::CONFLICT
+    }
+
     /// Decodes a status code from a slice of ASCII characters.
     ///
     /// The ASCII representation is expected to be in the form of `"NNN"`, where `N` is a numeric
@@ -138,6 +187,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn predicates() {
+        assert!(StatusCode::IDLE_HEARTBEAT.is_informational());
+        assert!(!StatusCode::IDLE_HEARTBEAT.is_error());
+        assert!(StatusCode::OK.is_success());
+        assert!(!StatusCode::OK.is_error());
+        for status_code in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::NOT_FOUND,
+            StatusCode::TIMEOUT,
+            StatusCode::CONFLICT,
+            StatusCode::NO_RESPONDERS,
+        ] {
+            assert!(status_code.is_error());
+            assert!(!status_code.is_informational());
+            assert!(!status_code.is_success());
+        }
+
+        assert!(StatusCode::NOT_FOUND.is_no_messages());
+        assert!(StatusCode::TIMEOUT.is_timeout());
+        assert!(StatusCode::CONFLICT.is_conflict());
+        assert!(!StatusCode::CONFLICT.is_no_messages());
+    }
+
     #[test]
     fn invalid_status_codes() {
         let status_codes = [0, 5, 55, 9999];
@@ -1,7 +1,8 @@
 use alloc::string::String;
 use core::{
-    fmt::{self, Display},
+    fmt::{self, Display, Write as _},
     ops::Deref,
+    str::FromStr,
 };
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
@@ -17,8 +18,8 @@ use bytestring::ByteString;
 /// * The value does not contain any whitespace characters or `:`
 /// * The value does not contain wrongly placed `*` or `>` characters
 ///
-/// `Subject` can be constructed from [`Subject::from_static`]
-/// or any of the `TryFrom` implementations.
+/// `Subject` can be constructed from [`Subject::from_static`],
+/// [`str::parse`] or any of the `TryFrom` implementations.
 ///
 /// [^1]: Because [`Subject::from_dangerous_value`] is safe to call,
 ///       unsafe code must not assume any of the above invariants.
@@ -26,6 +27,8 @@ use bytestring::ByteString;
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Subject(ByteString);
 
+const MAX_LEN: usize = 256;
+
 impl Subject {
     /// Construct `Subject` from a static string
     ///
@@ -40,7 +43,7 @@ impl Subject {
     /// Construct a `Subject` from a string, without checking invariants
     ///
     /// This method bypasses invariants checks implemented by [`Subject::from_static`]
-    /// and all `TryFrom` implementations.
+    /// and all `FromStr` and `TryFrom` implementations.
     ///
     /// # Security
     ///
@@ -63,10 +66,145 @@ impl Subject {
         Self(value)
     }
 
+    /// Construct a `Subject` by joining `tokens` with `.`
+    ///
+    /// Every token is validated on its own. The `*` and `>` wildcards are
+    /// allowed as whole tokens, with `>` only being allowed as the last one.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if any of the tokens is invalid or if the resulting
+    /// subject would be empty or too long.
+    pub fn from_tokens<'a>(
+        tokens: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, SubjectValidateError> {
+        let mut subject = String::new();
+        let mut full_wildcard = false;
+        for token in tokens {
+            if full_wildcard {
+                return Err(SubjectValidateError::BrokenWildcard);
+            }
+            validate_token(token, true)?;
+            full_wildcard = token == ">";
+
+            if !subject.is_empty() {
+                subject.push('.');
+            }
+            subject.push_str(token);
+        }
+
+        if subject.is_empty() {
+            return Err(SubjectValidateError::Empty);
+        }
+        if subject.len() > MAX_LEN {
+            return Err(SubjectValidateError::TooLong);
+        }
+        Ok(Self::from_dangerous_value(subject.into()))
+    }
+
+    /// Construct a new `Subject` by appending `token` to this subject
+    ///
+    /// Only `token` is validated, making this cheaper than formatting
+    /// and validating the whole subject again.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if `token` isn't a single valid token, if it is a wildcard,
+    /// if this subject ends with the `>` wildcard or if the resulting subject would be too long.
+    pub fn join(&self, token: &str) -> Result<Self, SubjectValidateError> {
+        self.join_tokens(&[token])
+    }
+
+    /// Construct a new `Subject` by appending every one of `tokens` to this subject
+    ///
+    /// Like [`Subject::join`], but allocates the resulting subject only once.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if any of the `tokens` isn't a single valid token, if it is a wildcard,
+    /// if this subject ends with the `>` wildcard or if the resulting subject would be too long.
+    pub fn join_tokens(&self, tokens: &[&str]) -> Result<Self, SubjectValidateError> {
+        let mut len = self.0.len();
+        for token in tokens {
+            validate_token(token, false)?;
+            len += 1 + token.len();
+        }
+        if !tokens.is_empty() && self.tokens().next_back() == Some(">") {
+            return Err(SubjectValidateError::BrokenWildcard);
+        }
+        if len > MAX_LEN {
+            return Err(SubjectValidateError::TooLong);
+        }
+
+        let mut subject = String::with_capacity(len);
+        subject.push_str(self);
+        for token in tokens {
+            subject.push('.');
+            subject.push_str(token);
+        }
+        Ok(Self::from_dangerous_value(subject.into()))
+    }
+
+    /// Construct a new `Subject` by appending the [`Display`] representation of every one of `tokens`
+    ///
+    /// Like [`Subject::join_tokens`], but formats the tokens straight into the
+    /// resulting subject instead of requiring them to be converted into strings first.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if any of the `tokens` isn't a single valid token, if it is a wildcard,
+    /// if this subject ends with the `>` wildcard or if the resulting subject would be too long.
+    pub fn join_display(&self, tokens: &[&dyn Display]) -> Result<Self, SubjectValidateError> {
+        // A valid subject can never be longer than `MAX_LEN`, so this is the only allocation
+        let mut subject = String::with_capacity(MAX_LEN);
+        subject.push_str(self);
+        for token in tokens {
+            subject.push('.');
+            let start = subject.len();
+            write!(subject, "{token}").expect("writing to a String can't fail");
+            validate_token(&subject[start..], false)?;
+        }
+        if !tokens.is_empty() && self.tokens().next_back() == Some(">") {
+            return Err(SubjectValidateError::BrokenWildcard);
+        }
+        if subject.len() > MAX_LEN {
+            return Err(SubjectValidateError::TooLong);
+        }
+
+        Ok(Self::from_dangerous_value(subject.into()))
+    }
+
     #[must_use]
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Returns an iterator over the `.` separated tokens of the subject
+    pub fn tokens(&self) -> impl DoubleEndedIterator<Item = &'_ str> {
+        self.as_str().split('.')
+    }
+
+    /// Returns true if this subject is matched by `filter`
+    ///
+    /// Tokens are compared one by one: a `*` in `filter` matches exactly
+    /// one token, a `>` in `filter` matches one or more trailing tokens and
+    /// any other token in `filter` only matches an identical token.
+    /// Wildcards in `self` have no special meaning and are compared like
+    /// any other token.
+    #[must_use]
+    pub fn matches(&self, filter: &Self) -> bool {
+        let mut tokens = self.tokens();
+        let mut filter_tokens = filter.tokens();
+
+        loop {
+            match (tokens.next(), filter_tokens.next()) {
+                (Some(_), Some(">")) | (None, None) => return true,
+                (Some(_), Some("*")) => {}
+                (Some(token), Some(filter_token)) if token == filter_token => {}
+                (Some(_) | None, Some(_)) | (Some(_), None) => return false,
+            }
+        }
+    }
 }
 
 impl Display for Subject {
@@ -93,6 +231,18 @@ impl TryFrom<String> for Subject {
     }
 }
 
+impl_str_eq!(Subject, |this: &Subject, other: &str| this.as_str()
+    == other);
+
+impl FromStr for Subject {
+    type Err = SubjectValidateError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        validate_subject(value)?;
+        Ok(Self::from_dangerous_value(ByteString::from(value)))
+    }
+}
+
 impl From<Subject> for ByteString {
     fn from(value: Subject) -> Self {
         value.0
@@ -133,7 +283,7 @@ impl<'de> Deserialize<'de> for Subject {
 }
 
 /// An error encountered while validating [`Subject`]
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub enum SubjectValidateError {
     /// The value is empty
@@ -154,12 +304,28 @@ pub enum SubjectValidateError {
     BrokenWildcard,
 }
 
+fn validate_token(token: &str, allow_wildcards: bool) -> Result<(), SubjectValidateError> {
+    if token.is_empty() || token.contains('.') {
+        return Err(SubjectValidateError::BrokenToken);
+    }
+
+    if token.chars().any(char::is_whitespace) {
+        return Err(SubjectValidateError::IllegalCharacter);
+    }
+
+    if token.contains(['*', '>']) && (!allow_wildcards || token.len() > 1) {
+        return Err(SubjectValidateError::BrokenWildcard);
+    }
+
+    Ok(())
+}
+
 fn validate_subject(subject: &str) -> Result<(), SubjectValidateError> {
     if subject.is_empty() {
         return Err(SubjectValidateError::Empty);
     }
 
-    if subject.len() > 256 {
+    if subject.len() > MAX_LEN {
         // This is an arbitrary limit, but I guess the server must also have one
         return Err(SubjectValidateError::TooLong);
     }
@@ -190,6 +356,8 @@ fn validate_subject(subject: &str) -> Result<(), SubjectValidateError> {
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::String;
+
     use bytestring::ByteString;
 
     use super::{Subject, SubjectValidateError};
@@ -256,4 +424,214 @@ mod tests {
             assert_eq!(expected_err, err);
         }
     }
+
+    #[test]
+    fn tokens() {
+        let subjects = [
+            ("cmd", ["cmd"].as_slice()),
+            ("cmd.endpoint", &["cmd", "endpoint"]),
+            ("cmd.*.detail", &["cmd", "*", "detail"]),
+            ("cmd.endpoint.>", &["cmd", "endpoint", ">"]),
+        ];
+        for (subject, expected_tokens) in subjects {
+            let subject = Subject::from_static(subject);
+            assert!(subject.tokens().eq(expected_tokens.iter().copied()));
+        }
+    }
+
+    #[test]
+    fn join() {
+        let subject = Subject::from_static("$JS.API");
+        assert_eq!(
+            Subject::from_static("$JS.API.STREAM"),
+            subject.join("STREAM").unwrap()
+        );
+
+        let invalid_tokens = [
+            ("", SubjectValidateError::BrokenToken),
+            ("STREAM.INFO", SubjectValidateError::BrokenToken),
+            ("STR EAM", SubjectValidateError::IllegalCharacter),
+            ("*", SubjectValidateError::BrokenWildcard),
+            (">", SubjectValidateError::BrokenWildcard),
+            ("STREAM*", SubjectValidateError::BrokenWildcard),
+        ];
+        for (token, expected_err) in invalid_tokens {
+            assert_eq!(expected_err, subject.join(token).unwrap_err());
+        }
+
+        assert_eq!(
+            SubjectValidateError::BrokenWildcard,
+            Subject::from_static("cmd.>").join("detail").unwrap_err()
+        );
+        assert_eq!(
+            SubjectValidateError::TooLong,
+            subject.join(&"a".repeat(250)).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn join_tokens() {
+        let subject = Subject::from_static("$JS.API");
+        assert_eq!(
+            Subject::from_static("$JS.API.CONSUMER.INFO.orders.worker"),
+            subject
+                .join_tokens(&["CONSUMER", "INFO", "orders", "worker"])
+                .unwrap()
+        );
+        assert_eq!(subject, subject.join_tokens(&[]).unwrap());
+
+        assert_eq!(
+            SubjectValidateError::BrokenToken,
+            subject.join_tokens(&["STREAM", ""]).unwrap_err()
+        );
+        assert_eq!(
+            SubjectValidateError::BrokenWildcard,
+            subject.join_tokens(&["STREAM", "*"]).unwrap_err()
+        );
+        assert_eq!(
+            SubjectValidateError::TooLong,
+            subject
+                .join_tokens(&["STREAM", &"a".repeat(200), &"a".repeat(50)])
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn join_display() {
+        let subject = Subject::from_static("$JS.API");
+        assert_eq!(
+            Subject::from_static("$JS.API.CONSUMER.INFO.orders.1"),
+            subject
+                .join_display(&[&"CONSUMER", &"INFO", &"orders", &1])
+                .unwrap()
+        );
+        assert_eq!(subject, subject.join_display(&[]).unwrap());
+
+        assert_eq!(
+            SubjectValidateError::BrokenToken,
+            subject.join_display(&[&"STREAM", &"a.b"]).unwrap_err()
+        );
+        assert_eq!(
+            SubjectValidateError::BrokenWildcard,
+            subject.join_display(&[&"STREAM", &">"]).unwrap_err()
+        );
+        assert_eq!(
+            SubjectValidateError::BrokenWildcard,
+            Subject::from_static("cmd.>")
+                .join_display(&[&"detail"])
+                .unwrap_err()
+        );
+        assert_eq!(
+            SubjectValidateError::TooLong,
+            subject
+                .join_display(&[&"STREAM", &"a".repeat(250)])
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn from_tokens() {
+        assert_eq!(
+            Subject::from_static("cmd.*.>"),
+            Subject::from_tokens(["cmd", "*", ">"]).unwrap()
+        );
+
+        let invalid_tokens = [
+            ([].as_slice(), SubjectValidateError::Empty),
+            (&["cmd", ""], SubjectValidateError::BrokenToken),
+            (&["cmd", "a.b"], SubjectValidateError::BrokenToken),
+            (
+                &["cmd", ">", "detail"],
+                SubjectValidateError::BrokenWildcard,
+            ),
+            (&["cmd", "a*"], SubjectValidateError::BrokenWildcard),
+        ];
+        for (tokens, expected_err) in invalid_tokens {
+            assert_eq!(
+                expected_err,
+                Subject::from_tokens(tokens.iter().copied()).unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn matching_subjects() {
+        let subjects = [
+            ("cmd", "cmd"),
+            ("cmd", "*"),
+            ("cmd", ">"),
+            ("cmd.endpoint", "cmd.endpoint"),
+            ("cmd.endpoint", "cmd.*"),
+            ("cmd.endpoint", "*.endpoint"),
+            ("cmd.endpoint", "*.*"),
+            ("cmd.endpoint", "cmd.>"),
+            ("cmd.endpoint", ">"),
+            ("cmd.endpoint.detail", "cmd.*.detail"),
+            ("cmd.endpoint.detail", "cmd.*.*"),
+            ("cmd.endpoint.detail", "cmd.endpoint.>"),
+            ("cmd.endpoint.detail", "cmd.>"),
+            ("cmd.endpoint.detail", "*.>"),
+            ("cmd.*.detail", "cmd.*.detail"),
+            ("cmd.endpoint.>", "cmd.endpoint.>"),
+        ];
+        for (subject, filter) in subjects {
+            let subject = Subject::from_static(subject);
+            let filter = Subject::from_static(filter);
+            assert!(subject.matches(&filter), "{subject} should match {filter}");
+        }
+    }
+
+    #[test]
+    fn non_matching_subjects() {
+        let subjects = [
+            ("cmd", "endpoint"),
+            ("cmd", "cmd.endpoint"),
+            ("cmd", "cmd.*"),
+            ("cmd", "cmd.>"),
+            ("cmd.endpoint", "cmd"),
+            ("cmd.endpoint", "*"),
+            ("cmd.endpoint", "cmd.detail"),
+            ("cmd.endpoint", "*.detail"),
+            ("cmd.endpoint", "cmd.endpoint.>"),
+            ("cmd.endpoint.detail", "cmd.*"),
+            ("cmd.endpoint.detail", "cmd.*.endpoint"),
+            ("cmd.endpoint.detail", "cmd.detail.>"),
+            ("cmd.endpoint.detail", "*.*"),
+            ("cmd.*.detail", "cmd.endpoint.detail"),
+            ("cmd.endpoint.>", "cmd.endpoint.detail"),
+        ];
+        for (subject, filter) in subjects {
+            let subject = Subject::from_static(subject);
+            let filter = Subject::from_static(filter);
+            assert!(
+                !subject.matches(&filter),
+                "{subject} shouldn't match {filter}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_str() {
+        for subject in ["cmd", "cmd.*.detail", "cmd.endpoint.>"] {
+            let s = subject.parse::<Subject>().unwrap();
+            assert_eq!(subject, s.to_string());
+            assert_eq!(s, s.to_string().parse::<Subject>().unwrap());
+        }
+        assert_eq!(
+            SubjectValidateError::BrokenToken,
+            "cmd..endpoint".parse::<Subject>().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn str_eq() {
+        let subject = Subject::from_static("orders.created");
+        let owned = String::from("orders.created");
+        assert!(subject == "orders.created");
+        assert!(subject == owned);
+        assert!("orders.created" == subject);
+        assert!(owned == subject);
+        assert!(subject != "orders.Created");
+        assert!("orders" != subject);
+    }
 }
@@ -51,10 +51,10 @@ impl<B: Buf> Buf for BufList<B> {
         while cnt > 0 {
             let entry = self.bufs.front_mut().unwrap();
             let remaining = entry.remaining();
-            if remaining < cnt {
+            if cnt < remaining {
                 entry.advance(cnt);
                 self.len -= cnt;
-                cnt -= cnt;
+                cnt = 0;
             } else {
                 let _ = self.bufs.remove(0);
                 self.len -= remaining;
@@ -101,11 +101,50 @@ impl<B: Buf> Buf for BufList<B> {
             }
         }
 
+        // `Take` advances `self`, which already updates `self.len`
         let mut bufs = BytesMut::with_capacity(len);
         bufs.put(self.take(len));
-        let bufs = bufs.freeze();
+        bufs.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Buf, Bytes};
+
+    use super::BufList;
+
+    #[test]
+    fn advance() {
+        let mut list = BufList::new();
+        list.push(Bytes::from_static(b"1234"));
+        list.push(Bytes::from_static(b"5678"));
+        list.push(Bytes::from_static(b"90"));
+
+        list.advance(2);
+        assert_eq!(8, list.remaining());
+        assert_eq!(b"34", list.chunk());
+
+        list.advance(4);
+        assert_eq!(4, list.remaining());
+        assert_eq!(b"78", list.chunk());
+
+        list.advance(4);
+        assert_eq!(0, list.remaining());
+        assert!(!list.has_remaining());
+    }
 
-        self.len -= len;
-        bufs
+    #[test]
+    fn copy_to_bytes() {
+        let mut list = BufList::new();
+        list.push(Bytes::from_static(b"1234"));
+        list.push(Bytes::from_static(b"5678"));
+        list.push(Bytes::from_static(b"90"));
+
+        assert_eq!(Bytes::from_static(b"12"), list.copy_to_bytes(2));
+        assert_eq!(Bytes::from_static(b"3456"), list.copy_to_bytes(4));
+        assert_eq!(4, list.remaining());
+        assert_eq!(Bytes::from_static(b"7890"), list.copy_to_bytes(4));
+        assert!(!list.has_remaining());
     }
 }
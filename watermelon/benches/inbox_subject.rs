@@ -0,0 +1,30 @@
+use std::{fmt::Write as _, hint::black_box};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::RngCore as _;
+use watermelon::{create_inbox_subject, proto::Subject};
+
+/// The random `u128` hex suffix inbox subjects used before switching to NUIDs
+fn hex_inbox_subject(prefix: &Subject) -> Subject {
+    let mut suffix = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut suffix);
+
+    let mut subject = String::with_capacity(prefix.len() + ".".len() + (suffix.len() * 2));
+    write!(&mut subject, "{}.{:x}", prefix, u128::from_ne_bytes(suffix)).unwrap();
+
+    Subject::from_dangerous_value(subject.into())
+}
+
+fn inbox_subject(c: &mut Criterion) {
+    let prefix = Subject::from_static("_INBOX");
+
+    let mut group = c.benchmark_group("inbox_subject");
+    group.bench_function("hex", |b| b.iter(|| hex_inbox_subject(black_box(&prefix))));
+    group.bench_function("nuid", |b| {
+        b.iter(|| create_inbox_subject(black_box(&prefix)));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, inbox_subject);
+criterion_main!(benches);
@@ -0,0 +1,375 @@
+//! Blocking wrapper around the async NATS Core client
+//!
+//! Useful for CLI tools and synchronous codebases that don't run inside
+//! of a Tokio runtime. Every [`Client`] drives the async client on a dedicated
+//! thread running a current-thread Tokio runtime.
+//!
+//! The methods of this module must not be called from within an async context.
+
+use std::{
+    fmt::{self, Debug},
+    future::{Future, IntoFuture as _},
+    io,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use bytes::Bytes;
+use futures_util::StreamExt as _;
+use tokio::{
+    runtime::{self, Handle},
+    sync::oneshot,
+};
+use watermelon_mini::ConnectError;
+use watermelon_proto::{error::ServerError, QueueGroup, ServerAddr, ServerMessage, Subject};
+
+use crate::core::{
+    error::{ClientClosedError, PublishError, ResponseError},
+    ClientBuilder,
+};
+
+/// A blocking NATS Core client
+///
+/// Wraps [`crate::core::Client`]. Cloning it is cheap and the
+/// background runtime is stopped once every clone, and every
+/// [`Subscription`] obtained from it, has been dropped.
+#[derive(Debug, Clone)]
+pub struct Client {
+    client: crate::core::Client,
+    runtime: Arc<RuntimeThread>,
+}
+
+/// A blocking subscription
+///
+/// Obtained from [`Client::subscribe`]. Iterating over it blocks
+/// the current thread until the next message is received.
+#[derive(Debug)]
+pub struct Subscription {
+    subscription: crate::core::Subscription,
+    runtime: Arc<RuntimeThread>,
+}
+
+/// An error encountered while making a request
+#[derive(Debug, thiserror::Error)]
+pub enum RequestError {
+    /// Publishing the request failed
+    #[error("publish error")]
+    Publish(#[source] PublishError),
+    /// No response was received
+    #[error("response error")]
+    Response(#[source] ResponseError),
+}
+
+struct RuntimeThread {
+    handle: Handle,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Client {
+    /// Creates a new [`Client`], connecting to one of the given addresses.
+    ///
+    /// See [`ClientBuilder::connect`] for how `addrs` are used.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the background runtime cannot be started
+    /// or if the connection fails.
+    pub fn connect(
+        builder: ClientBuilder,
+        addrs: impl IntoIterator<Item = ServerAddr>,
+    ) -> Result<Self, ConnectError> {
+        let runtime = Arc::new(RuntimeThread::spawn().map_err(ConnectError::Io)?);
+        let client = runtime.block_on(builder.connect(addrs))?;
+        Ok(Self { client, runtime })
+    }
+
+    /// Get a reference to the inner async client
+    #[must_use]
+    pub fn as_async(&self) -> &crate::core::Client {
+        &self.client
+    }
+
+    /// Publish a new message to the NATS server
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the message is bigger than the server's
+    /// `max_payload` or if the client has been closed.
+    pub fn publish(&self, subject: Subject, payload: Bytes) -> Result<(), PublishError> {
+        self.runtime
+            .block_on(self.client.publish(subject).payload(payload).into_future())
+    }
+
+    /// Publish a new request to the NATS server and wait for the response
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if publishing the request fails or
+    /// if no response is received.
+    pub fn request(&self, subject: Subject, payload: Bytes) -> Result<ServerMessage, RequestError> {
+        self.runtime.block_on(async {
+            let response = self
+                .client
+                .request(subject)
+                .payload(payload)
+                .await
+                .map_err(RequestError::Publish)?;
+            response.await.map_err(RequestError::Response)
+        })
+    }
+
+    /// Subscribe to the given filter subject
+    ///
+    /// See [`crate::core::Client::subscribe`].
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client has been closed.
+    pub fn subscribe(
+        &self,
+        filter_subject: Subject,
+        queue_group: Option<QueueGroup>,
+    ) -> Result<Subscription, ClientClosedError> {
+        let subscription = self
+            .runtime
+            .block_on(self.client.subscribe(filter_subject, queue_group))?;
+        Ok(Subscription {
+            subscription,
+            runtime: Arc::clone(&self.runtime),
+        })
+    }
+
+    /// Close this client, waiting for any remaining buffered messages to be processed first
+    ///
+    /// See [`crate::core::Client::close`].
+    pub fn close(&self) {
+        self.runtime.block_on(self.client.close());
+    }
+}
+
+impl Subscription {
+    /// Get a reference to the inner async subscription
+    #[must_use]
+    pub fn as_async(&self) -> &crate::core::Subscription {
+        &self.subscription
+    }
+
+    /// Unsubscribe and close this subscription
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client has been closed.
+    pub fn close(mut self) -> Result<(), ClientClosedError> {
+        self.runtime.block_on(self.subscription.close())
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = Result<ServerMessage, ServerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.subscription.next())
+    }
+}
+
+impl RuntimeThread {
+    fn spawn() -> io::Result<Self> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+
+        // `Handle::block_on` can't drive the IO and timer drivers of a current-thread
+        // runtime by itself, so a dedicated thread keeps driving them
+        let (shutdown, shutdown_receiver) = oneshot::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("watermelon-blocking".to_owned())
+            .spawn(move || {
+                let _ = runtime.block_on(shutdown_receiver);
+            })?;
+
+        Ok(Self {
+            handle,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.handle.block_on(future)
+    }
+}
+
+impl Drop for RuntimeThread {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Debug for RuntimeThread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeThread").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, sync::mpsc, thread, time::Duration};
+
+    use bytes::Bytes;
+    use tokio::{io::AsyncWriteExt as _, net::TcpListener, runtime};
+    use watermelon_mini::ConnectError;
+    use watermelon_proto::{ServerAddr, Subject};
+
+    use super::Client;
+    use crate::client::tests::{handshake, listen, read_line, read_publish};
+
+    /// Run `server` on a runtime of its own, since the blocking client can't be used from an async context
+    fn serve<F>(
+        server: impl FnOnce(TcpListener) -> F + Send + 'static,
+    ) -> (ServerAddr, thread::JoinHandle<()>)
+    where
+        F: Future<Output = ()>,
+    {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (listener, addr) = runtime.block_on(listen());
+        let server = thread::spawn(move || runtime.block_on(server(listener)));
+        (addr, server)
+    }
+
+    fn connect(addr: ServerAddr) -> Client {
+        Client::connect(crate::core::Client::builder().verbose(false), [addr]).unwrap()
+    }
+
+    #[test]
+    fn publish() {
+        let (addr, server) = serve(|listener| async move {
+            let mut socket = handshake(&listener).await;
+            let (subject, reply_subject, payload) = read_publish(&mut socket).await;
+            assert_eq!("foo", subject);
+            assert_eq!("", reply_subject);
+            assert_eq!(b"bar", payload.as_slice());
+        });
+
+        let client = connect(addr);
+        client
+            .publish(Subject::from_static("foo"), Bytes::from_static(b"bar"))
+            .unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn request() {
+        let (addr, server) = serve(|listener| async move {
+            let mut socket = handshake(&listener).await;
+            let sub = read_line(&mut socket).await;
+            assert!(sub.starts_with("SUB _INBOX."));
+            let mux_id = sub.trim_end().rsplit(' ').next().unwrap().to_owned();
+
+            let (subject, reply_subject, payload) = read_publish(&mut socket).await;
+            assert_eq!("ping", subject);
+            assert_eq!(b"ping", payload.as_slice());
+            socket
+                .write_all(format!("MSG {reply_subject} {mux_id} 4\r\npong\r\n").as_bytes())
+                .await
+                .unwrap();
+            // Wait for the client to go away
+            read_line(&mut socket).await;
+        });
+
+        let client = connect(addr);
+        let response = client
+            .request(Subject::from_static("ping"), Bytes::from_static(b"ping"))
+            .unwrap();
+        assert_eq!(b"pong".as_slice(), response.base.payload);
+        drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn subscription() {
+        let (addr, server) = serve(|listener| async move {
+            let mut socket = handshake(&listener).await;
+            let sub = read_line(&mut socket).await;
+            assert!(sub.starts_with("SUB foo "));
+            let id = sub.trim_end().rsplit(' ').next().unwrap().to_owned();
+
+            for payload in ["1", "2"] {
+                socket
+                    .write_all(format!("MSG foo {id} 1\r\n{payload}\r\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(format!("UNSUB {id}\r\n"), read_line(&mut socket).await);
+        });
+
+        let client = connect(addr);
+        let mut subscription = client.subscribe(Subject::from_static("foo"), None).unwrap();
+        let payloads = subscription
+            .by_ref()
+            .take(2)
+            .map(|message| message.unwrap().base.payload)
+            .collect::<Vec<_>>();
+        assert_eq!(payloads, ["1", "2"]);
+        subscription.close().unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn close() {
+        let (addr, server) = serve(|listener| async move {
+            let mut socket = handshake(&listener).await;
+            assert_eq!("", read_line(&mut socket).await);
+        });
+
+        let client = connect(addr);
+        client.close();
+        server.join().unwrap();
+        assert!(client
+            .publish(Subject::from_static("foo"), Bytes::new())
+            .is_err());
+    }
+
+    #[test]
+    fn shutdown_after_last_drop() {
+        let (addr, server) = serve(|listener| async move {
+            let mut socket = handshake(&listener).await;
+            assert!(read_line(&mut socket).await.starts_with("SUB foo "));
+            // Stopping the runtime drops the connection
+            while !read_line(&mut socket).await.is_empty() {}
+        });
+
+        let client = connect(addr);
+        let subscription = client.subscribe(Subject::from_static("foo"), None).unwrap();
+        drop(client.clone());
+        drop(client);
+
+        // The subscription keeps the runtime running until it is dropped too
+        let (dropped_sender, dropped) = mpsc::channel();
+        thread::spawn(move || {
+            drop(subscription);
+            dropped_sender.send(()).unwrap();
+        });
+        dropped.recv_timeout(Duration::from_secs(5)).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn connect_refused() {
+        let err = Client::connect(
+            crate::core::Client::builder(),
+            ["nats://127.0.0.1:1".parse().unwrap()],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConnectError::Io(_)));
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Exponential backoff applied between reconnection attempts
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ReconnectBackoff {
+    min: Duration,
+    max: Duration,
+    // INVARIANT: `(0.0..=1.0).contains(&jitter)`
+    jitter: f64,
+}
+
+impl ReconnectBackoff {
+    pub(crate) fn new(min: Duration, max: Duration, jitter: f64) -> Self {
+        let jitter = if jitter.is_nan() {
+            0.0
+        } else {
+            jitter.clamp(0.0, 1.0)
+        };
+
+        Self {
+            min,
+            max: max.max(min),
+            jitter,
+        }
+    }
+
+    /// The delay to wait for after `attempt` consecutive failed reconnection attempts
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .min
+            .saturating_mul(2_u32.saturating_pow(attempt))
+            .min(self.max);
+        if self.jitter == 0.0 {
+            return delay;
+        }
+
+        let factor = 1.0 + rand::thread_rng().gen_range(-self.jitter..=self.jitter);
+        delay.mul_f64(factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ReconnectBackoff;
+
+    #[test]
+    fn exponential() {
+        let backoff =
+            ReconnectBackoff::new(Duration::from_millis(100), Duration::from_secs(2), 0.0);
+        let delays = (0..8)
+            .map(|attempt| backoff.delay(attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            [
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(400),
+                Duration::from_millis(800),
+                Duration::from_millis(1600),
+                Duration::from_secs(2),
+                Duration::from_secs(2),
+                Duration::from_secs(2),
+            ]
+            .as_slice(),
+            delays.as_slice()
+        );
+        assert_eq!(Duration::from_secs(2), backoff.delay(u32::MAX));
+    }
+
+    #[test]
+    fn jitter() {
+        let backoff = ReconnectBackoff::new(Duration::from_secs(1), Duration::from_secs(1), 0.5);
+        for _ in 0..64 {
+            let delay = backoff.delay(0);
+            assert!(delay >= Duration::from_millis(500));
+            assert!(delay <= Duration::from_millis(1500));
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use std::time::Duration;
+use std::{num::NonZeroUsize, sync::Arc, time::Duration};
 
-use watermelon_mini::{AuthenticationMethod, ConnectError};
+use watermelon_mini::{
+    default_tls_config,
+    rustls::{crypto::CryptoProvider, ClientConfig},
+    tls_config_with_provider, AuthenticationMethod, ConnectError, TlsClientCert,
+};
+use watermelon_net::{IpPreference, Resolver, SocketOptions, TcpConnectOptions};
 use watermelon_proto::{ServerAddr, Subject};
 
+use super::backoff::ReconnectBackoff;
 #[cfg(feature = "from-env")]
 use super::from_env::FromEnv;
 use crate::core::Client;
@@ -11,12 +17,35 @@ use crate::core::Client;
 ///
 /// Obtained from [`Client::builder`].
 #[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ClientBuilder {
     pub(crate) auth_method: Option<AuthenticationMethod>,
+    pub(crate) tls_config: Option<Arc<ClientConfig>>,
+    pub(crate) tls_client_cert: Option<TlsClientCert>,
+    pub(crate) crypto_provider: Option<Arc<CryptoProvider>>,
     pub(crate) flush_interval: Duration,
     pub(crate) inbox_prefix: Subject,
     pub(crate) echo: Echo,
+    pub(crate) verbose: bool,
+    pub(crate) pedantic: bool,
     pub(crate) default_response_timeout: Duration,
+    pub(crate) reconnect_backoff: ReconnectBackoff,
+    pub(crate) max_reconnects: Option<usize>,
+    pub(crate) randomize_servers: bool,
+    pub(crate) reconnect_on_lameduck: bool,
+    pub(crate) subscription_capacity: NonZeroUsize,
+    pub(crate) offline_buffer: Option<(NonZeroUsize, OfflineBufferPolicy)>,
+    pub(crate) request_inbox_pool_size: usize,
+    pub(crate) flush_on_drop: bool,
+    pub(crate) tcp_options: TcpConnectOptions,
+    pub(crate) max_head_len: Option<usize>,
+    pub(crate) retain_raw_headers: bool,
+    pub(crate) write_flatten_threshold: Option<usize>,
+    pub(crate) connect_timeout: Option<Duration>,
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) propagate_trace_context: bool,
+    #[cfg(feature = "websocket-deflate")]
+    pub(crate) websocket_deflate: bool,
     #[cfg(feature = "non-standard-zstd")]
     pub(crate) non_standard_zstd: bool,
 }
@@ -35,14 +64,53 @@ pub enum Echo {
     Allow,
 }
 
+/// What to do with a message published while disconnected when the offline buffer is full
+///
+/// See [`ClientBuilder::offline_buffer`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OfflineBufferPolicy {
+    /// Drop the oldest buffered message to make room for the new one
+    DropOldest,
+    /// Drop the new message
+    DropNewest,
+    /// Fail publishing the new message with an error
+    Reject,
+}
+
 impl ClientBuilder {
     pub(super) fn new() -> Self {
         Self {
             auth_method: None,
+            tls_config: None,
+            tls_client_cert: None,
+            crypto_provider: None,
             flush_interval: Duration::ZERO,
             inbox_prefix: Subject::from_static("_INBOX"),
             echo: Echo::Prevent,
+            verbose: true,
+            pedantic: false,
             default_response_timeout: Duration::from_secs(5),
+            reconnect_backoff: ReconnectBackoff::new(
+                Duration::from_secs(10),
+                Duration::from_secs(10),
+                0.0,
+            ),
+            max_reconnects: None,
+            randomize_servers: true,
+            reconnect_on_lameduck: false,
+            subscription_capacity: NonZeroUsize::new(256).unwrap(),
+            offline_buffer: None,
+            request_inbox_pool_size: 0,
+            flush_on_drop: false,
+            tcp_options: TcpConnectOptions::default(),
+            max_head_len: None,
+            retain_raw_headers: false,
+            write_flatten_threshold: None,
+            connect_timeout: None,
+            #[cfg(feature = "opentelemetry")]
+            propagate_trace_context: false,
+            #[cfg(feature = "websocket-deflate")]
+            websocket_deflate: false,
             #[cfg(feature = "non-standard-zstd")]
             non_standard_zstd: true,
         }
@@ -58,13 +126,21 @@ impl ClientBuilder {
     /// * `NATS_CREDS_FILE`: read JWT and NKEY from the provided `.creds` file
     /// * `NATS_USERNAME` and `NATS_PASSWORD`: use username and password authentication
     ///
+    /// TLS:
+    ///
+    /// * `NATS_TLS_CERT` and `NATS_TLS_KEY`: authenticate via mutual TLS using the
+    ///   PEM encoded certificate chain and private key read from the provided files.
+    ///   Both must be set together.
+    ///
     /// # Panics
     ///
     /// It panics if:
     ///
     /// - it is not possible to get the environment variables;
     /// - an error occurs when trying to read the credentials file;
-    /// - the credentials file is invalid.
+    /// - the credentials file is invalid;
+    /// - only one of `NATS_TLS_CERT` and `NATS_TLS_KEY` is set;
+    /// - the TLS client certificate or private key can't be read.
     #[cfg(feature = "from-env")]
     #[must_use]
     pub fn from_env() -> Self {
@@ -72,6 +148,14 @@ impl ClientBuilder {
 
         let env = envy::from_env::<FromEnv>().expect("FromEnv deserialization error");
 
+        let client_cert = env
+            .tls_client_cert()
+            .expect("TLS client certificate environment variables")
+            .map(|(tls_cert, tls_key)| {
+                TlsClientCert::from_pem_files(tls_cert, tls_key)
+                    .expect("read TLS client certificate")
+            });
+
         let mut this = Self::new();
 
         match env.auth {
@@ -99,6 +183,10 @@ impl ClientBuilder {
             this = this.inbox_prefix(inbox_prefix);
         }
 
+        if let Some(client_cert) = client_cert {
+            this = this.tls_client_cert(Some(client_cert));
+        }
+
         this
     }
 
@@ -109,6 +197,47 @@ impl ClientBuilder {
         self
     }
 
+    /// Define a custom TLS configuration
+    ///
+    /// Allows using mutual TLS, a custom root store or pinned certificates.
+    /// The configuration is used for every connection and reconnection made by the client.
+    /// It takes precedence over [`ClientBuilder::tls_client_cert`] and
+    /// [`ClientBuilder::crypto_provider`], which are ignored when it is set.
+    ///
+    /// Default: TLS 1.3 with the platform certificate verifier and no client authentication.
+    #[must_use]
+    pub fn tls_config(mut self, tls_config: Arc<ClientConfig>) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// Define a client certificate to authenticate via mutual TLS
+    ///
+    /// The certificate is presented to the server by the default TLS configuration.
+    /// It is ignored if a custom configuration is set via [`ClientBuilder::tls_config`],
+    /// in which case the client certificate must be set in the custom configuration itself.
+    ///
+    /// Default: no client certificate.
+    #[must_use]
+    pub fn tls_client_cert(mut self, tls_client_cert: Option<TlsClientCert>) -> Self {
+        self.tls_client_cert = tls_client_cert;
+        self
+    }
+
+    /// Define the crypto provider used by the default TLS configuration
+    ///
+    /// Allows using a custom cryptography backend, for example for FIPS builds,
+    /// without having to build a custom TLS configuration. The provider is used
+    /// both for the TLS connection and for verifying the server certificate.
+    /// It is ignored if a custom configuration is set via [`ClientBuilder::tls_config`].
+    ///
+    /// Default: the provider selected via the `aws-lc-rs` or `ring` features.
+    #[must_use]
+    pub fn crypto_provider(mut self, crypto_provider: Arc<CryptoProvider>) -> Self {
+        self.crypto_provider = Some(crypto_provider);
+        self
+    }
+
     /// Define a flush interval
     ///
     /// Setting a non-zero flush interval allows the client to generate
@@ -150,6 +279,40 @@ impl ClientBuilder {
         self
     }
 
+    /// Whether or not the server should acknowledge every command with `+OK`
+    ///
+    /// Acknowledgements roughly double the traffic from the server for
+    /// publish-heavy workloads. Without them errors caused by a command,
+    /// like a failed subscription due to a permissions violation, can't be
+    /// traced back to it, so they are ignored instead of being delivered
+    /// to the [`Subscription`].
+    ///
+    /// Default: `true`.
+    ///
+    /// [`Subscription`]: crate::core::Subscription
+    #[must_use]
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Whether or not the server should strictly validate subjects and protocol commands
+    ///
+    /// This is mostly useful during development. Violations are reported by the
+    /// server via `-ERR`, which doesn't always close the connection. With
+    /// [`ClientBuilder::verbose`] enabled, errors caused by subscribing are delivered
+    /// to the [`Subscription`] while the others are ignored. Without it every
+    /// non-fatal error is ignored.
+    ///
+    /// Default: `false`.
+    ///
+    /// [`Subscription`]: crate::core::Subscription
+    #[must_use]
+    pub fn pedantic(mut self, pedantic: bool) -> Self {
+        self.pedantic = pedantic;
+        self
+    }
+
     /// The default timeout for [`ResponseFut`]
     ///
     /// Defines how long we should wait for a response in [`Client::request`].
@@ -163,6 +326,319 @@ impl ClientBuilder {
         self
     }
 
+    /// Configure the delay between reconnection attempts
+    ///
+    /// After losing the connection the client immediately tries to reconnect.
+    /// If that fails, it waits for `min` before trying again, doubling the delay
+    /// after every failed attempt until it reaches `max`. The delay goes back to
+    /// `min` once the client manages to reconnect. Errors which are going to occur
+    /// again with the same configuration, like an authorization violation,
+    /// make the client wait for `max` straight away.
+    ///
+    /// `jitter` randomizes every delay by up to the given fraction of it,
+    /// in both directions, in order to prevent many clients from reconnecting
+    /// at the same time. It is clamped to the `0.0..=1.0` range.
+    ///
+    /// Default: 10 seconds for both `min` and `max`, with no jitter.
+    #[must_use]
+    pub fn reconnect_backoff(mut self, min: Duration, max: Duration, jitter: f64) -> Self {
+        self.reconnect_backoff = ReconnectBackoff::new(min, max, jitter);
+        self
+    }
+
+    /// Give up reconnecting after `max_reconnects` consecutive failed attempts
+    ///
+    /// Every attempt at connecting to a server counts, so with multiple known
+    /// servers the budget is consumed by going through them. Once exhausted the
+    /// client is closed for good: [`ClientEvent::ReconnectExhausted`] is emitted
+    /// and every pending and future command fails with a [`ClientClosedError`]
+    /// for which [`ClientClosedError::is_reconnect_exhausted`] returns `true`.
+    /// Messages still waiting in the [offline buffer] are dropped, and counted
+    /// by the event.
+    /// `None` keeps reconnecting forever.
+    ///
+    /// Default: `None`.
+    ///
+    /// [`ClientEvent::ReconnectExhausted`]: crate::core::ClientEvent::ReconnectExhausted
+    /// [`ClientClosedError`]: crate::core::error::ClientClosedError
+    /// [`ClientClosedError::is_reconnect_exhausted`]: crate::core::error::ClientClosedError::is_reconnect_exhausted
+    /// [offline buffer]: Self::offline_buffer
+    #[must_use]
+    pub fn max_reconnects(mut self, max_reconnects: Option<usize>) -> Self {
+        self.max_reconnects = max_reconnects;
+        self
+    }
+
+    /// Disable shuffling the servers the client connects to
+    ///
+    /// By default the seed servers passed to [`ClientBuilder::connect`] and the
+    /// servers discovered from the cluster are shuffled, in order to spread
+    /// clients across the cluster. Setting this to `true` makes the client
+    /// try them in order instead.
+    ///
+    /// Default: `false`.
+    #[must_use]
+    pub fn no_randomize(mut self, no_randomize: bool) -> Self {
+        self.randomize_servers = !no_randomize;
+        self
+    }
+
+    /// Proactively move to another server when the current one enters lame duck mode
+    ///
+    /// A server in lame duck mode is about to shut down, for example during a
+    /// rolling upgrade of the cluster. With this option enabled the client writes
+    /// out every buffered command to the old server and then reconnects to the next
+    /// known server, instead of waiting to be disconnected. It has no effect
+    /// if the client only knows about a single server.
+    ///
+    /// Default: `false`.
+    #[must_use]
+    pub fn reconnect_on_lameduck(mut self, reconnect_on_lameduck: bool) -> Self {
+        self.reconnect_on_lameduck = reconnect_on_lameduck;
+        self
+    }
+
+    /// The number of messages each [`Subscription`] can buffer
+    ///
+    /// Messages received while the buffer of the [`Subscription`] is full
+    /// are dropped and counted by [`QuickInfo::dropped_messages`].
+    /// [`Client::subscribe_with_capacity`] overrides this for a single subscription.
+    ///
+    /// Default: 256.
+    ///
+    /// [`Subscription`]: crate::core::Subscription
+    /// [`QuickInfo::dropped_messages`]: crate::core::QuickInfo::dropped_messages
+    #[must_use]
+    pub fn subscription_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.subscription_capacity = capacity;
+        self
+    }
+
+    /// Buffer up to `capacity` messages published while the client is disconnected
+    ///
+    /// By default messages published while the client is reconnecting queue
+    /// up in the channel to the connection handler, until it fills up
+    /// and publishing starts waiting for the client to reconnect.
+    /// With this option enabled messages published while disconnected are
+    /// instead kept in a dedicated buffer, which `policy` manages once full,
+    /// and are sent as soon as the client reconnects.
+    ///
+    /// Publishes the connection handler didn't get to write when the
+    /// connection dropped are moved into the buffer too, ahead of the ones
+    /// made afterwards, so that publishes are sent in the order they were made.
+    ///
+    /// [`Client::publish_backpressured`] is buffered as well, but keeps waiting
+    /// until the message is replayed, failing with
+    /// [`PublishError::OfflineBufferFull`] if `policy` drops it.
+    /// Requests aren't buffered and keep waiting for the client to reconnect.
+    /// Buffered messages are lost if the client gives up reconnecting,
+    /// see [`Self::max_reconnects`].
+    ///
+    /// Default: disabled.
+    ///
+    /// [`Client::publish_backpressured`]: crate::core::Client::publish_backpressured
+    /// [`PublishError::OfflineBufferFull`]: crate::core::error::PublishError::OfflineBufferFull
+    #[must_use]
+    pub fn offline_buffer(mut self, capacity: NonZeroUsize, policy: OfflineBufferPolicy) -> Self {
+        self.offline_buffer = Some((capacity, policy));
+        self
+    }
+
+    /// Flush buffered commands when the last handle to the [`Client`] is dropped
+    ///
+    /// By default dropping the [`Client`] immediately stops the connection,
+    /// losing any message that was published but not yet written to the socket.
+    /// With this option enabled a background task keeps the connection alive until
+    /// the server has acknowledged every previously sent command, giving up after 5 seconds.
+    ///
+    /// This is best-effort: messages may still be lost if the connection is down,
+    /// if the deadline is reached or if the [`Client`] is dropped outside
+    /// of a Tokio runtime. Call [`Client::close`] to reliably close the client.
+    ///
+    /// Default: `false`.
+    ///
+    /// [`Client`]: crate::core::Client
+    /// [`Client::close`]: crate::core::Client::close
+    #[must_use]
+    pub fn flush_on_drop(mut self, flush_on_drop: bool) -> Self {
+        self.flush_on_drop = flush_on_drop;
+        self
+    }
+
+    /// Define how many reply subjects of completed requests are kept for reuse
+    ///
+    /// Requests sent via [`Client::request`] receive their response through a
+    /// single multiplexed subscription, each one waiting on a dedicated reply subject.
+    /// Pooling the reply subjects of requests that received their response avoids
+    /// allocating a new one for every request, which is noticeable at very high
+    /// request rates. Reply subjects of cancelled or timed out requests are
+    /// never reused.
+    ///
+    /// Only enable this when every request is answered by at most one responder,
+    /// otherwise a late response to a previous request could be received by
+    /// a newer request reusing the same reply subject.
+    ///
+    /// Default: 0, meaning that reply subjects are never reused.
+    ///
+    /// [`Client::request`]: crate::core::Client::request
+    #[must_use]
+    pub fn request_inbox_pool(mut self, size: usize) -> Self {
+        self.request_inbox_pool_size = size;
+        self
+    }
+
+    /// Define how long to wait for a TCP connection attempt before racing it with the next address
+    ///
+    /// Only used when the server address is a hostname resolving to multiple IPs.
+    ///
+    /// Default: 250 milliseconds.
+    #[must_use]
+    pub fn connect_attempt_delay(mut self, attempt_delay: Duration) -> Self {
+        self.tcp_options.attempt_delay = attempt_delay;
+        self
+    }
+
+    /// Define the maximum amount of time connecting to a server may take
+    ///
+    /// Covers the whole connect sequence: resolving the hostname of the server,
+    /// establishing the TCP connection, the TLS handshake and the initial exchange
+    /// with the server. Attempts taking longer fail with [`ConnectError::TimedOut`].
+    /// See [`ClientBuilder::tcp_connect_timeout`] for only limiting the first two steps.
+    ///
+    /// Default: no timeout.
+    ///
+    /// [`ConnectError::TimedOut`]: watermelon_mini::ConnectError::TimedOut
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Define the maximum amount of time establishing a TCP connection to a server may take
+    ///
+    /// Includes resolving the hostname of the server, but not the TLS handshake
+    /// and the initial exchange with the server, which are covered by
+    /// [`ClientBuilder::connect_timeout`]. Attempts taking longer fail with an
+    /// [`io::ErrorKind::TimedOut`] error.
+    ///
+    /// Default: no timeout.
+    ///
+    /// [`io::ErrorKind::TimedOut`]: std::io::ErrorKind::TimedOut
+    #[must_use]
+    pub fn tcp_connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.tcp_options.timeout = timeout;
+        self
+    }
+
+    /// Define which IP version to attempt first when a hostname resolves to both
+    ///
+    /// Default: [`IpPreference::Ipv6`].
+    #[must_use]
+    pub fn ip_preference(mut self, ip_preference: IpPreference) -> Self {
+        self.tcp_options.ip_preference = ip_preference;
+        self
+    }
+
+    /// Define the DNS resolver used to lookup the IPs of the servers
+    ///
+    /// Default: [`SystemResolver`].
+    ///
+    /// [`SystemResolver`]: crate::core::SystemResolver
+    #[must_use]
+    pub fn resolver(mut self, resolver: Arc<dyn Resolver>) -> Self {
+        self.tcp_options.resolver = resolver;
+        self
+    }
+
+    /// Define the options applied to the TCP socket of every connection
+    ///
+    /// Allows enabling TCP keepalive, which is important for long-lived
+    /// connections going through NAT gateways, and tuning the socket buffers.
+    ///
+    /// Default: [`SocketOptions::default`], which only enables `TCP_NODELAY`.
+    ///
+    /// [`SocketOptions::default`]: crate::core::SocketOptions::default
+    #[must_use]
+    pub fn socket_options(mut self, socket_options: SocketOptions) -> Self {
+        self.tcp_options.socket = socket_options;
+        self
+    }
+
+    /// Define the maximum length of a control line received from the server
+    ///
+    /// Control lines, like the head of a message, longer than this cause the
+    /// connection to be dropped. Raise it when receiving messages with very long
+    /// subjects or reply subjects, lower it to bound memory usage in
+    /// constrained environments. `INFO`, which carries the topology of the
+    /// whole cluster, may be up to 16 times longer.
+    ///
+    /// Default: 16 KiB.
+    #[must_use]
+    pub fn max_head_len(mut self, max_head_len: usize) -> Self {
+        self.max_head_len = Some(max_head_len);
+        self
+    }
+
+    /// Keep the header block of every received message alongside its parsed headers
+    ///
+    /// Allows relaying messages without re-encoding their headers, since
+    /// publishing unmodified headers writes the retained block back verbatim.
+    /// See [`HeaderMap::raw`].
+    ///
+    /// Default: `false`.
+    ///
+    /// [`HeaderMap::raw`]: crate::proto::headers::HeaderMap::raw
+    #[must_use]
+    pub fn retain_raw_headers(mut self, retain_raw_headers: bool) -> Self {
+        self.retain_raw_headers = retain_raw_headers;
+        self
+    }
+
+    /// Define the length below which a payload is copied into the write buffer
+    ///
+    /// Payloads shorter than this are copied together with the surrounding
+    /// protocol framing, while longer ones are written without copying as
+    /// a separate chunk of a vectored write. Raising it reduces the number of
+    /// chunks per write, which may benefit workloads publishing many
+    /// medium-sized payloads, at the cost of more memory copies.
+    /// Ignored by websocket connections.
+    ///
+    /// Default: 4 KiB.
+    #[must_use]
+    pub fn write_flatten_threshold(mut self, write_flatten_threshold: usize) -> Self {
+        self.write_flatten_threshold = Some(write_flatten_threshold);
+        self
+    }
+
+    /// Propagate the W3C Trace Context of the current `tracing` span on outgoing messages
+    ///
+    /// The context is only known when spans are recorded via `tracing-opentelemetry`.
+    /// Messages which already carry a trace context, for example one set via
+    /// [`PublishBuilder::trace_context`], are left untouched.
+    ///
+    /// Default: `false`.
+    ///
+    /// [`PublishBuilder::trace_context`]: crate::core::publish::PublishBuilder::trace_context
+    #[cfg(feature = "opentelemetry")]
+    #[must_use]
+    pub fn propagate_trace_context(mut self, propagate_trace_context: bool) -> Self {
+        self.propagate_trace_context = propagate_trace_context;
+        self
+    }
+
+    /// Offer the `permessage-deflate` extension when connecting to the server over websocket
+    ///
+    /// If the server accepts it, every websocket message is compressed in both
+    /// directions. Servers declining the extension are talked to uncompressed.
+    ///
+    /// Default: `false`.
+    #[cfg(feature = "websocket-deflate")]
+    #[must_use]
+    pub fn websocket_deflate(mut self, websocket_deflate: bool) -> Self {
+        self.websocket_deflate = websocket_deflate;
+        self
+    }
+
     /// Have the client compress the connection using zstd when talking to a NATS server
     /// behind a custom zstd proxy
     ///
@@ -184,13 +660,35 @@ impl ClientBuilder {
         self
     }
 
-    /// Creates a new [`Client`], connecting to the given address.
+    /// Creates a new [`Client`], connecting to one of the given addresses.
+    ///
+    /// The addresses are tried one after the other until a connection succeeds.
+    /// After losing the connection, the client reconnects to them and to any other
+    /// server advertised by the cluster, in round-robin order.
+    ///
+    /// A single server is passed as a one element array, like `connect([addr])`.
     ///
     /// # Errors
     ///
-    /// It returns an error if the connection fails.
-    pub async fn connect(self, addr: ServerAddr) -> Result<Client, ConnectError> {
-        Client::connect(addr, self).await
+    /// It returns an error if `addrs` is empty, if the TLS client certificate or
+    /// the crypto provider are invalid or if connecting to all of them fails,
+    /// in which case the error of the last attempt is returned.
+    pub async fn connect(
+        mut self,
+        addrs: impl IntoIterator<Item = ServerAddr>,
+    ) -> Result<Client, ConnectError> {
+        let tls_client_cert = self.tls_client_cert.take();
+        let crypto_provider = self.crypto_provider.take();
+        if self.tls_config.is_none() && (tls_client_cert.is_some() || crypto_provider.is_some()) {
+            let tls_config = match crypto_provider {
+                Some(crypto_provider) => tls_config_with_provider(crypto_provider, tls_client_cert),
+                None => default_tls_config(tls_client_cert),
+            }
+            .map_err(ConnectError::TlsConfig)?;
+            self.tls_config = Some(tls_config);
+        }
+
+        Client::connect(addrs.into_iter().collect(), self).await
     }
 }
 
@@ -199,3 +697,212 @@ impl Default for ClientBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+
+    use futures_core::future::BoxFuture;
+    use futures_util::future;
+    use tokio::{
+        io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader},
+        net::TcpListener,
+    };
+    use watermelon_mini::ConnectError;
+    use watermelon_net::Resolver;
+    use watermelon_proto::Subject;
+
+    use crate::{
+        client::tests::{handshake, handshake_with_info, listen, INFO},
+        core::Client,
+    };
+
+    const LAMEDUCK_INFO: &[u8] = b"INFO {\"server_id\":\"id\",\"server_name\":\"name\",\"version\":\"2.10.0\",\"go\":\"go1.22\",\"host\":\"127.0.0.1\",\"port\":4222,\"headers\":true,\"max_payload\":1048576,\"proto\":1,\"ldm\":true}\r\n";
+
+    #[tokio::test]
+    async fn connect_timeout() {
+        // Accept the TCP connection but never send `INFO`
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("nats://{}", listener.local_addr().unwrap());
+        let accept = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let err = Client::builder()
+            .connect_timeout(Some(Duration::from_millis(100)))
+            .connect([addr.parse().unwrap()])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ConnectError::TimedOut));
+
+        drop(accept.await.unwrap());
+    }
+
+    #[derive(Debug)]
+    struct PendingResolver;
+
+    impl Resolver for PendingResolver {
+        fn resolve<'a>(
+            &'a self,
+            _host: &'a str,
+            _port: u16,
+        ) -> BoxFuture<'a, io::Result<Vec<SocketAddr>>> {
+            Box::pin(future::pending())
+        }
+    }
+
+    #[tokio::test]
+    async fn tcp_connect_timeout() {
+        let err = Client::builder()
+            .resolver(Arc::new(PendingResolver))
+            .tcp_connect_timeout(Some(Duration::from_millis(100)))
+            .connect(["nats://nats.invalid:4222".parse().unwrap()])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ConnectError::Io(err) if err.kind() == io::ErrorKind::TimedOut));
+    }
+
+    #[tokio::test]
+    async fn non_verbose() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("nats://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            socket.write_all(INFO).await.unwrap();
+
+            let mut line = String::new();
+            socket.read_line(&mut line).await.unwrap();
+            assert!(line.starts_with("CONNECT "));
+            assert!(line.contains("\"verbose\":false"));
+            line.clear();
+            socket.read_line(&mut line).await.unwrap();
+            assert_eq!("PING\r\n", line);
+            socket.write_all(b"PONG\r\n").await.unwrap();
+
+            // No `+OK` for the `PUB`, followed by errors which can't be traced back to a command
+            let mut line = String::new();
+            while line != "PING\r\n" {
+                line.clear();
+                socket.read_line(&mut line).await.unwrap();
+            }
+            socket
+                .write_all(b"-ERR 'Permissions Violation for Publish to \"foo\"'\r\n-ERR 'Permissions Violation for Publish to \"foo\"'\r\nPONG\r\n")
+                .await
+                .unwrap();
+
+            line.clear();
+            socket.read_line(&mut line).await.unwrap();
+            assert_eq!("PING\r\n", line);
+            socket.write_all(b"PONG\r\n").await.unwrap();
+            socket
+        });
+
+        let client = Client::builder()
+            .verbose(false)
+            .connect([addr.parse().unwrap()])
+            .await
+            .unwrap();
+        client
+            .publish(Subject::from_static("foo"))
+            .payload("bar".into())
+            .await
+            .unwrap();
+        client.flush().await.unwrap();
+        client.flush().await.unwrap();
+        assert!(client.quick_info().is_connected());
+
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn pedantic() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("nats://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            socket.write_all(INFO).await.unwrap();
+
+            let mut line = String::new();
+            socket.read_line(&mut line).await.unwrap();
+            assert!(line.starts_with("CONNECT "));
+            assert!(line.contains("\"pedantic\":true"));
+            line.clear();
+            socket.read_line(&mut line).await.unwrap();
+            assert_eq!("PING\r\n", line);
+            socket.write_all(b"PONG\r\n").await.unwrap();
+            socket
+        });
+
+        let _client = Client::builder()
+            .pedantic(true)
+            .connect([addr.parse().unwrap()])
+            .await
+            .unwrap();
+
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn reconnect_on_lameduck() {
+        let (listener1, addr1) = listen().await;
+        let (listener2, addr2) = listen().await;
+        let server1 = tokio::spawn(async move {
+            let mut socket = handshake(&listener1).await;
+            socket.write_all(LAMEDUCK_INFO).await.unwrap();
+            socket
+        });
+        let server2 = tokio::spawn(async move { handshake(&listener2).await });
+
+        let client = Client::builder()
+            .no_randomize(true)
+            .reconnect_on_lameduck(true)
+            .connect([addr1, addr2])
+            .await
+            .unwrap();
+
+        // The first server never closes the connection
+        let _socket2 = tokio::time::timeout(Duration::from_secs(5), server2)
+            .await
+            .unwrap()
+            .unwrap();
+        drop(client);
+        drop(server1.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn failover_to_discovered_server() {
+        let (listener1, addr1) = listen().await;
+        let (listener2, _addr2) = listen().await;
+        let advertised = listener2.local_addr().unwrap();
+
+        // Servers advertise their peers as `host:port`, without a scheme
+        let info = format!(
+            "INFO {{\"server_id\":\"id\",\"server_name\":\"name\",\"version\":\"2.10.0\",\"go\":\"go1.22\",\"host\":\"127.0.0.1\",\"port\":4222,\"headers\":true,\"max_payload\":1048576,\"proto\":1,\"connect_urls\":[\"{advertised}\"]}}\r\n"
+        );
+        let server1 =
+            tokio::spawn(async move { handshake_with_info(&listener1, info.as_bytes()).await });
+        let server2 = tokio::spawn(async move {
+            handshake_with_info(
+                &listener2,
+                b"INFO {\"server_id\":\"id2\",\"server_name\":\"name2\",\"version\":\"2.11.0\",\"go\":\"go1.23\",\"host\":\"127.0.0.1\",\"port\":4222,\"headers\":true,\"max_payload\":2048,\"proto\":1}\r\n",
+            )
+            .await
+        });
+
+        let client = Client::builder().connect([addr1]).await.unwrap();
+        assert_eq!("name", client.server_info().name);
+        // The seed server goes away, leaving the discovered one as the only reachable server
+        drop(server1.await.unwrap());
+
+        let _socket2 = tokio::time::timeout(Duration::from_secs(5), server2)
+            .await
+            .unwrap()
+            .unwrap();
+        client.wait_connected().await.unwrap();
+        // The client reads the `INFO` of the server it failed over to
+        let info = client.server_info();
+        assert_eq!("name2", info.name);
+        assert_eq!(2048, info.max_payload.get());
+        drop(client);
+    }
+}
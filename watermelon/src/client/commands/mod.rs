@@ -1,11 +1,16 @@
+#[cfg(feature = "opentelemetry")]
+pub(crate) use self::publish::insert_trace_context;
+pub(crate) use self::publish::publish_backpressured;
 pub use self::publish::{
-    ClientPublish, DoClientPublish, DoOwnedClientPublish, OwnedClientPublish, Publish,
-    PublishBuilder,
+    propagated_deadline, ClientPublish, DoClientPublish, DoOwnedClientPublish, OwnedClientPublish,
+    Publish, PublishBuilder,
 };
 pub use self::request::{
-    ClientRequest, DoClientRequest, DoOwnedClientRequest, OwnedClientRequest, Request,
-    RequestBuilder, ResponseError, ResponseFut,
+    ClientRequest, DoClientRequest, DoOwnedClientRequest, JsonRequestError, OwnedClientRequest,
+    Request, RequestBuilder, ResponseError, ResponseFut, RetryRequestError,
 };
+pub use self::request_many::{RequestMany, RequestManyConfig};
 
 mod publish;
 mod request;
+mod request_many;
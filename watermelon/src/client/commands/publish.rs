@@ -1,22 +1,28 @@
 use std::{
     fmt::{self, Debug},
     future::IntoFuture,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use bytes::Bytes;
 use futures_core::future::BoxFuture;
+use tokio::sync::oneshot;
 use watermelon_proto::{
     headers::{HeaderMap, HeaderName, HeaderValue},
     MessageBase, Subject,
 };
 
 use crate::{
-    client::{Client, ClientClosedError, TryCommandError},
+    client::{Client, OfflineBufferFull, PublishError, TryCommandError},
     handler::HandlerCommand,
 };
 
 use super::Request;
 
+/// The header carrying the deadline by which the message should have been handled,
+/// as milliseconds since the Unix epoch
+pub(crate) const DEADLINE_HEADER: &str = "Watermelon-Deadline";
+
 /// A publishable message
 #[derive(Debug, Clone)]
 pub struct Publish {
@@ -83,6 +89,66 @@ macro_rules! publish {
             self.publish_mut().headers = headers;
             self
         }
+
+        /// Propagate the W3C Trace Context of the caller to the receiver of the message
+        ///
+        /// Sets the [`HeaderName::TRACEPARENT`] header and, if given,
+        /// the [`HeaderName::TRACESTATE`] header.
+        ///
+        /// With the `opentelemetry` feature the context of the current span can instead
+        /// be propagated automatically, see `ClientBuilder::propagate_trace_context`.
+        /// An explicitly set context takes precedence over it.
+        #[must_use]
+        pub fn trace_context(
+            mut self,
+            traceparent: HeaderValue,
+            tracestate: Option<HeaderValue>,
+        ) -> Self {
+            insert_trace_context(&mut self.publish_mut().headers, traceparent, tracestate);
+            self
+        }
+
+        /// Propagate `deadline` to the receiver of the message
+        ///
+        /// Sets the `Watermelon-Deadline` header, which can be read back
+        /// on the receiving side via [`propagated_deadline`].
+        ///
+        /// [`propagated_deadline`]: crate::core::publish::propagated_deadline
+        #[must_use]
+        pub fn propagate_deadline(mut self, deadline: SystemTime) -> Self {
+            insert_deadline(&mut self.publish_mut().headers, deadline);
+            self
+        }
+    };
+}
+
+macro_rules! publish_payload {
+    ($(#[$attr:meta])* $output:ty) => {
+        /// Set a `'static` payload
+        ///
+        /// This doesn't allocate nor copy `payload`.
+        $(#[$attr])*
+        pub fn payload_static(self, payload: &'static [u8]) -> $output {
+            self.payload(Bytes::from_static(payload))
+        }
+
+        /// Set a payload by copying `payload`
+        ///
+        /// This allocates and copies `payload`. Consider using [`Self::payload_from`]
+        /// if an owned buffer is already available.
+        $(#[$attr])*
+        pub fn payload_copy(self, payload: &[u8]) -> $output {
+            self.payload(Bytes::copy_from_slice(payload))
+        }
+
+        /// Set a payload from any type convertible into [`Bytes`]
+        ///
+        /// Converting from `Vec<u8>`, `Box<[u8]>` and `String` takes ownership
+        /// of the existing allocation without copying it.
+        $(#[$attr])*
+        pub fn payload_from<T: Into<Bytes>>(self, payload: T) -> $output {
+            self.payload(payload.into())
+        }
     };
 }
 
@@ -113,6 +179,9 @@ impl Publish {
         Request {
             publish: self,
             response_timeout: None,
+            response_deadline: None,
+            no_responders_is_error: true,
+            inbox_prefix: None,
         }
     }
 
@@ -153,6 +222,11 @@ impl PublishBuilder {
         self.publish
     }
 
+    publish_payload!(
+        #[must_use]
+        Publish
+    );
+
     fn publish_mut(&mut self) -> &mut Publish {
         &mut self.publish
     }
@@ -173,6 +247,8 @@ impl<'a> ClientPublish<'a> {
         self.publish.client(self.client)
     }
 
+    publish_payload!(DoClientPublish<'a>);
+
     /// Convert this into [`OwnedClientPublish`]
     #[must_use]
     pub fn to_owned(self) -> OwnedClientPublish {
@@ -202,6 +278,8 @@ impl OwnedClientPublish {
         self.publish.client_owned(self.client)
     }
 
+    publish_payload!(DoOwnedClientPublish);
+
     fn publish_mut(&mut self) -> &mut Publish {
         &mut self.publish
     }
@@ -216,14 +294,16 @@ impl DoClientPublish<'_> {
     ///
     /// # Errors
     ///
-    /// It returns an error if the client's buffer is full or if the client has been closed.
+    /// It returns an error if the client's buffer is full, if the message is bigger
+    /// than the server's `max_payload`, if the client is disconnected and its offline
+    /// buffer is full or if the client has been closed.
     pub fn try_publish(self) -> Result<(), TryCommandError> {
         try_publish(self.client, self.publish)
     }
 }
 
 impl<'a> IntoFuture for DoClientPublish<'a> {
-    type Output = Result<(), ClientClosedError>;
+    type Output = Result<(), PublishError>;
     type IntoFuture = BoxFuture<'a, Self::Output>;
 
     fn into_future(self) -> Self::IntoFuture {
@@ -240,14 +320,16 @@ impl DoOwnedClientPublish {
     ///
     /// # Errors
     ///
-    /// It returns an error if the client's buffer is full or if the client has been closed.
+    /// It returns an error if the client's buffer is full, if the message is bigger
+    /// than the server's `max_payload`, if the client is disconnected and its offline
+    /// buffer is full or if the client has been closed.
     pub fn try_publish(self) -> Result<(), TryCommandError> {
         try_publish(&self.client, self.publish)
     }
 }
 
 impl IntoFuture for DoOwnedClientPublish {
-    type Output = Result<(), ClientClosedError>;
+    type Output = Result<(), PublishError>;
     type IntoFuture = BoxFuture<'static, Self::Output>;
 
     fn into_future(self) -> Self::IntoFuture {
@@ -255,18 +337,107 @@ impl IntoFuture for DoOwnedClientPublish {
     }
 }
 
-fn try_publish(client: &Client, publish: Publish) -> Result<(), TryCommandError> {
-    client.try_enqueue_command(HandlerCommand::Publish {
-        message: publish.into_message_base(),
-    })
+/// Read the deadline propagated via the `Watermelon-Deadline` header
+///
+/// Returns `None` if the header is missing or malformed.
+#[must_use]
+pub fn propagated_deadline(headers: &HeaderMap) -> Option<SystemTime> {
+    let millis = headers
+        .get(&deadline_header_name())?
+        .as_str()
+        .parse()
+        .ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_millis(millis))
+}
+
+pub(crate) fn insert_trace_context(
+    headers: &mut HeaderMap,
+    traceparent: HeaderValue,
+    tracestate: Option<HeaderValue>,
+) {
+    headers.insert(HeaderName::TRACEPARENT, traceparent);
+    match tracestate {
+        Some(tracestate) => headers.insert(HeaderName::TRACESTATE, tracestate),
+        None => headers.remove(&HeaderName::TRACESTATE),
+    }
+}
+
+pub(super) fn insert_deadline(headers: &mut HeaderMap, deadline: SystemTime) {
+    let millis = deadline
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    headers.insert(
+        deadline_header_name(),
+        HeaderValue::from_dangerous_value(millis.to_string().into()),
+    );
+}
+
+fn deadline_header_name() -> HeaderName {
+    HeaderName::from_static(DEADLINE_HEADER)
+}
+
+fn try_publish(client: &Client, mut publish: Publish) -> Result<(), TryCommandError> {
+    client.tag_outgoing(&publish.subject, &mut publish.headers);
+    client
+        .check_payload_size(&publish.headers, &publish.payload)
+        .map_err(|max| TryCommandError::PayloadTooLarge { max })?;
+
+    let Some(command) = client
+        .buffer_offline(HandlerCommand::Publish {
+            message: publish.into_message_base(),
+        })
+        .map_err(|OfflineBufferFull| TryCommandError::OfflineBufferFull)?
+    else {
+        return Ok(());
+    };
+    client.try_enqueue_command(command)
+}
+
+async fn publish(client: &Client, mut publish: Publish) -> Result<(), PublishError> {
+    client.tag_outgoing(&publish.subject, &mut publish.headers);
+    client
+        .check_payload_size(&publish.headers, &publish.payload)
+        .map_err(|max| PublishError::PayloadTooLarge { max })?;
+
+    let Some(command) = client
+        .buffer_offline(HandlerCommand::Publish {
+            message: publish.into_message_base(),
+        })
+        .map_err(|OfflineBufferFull| PublishError::OfflineBufferFull)?
+    else {
+        return Ok(());
+    };
+    client.enqueue_command(command).await?;
+    Ok(())
 }
 
-async fn publish(client: &Client, publish: Publish) -> Result<(), ClientClosedError> {
+pub(crate) async fn publish_backpressured(
+    client: &Client,
+    mut publish: Publish,
+) -> Result<(), PublishError> {
+    client.tag_outgoing(&publish.subject, &mut publish.headers);
     client
-        .enqueue_command(HandlerCommand::Publish {
+        .check_payload_size(&publish.headers, &publish.payload)
+        .map_err(|max| PublishError::PayloadTooLarge { max })?;
+
+    let (enqueued, wait_enqueued) = oneshot::channel();
+    let command = client
+        .buffer_offline(HandlerCommand::PublishBackpressured {
             message: publish.into_message_base(),
+            enqueued,
         })
-        .await
+        .map_err(|OfflineBufferFull| PublishError::OfflineBufferFull)?;
+    if let Some(command) = command {
+        client.enqueue_command(command).await?;
+    }
+    wait_enqueued.await.map_err(|_| {
+        if client.dropped_offline() {
+            PublishError::OfflineBufferFull
+        } else {
+            PublishError::Closed(client.closed_error())
+        }
+    })
 }
 
 impl Debug for ClientPublish<'_> {
@@ -300,3 +471,299 @@ impl Debug for DoOwnedClientPublish {
             .finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        num::NonZeroUsize,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use bytes::Bytes;
+    use watermelon_proto::{
+        headers::{HeaderMap, HeaderName, HeaderValue},
+        Message, ServerMessage, Subject, SubscriptionId,
+    };
+
+    use crate::{
+        client::{PublishError, RespondError, TryCommandError},
+        core::{Client, OfflineBufferPolicy},
+        handler::HandlerCommand,
+    };
+
+    use super::{propagated_deadline, Publish, DEADLINE_HEADER};
+
+    #[test]
+    fn payload_helpers() {
+        let subject = Subject::from_static("abcd");
+        let payloads = [
+            Publish::builder(subject.clone()).payload_static(b"test"),
+            Publish::builder(subject.clone()).payload_copy(b"test"),
+            Publish::builder(subject.clone()).payload_from(b"test".to_vec()),
+            Publish::builder(subject.clone()).payload_from("test".to_owned()),
+        ];
+        for publish in payloads {
+            assert_eq!(subject, publish.subject);
+            assert_eq!(Bytes::from_static(b"test"), publish.payload);
+        }
+    }
+
+    #[test]
+    fn propagation_headers() {
+        let deadline = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let publish = Publish::builder(Subject::from_static("abcd"))
+            .trace_context(
+                HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+                Some(HeaderValue::from_static("congo=t61rcWkgMzE")),
+            )
+            .propagate_deadline(deadline)
+            .payload(Bytes::new());
+
+        assert_eq!(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            publish
+                .headers
+                .get(&HeaderName::TRACEPARENT)
+                .unwrap()
+                .as_str()
+        );
+        assert_eq!(
+            "congo=t61rcWkgMzE",
+            publish
+                .headers
+                .get(&HeaderName::TRACESTATE)
+                .unwrap()
+                .as_str()
+        );
+        assert_eq!(
+            "1700000000123",
+            publish
+                .headers
+                .get(&HeaderName::from_static(DEADLINE_HEADER))
+                .unwrap()
+                .as_str()
+        );
+        assert_eq!(Some(deadline), propagated_deadline(&publish.headers));
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(None, propagated_deadline(&headers));
+        headers.insert(
+            HeaderName::from_static(DEADLINE_HEADER),
+            HeaderValue::from_static("soon"),
+        );
+        assert_eq!(None, propagated_deadline(&headers));
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    #[tokio::test]
+    async fn inject_trace_context() {
+        use opentelemetry::trace::{TraceContextExt as _, TracerProvider as _};
+        use tracing::Instrument as _;
+        use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+        use tracing_subscriber::layer::SubscriberExt as _;
+
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // Disabled by default
+        let (client, mut handler) = Client::test(8);
+        async {
+            client
+                .publish(Subject::from_static("abcd"))
+                .payload(Bytes::new())
+                .await
+                .unwrap();
+        }
+        .instrument(tracing::info_span!("publish"))
+        .await;
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert!(message.headers.get(&HeaderName::TRACEPARENT).is_none());
+
+        let (client, mut handler) =
+            Client::test_with_builder(Client::builder().propagate_trace_context(true), 8);
+        let span = tracing::info_span!("publish");
+        let span_context = span.context().span().span_context().clone();
+        let explicit =
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01");
+
+        async {
+            client
+                .publish(Subject::from_static("abcd"))
+                .payload(Bytes::new())
+                .await
+                .unwrap();
+            client
+                .publish(Subject::from_static("abcd"))
+                .trace_context(explicit.clone(), None)
+                .payload(Bytes::new())
+                .await
+                .unwrap();
+        }
+        .instrument(span)
+        .await;
+        client
+            .publish(Subject::from_static("abcd"))
+            .payload(Bytes::new())
+            .await
+            .unwrap();
+
+        let expected = [
+            Some(format!(
+                "00-{}-{}-01",
+                span_context.trace_id(),
+                span_context.span_id()
+            )),
+            Some(explicit.as_str().to_owned()),
+            // Outside of any span
+            None,
+        ];
+        for expected in expected {
+            let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+                unreachable!()
+            };
+            assert_eq!(
+                expected.as_deref(),
+                message
+                    .headers
+                    .get(&HeaderName::TRACEPARENT)
+                    .map(HeaderValue::as_str)
+            );
+            assert!(message.headers.get(&HeaderName::TRACESTATE).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn client_payload_from() {
+        let (client, mut handler) = Client::test(1);
+
+        let payload = b"test".to_vec();
+        let ptr = payload.as_ptr();
+        client
+            .publish(Subject::from_static("abcd"))
+            .payload_from(payload)
+            .await
+            .unwrap();
+
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(Bytes::from_static(b"test"), message.payload);
+        assert_eq!(ptr, message.payload.as_ptr());
+    }
+
+    #[tokio::test]
+    async fn payload_too_large() {
+        let (client, mut handler) = Client::test(1);
+        let max_payload = client.server_info().max_payload;
+        let max_len = usize::try_from(max_payload.get()).unwrap();
+
+        let err = client
+            .publish(Subject::from_static("abcd"))
+            .payload(vec![0; max_len + 1].into())
+            .try_publish()
+            .unwrap_err();
+        assert!(matches!(err, TryCommandError::PayloadTooLarge { max } if max == max_payload));
+
+        let err = client
+            .publish(Subject::from_static("abcd"))
+            .payload(vec![0; max_len + 1].into())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PublishError::PayloadTooLarge { max } if max == max_payload));
+        assert!(handler.receiver.try_recv().is_err());
+
+        client
+            .publish(Subject::from_static("abcd"))
+            .payload(vec![0; max_len].into())
+            .await
+            .unwrap();
+        assert!(handler.receiver.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn respond() {
+        let (client, mut handler) = Client::test(1);
+
+        let mut message = Message {
+            subject: Subject::from_static("ping"),
+            reply: None,
+            headers: HeaderMap::new(),
+            payload: Bytes::new(),
+        };
+        let err = client
+            .respond(&message, Bytes::from_static(b"pong"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RespondError::MissingReplySubject));
+
+        message.reply = Some(Subject::from_static("_INBOX.abcd"));
+        client
+            .respond(&message, Bytes::from_static(b"pong"))
+            .await
+            .unwrap();
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(Subject::from_static("_INBOX.abcd"), message.subject);
+        assert_eq!(Bytes::from_static(b"pong"), message.payload);
+    }
+
+    #[tokio::test]
+    async fn respond_to_server_message() {
+        let (client, mut handler) = Client::test(1);
+
+        let request = ServerMessage {
+            status_code: None,
+            subscription_id: SubscriptionId::from(1),
+            base: Message {
+                subject: Subject::from_static("ping"),
+                reply: Some(Subject::from_static("_INBOX.efgh")),
+                headers: HeaderMap::new(),
+                payload: Bytes::new(),
+            }
+            .into(),
+        };
+        client
+            .respond(&request, Bytes::from_static(b"pong"))
+            .await
+            .unwrap();
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(Subject::from_static("_INBOX.efgh"), message.subject);
+    }
+
+    #[tokio::test]
+    async fn offline_buffer() {
+        let (client, mut handler) = Client::test_with_builder(
+            Client::builder()
+                .offline_buffer(NonZeroUsize::new(1).unwrap(), OfflineBufferPolicy::Reject),
+            1,
+        );
+
+        // The test client is never marked as connected
+        client
+            .publish(Subject::from_static("abcd"))
+            .payload_static(b"1")
+            .await
+            .unwrap();
+        let err = client
+            .publish(Subject::from_static("abcd"))
+            .payload_static(b"2")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PublishError::OfflineBufferFull));
+        let err = client
+            .publish(Subject::from_static("abcd"))
+            .payload_static(b"3")
+            .try_publish()
+            .unwrap_err();
+        assert!(matches!(err, TryCommandError::OfflineBufferFull));
+
+        assert!(handler.receiver.try_recv().is_err());
+    }
+}
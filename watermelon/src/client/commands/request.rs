@@ -1,16 +1,20 @@
 use std::{
     fmt::{self, Debug},
     future::{Future, IntoFuture},
-    num::NonZeroU64,
+    num::{NonZeroU32, NonZeroU64},
     pin::Pin,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use bytes::Bytes;
-use futures_core::{future::BoxFuture, Stream};
+use futures_core::{
+    future::{BoxFuture, FusedFuture},
+    Stream,
+};
 use pin_project_lite::pin_project;
-use tokio::time::{sleep, Sleep};
+use serde::de::DeserializeOwned;
+use tokio::time::{sleep, sleep_until, Instant, Sleep};
 use watermelon_proto::{
     error::ServerError,
     headers::{HeaderMap, HeaderName, HeaderValue},
@@ -18,18 +22,26 @@ use watermelon_proto::{
 };
 
 use crate::{
-    client::{Client, ClientClosedError, TryCommandError},
+    client::{
+        backoff::ReconnectBackoff, create_inbox_subject, Client, PublishError, TryCommandError,
+    },
     core::MultiplexedSubscription,
     subscription::Subscription,
 };
 
-use super::Publish;
+use super::{
+    publish::{insert_deadline, insert_trace_context},
+    Publish,
+};
 
 /// A publishable request
 #[derive(Debug, Clone)]
 pub struct Request {
     pub(super) publish: Publish,
     pub(super) response_timeout: Option<Duration>,
+    pub(super) response_deadline: Option<Instant>,
+    pub(super) no_responders_is_error: bool,
+    pub(super) inbox_prefix: Option<Subject>,
 }
 
 /// A constructor for a publishable request
@@ -78,6 +90,8 @@ pin_project! {
         subscription: ResponseSubscription,
         #[pin]
         timeout: Sleep,
+        no_responders_is_error: bool,
+        terminated: bool,
     }
 }
 
@@ -107,14 +121,62 @@ pub enum ResponseError {
     SubscriptionClosed,
 }
 
+/// An error encountered while making a request with a JSON response
+///
+/// Returned by [`DoClientRequest::json`] and [`DoOwnedClientRequest::json`].
+#[derive(Debug, thiserror::Error)]
+pub enum JsonRequestError {
+    /// Publishing the request failed
+    #[error("publish error")]
+    Publish(#[source] PublishError),
+    /// No response was received
+    #[error("response error")]
+    Response(#[source] ResponseError),
+    /// The response payload couldn't be deserialized
+    #[error("deserialize error")]
+    Deserialize(#[source] serde_json::Error),
+}
+
+/// An error encountered while making a request with retries
+///
+/// Returned by [`DoClientRequest::retry`] and [`DoOwnedClientRequest::retry`].
+#[derive(Debug, thiserror::Error)]
+pub enum RetryRequestError {
+    /// Publishing the request failed
+    #[error("publish error")]
+    Publish(#[source] PublishError),
+    /// No response was received by the last attempt, or a non-retryable error occurred
+    #[error("response error")]
+    Response(#[source] ResponseError),
+}
+
 macro_rules! request {
     () => {
+        /// Receive the response on a dedicated subscription to `reply_subject`
+        ///
+        /// By default responses are received through a subscription shared by
+        /// all requests made by the client. Setting a reply subject opts out of it.
         #[must_use]
         pub fn reply_subject(mut self, reply_subject: Option<Subject>) -> Self {
             self.request_mut().publish.reply_subject = reply_subject;
             self
         }
 
+        /// Receive the response on a dedicated subscription to a new inbox under `inbox_prefix`
+        ///
+        /// Overrides [`ClientBuilder::inbox_prefix`] for this request only, for example
+        /// to route the response through a different account. Ignored if a reply subject
+        /// was set via [`Self::reply_subject`]. Publishing the request fails with
+        /// [`PublishError::InvalidInboxPrefix`] if `inbox_prefix` contains wildcards.
+        ///
+        /// [`ClientBuilder::inbox_prefix`]: crate::core::ClientBuilder::inbox_prefix
+        /// [`PublishError::InvalidInboxPrefix`]: crate::core::error::PublishError::InvalidInboxPrefix
+        #[must_use]
+        pub fn inbox_prefix(mut self, inbox_prefix: Subject) -> Self {
+            self.request_mut().inbox_prefix = Some(inbox_prefix);
+            self
+        }
+
         #[must_use]
         pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
             self.request_mut().publish.headers.insert(name, value);
@@ -132,6 +194,75 @@ macro_rules! request {
             self.request_mut().response_timeout = Some(timeout);
             self
         }
+
+        /// Stop waiting for the response at `deadline`
+        ///
+        /// Useful when propagating an overall deadline across multiple calls,
+        /// without having to recompute the remaining time for each one.
+        /// A [`std::time::Instant`] can be converted via [`Instant::from_std`].
+        /// Takes precedence over [`Self::response_timeout`] when both are set.
+        ///
+        /// [`Instant::from_std`]: tokio::time::Instant::from_std
+        #[must_use]
+        pub fn response_deadline(mut self, deadline: Instant) -> Self {
+            self.request_mut().response_deadline = Some(deadline);
+            self
+        }
+
+        /// Propagate the W3C Trace Context of the caller to the responder
+        ///
+        /// Sets the [`HeaderName::TRACEPARENT`] header and, if given,
+        /// the [`HeaderName::TRACESTATE`] header. As with publishes, this takes
+        /// precedence over the context of the current span propagated
+        /// via `ClientBuilder::propagate_trace_context`.
+        #[must_use]
+        pub fn trace_context(
+            mut self,
+            traceparent: HeaderValue,
+            tracestate: Option<HeaderValue>,
+        ) -> Self {
+            insert_trace_context(
+                &mut self.request_mut().publish.headers,
+                traceparent,
+                tracestate,
+            );
+            self
+        }
+
+        /// Propagate `deadline` to the responder and stop waiting for the response at `deadline`
+        ///
+        /// Sets the `Watermelon-Deadline` header, which can be read back
+        /// by the responder via [`propagated_deadline`], so that it can
+        /// propagate the same deadline to the requests it makes in turn.
+        /// Overrides any previously set [`Self::response_deadline`].
+        ///
+        /// [`propagated_deadline`]: crate::core::publish::propagated_deadline
+        #[must_use]
+        pub fn propagate_deadline(mut self, deadline: SystemTime) -> Self {
+            let request = self.request_mut();
+            insert_deadline(&mut request.publish.headers, deadline);
+            request.response_deadline = Some(
+                Instant::now()
+                    + deadline
+                        .duration_since(SystemTime::now())
+                        .unwrap_or_default(),
+            );
+            self
+        }
+
+        /// Whether a no responders notification from the server should be reported as an error
+        ///
+        /// When `true`, [`ResponseFut`] resolves to [`ResponseError::NoResponders`] as soon
+        /// as the server reports that nobody is subscribed to the subject.
+        /// When `false`, the notification is returned as a message having the
+        /// [`StatusCode::NO_RESPONDERS`] status code.
+        ///
+        /// Default: `true`.
+        #[must_use]
+        pub fn no_responders_is_error(mut self, no_responders_is_error: bool) -> Self {
+            self.request_mut().no_responders_is_error = no_responders_is_error;
+            self
+        }
     };
 }
 
@@ -171,6 +302,9 @@ impl RequestBuilder {
                     payload: Bytes::new(),
                 },
                 response_timeout: None,
+                response_deadline: None,
+                no_responders_is_error: true,
+                inbox_prefix: None,
             },
         }
     }
@@ -246,14 +380,62 @@ impl DoClientRequest<'_> {
     ///
     /// # Errors
     ///
-    /// It returns an error if the client's buffer is full or if the client has been closed.
+    /// It returns an error if the client's buffer is full, if the message is bigger
+    /// than the server's `max_payload` or if the client has been closed.
     pub fn try_request(self) -> Result<ResponseFut, TryCommandError> {
         try_request(self.client, self.request)
     }
+
+    /// Publish this request and deserialize the JSON payload of the response into `T`
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if publishing the request fails, if no response is
+    /// received, if the server reports that there are no responders or if
+    /// the response payload cannot be deserialized into `T`.
+    pub async fn json<T>(self) -> Result<T, JsonRequestError>
+    where
+        T: DeserializeOwned,
+    {
+        json_response(self.await).await
+    }
+
+    /// Publish this request, re-issuing it if no response is received
+    ///
+    /// The request is made up to `max_attempts` times, as long as it fails with
+    /// [`ResponseError::NoResponders`], [`ResponseError::TimedOut`] or
+    /// [`ResponseError::SubscriptionClosed`] caused by the client reconnecting
+    /// to the server. Requests cancelled via [`Client::cancel_all_requests`]
+    /// aren't retried. The delay between attempts starts from `backoff` and
+    /// doubles after every failed attempt, up to `max_backoff`.
+    ///
+    /// When a [`response_deadline`] is set, no attempt is made past it.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if publishing the request fails, if a non-retryable
+    /// [`ResponseError`] is received or if the last attempt fails.
+    ///
+    /// [`response_deadline`]: ClientRequest::response_deadline
+    pub async fn retry(
+        self,
+        max_attempts: NonZeroU32,
+        backoff: Duration,
+        max_backoff: Duration,
+    ) -> Result<ServerMessage, RetryRequestError> {
+        retry_request(
+            self.client,
+            self.request,
+            max_attempts,
+            backoff,
+            max_backoff,
+        )
+        .await
+    }
 }
 
 impl<'a> IntoFuture for DoClientRequest<'a> {
-    type Output = Result<ResponseFut, ClientClosedError>;
+    type Output = Result<ResponseFut, PublishError>;
     type IntoFuture = BoxFuture<'a, Self::Output>;
 
     fn into_future(self) -> Self::IntoFuture {
@@ -270,14 +452,62 @@ impl DoOwnedClientRequest {
     ///
     /// # Errors
     ///
-    /// It returns an error if the client's buffer is full or if the client has been closed.
+    /// It returns an error if the client's buffer is full, if the message is bigger
+    /// than the server's `max_payload` or if the client has been closed.
     pub fn try_request(self) -> Result<ResponseFut, TryCommandError> {
         try_request(&self.client, self.request)
     }
+
+    /// Publish this request and deserialize the JSON payload of the response into `T`
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if publishing the request fails, if no response is
+    /// received, if the server reports that there are no responders or if
+    /// the response payload cannot be deserialized into `T`.
+    pub async fn json<T>(self) -> Result<T, JsonRequestError>
+    where
+        T: DeserializeOwned,
+    {
+        json_response(self.await).await
+    }
+
+    /// Publish this request, re-issuing it if no response is received
+    ///
+    /// The request is made up to `max_attempts` times, as long as it fails with
+    /// [`ResponseError::NoResponders`], [`ResponseError::TimedOut`] or
+    /// [`ResponseError::SubscriptionClosed`] caused by the client reconnecting
+    /// to the server. Requests cancelled via [`Client::cancel_all_requests`]
+    /// aren't retried. The delay between attempts starts from `backoff` and
+    /// doubles after every failed attempt, up to `max_backoff`.
+    ///
+    /// When a [`response_deadline`] is set, no attempt is made past it.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if publishing the request fails, if a non-retryable
+    /// [`ResponseError`] is received or if the last attempt fails.
+    ///
+    /// [`response_deadline`]: OwnedClientRequest::response_deadline
+    pub async fn retry(
+        self,
+        max_attempts: NonZeroU32,
+        backoff: Duration,
+        max_backoff: Duration,
+    ) -> Result<ServerMessage, RetryRequestError> {
+        retry_request(
+            &self.client,
+            self.request,
+            max_attempts,
+            backoff,
+            max_backoff,
+        )
+        .await
+    }
 }
 
 impl IntoFuture for DoOwnedClientRequest {
-    type Output = Result<ResponseFut, ClientClosedError>;
+    type Output = Result<ResponseFut, PublishError>;
     type IntoFuture = BoxFuture<'static, Self::Output>;
 
     fn into_future(self) -> Self::IntoFuture {
@@ -290,15 +520,20 @@ impl Future for ResponseFut {
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
+        if *this.terminated {
+            // Already resolved. The inner receiver must not be polled again
+            return Poll::Pending;
+        }
 
-        match this.subscription {
+        let result = match this.subscription {
             ResponseSubscription::Multiplexed(receiver) => match Pin::new(receiver).poll(cx) {
                 Poll::Pending => match this.timeout.poll(cx) {
                     Poll::Pending => Poll::Pending,
                     Poll::Ready(()) => Poll::Ready(Err(ResponseError::TimedOut)),
                 },
                 Poll::Ready(Ok(message))
-                    if message.status_code == Some(StatusCode::NO_RESPONDERS) =>
+                    if *this.no_responders_is_error
+                        && message.status_code == Some(StatusCode::NO_RESPONDERS) =>
                 {
                     Poll::Ready(Err(ResponseError::NoResponders))
                 }
@@ -312,7 +547,8 @@ impl Future for ResponseFut {
                         Poll::Ready(()) => Poll::Ready(Err(ResponseError::TimedOut)),
                     },
                     Poll::Ready(Some(Ok(message)))
-                        if message.status_code == Some(StatusCode::NO_RESPONDERS) =>
+                        if *this.no_responders_is_error
+                            && message.status_code == Some(StatusCode::NO_RESPONDERS) =>
                     {
                         Poll::Ready(Err(ResponseError::NoResponders))
                     }
@@ -323,11 +559,26 @@ impl Future for ResponseFut {
                     Poll::Ready(None) => Poll::Ready(Err(ResponseError::SubscriptionClosed)),
                 }
             }
+        };
+        if result.is_ready() {
+            *this.terminated = true;
         }
+        result
     }
 }
 
-fn try_request(client: &Client, request: Request) -> Result<ResponseFut, TryCommandError> {
+impl FusedFuture for ResponseFut {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+fn try_request(client: &Client, mut request: Request) -> Result<ResponseFut, TryCommandError> {
+    client
+        .check_payload_size(&request.publish.headers, &request.publish.payload)
+        .map_err(|max| TryCommandError::PayloadTooLarge { max })?;
+    apply_inbox_prefix(&mut request).map_err(|()| TryCommandError::InvalidInboxPrefix)?;
+
     let subscription = if let Some(reply_subject) = &request.publish.reply_subject {
         let subscription = client.try_subscribe(reply_subject.clone(), None)?;
         client.lazy_unsubscribe(subscription.id, Some(NonZeroU64::new(1).unwrap()));
@@ -343,18 +594,21 @@ fn try_request(client: &Client, request: Request) -> Result<ResponseFut, TryComm
         ResponseSubscription::Multiplexed(receiver)
     };
 
-    let timeout = sleep(
-        request
-            .response_timeout
-            .unwrap_or(client.default_response_timeout()),
-    );
+    let timeout = response_timeout(client, request.response_timeout, request.response_deadline);
     Ok(ResponseFut {
         subscription,
         timeout,
+        no_responders_is_error: request.no_responders_is_error,
+        terminated: false,
     })
 }
 
-async fn request(client: &Client, request: Request) -> Result<ResponseFut, ClientClosedError> {
+async fn request(client: &Client, mut request: Request) -> Result<ResponseFut, PublishError> {
+    client
+        .check_payload_size(&request.publish.headers, &request.publish.payload)
+        .map_err(|max| PublishError::PayloadTooLarge { max })?;
+    apply_inbox_prefix(&mut request).map_err(|()| PublishError::InvalidInboxPrefix)?;
+
     let subscription = if let Some(reply_subject) = &request.publish.reply_subject {
         let subscription = client.subscribe(reply_subject.clone(), None).await?;
         client.lazy_unsubscribe(subscription.id, Some(NonZeroU64::new(1).unwrap()));
@@ -372,17 +626,104 @@ async fn request(client: &Client, request: Request) -> Result<ResponseFut, Clien
         ResponseSubscription::Multiplexed(receiver)
     };
 
-    let timeout = sleep(
-        request
-            .response_timeout
-            .unwrap_or(client.default_response_timeout()),
-    );
+    let timeout = response_timeout(client, request.response_timeout, request.response_deadline);
     Ok(ResponseFut {
         subscription,
         timeout,
+        no_responders_is_error: request.no_responders_is_error,
+        terminated: false,
     })
 }
 
+async fn retry_request(
+    client: &Client,
+    request: Request,
+    max_attempts: NonZeroU32,
+    backoff: Duration,
+    max_backoff: Duration,
+) -> Result<ServerMessage, RetryRequestError> {
+    let backoff = ReconnectBackoff::new(backoff, max_backoff, 0.0);
+    let quick_info = &client.inner.quick_info;
+
+    let mut attempt = 1;
+    loop {
+        let connections = quick_info.connections();
+        let response = self::request(client, request.clone())
+            .await
+            .map_err(RetryRequestError::Publish)?;
+        let err = match response.await {
+            Ok(message) => return Ok(message),
+            Err(err) => err,
+        };
+
+        let is_retryable = match err {
+            ResponseError::NoResponders | ResponseError::TimedOut => true,
+            // Closed by the client disconnecting, rather than by `Client::cancel_all_requests`
+            ResponseError::SubscriptionClosed => {
+                !quick_info.get().is_connected || quick_info.connections() != connections
+            }
+            ResponseError::ServerError(_) => false,
+        };
+        let delay = backoff.delay(attempt - 1);
+        let is_past_deadline = request
+            .response_deadline
+            .is_some_and(|deadline| Instant::now() + delay >= deadline);
+        if !is_retryable || attempt >= max_attempts.get() || is_past_deadline {
+            return Err(RetryRequestError::Response(err));
+        }
+
+        trace_event!(debug, "retrying request", attempt = %attempt, error = %err);
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Generate the reply subject of a request having a custom inbox prefix
+///
+/// Returns `Err` if the inbox prefix contains wildcards.
+fn apply_inbox_prefix(request: &mut Request) -> Result<(), ()> {
+    let Some(inbox_prefix) = request.inbox_prefix.take() else {
+        return Ok(());
+    };
+    if inbox_prefix
+        .tokens()
+        .any(|token| token == "*" || token == ">")
+    {
+        return Err(());
+    }
+
+    if request.publish.reply_subject.is_none() {
+        request.publish.reply_subject = Some(create_inbox_subject(&inbox_prefix));
+    }
+    Ok(())
+}
+
+fn response_timeout(
+    client: &Client,
+    response_timeout: Option<Duration>,
+    response_deadline: Option<Instant>,
+) -> Sleep {
+    match response_deadline {
+        Some(deadline) => sleep_until(deadline),
+        None => sleep(response_timeout.unwrap_or(client.default_response_timeout())),
+    }
+}
+
+async fn json_response<T>(
+    response: Result<ResponseFut, PublishError>,
+) -> Result<T, JsonRequestError>
+where
+    T: DeserializeOwned,
+{
+    let response = response.map_err(JsonRequestError::Publish)?;
+    let message = response.await.map_err(JsonRequestError::Response)?;
+    if message.status_code == Some(StatusCode::NO_RESPONDERS) {
+        return Err(JsonRequestError::Response(ResponseError::NoResponders));
+    }
+
+    serde_json::from_slice(&message.base.payload).map_err(JsonRequestError::Deserialize)
+}
+
 impl Debug for ClientRequest<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ClientRequest")
@@ -414,3 +755,469 @@ impl Debug for DoOwnedClientRequest {
             .finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::{poll_fn, Future as _},
+        num::NonZeroU32,
+        task::Poll,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use bytes::Bytes;
+    use futures_core::future::FusedFuture as _;
+    use serde::Deserialize;
+    use tokio::time::Instant;
+    use watermelon_proto::{StatusCode, Subject};
+
+    use crate::{
+        client::{tests::status_response, PublishError},
+        core::{publish::propagated_deadline, Client},
+        handler::HandlerCommand,
+    };
+
+    use super::{JsonRequestError, Request, ResponseError, RetryRequestError};
+
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    struct Pong {
+        pong: u32,
+    }
+
+    #[tokio::test]
+    async fn fused() {
+        let (client, mut handler) = Client::test(8);
+
+        let response_fut = client
+            .request(Subject::from_static("ping"))
+            .payload(Bytes::new())
+            .await
+            .unwrap();
+        tokio::pin!(response_fut);
+        assert!(!response_fut.is_terminated());
+
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        reply
+            .send(status_response(StatusCode::OK, b"pong"))
+            .unwrap();
+
+        let message = (&mut response_fut).await.unwrap();
+        assert_eq!(Bytes::from_static(b"pong"), message.base.payload);
+        assert!(response_fut.is_terminated());
+
+        let poll = poll_fn(|cx| Poll::Ready(response_fut.as_mut().poll(cx))).await;
+        assert!(poll.is_pending());
+        assert!(response_fut.is_terminated());
+    }
+
+    #[tokio::test]
+    async fn no_responders_is_error() {
+        let (client, mut handler) = Client::test(8);
+
+        for no_responders_is_error in [true, false] {
+            let response_fut = client
+                .request(Subject::from_static("ping"))
+                .no_responders_is_error(no_responders_is_error)
+                .payload(Bytes::new())
+                .await
+                .unwrap();
+            let Some(HandlerCommand::RequestMultiplexed { reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            reply
+                .send(status_response(StatusCode::NO_RESPONDERS, b""))
+                .unwrap();
+            match response_fut.await {
+                Err(ResponseError::NoResponders) => assert!(no_responders_is_error),
+                Ok(message) => {
+                    assert!(!no_responders_is_error);
+                    assert_eq!(Some(StatusCode::NO_RESPONDERS), message.status_code);
+                }
+                Err(err) => panic!("unexpected error {err:?}"),
+            }
+
+            // Requests made through a dedicated subscription
+            let response_fut = client
+                .request(Subject::from_static("ping"))
+                .no_responders_is_error(no_responders_is_error)
+                .inbox_prefix(Subject::from_static("_INBOX_TENANT"))
+                .payload(Bytes::new())
+                .await
+                .unwrap();
+            let Some(HandlerCommand::Subscribe { messages, .. }) = handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            messages
+                .try_send(Ok(status_response(StatusCode::NO_RESPONDERS, b"")))
+                .unwrap();
+            match response_fut.await {
+                Err(ResponseError::NoResponders) => assert!(no_responders_is_error),
+                Ok(message) => {
+                    assert!(!no_responders_is_error);
+                    assert_eq!(Some(StatusCode::NO_RESPONDERS), message.status_code);
+                }
+                Err(err) => panic!("unexpected error {err:?}"),
+            }
+            while handler.receiver.try_recv().is_ok() {}
+        }
+    }
+
+    #[tokio::test]
+    async fn retry() {
+        let (client, mut handler) = Client::test(8);
+
+        let request = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .request(Subject::from_static("ping"))
+                    .payload(Bytes::new())
+                    .retry(
+                        NonZeroU32::new(3).unwrap(),
+                        Duration::from_millis(1),
+                        Duration::from_millis(10),
+                    )
+                    .await
+            }
+        });
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        reply
+            .send(status_response(StatusCode::NO_RESPONDERS, b""))
+            .unwrap();
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        reply
+            .send(status_response(StatusCode::OK, b"pong"))
+            .unwrap();
+        let message = request.await.unwrap().unwrap();
+        assert_eq!(Bytes::from_static(b"pong"), message.base.payload);
+
+        let request = tokio::spawn(async move {
+            client
+                .request(Subject::from_static("ping"))
+                .payload(Bytes::new())
+                .retry(
+                    NonZeroU32::new(2).unwrap(),
+                    Duration::from_millis(1),
+                    Duration::from_millis(10),
+                )
+                .await
+        });
+        for _ in 0..2 {
+            let Some(HandlerCommand::RequestMultiplexed { reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            reply
+                .send(status_response(StatusCode::NO_RESPONDERS, b""))
+                .unwrap();
+        }
+        assert!(matches!(
+            request.await.unwrap(),
+            Err(RetryRequestError::Response(ResponseError::NoResponders))
+        ));
+        assert!(handler.receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn retry_subscription_closed() {
+        let (client, mut handler) = Client::test(8);
+        handler.quick_info.store_is_connected(true);
+
+        let request = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .request(Subject::from_static("ping"))
+                    .payload(Bytes::new())
+                    .retry(
+                        NonZeroU32::new(3).unwrap(),
+                        Duration::from_millis(1),
+                        Duration::from_millis(10),
+                    )
+                    .await
+            }
+        });
+
+        // Closed by the client disconnecting
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        handler.quick_info.store_is_connected(false);
+        drop(reply);
+        handler.quick_info.store_is_connected(true);
+
+        // Closed while connected, as done by `Client::cancel_all_requests`
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        drop(reply);
+        assert!(matches!(
+            request.await.unwrap(),
+            Err(RetryRequestError::Response(
+                ResponseError::SubscriptionClosed
+            ))
+        ));
+        assert!(handler.receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn retry_deadline() {
+        let (client, mut handler) = Client::test(8);
+
+        let request = tokio::spawn(async move {
+            client
+                .request(Subject::from_static("ping"))
+                .response_deadline(Instant::now() + Duration::from_secs(5))
+                .payload(Bytes::new())
+                .retry(
+                    NonZeroU32::new(3).unwrap(),
+                    Duration::from_secs(10),
+                    Duration::from_secs(10),
+                )
+                .await
+        });
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        reply
+            .send(status_response(StatusCode::NO_RESPONDERS, b""))
+            .unwrap();
+
+        // Backing off would go past the deadline
+        assert!(matches!(
+            request.await.unwrap(),
+            Err(RetryRequestError::Response(ResponseError::NoResponders))
+        ));
+        assert!(handler.receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn inbox_prefix() {
+        let (client, mut handler) = Client::test(8);
+
+        let _response_fut = client
+            .request(Subject::from_static("ping"))
+            .inbox_prefix(Subject::from_static("_INBOX_TENANT"))
+            .payload(Bytes::new())
+            .try_request()
+            .unwrap();
+        let HandlerCommand::Subscribe { subject: inbox, .. } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        assert!(inbox.as_str().starts_with("_INBOX_TENANT."));
+        let _ = handler.receiver.try_recv().unwrap();
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(Some(inbox), message.reply_subject);
+
+        let err = client
+            .request(Subject::from_static("ping"))
+            .inbox_prefix(Subject::from_static("_INBOX.*"))
+            .payload(Bytes::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PublishError::InvalidInboxPrefix));
+    }
+
+    #[tokio::test]
+    async fn json() {
+        let (client, mut handler) = Client::test(8);
+
+        let request = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .request(Subject::from_static("ping"))
+                    .payload(Bytes::new())
+                    .json::<Pong>()
+                    .await
+            }
+        });
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        reply
+            .send(status_response(StatusCode::OK, br#"{"pong":42}"#))
+            .unwrap();
+        assert_eq!(Pong { pong: 42 }, request.await.unwrap().unwrap());
+
+        let request = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .request(Subject::from_static("ping"))
+                    .no_responders_is_error(false)
+                    .payload(Bytes::new())
+                    .json::<Pong>()
+                    .await
+            }
+        });
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        reply
+            .send(status_response(StatusCode::NO_RESPONDERS, b""))
+            .unwrap();
+        assert!(matches!(
+            request.await.unwrap(),
+            Err(JsonRequestError::Response(ResponseError::NoResponders))
+        ));
+
+        let request = tokio::spawn(async move {
+            client
+                .request(Subject::from_static("ping"))
+                .payload(Bytes::new())
+                .json::<Pong>()
+                .await
+        });
+        let Some(HandlerCommand::RequestMultiplexed { reply, .. }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        reply
+            .send(status_response(StatusCode::OK, b"pong"))
+            .unwrap();
+        assert!(matches!(
+            request.await.unwrap(),
+            Err(JsonRequestError::Deserialize(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn pooled_reply_subjects() {
+        let (client, mut handler) =
+            Client::test_with_builder(Client::builder().request_inbox_pool(4), 8);
+
+        let request = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .request(Subject::from_static("ping"))
+                    .payload(Bytes::new())
+                    .await
+                    .unwrap()
+                    .await
+            }
+        });
+        let Some(HandlerCommand::RequestMultiplexed {
+            reply_subject: first_reply_subject,
+            reply,
+            ..
+        }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        reply
+            .send(status_response(StatusCode::OK, b"pong"))
+            .unwrap();
+        request.await.unwrap().unwrap();
+
+        // The reply subject of the completed request is reused
+        let request = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .request(Subject::from_static("ping"))
+                    .payload(Bytes::new())
+                    .await
+                    .unwrap()
+                    .await
+            }
+        });
+        let Some(HandlerCommand::RequestMultiplexed {
+            reply_subject: second_reply_subject,
+            ..
+        }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        assert_eq!(first_reply_subject, second_reply_subject);
+
+        // The reply subject of the cancelled request isn't reused
+        request.abort();
+        let _ = request.await;
+        let Some(HandlerCommand::UnsubscribeMultiplexed { reply_subject }) =
+            handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        assert_eq!(second_reply_subject, reply_subject);
+
+        let _request = client
+            .request(Subject::from_static("ping"))
+            .payload(Bytes::new())
+            .await
+            .unwrap();
+        let Some(HandlerCommand::RequestMultiplexed {
+            reply_subject: third_reply_subject,
+            ..
+        }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        assert_ne!(second_reply_subject, third_reply_subject);
+    }
+
+    #[tokio::test]
+    async fn response_deadline() {
+        let (client, mut handler) = Client::test(8);
+
+        let response = client
+            .request(Subject::from_static("ping"))
+            .response_timeout(Duration::from_secs(60))
+            .response_deadline(Instant::now())
+            .payload(Bytes::new())
+            .await
+            .unwrap();
+        let Some(HandlerCommand::RequestMultiplexed { reply: _reply, .. }) =
+            handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        assert!(matches!(response.await, Err(ResponseError::TimedOut)));
+    }
+
+    #[test]
+    fn propagate_deadline() {
+        let deadline = SystemTime::now() + Duration::from_secs(30);
+        let request = Request::builder(Subject::from_static("ping"))
+            .response_deadline(Instant::now())
+            .propagate_deadline(deadline)
+            .payload(Bytes::new());
+
+        let response_deadline = request.response_deadline.unwrap();
+        assert!(response_deadline > Instant::now() + Duration::from_secs(25));
+        assert!(response_deadline <= Instant::now() + Duration::from_secs(30));
+        assert_eq!(
+            Some(deadline.duration_since(UNIX_EPOCH).unwrap().as_millis()),
+            propagated_deadline(&request.publish.headers)
+                .map(|deadline| deadline.duration_since(UNIX_EPOCH).unwrap().as_millis())
+        );
+
+        // A deadline in the past stops waiting right away
+        let request = Request::builder(Subject::from_static("ping"))
+            .propagate_deadline(UNIX_EPOCH)
+            .payload(Bytes::new());
+        assert!(request.response_deadline.unwrap() <= Instant::now());
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    future::Future,
+    num::NonZeroU64,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures_core::{FusedStream, Stream};
+use tokio::time::{sleep, Sleep};
+use watermelon_proto::{ServerMessage, StatusCode, Subject};
+
+use crate::{
+    client::{Client, PublishError},
+    subscription::Subscription,
+};
+
+/// Configuration for [`Client::request_many`]
+///
+/// The [`RequestMany`] stream terminates as soon as any of the configured limits is hit.
+#[derive(Debug, Clone)]
+pub struct RequestManyConfig {
+    max_messages: Option<NonZeroU64>,
+    timeout: Duration,
+    stall: Option<Duration>,
+}
+
+/// A [`Stream`] of responses to a request
+///
+/// Obtained from [`Client::request_many`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct RequestMany {
+    subscription: Subscription,
+    remaining: Option<NonZeroU64>,
+    timeout: Pin<Box<Sleep>>,
+    stall: Option<Duration>,
+    stall_timeout: Option<Pin<Box<Sleep>>>,
+    terminated: bool,
+}
+
+impl RequestManyConfig {
+    /// Construct a new configuration with the default values
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            max_messages: None,
+            timeout: Duration::from_secs(5),
+            stall: None,
+        }
+    }
+
+    /// Stop after receiving `max_messages` responses
+    ///
+    /// Default: no limit.
+    #[must_use]
+    pub fn max_messages(mut self, max_messages: Option<NonZeroU64>) -> Self {
+        self.max_messages = max_messages;
+        self
+    }
+
+    /// Stop after `timeout` has elapsed since the request was published
+    ///
+    /// Default: 5 seconds.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Stop if no further responses are received within `stall` of the previous one
+    ///
+    /// The interval only starts being enforced after the first response is received.
+    ///
+    /// Default: disabled.
+    #[must_use]
+    pub fn stall(mut self, stall: Option<Duration>) -> Self {
+        self.stall = stall;
+        self
+    }
+}
+
+impl Default for RequestManyConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestMany {
+    pub(crate) async fn publish(
+        client: &Client,
+        subject: Subject,
+        payload: Bytes,
+        config: RequestManyConfig,
+    ) -> Result<Self, PublishError> {
+        let reply_subject = client.new_inbox();
+
+        let mut subscription = client.subscribe(reply_subject.clone(), None).await?;
+        if let Some(max_messages) = config.max_messages {
+            subscription.close_after(max_messages).await?;
+        }
+
+        client
+            .publish(subject)
+            .reply_subject(Some(reply_subject))
+            .payload(payload)
+            .await?;
+
+        Ok(Self {
+            subscription,
+            remaining: config.max_messages,
+            timeout: Box::pin(sleep(config.timeout)),
+            stall: config.stall,
+            stall_timeout: None,
+            terminated: false,
+        })
+    }
+}
+
+impl Stream for RequestMany {
+    type Item = ServerMessage;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.terminated {
+            return Poll::Ready(None);
+        }
+
+        match Pin::new(&mut this.subscription).poll_next(cx) {
+            Poll::Ready(Some(Ok(message)))
+                if message.status_code == Some(StatusCode::NO_RESPONDERS) =>
+            {
+                this.terminated = true;
+                return Poll::Ready(None);
+            }
+            Poll::Ready(Some(Ok(message))) => {
+                if let Some(remaining) = this.remaining {
+                    this.remaining = NonZeroU64::new(remaining.get() - 1);
+                    this.terminated = this.remaining.is_none();
+                }
+                if let Some(stall) = this.stall {
+                    this.stall_timeout = Some(Box::pin(sleep(stall)));
+                }
+                return Poll::Ready(Some(message));
+            }
+            Poll::Ready(Some(Err(_)) | None) => {
+                this.terminated = true;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => {}
+        }
+
+        let stalled = this
+            .stall_timeout
+            .as_mut()
+            .is_some_and(|stall_timeout| stall_timeout.as_mut().poll(cx).is_ready());
+        if stalled || this.timeout.as_mut().poll(cx).is_ready() {
+            this.terminated = true;
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+}
+
+impl FusedStream for RequestMany {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroU64, time::Duration};
+
+    use bytes::Bytes;
+    use futures_util::StreamExt as _;
+    use watermelon_proto::{StatusCode, Subject};
+
+    use crate::{client::tests::status_response, core::Client, handler::HandlerCommand};
+
+    use super::{RequestMany, RequestManyConfig};
+
+    #[tokio::test]
+    async fn max_messages() {
+        let (client, mut handler) = Client::test(8);
+
+        let mut responses = RequestMany::publish(
+            &client,
+            Subject::from_static("ping"),
+            Bytes::new(),
+            RequestManyConfig::new().max_messages(NonZeroU64::new(2)),
+        )
+        .await
+        .unwrap();
+
+        let HandlerCommand::Subscribe {
+            subject: inbox,
+            messages,
+            ..
+        } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        let HandlerCommand::Unsubscribe {
+            max_messages: Some(max_messages),
+            ..
+        } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(2, max_messages.get());
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(Some(inbox), message.reply_subject);
+
+        for _ in 0..3 {
+            messages
+                .try_send(Ok(status_response(StatusCode::OK, b"pong")))
+                .unwrap();
+        }
+        assert!(responses.next().await.is_some());
+        assert!(responses.next().await.is_some());
+        assert!(responses.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn no_responders_and_stall() {
+        let (client, mut handler) = Client::test(8);
+
+        let mut responses = RequestMany::publish(
+            &client,
+            Subject::from_static("ping"),
+            Bytes::new(),
+            RequestManyConfig::new(),
+        )
+        .await
+        .unwrap();
+        let HandlerCommand::Subscribe { messages, .. } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        messages
+            .try_send(Ok(status_response(StatusCode::NO_RESPONDERS, b"pong")))
+            .unwrap();
+        assert!(responses.next().await.is_none());
+
+        let mut responses = RequestMany::publish(
+            &client,
+            Subject::from_static("ping"),
+            Bytes::new(),
+            RequestManyConfig::new()
+                .timeout(Duration::from_secs(60))
+                .stall(Some(Duration::from_millis(100))),
+        )
+        .await
+        .unwrap();
+        let _ = handler.receiver.try_recv().unwrap();
+        let HandlerCommand::Subscribe { messages, .. } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        messages
+            .try_send(Ok(status_response(StatusCode::OK, b"pong")))
+            .unwrap();
+        assert!(responses.next().await.is_some());
+        assert!(responses.next().await.is_none());
+    }
+}
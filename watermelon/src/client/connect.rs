@@ -0,0 +1,145 @@
+use watermelon_mini::ConnectError;
+use watermelon_proto::{error::ServerAddrError, ServerAddr};
+
+use crate::core::Client;
+
+/// A value which can be converted into one or more [`ServerAddr`]es
+///
+/// Strings may contain multiple comma separated URLs,
+/// like `nats://127.0.0.1:4222,nats://127.0.0.1:4223`.
+/// Addresses without a scheme, like `127.0.0.1:4222`, default to `nats://`.
+pub trait ToServerAddrs {
+    /// Parse `self` into the list of server addresses
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if any of the addresses is invalid.
+    fn to_server_addrs(&self) -> Result<Vec<ServerAddr>, ServerAddrError>;
+}
+
+/// Connect to the NATS server(s) at `addrs` using the default [`ClientBuilder`] configuration
+///
+/// This is a shorthand for `Client::builder().connect(addrs)`, easing migrations
+/// from other NATS clients.
+///
+/// # Errors
+///
+/// It returns an error if `addrs` is empty or contains an invalid address,
+/// or if connecting to all of the servers fails.
+///
+/// [`ClientBuilder`]: crate::core::ClientBuilder
+pub async fn connect(addrs: impl ToServerAddrs) -> Result<Client, ConnectError> {
+    let addrs = addrs
+        .to_server_addrs()
+        .map_err(ConnectError::InvalidServerAddr)?;
+    Client::builder().connect(addrs).await
+}
+
+impl ToServerAddrs for ServerAddr {
+    fn to_server_addrs(&self) -> Result<Vec<ServerAddr>, ServerAddrError> {
+        Ok(vec![self.clone()])
+    }
+}
+
+impl ToServerAddrs for str {
+    fn to_server_addrs(&self) -> Result<Vec<ServerAddr>, ServerAddrError> {
+        self.split(',')
+            .map(|addr| {
+                let addr = addr.trim();
+                if addr.contains("://") {
+                    addr.parse()
+                } else {
+                    format!("nats://{addr}").parse()
+                }
+            })
+            .collect()
+    }
+}
+
+impl ToServerAddrs for String {
+    fn to_server_addrs(&self) -> Result<Vec<ServerAddr>, ServerAddrError> {
+        self.as_str().to_server_addrs()
+    }
+}
+
+impl<T: ToServerAddrs> ToServerAddrs for [T] {
+    fn to_server_addrs(&self) -> Result<Vec<ServerAddr>, ServerAddrError> {
+        let mut addrs = Vec::with_capacity(self.len());
+        for item in self {
+            addrs.extend(item.to_server_addrs()?);
+        }
+        Ok(addrs)
+    }
+}
+
+impl<T: ToServerAddrs, const N: usize> ToServerAddrs for [T; N] {
+    fn to_server_addrs(&self) -> Result<Vec<ServerAddr>, ServerAddrError> {
+        self.as_slice().to_server_addrs()
+    }
+}
+
+impl<T: ToServerAddrs> ToServerAddrs for Vec<T> {
+    fn to_server_addrs(&self) -> Result<Vec<ServerAddr>, ServerAddrError> {
+        self.as_slice().to_server_addrs()
+    }
+}
+
+impl<T: ToServerAddrs + ?Sized> ToServerAddrs for &T {
+    fn to_server_addrs(&self) -> Result<Vec<ServerAddr>, ServerAddrError> {
+        (**self).to_server_addrs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use watermelon_mini::ConnectError;
+    use watermelon_proto::ServerAddr;
+
+    use super::{connect, ToServerAddrs};
+
+    #[test]
+    fn parse() {
+        let expected = ["nats://127.0.0.1:4222", "nats://127.0.0.1:4223"]
+            .map(|addr| addr.parse::<ServerAddr>().unwrap())
+            .to_vec();
+
+        assert_eq!(
+            expected,
+            "nats://127.0.0.1:4222, nats://127.0.0.1:4223"
+                .to_server_addrs()
+                .unwrap()
+        );
+        assert_eq!(
+            expected,
+            ["nats://127.0.0.1:4222", "nats://127.0.0.1:4223"]
+                .to_server_addrs()
+                .unwrap()
+        );
+        assert_eq!(
+            expected,
+            vec![String::from("nats://127.0.0.1:4222,nats://127.0.0.1:4223")]
+                .to_server_addrs()
+                .unwrap()
+        );
+        assert_eq!(expected, expected.to_server_addrs().unwrap());
+        assert_eq!(expected[..1], expected[0].to_server_addrs().unwrap());
+
+        assert_eq!(
+            expected,
+            "127.0.0.1:4222, 127.0.0.1:4223".to_server_addrs().unwrap()
+        );
+        assert_eq!(
+            ["nats://localhost:4222".parse::<ServerAddr>().unwrap()].to_vec(),
+            "localhost:4222".to_server_addrs().unwrap()
+        );
+
+        assert!("nats://127.0.0.1:4222,".to_server_addrs().is_err());
+        assert!("http://127.0.0.1:4222".to_server_addrs().is_err());
+    }
+
+    #[tokio::test]
+    async fn connect_invalid_addr() {
+        let err = connect("http://127.0.0.1:4222").await.unwrap_err();
+        assert!(matches!(err, ConnectError::InvalidServerAddr(_)));
+    }
+}
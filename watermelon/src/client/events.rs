@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use futures_core::Stream;
+use tokio::sync::broadcast::{self, error::RecvError};
+use watermelon_mini::ConnectError;
+use watermelon_proto::SubscriptionId;
+
+use crate::client::JetstreamError2;
+
+/// A connection lifecycle event
+///
+/// Obtained from [`Client::events`].
+///
+/// [`Client::events`]: crate::core::Client::events
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ClientEvent {
+    /// The client connected, or reconnected, to the server
+    Connected,
+    /// The client lost the connection to the server
+    Disconnected,
+    /// The server entered Lame Duck Mode and is about to shut down
+    LameDuck,
+    /// An attempt at reconnecting to the server failed
+    ReconnectFailed { error: Arc<ConnectError> },
+    /// The client gave up reconnecting to the server and has been closed
+    ///
+    /// This is the last event emitted by the client.
+    /// `discarded_messages` is the number of messages which were waiting
+    /// in the offline buffer and will never be sent.
+    /// See [`ClientBuilder::max_reconnects`] and [`ClientBuilder::offline_buffer`].
+    ///
+    /// [`ClientBuilder::max_reconnects`]: crate::core::ClientBuilder::max_reconnects
+    /// [`ClientBuilder::offline_buffer`]: crate::core::ClientBuilder::offline_buffer
+    ReconnectExhausted { discarded_messages: usize },
+    /// A [`Subscription`] started dropping messages because it isn't being polled fast enough
+    ///
+    /// The event is emitted again only after the subscription has caught up.
+    /// See [`Subscription::dropped_messages`].
+    ///
+    /// [`Subscription`]: crate::core::Subscription
+    /// [`Subscription::dropped_messages`]: crate::core::Subscription::dropped_messages
+    SlowConsumer { subscription_id: SubscriptionId },
+    /// The push consumer feeding a resumable [`Subscription`] couldn't be recreated after reconnecting
+    ///
+    /// The subscription might not receive any more messages.
+    /// See [`JetstreamClient::subscribe_push_resumable`].
+    ///
+    /// [`Subscription`]: crate::core::Subscription
+    /// [`JetstreamClient::subscribe_push_resumable`]: crate::jetstream::JetstreamClient::subscribe_push_resumable
+    ConsumerResumeFailed {
+        subscription_id: SubscriptionId,
+        error: Arc<JetstreamError2>,
+    },
+}
+
+pub(crate) fn events_stream(
+    receiver: broadcast::Receiver<ClientEvent>,
+) -> impl Stream<Item = ClientEvent> {
+    futures_util::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(RecvError::Lagged(_)) => {
+                    // The receiver wasn't fast enough. Skip the lost events
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
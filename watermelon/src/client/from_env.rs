@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{de, Deserialize, Deserializer};
 use watermelon_nkeys::KeyPair;
@@ -9,6 +9,26 @@ pub(super) struct FromEnv {
     #[serde(flatten)]
     pub(super) auth: AuthenticationMethod,
     pub(super) inbox_prefix: Option<Subject>,
+    #[serde(rename = "nats_tls_cert")]
+    pub(super) tls_cert: Option<PathBuf>,
+    #[serde(rename = "nats_tls_key")]
+    pub(super) tls_key: Option<PathBuf>,
+}
+
+/// Only one of `NATS_TLS_CERT` and `NATS_TLS_KEY` was set
+#[derive(Debug, thiserror::Error)]
+#[error("NATS_TLS_CERT and NATS_TLS_KEY must be set together")]
+pub(super) struct TlsClientCertMismatch;
+
+impl FromEnv {
+    /// The paths of the TLS client certificate chain and private key
+    pub(super) fn tls_client_cert(&self) -> Result<Option<(&Path, &Path)>, TlsClientCertMismatch> {
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(tls_cert), Some(tls_key)) => Ok(Some((tls_cert, tls_key))),
+            (None, None) => Ok(None),
+            (Some(_), None) | (None, Some(_)) => Err(TlsClientCertMismatch),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -40,3 +60,50 @@ where
     let secret = String::deserialize(deserializer)?;
     KeyPair::from_encoded_seed(&secret).map_err(de::Error::custom)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{AuthenticationMethod, FromEnv};
+
+    #[test]
+    fn tls_client_cert() {
+        let env = envy::from_iter::<_, FromEnv>([
+            ("NATS_USERNAME".to_owned(), "user".to_owned()),
+            ("NATS_PASSWORD".to_owned(), "pass".to_owned()),
+            ("NATS_TLS_CERT".to_owned(), "/etc/nats/cert.pem".to_owned()),
+            ("NATS_TLS_KEY".to_owned(), "/etc/nats/key.pem".to_owned()),
+        ])
+        .unwrap();
+        assert!(matches!(
+            env.auth,
+            AuthenticationMethod::UserAndPassword { .. }
+        ));
+        assert_eq!(
+            Some(Path::new("/etc/nats/cert.pem")),
+            env.tls_cert.as_deref()
+        );
+        assert_eq!(Some(Path::new("/etc/nats/key.pem")), env.tls_key.as_deref());
+        assert_eq!(
+            Some((
+                Path::new("/etc/nats/cert.pem"),
+                Path::new("/etc/nats/key.pem")
+            )),
+            env.tls_client_cert().unwrap()
+        );
+    }
+
+    #[test]
+    fn tls_client_cert_mismatch() {
+        for var in ["NATS_TLS_CERT", "NATS_TLS_KEY"] {
+            let env = envy::from_iter::<_, FromEnv>([
+                ("NATS_USERNAME".to_owned(), "user".to_owned()),
+                ("NATS_PASSWORD".to_owned(), "pass".to_owned()),
+                (var.to_owned(), "/etc/nats/file.pem".to_owned()),
+            ])
+            .unwrap();
+            assert!(env.tls_client_cert().is_err());
+        }
+    }
+}
@@ -11,10 +11,12 @@ use tokio::time::{sleep, Sleep};
 use watermelon_proto::{error::ServerError, ServerMessage, StatusCode};
 
 use crate::{
-    client::{Consumer, JetstreamClient, JetstreamError2},
+    client::{AckPolicy, Client, Consumer, JetstreamClient, JetstreamError2},
     subscription::Subscription,
 };
 
+use super::JetstreamMessage;
+
 pin_project! {
     /// A consumer batch request
     ///
@@ -23,6 +25,8 @@ pin_project! {
     #[must_use = "streams do nothing unless polled"]
     pub struct ConsumerBatch {
         subscription: Subscription,
+        client: Client,
+        ack_policy: AckPolicy,
         #[pin]
         timeout: Sleep,
         pending_msgs: usize,
@@ -44,15 +48,13 @@ impl ConsumerBatch {
         expires: Duration,
         max_msgs: usize,
     ) -> impl Future<Output = Result<Self, JetstreamError2>> {
-        let subject = format!(
-            "{}.CONSUMER.MSG.NEXT.{}.{}",
-            client.prefix, consumer.stream_name, consumer.config.name
-        )
-        .try_into();
+        let subject =
+            client.consumer_msg_next_subject(&consumer.stream_name, &consumer.config.name);
+        let ack_policy = consumer.config.ack_policy;
 
         async move {
             let subject = subject.map_err(JetstreamError2::Subject)?;
-            let incoming_subject = client.client.create_inbox_subject();
+            let incoming_subject = client.client.new_inbox();
             let payload = serde_json::to_vec(&if expires.is_zero() {
                 json!({
                     "batch": max_msgs,
@@ -78,11 +80,13 @@ impl ConsumerBatch {
                 .reply_subject(Some(incoming_subject.clone()))
                 .payload(payload.into())
                 .await
-                .map_err(JetstreamError2::ClientClosed)?;
+                .map_err(JetstreamError2::Publish)?;
 
             let timeout = sleep(expires.saturating_add(client.request_timeout));
             Ok(Self {
                 subscription,
+                client: client.client,
+                ack_policy,
                 timeout,
                 pending_msgs: max_msgs,
             })
@@ -90,8 +94,15 @@ impl ConsumerBatch {
     }
 }
 
+impl ConsumerBatch {
+    /// The number of messages that have been requested but not yet received
+    pub(crate) fn pending_msgs(&self) -> usize {
+        self.pending_msgs
+    }
+}
+
 impl Stream for ConsumerBatch {
-    type Item = Result<ServerMessage, ConsumerBatchError>;
+    type Item = Result<JetstreamMessage, ConsumerBatchError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
@@ -112,7 +123,11 @@ impl Stream for ConsumerBatch {
                 None | Some(StatusCode::OK) => {
                     *this.pending_msgs -= 1;
 
-                    Poll::Ready(Some(Ok(msg)))
+                    Poll::Ready(Some(Ok(JetstreamMessage::new(
+                        msg,
+                        this.client.clone(),
+                        *this.ack_policy,
+                    ))))
                 }
                 Some(StatusCode::IDLE_HEARTBEAT) => {
                     cx.waker().wake_by_ref();
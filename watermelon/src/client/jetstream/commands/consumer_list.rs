@@ -20,7 +20,7 @@ use crate::client::{self, jetstream::JetstreamError2, JetstreamClient};
 pub struct Consumers {
     client: JetstreamClient,
     offset: u32,
-    partial_subject: Subject,
+    subject: Subject,
     fetch: Option<BoxFuture<'static, Result<ConsumersResponse, JetstreamError2>>>,
     buffer: VecDeque<client::Consumer>,
     exhausted: bool,
@@ -34,13 +34,13 @@ struct ConsumersResponse {
 
 impl Consumers {
     pub(crate) fn new(client: JetstreamClient, stream_name: impl Display) -> Self {
-        let partial_subject = format!("CONSUMER.LIST.{stream_name}")
-            .try_into()
+        let subject = client
+            .api_subject(&["CONSUMER", "LIST", &stream_name.to_string()])
             .expect("stream name is valid");
         Self {
             client,
             offset: 0,
-            partial_subject,
+            subject,
             fetch: None,
             buffer: VecDeque::new(),
             exhausted: false,
@@ -64,13 +64,13 @@ impl Stream for Consumers {
 
         let fetch = this.fetch.get_or_insert_with(|| {
             let client = this.client.clone();
-            let partial_subject = this.partial_subject.clone();
+            let subject = this.subject.clone();
             let offset = this.offset;
 
             Box::pin(async move {
                 let response_fut = client
                     .client()
-                    .request(client.subject_for_request(&partial_subject))
+                    .request(subject)
                     .response_timeout(client.request_timeout)
                     .payload(
                         serde_json::to_vec(&json!({
@@ -80,7 +80,7 @@ impl Stream for Consumers {
                         .into(),
                     )
                     .await
-                    .map_err(JetstreamError2::ClientClosed)?;
+                    .map_err(JetstreamError2::Publish)?;
                 let response = response_fut.await.map_err(JetstreamError2::ResponseError)?;
                 let payload = serde_json::from_slice(&response.base.payload)
                     .map_err(JetstreamError2::Json)?;
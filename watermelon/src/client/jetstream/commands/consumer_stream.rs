@@ -1,28 +1,34 @@
 use std::{
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
 
-use futures_core::{future::BoxFuture, FusedStream, Stream};
+use futures_core::{future::BoxFuture, ready, FusedStream, Stream};
 use pin_project_lite::pin_project;
 use watermelon_proto::ServerMessage;
 
 use crate::client::{Consumer, JetstreamClient, JetstreamError2};
 
-use super::{consumer_batch::ConsumerBatchError, ConsumerBatch};
+use super::{consumer_batch::ConsumerBatchError, AckError, ConsumerBatch, JetstreamMessage};
 
 pin_project! {
     /// A consumer stream of batch requests
     ///
     /// Obtained from [`JetstreamClient::consumer_stream`].
+    ///
+    /// When the client reconnects to the server, the pull request of the
+    /// batch currently being received is re-sent for the messages that
+    /// haven't been delivered yet.
     #[must_use = "streams do nothing unless polled"]
     pub struct ConsumerStream {
         #[pin]
         status: ConsumerStreamStatus,
         consumer: Consumer,
         client: JetstreamClient,
+        reconnected: BoxFuture<'static, u64>,
 
         expires: Duration,
         max_msgs: usize,
@@ -49,6 +55,43 @@ pub enum ConsumerStreamError {
     BatchError(#[source] ConsumerBatchError),
     #[error("jetstream error")]
     Jetstream(#[source] JetstreamError2),
+    /// A message couldn't be acknowledged by an [`AutoAckConsumerStream`]
+    #[error("acknowledgement error")]
+    Ack(#[source] AckError),
+}
+
+pin_project! {
+    /// A [`ConsumerStream`] which automatically acknowledges every message
+    ///
+    /// Obtained from [`ConsumerStream::auto_ack`]. See [`AckTiming`] for when
+    /// messages are acknowledged. Messages are yielded without their
+    /// acknowledgement methods, since acknowledging them is taken care of.
+    #[must_use = "streams do nothing unless polled"]
+    pub struct AutoAckConsumerStream {
+        #[pin]
+        stream: ConsumerStream,
+        timing: AckTiming,
+        acking: Option<BoxFuture<'static, Result<(), AckError>>>,
+        acked: Option<ServerMessage>,
+    }
+}
+
+/// When an [`AutoAckConsumerStream`] acknowledges a message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AckTiming {
+    /// Acknowledge the message before yielding it
+    ///
+    /// This gives at-most-once semantics: a message which fails
+    /// to be processed, for example because the application crashes,
+    /// has already been acknowledged and isn't redelivered.
+    OnYield,
+    /// Acknowledge the message when the stream is polled for the next one
+    ///
+    /// This gives at-least-once semantics: a message is only acknowledged
+    /// once the application is done with it and asks for the next one.
+    /// A message which fails to be processed is redelivered after the
+    /// `ack_wait` of the consumer expires, so processing should be idempotent.
+    OnNextPoll,
 }
 
 impl ConsumerStream {
@@ -58,6 +101,9 @@ impl ConsumerStream {
         expires: Duration,
         max_msgs: usize,
     ) -> Self {
+        let quick_info = Arc::clone(&client.client().inner.quick_info);
+        let connections = quick_info.connections();
+        let reconnected = Box::pin(async move { quick_info.wait_reconnected(connections).await });
         let poll_fut = {
             let client = client.clone();
             Box::pin(ConsumerBatch::new(&consumer, client, expires, max_msgs))
@@ -67,6 +113,7 @@ impl ConsumerStream {
             status: ConsumerStreamStatus::Polling { future: poll_fut },
             consumer,
             client,
+            reconnected,
 
             expires,
             max_msgs,
@@ -74,11 +121,57 @@ impl ConsumerStream {
     }
 }
 
+impl ConsumerStream {
+    /// Automatically acknowledge every message yielded by this stream
+    ///
+    /// `timing` decides whether messages are acknowledged before being
+    /// yielded, or once the next message is requested.
+    /// Acknowledging doesn't do anything if the consumer was configured
+    /// with [`AckPolicy::None`].
+    ///
+    /// [`AckPolicy::None`]: crate::jetstream::AckPolicy::None
+    pub fn auto_ack(self, timing: AckTiming) -> AutoAckConsumerStream {
+        AutoAckConsumerStream {
+            stream: self,
+            timing,
+            acking: None,
+            acked: None,
+        }
+    }
+}
+
 impl Stream for ConsumerStream {
-    type Item = Result<ServerMessage, ConsumerStreamError>;
+    type Item = Result<JetstreamMessage, ConsumerStreamError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
+
+        let mut reconnected = false;
+        while let Poll::Ready(connections) = this.reconnected.as_mut().poll(cx) {
+            let quick_info = Arc::clone(&this.client.client().inner.quick_info);
+            *this.reconnected =
+                Box::pin(async move { quick_info.wait_reconnected(connections).await });
+            reconnected = true;
+        }
+        if reconnected {
+            // The pull request may have been lost together with the previous connection
+            let max_msgs = match this.status.as_mut().project() {
+                ConsumerStreamStatusProj::Polling { .. } => *this.max_msgs,
+                ConsumerStreamStatusProj::RunningBatch { batch } => batch.pending_msgs(),
+                ConsumerStreamStatusProj::Broken => 0,
+            };
+            if max_msgs > 0 {
+                this.status.set(ConsumerStreamStatus::Polling {
+                    future: Box::pin(ConsumerBatch::new(
+                        this.consumer,
+                        this.client.clone(),
+                        *this.expires,
+                        max_msgs,
+                    )),
+                });
+            }
+        }
+
         match this.status.as_mut().project() {
             ConsumerStreamStatusProj::RunningBatch { batch } => match batch.poll_next(cx) {
                 Poll::Pending => Poll::Pending,
@@ -125,3 +218,195 @@ impl FusedStream for ConsumerStream {
         matches!(self.status, ConsumerStreamStatus::Broken)
     }
 }
+
+impl Stream for AutoAckConsumerStream {
+    type Item = Result<ServerMessage, ConsumerStreamError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(acking) = this.acking {
+                let result = ready!(acking.as_mut().poll(cx));
+                *this.acking = None;
+                let acked = this.acked.take();
+                match (result, acked) {
+                    (Ok(()), Some(message)) => return Poll::Ready(Some(Ok(message))),
+                    (Ok(()), None) => {}
+                    (Err(err), _) => return Poll::Ready(Some(Err(ConsumerStreamError::Ack(err)))),
+                }
+            }
+
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(message)) => {
+                    let (message, ack) = message.into_message_and_ack();
+                    *this.acking = Some(ack);
+                    match this.timing {
+                        AckTiming::OnYield => *this.acked = Some(message),
+                        AckTiming::OnNextPoll => return Poll::Ready(Some(Ok(message))),
+                    }
+                }
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl FusedStream for AutoAckConsumerStream {
+    fn is_terminated(&self) -> bool {
+        self.acking.is_none() && self.stream.is_terminated()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{pin::pin, time::Duration};
+
+    use bytes::Bytes;
+    use futures_util::{FutureExt as _, StreamExt as _};
+    use tokio::sync::mpsc;
+    use watermelon_proto::{error::ServerError, ServerMessage, Subject, SubscriptionId};
+
+    use crate::{
+        client::{
+            tests::{message, TestHandler},
+            AckTiming, Consumer, JetstreamClient,
+        },
+        core::Client,
+        handler::HandlerCommand,
+    };
+
+    fn consumer(ack_policy: &str) -> Consumer {
+        serde_json::from_value(serde_json::json!({
+            "stream_name": "orders",
+            "config": {
+                "durable_name": "processor",
+                "deliver_policy": "all",
+                "ack_policy": ack_policy,
+                "ack_wait": 30_000_000_000_u64,
+                "max_ack_pending": -1,
+                "max_deliver": -1,
+                "replay_policy": "instant",
+                "num_replicas": 0,
+            },
+            "created": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn expect_pull(
+        handler: &mut TestHandler,
+        batch: usize,
+    ) -> (
+        SubscriptionId,
+        mpsc::Sender<Result<ServerMessage, ServerError>>,
+    ) {
+        let HandlerCommand::Subscribe { id, messages, .. } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        let payload = serde_json::from_slice::<serde_json::Value>(&message.payload).unwrap();
+        assert_eq!(batch, payload["batch"]);
+        (id, messages)
+    }
+
+    #[tokio::test]
+    async fn resume_after_reconnect() {
+        let (client, mut handler) = Client::test(8);
+        handler.quick_info.store_is_connected(true);
+        let jetstream = JetstreamClient::new(client);
+
+        let mut stream =
+            pin!(jetstream.consumer_stream(consumer("none"), Duration::from_secs(30), 10));
+        assert!(stream.next().now_or_never().is_none());
+
+        let (id, messages) = expect_pull(&mut handler, 10);
+        messages
+            .try_send(Ok(message(
+                id,
+                Subject::from_static("orders.created"),
+                None,
+                Bytes::from_static(b"order"),
+            )))
+            .unwrap();
+        assert!(stream.next().await.unwrap().is_ok());
+
+        handler.quick_info.store_is_connected(false);
+        handler.quick_info.store_is_connected(true);
+        assert!(stream.next().now_or_never().is_none());
+
+        let HandlerCommand::Unsubscribe { id: old_id, .. } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(id, old_id);
+
+        let (id, messages) = expect_pull(&mut handler, 9);
+        messages
+            .try_send(Ok(message(
+                id,
+                Subject::from_static("orders.created"),
+                None,
+                Bytes::from_static(b"order"),
+            )))
+            .unwrap();
+        assert!(stream.next().await.unwrap().is_ok());
+    }
+
+    fn expect_ack(handler: &mut TestHandler, reply_subject: &str) {
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(reply_subject, message.subject.as_str());
+        assert_eq!(Bytes::from_static(b"+ACK"), message.payload);
+    }
+
+    #[tokio::test]
+    async fn auto_ack() {
+        for timing in [AckTiming::OnYield, AckTiming::OnNextPoll] {
+            let (client, mut handler) = Client::test(8);
+            let jetstream = JetstreamClient::new(client);
+
+            let mut stream = pin!(jetstream
+                .consumer_stream(consumer("explicit"), Duration::from_secs(30), 10)
+                .auto_ack(timing));
+            assert!(stream.next().now_or_never().is_none());
+
+            let (id, messages) = expect_pull(&mut handler, 10);
+            for reply_subject in ["$JS.ACK.orders.1", "$JS.ACK.orders.2"] {
+                messages
+                    .try_send(Ok(message(
+                        id,
+                        Subject::from_static("orders.created"),
+                        Some(Subject::from_static(reply_subject)),
+                        Bytes::from_static(b"order"),
+                    )))
+                    .unwrap();
+            }
+
+            let message = stream.next().await.unwrap().unwrap();
+            assert_eq!(
+                Some("$JS.ACK.orders.1"),
+                message.base.reply_subject.as_ref().map(Subject::as_str)
+            );
+            match timing {
+                AckTiming::OnYield => expect_ack(&mut handler, "$JS.ACK.orders.1"),
+                AckTiming::OnNextPoll => assert!(handler.receiver.try_recv().is_err()),
+            }
+
+            let message = stream.next().await.unwrap().unwrap();
+            assert_eq!(
+                Some("$JS.ACK.orders.2"),
+                message.base.reply_subject.as_ref().map(Subject::as_str)
+            );
+            match timing {
+                AckTiming::OnYield => expect_ack(&mut handler, "$JS.ACK.orders.2"),
+                AckTiming::OnNextPoll => expect_ack(&mut handler, "$JS.ACK.orders.1"),
+            }
+        }
+    }
+}
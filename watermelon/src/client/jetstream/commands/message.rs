@@ -0,0 +1,298 @@
+use std::{ops::Deref, time::Duration};
+
+use bytes::Bytes;
+use futures_core::future::BoxFuture;
+use serde_json::json;
+use watermelon_proto::{ServerMessage, Subject};
+
+use crate::client::{AckPolicy, Client, JetstreamMessageMetadata, PublishError, ResponseError};
+
+/// A message delivered by a Jetstream consumer
+///
+/// Obtained from [`ConsumerBatch`] and [`ConsumerStream`].
+/// Dereferences to the inner [`ServerMessage`].
+///
+/// The acknowledgement methods don't do anything if the consumer
+/// was configured with [`AckPolicy::None`].
+///
+/// [`ConsumerBatch`]: crate::jetstream::ConsumerBatch
+/// [`ConsumerStream`]: crate::jetstream::ConsumerStream
+#[derive(Debug)]
+pub struct JetstreamMessage {
+    message: ServerMessage,
+    client: Client,
+    ack_policy: AckPolicy,
+}
+
+/// An error encountered while acknowledging a [`JetstreamMessage`]
+#[derive(Debug, thiserror::Error)]
+pub enum AckError {
+    /// The message doesn't have a reply subject to send the acknowledgement to
+    #[error("message has no reply subject")]
+    MissingReplySubject,
+    /// The acknowledgement couldn't be published
+    #[error("publish failure")]
+    Publish(#[source] PublishError),
+    /// The server didn't confirm the acknowledgement
+    #[error("acknowledgement not confirmed")]
+    Response(#[source] ResponseError),
+}
+
+impl JetstreamMessage {
+    pub(crate) fn new(message: ServerMessage, client: Client, ack_policy: AckPolicy) -> Self {
+        Self {
+            message,
+            client,
+            ack_policy,
+        }
+    }
+
+    /// Get a reference to the inner message
+    #[must_use]
+    pub fn message(&self) -> &ServerMessage {
+        &self.message
+    }
+
+    /// Parse the metadata of the message out of its reply subject
+    ///
+    /// Returns `None` if the message doesn't have a valid Jetstream ack subject.
+    #[must_use]
+    pub fn metadata(&self) -> Option<JetstreamMessageMetadata> {
+        self.message
+            .base
+            .reply_subject
+            .as_ref()
+            .and_then(JetstreamMessageMetadata::from_reply_subject)
+    }
+
+    /// Get the inner message, without acknowledging it
+    #[must_use]
+    pub fn into_message(self) -> ServerMessage {
+        self.message
+    }
+
+    /// Acknowledge the message
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the message has no reply subject or if the client has been closed.
+    pub async fn ack(&self) -> Result<(), AckError> {
+        self.send(Bytes::from_static(b"+ACK")).await
+    }
+
+    /// Acknowledge the message and wait for the server to confirm it
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the message has no reply subject, if the client has been closed
+    /// or if the server doesn't confirm the acknowledgement.
+    pub async fn ack_sync(&self) -> Result<(), AckError> {
+        let Some(reply_subject) = self.reply_subject()? else {
+            return Ok(());
+        };
+
+        let resp = self
+            .client
+            .request(reply_subject)
+            .payload(Bytes::from_static(b"+ACK"))
+            .await
+            .map_err(AckError::Publish)?;
+        resp.await.map_err(AckError::Response)?;
+        Ok(())
+    }
+
+    /// Negatively acknowledge the message, asking for it to be redelivered
+    ///
+    /// If `delay` is `Some` the server will wait for at least `delay` before redelivering it.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the message has no reply subject or if the client has been closed.
+    pub async fn nak(&self, delay: Option<Duration>) -> Result<(), AckError> {
+        let payload = match delay {
+            Some(delay) => format!("-NAK {}", json!({ "delay": delay.as_nanos() })).into(),
+            None => Bytes::from_static(b"-NAK"),
+        };
+        self.send(payload).await
+    }
+
+    /// Terminate the message, telling the server to never redeliver it
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the message has no reply subject or if the client has been closed.
+    pub async fn term(&self) -> Result<(), AckError> {
+        self.send(Bytes::from_static(b"+TERM")).await
+    }
+
+    /// Tell the server that the message is still being processed, resetting the ack wait timer
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the message has no reply subject or if the client has been closed.
+    pub async fn in_progress(&self) -> Result<(), AckError> {
+        self.send(Bytes::from_static(b"+WIP")).await
+    }
+
+    /// Split the message from a future acknowledging it
+    ///
+    /// The acknowledgement is only sent once the future is polled.
+    pub(crate) fn into_message_and_ack(
+        self,
+    ) -> (ServerMessage, BoxFuture<'static, Result<(), AckError>>) {
+        let reply_subject = self.reply_subject();
+        let Self {
+            message, client, ..
+        } = self;
+
+        let ack =
+            Box::pin(
+                async move { send(&client, reply_subject?, Bytes::from_static(b"+ACK")).await },
+            );
+        (message, ack)
+    }
+
+    async fn send(&self, payload: Bytes) -> Result<(), AckError> {
+        send(&self.client, self.reply_subject()?, payload).await
+    }
+
+    fn reply_subject(&self) -> Result<Option<Subject>, AckError> {
+        if matches!(self.ack_policy, AckPolicy::None) {
+            return Ok(None);
+        }
+
+        self.message
+            .base
+            .reply_subject
+            .clone()
+            .map(Some)
+            .ok_or(AckError::MissingReplySubject)
+    }
+}
+
+async fn send(
+    client: &Client,
+    reply_subject: Option<Subject>,
+    payload: Bytes,
+) -> Result<(), AckError> {
+    let Some(reply_subject) = reply_subject else {
+        return Ok(());
+    };
+
+    client
+        .publish(reply_subject)
+        .payload(payload)
+        .await
+        .map_err(AckError::Publish)
+}
+
+impl Deref for JetstreamMessage {
+    type Target = ServerMessage;
+
+    fn deref(&self) -> &Self::Target {
+        &self.message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use claims::assert_matches;
+    use tokio::sync::mpsc::error::TryRecvError;
+    use watermelon_proto::{Subject, SubscriptionId};
+
+    use crate::{
+        client::{tests::message, AckPolicy},
+        core::Client,
+        handler::HandlerCommand,
+    };
+
+    use super::{AckError, JetstreamMessage};
+
+    #[tokio::test]
+    async fn acks() {
+        let (client, mut handler) = Client::test(8);
+        let reply_subject = Subject::from_static("$JS.ACK.stream.consumer.1.1.1.0.0");
+        let message = JetstreamMessage::new(
+            message(
+                SubscriptionId::MIN,
+                Subject::from_static("orders.eu.created"),
+                Some(reply_subject.clone()),
+                Bytes::from_static(b"test"),
+            ),
+            client,
+            AckPolicy::Explicit {
+                wait: Duration::from_secs(30),
+                max_pending: None,
+            },
+        );
+
+        message.ack().await.unwrap();
+        message.nak(None).await.unwrap();
+        message
+            .nak(Some(Duration::from_millis(1500)))
+            .await
+            .unwrap();
+        message.term().await.unwrap();
+        message.in_progress().await.unwrap();
+
+        for expected_payload in [
+            &b"+ACK"[..],
+            b"-NAK",
+            br#"-NAK {"delay":1500000000}"#,
+            b"+TERM",
+            b"+WIP",
+        ] {
+            let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+                unreachable!()
+            };
+            assert_eq!(reply_subject, message.subject);
+            assert_eq!(expected_payload, message.payload);
+        }
+        assert_matches!(handler.receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[tokio::test]
+    async fn ack_policy_none() {
+        let (client, mut handler) = Client::test(8);
+        let message = JetstreamMessage::new(
+            message(
+                SubscriptionId::MIN,
+                Subject::from_static("orders.eu.created"),
+                Some(Subject::from_static("$JS.ACK.stream.consumer.1.1.1.0.0")),
+                Bytes::from_static(b"test"),
+            ),
+            client,
+            AckPolicy::None,
+        );
+
+        message.ack().await.unwrap();
+        message.ack_sync().await.unwrap();
+        message.nak(None).await.unwrap();
+        message.term().await.unwrap();
+        message.in_progress().await.unwrap();
+        assert_matches!(handler.receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[tokio::test]
+    async fn missing_reply_subject() {
+        let (client, _handler) = Client::test(8);
+        let message = JetstreamMessage::new(
+            message(
+                SubscriptionId::MIN,
+                Subject::from_static("orders.eu.created"),
+                None,
+                Bytes::from_static(b"test"),
+            ),
+            client,
+            AckPolicy::All {
+                wait: Duration::from_secs(30),
+                max_pending: None,
+            },
+        );
+
+        assert_matches!(message.ack().await, Err(AckError::MissingReplySubject));
+    }
+}
@@ -1,9 +1,17 @@
 pub use self::consumer_batch::ConsumerBatch;
 pub use self::consumer_list::Consumers;
-pub use self::consumer_stream::{ConsumerStream, ConsumerStreamError};
+pub use self::consumer_stream::{
+    AckTiming, AutoAckConsumerStream, ConsumerStream, ConsumerStreamError,
+};
+pub use self::message::{AckError, JetstreamMessage};
+pub use self::publish::{DoJetstreamPublish, JetstreamPublish, Rollup};
 pub use self::stream_list::Streams;
+pub use self::stream_names::StreamNames;
 
 mod consumer_batch;
 mod consumer_list;
 mod consumer_stream;
+mod message;
+mod publish;
 mod stream_list;
+mod stream_names;
@@ -0,0 +1,317 @@
+use std::future::IntoFuture;
+
+use bytes::Bytes;
+use futures_core::future::BoxFuture;
+use watermelon_proto::{
+    headers::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode, Subject,
+};
+
+use crate::client::{
+    jetstream::resources::Response, JetstreamClient, JetstreamError2, PubAck, ResponseError,
+};
+
+/// A constructor for a message to be published to a Jetstream stream
+///
+/// Obtained from [`JetstreamClient::publish`].
+#[derive(Debug)]
+pub struct JetstreamPublish<'a> {
+    client: &'a JetstreamClient,
+    subject: Subject,
+    headers: HeaderMap,
+}
+
+/// The scope of the messages replaced by a rollup publish
+///
+/// Used with [`JetstreamPublish::rollup`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rollup {
+    /// Purge every other message stored on the subject of the published message
+    Subject,
+    /// Purge every other message stored in the stream
+    ///
+    /// Requires the stream to be configured with `allow_rollup_hdrs`
+    /// and the publisher to be allowed to purge the whole stream.
+    All,
+}
+
+/// A message ready to be published to a Jetstream stream
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct DoJetstreamPublish<'a> {
+    client: &'a JetstreamClient,
+    subject: Subject,
+    headers: HeaderMap,
+    payload: Bytes,
+}
+
+impl<'a> JetstreamPublish<'a> {
+    pub(crate) fn build(client: &'a JetstreamClient, subject: Subject) -> Self {
+        Self {
+            client,
+            subject,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Set an unique identifier used by the server to de-duplicate the message
+    ///
+    /// Messages published with the same ID within the stream's duplicate window
+    /// are only stored once.
+    #[must_use]
+    pub fn message_id(self, message_id: HeaderValue) -> Self {
+        self.header(HeaderName::MESSAGE_ID, message_id)
+    }
+
+    /// Only store the message if the subject is bound to the stream named `stream`
+    ///
+    /// Guards against the message landing in a different stream, for example
+    /// when accounts have overlapping subject mappings. If the assertion fails
+    /// the server rejects the publish with a bad request status and the publish
+    /// returns an error with code [`JetstreamErrorCode::STREAM_NOT_MATCH`].
+    ///
+    /// [`JetstreamErrorCode::STREAM_NOT_MATCH`]: crate::jetstream::error::JetstreamErrorCode::STREAM_NOT_MATCH
+    #[must_use]
+    pub fn expected_stream(self, stream: HeaderValue) -> Self {
+        self.header(HeaderName::EXPECTED_STREAM, stream)
+    }
+
+    /// Only store the message if the last message in the stream has `message_id` as its ID
+    #[must_use]
+    pub fn expected_last_message_id(self, message_id: HeaderValue) -> Self {
+        self.header(HeaderName::EXPECTED_LAST_MESSAGE_ID, message_id)
+    }
+
+    /// Only store the message if the last message in the stream is at `sequence`
+    ///
+    /// If the assertion fails the publish returns an error with code
+    /// [`JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE`].
+    ///
+    /// [`JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE`]: crate::jetstream::error::JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE
+    #[must_use]
+    pub fn expected_last_sequence(self, sequence: u64) -> Self {
+        self.header(
+            HeaderName::EXPECTED_LAST_SEQUENCE,
+            HeaderValue::from_dangerous_value(sequence.to_string().into()),
+        )
+    }
+
+    /// Only store the message if the last message stored on the same subject is at `sequence`
+    ///
+    /// A `sequence` of `0` only stores the message if the subject has no messages yet.
+    /// This enables optimistic concurrency control scoped to a single subject,
+    /// as used by Key-Value workloads. If the assertion fails the publish returns
+    /// an error with code [`JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE`].
+    ///
+    /// [`JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE`]: crate::jetstream::error::JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE
+    #[must_use]
+    pub fn expected_last_subject_sequence(self, sequence: u64) -> Self {
+        self.header(
+            HeaderName::EXPECTED_LAST_SUBJECT_SEQUENCE,
+            HeaderValue::from_dangerous_value(sequence.to_string().into()),
+        )
+    }
+
+    /// Replace the previously stored messages with this one once it has been stored
+    ///
+    /// The stream must have rollups enabled, otherwise the publish is rejected.
+    /// [`Rollup::All`] additionally requires the permission to purge the whole stream.
+    #[must_use]
+    pub fn rollup(self, rollup: Rollup) -> Self {
+        self.header(HeaderName::ROLLUP, rollup.header_value())
+    }
+
+    #[must_use]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    #[must_use]
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn payload(self, payload: Bytes) -> DoJetstreamPublish<'a> {
+        let Self {
+            client,
+            subject,
+            headers,
+        } = self;
+        DoJetstreamPublish {
+            client,
+            subject,
+            headers,
+            payload,
+        }
+    }
+}
+
+impl Rollup {
+    pub(crate) fn header_value(self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            Self::Subject => "sub",
+            Self::All => "all",
+        })
+    }
+}
+
+impl<'a> IntoFuture for DoJetstreamPublish<'a> {
+    type Output = Result<PubAck, JetstreamError2>;
+    type IntoFuture = BoxFuture<'a, Self::Output>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let Self {
+            client,
+            subject,
+            headers,
+            payload,
+        } = self;
+
+        Box::pin(async move {
+            let resp = client
+                .client
+                .request(subject)
+                .headers(headers)
+                .response_timeout(client.request_timeout)
+                .payload(payload)
+                .await
+                .map_err(JetstreamError2::Publish)?;
+            let resp = resp.await.map_err(JetstreamError2::ResponseError)?;
+
+            if resp.status_code == Some(StatusCode::NO_RESPONDERS) {
+                return Err(JetstreamError2::ResponseError(ResponseError::NoResponders));
+            }
+
+            let json = serde_json::from_slice::<Response<PubAck>>(&resp.base.payload)
+                .map_err(JetstreamError2::Json)?;
+            match json {
+                Response::Response(pub_ack) => Ok(pub_ack),
+                Response::Error { error } => Err(JetstreamError2::Status(error)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::IntoFuture as _;
+
+    use bytes::Bytes;
+    use watermelon_proto::{
+        headers::{HeaderName, HeaderValue},
+        Subject,
+    };
+
+    use crate::{
+        client::{tests::response, JetstreamClient, JetstreamError2},
+        core::Client,
+        handler::HandlerCommand,
+        jetstream::error::JetstreamErrorCode,
+    };
+
+    use super::Rollup;
+
+    #[tokio::test]
+    async fn rollup() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        for (rollup, expected) in [(Rollup::Subject, "sub"), (Rollup::All, "all")] {
+            let publish = jetstream
+                .publish(Subject::from_static("prices.eurusd"))
+                .rollup(rollup)
+                .payload(Bytes::from_static(b"1.08"))
+                .into_future();
+            let (_result, ()) = tokio::join!(publish, async {
+                let Some(HandlerCommand::RequestMultiplexed { headers, .. }) =
+                    handler.receiver.recv().await
+                else {
+                    unreachable!()
+                };
+                assert_eq!(expected, headers.get(&HeaderName::ROLLUP).unwrap().as_str());
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn expected_stream() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let (result, ()) = tokio::join!(
+            jetstream
+                .publish(Subject::from_static("orders.1"))
+                .expected_stream(HeaderValue::from_static("ORDERS"))
+                .payload(Bytes::from_static(b"shipped"))
+                .into_future(),
+            async {
+                let Some(HandlerCommand::RequestMultiplexed {
+                    subject,
+                    headers,
+                    reply,
+                    ..
+                }) = handler.receiver.recv().await
+                else {
+                    unreachable!()
+                };
+                assert_eq!(
+                    "ORDERS",
+                    headers.get(&HeaderName::EXPECTED_STREAM).unwrap().as_str()
+                );
+
+                let payload = br#"{"type":"io.nats.jetstream.api.v1.pub_ack_response","error":{"code":400,"err_code":10060,"description":"expected stream does not match"}}"#;
+                reply
+                    .send(response(subject, Bytes::from_static(payload)))
+                    .unwrap();
+            }
+        );
+        let Err(JetstreamError2::Status(err)) = result else {
+            unreachable!()
+        };
+        assert_eq!(JetstreamErrorCode::STREAM_NOT_MATCH, err.code());
+    }
+
+    #[tokio::test]
+    async fn expected_last_subject_sequence() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let (result, ()) = tokio::join!(
+            jetstream
+                .publish(Subject::from_static("orders.1"))
+                .expected_last_subject_sequence(42)
+                .payload(Bytes::from_static(b"shipped"))
+                .into_future(),
+            async {
+                let Some(HandlerCommand::RequestMultiplexed {
+                    subject,
+                    headers,
+                    reply,
+                    ..
+                }) = handler.receiver.recv().await
+                else {
+                    unreachable!()
+                };
+                assert_eq!("orders.1", subject.as_str());
+                assert_eq!(
+                    "42",
+                    headers
+                        .get(&HeaderName::EXPECTED_LAST_SUBJECT_SEQUENCE)
+                        .unwrap()
+                        .as_str()
+                );
+
+                let payload = br#"{"type":"io.nats.jetstream.api.v1.pub_ack_response","error":{"code":400,"err_code":10071,"description":"wrong last sequence: 43"}}"#;
+                reply
+                    .send(response(subject, Bytes::from_static(payload)))
+                    .unwrap();
+            }
+        );
+        let Err(JetstreamError2::Status(err)) = result else {
+            unreachable!()
+        };
+        assert_eq!(JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE, err.code());
+    }
+}
@@ -8,7 +8,6 @@ use std::{
 use futures_core::{future::BoxFuture, FusedStream, Stream};
 use serde::Deserialize;
 use serde_json::json;
-use watermelon_proto::Subject;
 
 use crate::client::{self, jetstream::JetstreamError2, JetstreamClient};
 
@@ -63,7 +62,11 @@ impl Stream for Streams {
             Box::pin(async move {
                 let response_fut = client
                     .client()
-                    .request(client.subject_for_request(&Subject::from_static("STREAM.LIST")))
+                    .request(
+                        client
+                            .api_subject(&["STREAM", "LIST"])
+                            .map_err(JetstreamError2::Subject)?,
+                    )
                     .response_timeout(client.request_timeout)
                     .payload(
                         serde_json::to_vec(&json!({
@@ -73,7 +76,7 @@ impl Stream for Streams {
                         .into(),
                     )
                     .await
-                    .map_err(JetstreamError2::ClientClosed)?;
+                    .map_err(JetstreamError2::Publish)?;
                 let response = response_fut.await.map_err(JetstreamError2::ResponseError)?;
                 let payload = serde_json::from_slice(&response.base.payload)
                     .map_err(JetstreamError2::Json)?;
@@ -0,0 +1,191 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::{future::BoxFuture, FusedStream, Stream};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::client::{
+    jetstream::{resources::Response, JetstreamError2},
+    JetstreamClient,
+};
+
+/// A request to list the names of the streams
+///
+/// Obtained from [`JetstreamClient::stream_names`].
+#[must_use = "streams do nothing unless polled"]
+pub struct StreamNames {
+    client: JetstreamClient,
+    offset: u32,
+    fetch: Option<BoxFuture<'static, Result<StreamNamesResponse, JetstreamError2>>>,
+    buffer: VecDeque<String>,
+    exhausted: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamNamesResponse {
+    total: u32,
+    offset: u32,
+    // `null` when there are no streams
+    streams: Option<VecDeque<String>>,
+}
+
+impl StreamNames {
+    pub(crate) fn new(client: JetstreamClient) -> Self {
+        Self {
+            client,
+            offset: 0,
+            fetch: None,
+            buffer: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+}
+
+impl Stream for StreamNames {
+    type Item = Result<String, JetstreamError2>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if let Some(name) = this.buffer.pop_front() {
+            return Poll::Ready(Some(Ok(name)));
+        }
+
+        if this.exhausted {
+            return Poll::Ready(None);
+        }
+
+        let fetch = this.fetch.get_or_insert_with(|| {
+            let client = this.client.clone();
+            let offset = this.offset;
+
+            Box::pin(async move {
+                let response_fut = client
+                    .client()
+                    .request(
+                        client
+                            .api_subject(&["STREAM", "NAMES"])
+                            .map_err(JetstreamError2::Subject)?,
+                    )
+                    .response_timeout(client.request_timeout)
+                    .payload(
+                        serde_json::to_vec(&json!({
+                            "offset": offset,
+                        }))
+                        .unwrap()
+                        .into(),
+                    )
+                    .await
+                    .map_err(JetstreamError2::Publish)?;
+                let response = response_fut.await.map_err(JetstreamError2::ResponseError)?;
+                let payload = serde_json::from_slice(&response.base.payload)
+                    .map_err(JetstreamError2::Json)?;
+                match payload {
+                    Response::Response(response) => Ok(response),
+                    Response::Error { error } => Err(JetstreamError2::Status(error)),
+                }
+            })
+        });
+
+        match Pin::new(fetch).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(response)) => {
+                this.fetch = None;
+                this.buffer = response.streams.unwrap_or_default();
+
+                let len = u32::try_from(this.buffer.len()).unwrap_or(u32::MAX);
+                this.offset = response.offset.saturating_add(len);
+                if this.buffer.is_empty() || this.offset >= response.total {
+                    this.exhausted = true;
+                }
+
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Poll::Ready(Err(err)) => {
+                this.fetch = None;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+impl FusedStream for StreamNames {
+    fn is_terminated(&self) -> bool {
+        self.buffer.is_empty() && self.exhausted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures_util::StreamExt as _;
+
+    use crate::{
+        client::{tests::response, JetstreamClient},
+        core::Client,
+        handler::HandlerCommand,
+    };
+
+    #[tokio::test]
+    async fn paginated() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let (names, ()) = tokio::join!(jetstream.stream_names().collect::<Vec<_>>(), async {
+            for (expected_offset, payload) in [
+                (
+                    0,
+                    &br#"{"type":"io.nats.jetstream.api.v1.stream_names_response","total":3,"offset":0,"limit":2,"streams":["events","orders"]}"#[..],
+                ),
+                (
+                    2,
+                    &br#"{"type":"io.nats.jetstream.api.v1.stream_names_response","total":3,"offset":2,"limit":2,"streams":["payments"]}"#[..],
+                ),
+            ] {
+                let Some(HandlerCommand::RequestMultiplexed {
+                    subject,
+                    payload: request,
+                    reply,
+                    ..
+                }) = handler.receiver.recv().await
+                else {
+                    unreachable!()
+                };
+                assert_eq!("$JS.API.STREAM.NAMES", subject.as_str());
+                let request = serde_json::from_slice::<serde_json::Value>(&request).unwrap();
+                assert_eq!(expected_offset, request["offset"]);
+
+                reply
+                    .send(response(subject, payload))
+                    .unwrap();
+            }
+        });
+
+        let names = names.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(["events", "orders", "payments"], names.as_slice());
+    }
+
+    #[tokio::test]
+    async fn no_streams() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let (names, ()) = tokio::join!(jetstream.stream_names().collect::<Vec<_>>(), async {
+            let Some(HandlerCommand::RequestMultiplexed { subject, reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            reply
+                .send(response(subject, Bytes::from_static(br#"{"type":"io.nats.jetstream.api.v1.stream_names_response","total":0,"offset":0,"limit":1024,"streams":null}"#)))
+                .unwrap();
+        });
+        assert!(names.is_empty());
+    }
+}
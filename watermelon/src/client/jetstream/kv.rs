@@ -0,0 +1,406 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_core::{FusedStream, Stream};
+use watermelon_proto::{
+    error::ServerError,
+    headers::{HeaderMap, HeaderName, HeaderValue},
+    ServerMessage, Subject,
+};
+
+use crate::{
+    client::{
+        AckPolicy, ConsumerConfig, ConsumerDurability, ConsumerSpecificConfig, ConsumerStorage,
+        DeliverPolicy, JetstreamClient, JetstreamError2, JetstreamMessageMetadata, ReplayPolicy,
+        StoredMessage,
+    },
+    subscription::Subscription,
+};
+
+const OPERATION_HEADER: &str = "KV-Operation";
+/// How long the consumer of a watcher is kept by the server after the client went away
+const WATCHER_INACTIVE_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+
+/// A Jetstream Key-Value bucket
+///
+/// Obtained from [`JetstreamClient::key_value`].
+///
+/// Each bucket is backed by the `KV_{bucket}` stream, with every key
+/// stored under the `$KV.{bucket}.{key}` subject.
+#[derive(Debug, Clone)]
+pub struct KeyValue {
+    client: JetstreamClient,
+    bucket: String,
+    stream_name: String,
+}
+
+/// An entry of a [`KeyValue`] bucket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvEntry {
+    pub key: String,
+    pub value: Bytes,
+    pub revision: u64,
+    pub created: DateTime<Utc>,
+    pub operation: KvOperation,
+}
+
+/// The operation that produced a [`KvEntry`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KvOperation {
+    Put,
+    Delete,
+    Purge,
+}
+
+/// A stream of updates to the keys of a [`KeyValue`] bucket
+///
+/// Obtained from [`KeyValue::watch`].
+///
+/// Updates published while the client is disconnected from the server
+/// aren't lost: the watcher is backed by a consumer created via
+/// [`JetstreamClient::subscribe_push_resumable`], which after reconnecting
+/// is resumed from the revision following the last one it has yielded.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct KvWatch {
+    subscription: Subscription,
+    key_prefix: String,
+}
+
+/// An error encountered while watching a [`KeyValue`] bucket
+#[derive(Debug, thiserror::Error)]
+pub enum KvWatchError {
+    #[error("an error returned by the server")]
+    ServerError(#[source] ServerError),
+    #[error("message is missing the Jetstream metadata")]
+    InvalidMetadata(ServerMessage),
+}
+
+impl JetstreamClient {
+    /// Obtain a handle to the Key-Value `bucket`
+    ///
+    /// The bucket is not checked for existence.
+    pub fn key_value(&self, bucket: impl Display) -> KeyValue {
+        let bucket = bucket.to_string();
+        KeyValue {
+            client: self.clone(),
+            stream_name: format!("KV_{bucket}"),
+            bucket,
+        }
+    }
+}
+
+impl KeyValue {
+    /// The name of this bucket
+    #[must_use]
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// Get the current value of `key`
+    ///
+    /// Returns `None` if the key doesn't exist or if it has been deleted.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `key` produces an invalid subject or if an error
+    /// occurs while getting the value.
+    pub async fn get(&self, key: impl Display) -> Result<Option<KvEntry>, JetstreamError2> {
+        let subject = self.key_subject(key)?;
+        let Some(message) = self
+            .client
+            .get_last_message_for_subject(&self.stream_name, &subject)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let entry = self.entry_from_stored_message(message);
+        Ok(match entry.operation {
+            KvOperation::Put => Some(entry),
+            KvOperation::Delete | KvOperation::Purge => None,
+        })
+    }
+
+    /// Set `key` to `value`
+    ///
+    /// Returns the revision of the new entry.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `key` produces an invalid subject or if an error
+    /// occurs while publishing the value.
+    pub async fn put(&self, key: impl Display, value: Bytes) -> Result<u64, JetstreamError2> {
+        let subject = self.key_subject(key)?;
+        let pub_ack = self.client.publish(subject).payload(value).await?;
+        Ok(pub_ack.sequence)
+    }
+
+    /// Set `key` to `value`, only if the latest revision of `key` is `expected_revision`
+    ///
+    /// Returns the revision of the new entry.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `key` produces an invalid subject or if an error
+    /// occurs while publishing the value. If the latest revision doesn't match
+    /// [`JetstreamError2::Status`] is returned with code
+    /// [`JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE`].
+    ///
+    /// [`JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE`]: crate::jetstream::error::JetstreamErrorCode::STREAM_WRONG_LAST_SEQUENCE
+    pub async fn update(
+        &self,
+        key: impl Display,
+        value: Bytes,
+        expected_revision: u64,
+    ) -> Result<u64, JetstreamError2> {
+        let subject = self.key_subject(key)?;
+        let pub_ack = self
+            .client
+            .publish(subject)
+            .expected_last_subject_sequence(expected_revision)
+            .payload(value)
+            .await?;
+        Ok(pub_ack.sequence)
+    }
+
+    /// Delete `key`
+    ///
+    /// A delete marker is stored in place of the value, so that the deletion
+    /// is observed by [`KeyValue::watch`].
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `key` produces an invalid subject or if an error
+    /// occurs while publishing the delete marker.
+    pub async fn delete(&self, key: impl Display) -> Result<(), JetstreamError2> {
+        let subject = self.key_subject(key)?;
+        self.client
+            .publish(subject)
+            .header(
+                HeaderName::from_static(OPERATION_HEADER),
+                HeaderValue::from_static("DEL"),
+            )
+            .payload(Bytes::new())
+            .await?;
+        Ok(())
+    }
+
+    /// Watch the keys matching `key_filter`
+    ///
+    /// The returned stream first yields the latest entry of every matching key,
+    /// including delete markers, and then every following update.
+    /// `key_filter` may contain the `*` and `>` wildcards.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `key_filter` produces an invalid subject or if an error
+    /// occurs while creating the consumer backing the watcher.
+    pub async fn watch(&self, key_filter: impl Display) -> Result<KvWatch, JetstreamError2> {
+        let config = ConsumerConfig {
+            durability: ConsumerDurability::Ephemeral,
+            name: String::new(),
+            description: String::new(),
+            deliver_policy: DeliverPolicy::LastPerSubject,
+            ack_policy: AckPolicy::None,
+            max_deliver: None,
+            backoff: Vec::new(),
+            filter_subjects: vec![self.key_subject(key_filter)?],
+            replay_policy: ReplayPolicy::Instant,
+            rate_limit: None,
+            flow_control: None,
+            idle_heartbeat: Duration::ZERO,
+            headers_only: false,
+            specs: ConsumerSpecificConfig::Push {
+                deliver_subject: self.client.client().new_inbox(),
+                deliver_group: None,
+            },
+            inactive_threshold: WATCHER_INACTIVE_THRESHOLD,
+            replicas: None,
+            storage: ConsumerStorage::Memory,
+            metadata: BTreeMap::new(),
+        };
+        let subscription = self
+            .client
+            .subscribe_push_resumable(&self.stream_name, config)
+            .await?;
+
+        Ok(KvWatch {
+            subscription,
+            key_prefix: self.key_prefix(),
+        })
+    }
+
+    fn key_subject(&self, key: impl Display) -> Result<Subject, JetstreamError2> {
+        format!("$KV.{}.{}", self.bucket, key)
+            .try_into()
+            .map_err(JetstreamError2::Subject)
+    }
+
+    fn key_prefix(&self) -> String {
+        format!("$KV.{}.", self.bucket)
+    }
+
+    fn entry_from_stored_message(&self, message: StoredMessage) -> KvEntry {
+        KvEntry {
+            key: strip_key_prefix(&message.subject, &self.key_prefix()),
+            operation: KvOperation::from_headers(&message.headers),
+            value: message.payload,
+            revision: message.sequence,
+            created: message.timestamp,
+        }
+    }
+}
+
+impl KvOperation {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        match headers
+            .get(&HeaderName::from_static(OPERATION_HEADER))
+            .map(HeaderValue::as_str)
+        {
+            Some("DEL") => Self::Delete,
+            Some("PURGE") => Self::Purge,
+            _ => Self::Put,
+        }
+    }
+}
+
+impl Stream for KvWatch {
+    type Item = Result<KvEntry, KvWatchError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let message = match Pin::new(&mut this.subscription).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(err))) => {
+                    return Poll::Ready(Some(Err(KvWatchError::ServerError(err))))
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+            };
+
+            if message.status_code.is_some() {
+                // Heartbeats and flow control messages
+                continue;
+            }
+
+            let Some(metadata) = message
+                .base
+                .reply_subject
+                .as_ref()
+                .and_then(JetstreamMessageMetadata::from_reply_subject)
+            else {
+                return Poll::Ready(Some(Err(KvWatchError::InvalidMetadata(message))));
+            };
+
+            return Poll::Ready(Some(Ok(KvEntry {
+                key: strip_key_prefix(&message.base.subject, &this.key_prefix),
+                operation: KvOperation::from_headers(&message.base.headers),
+                value: message.base.payload,
+                revision: metadata.stream_sequence,
+                created: metadata.timestamp,
+            })));
+        }
+    }
+}
+
+impl FusedStream for KvWatch {
+    fn is_terminated(&self) -> bool {
+        self.subscription.is_terminated()
+    }
+}
+
+fn strip_key_prefix(subject: &Subject, key_prefix: &str) -> String {
+    subject
+        .strip_prefix(key_prefix)
+        .unwrap_or(subject)
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use bytes::Bytes;
+    use chrono::{DateTime, Utc};
+    use futures_util::StreamExt as _;
+    use watermelon_proto::{
+        headers::{HeaderMap, HeaderName, HeaderValue},
+        Subject, SubscriptionId,
+    };
+
+    use super::{KvOperation, OPERATION_HEADER};
+    use crate::{
+        client::{
+            tests::{accept_consumer, ack_subject, message},
+            JetstreamClient, StoredMessage,
+        },
+        core::Client,
+    };
+
+    #[tokio::test]
+    async fn entry_from_stored_message() {
+        let (client, _handler) = Client::test(1);
+        let kv = JetstreamClient::new(client).key_value("config");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static(OPERATION_HEADER),
+            HeaderValue::from_static("DEL"),
+        );
+        let entry = kv.entry_from_stored_message(StoredMessage {
+            subject: Subject::from_static("$KV.config.app.port"),
+            sequence: 9,
+            timestamp: DateTime::<Utc>::from_timestamp_nanos(0),
+            headers,
+            payload: Bytes::new(),
+        });
+
+        assert_eq!("app.port", entry.key);
+        assert_eq!(9, entry.revision);
+        assert_eq!(KvOperation::Delete, entry.operation);
+    }
+
+    #[tokio::test]
+    async fn watch() {
+        let (client, mut handler) = Client::test(8);
+        let kv = JetstreamClient::new(client).key_value("config");
+
+        let (watch, (messages, subject, config)) =
+            tokio::join!(kv.watch(">"), accept_consumer(&mut handler, "KV_config"));
+        let mut watch = pin!(watch.unwrap());
+
+        let name = config["name"].as_str().unwrap();
+        assert_eq!(
+            format!("$JS.API.CONSUMER.CREATE.KV_config.{name}"),
+            &*subject
+        );
+        assert_eq!("last_per_subject", config["deliver_policy"]);
+        assert_eq!(300_000_000_000_u64, config["inactive_threshold"]);
+
+        messages
+            .try_send(Ok(message(
+                SubscriptionId::MIN,
+                Subject::from_static("$KV.config.app.port"),
+                Some(ack_subject("KV_config", name, 42, 1)),
+                Bytes::from_static(b"4222"),
+            )))
+            .unwrap();
+        let entry = watch.next().await.unwrap().unwrap();
+        assert_eq!("app.port", entry.key);
+        assert_eq!(42, entry.revision);
+        assert_eq!(
+            DateTime::<Utc>::from_timestamp_nanos(1_700_000_000_123_456_789),
+            entry.created
+        );
+    }
+}
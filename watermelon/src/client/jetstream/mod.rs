@@ -1,25 +1,44 @@
 use std::{fmt::Display, time::Duration};
 
 use bytes::Bytes;
-use resources::Response;
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use resources::{
+    CreateConsumerRequest, DeleteConsumerResponse, DeleteStreamResponse, GetMessageRequest,
+    GetMessageResponse, PauseConsumerRequest, PurgeStreamResponse, Response,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use watermelon_proto::StatusCode;
 use watermelon_proto::{error::SubjectValidateError, Subject};
 
-pub use self::commands::{ConsumerBatch, ConsumerStream, ConsumerStreamError, Consumers, Streams};
+pub use self::commands::{
+    AckError, AckTiming, AutoAckConsumerStream, ConsumerBatch, ConsumerStream, ConsumerStreamError,
+    Consumers, DoJetstreamPublish, JetstreamMessage, JetstreamPublish, Rollup, StreamNames,
+    Streams,
+};
+pub use self::kv::{KeyValue, KvEntry, KvOperation, KvWatch, KvWatchError};
+#[cfg(feature = "object-store")]
+pub use self::object_store::{ObjectInfo, ObjectList, ObjectPutError, ObjectReader, ObjectStore};
+pub use self::ordered_consumer::{OrderedConfig, OrderedConsumer, OrderedConsumerError};
 pub use self::resources::{
-    AckPolicy, Compression, Consumer, ConsumerConfig, ConsumerDurability, ConsumerSpecificConfig,
-    ConsumerStorage, DeliverPolicy, DiscardPolicy, ReplayPolicy, RetentionPolicy, Storage, Stream,
-    StreamConfig, StreamState,
+    AckPolicy, Compression, Consumer, ConsumerConfig, ConsumerConfigBuilder, ConsumerConfigError,
+    ConsumerDurability, ConsumerPause, ConsumerSpecificConfig, ConsumerStorage, DeliverPolicy,
+    DiscardPolicy, JetstreamMessageMetadata, PubAck, PurgeRequest, ReplayPolicy, RetentionPolicy,
+    Storage, StoredMessage, Stream, StreamConfig, StreamState,
 };
-use crate::core::Client;
+pub(crate) use self::resume::{resume_response, ConsumerResume};
+use crate::core::{Client, Subscription};
 
-use super::{ClientClosedError, ResponseError};
+use super::{ClientClosedError, PublishError, ResponseError};
 
 const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
 
 mod commands;
+mod kv;
+#[cfg(feature = "object-store")]
+mod object_store;
+mod ordered_consumer;
 mod resources;
+mod resume;
 
 /// A NATS Jetstream client
 ///
@@ -29,9 +48,21 @@ mod resources;
 pub struct JetstreamClient {
     client: Client,
     prefix: Subject,
+    api_prefixes: Result<ApiPrefixes, SubjectValidateError>,
     request_timeout: Duration,
 }
 
+/// The subjects of the most frequently used Jetstream API endpoints
+///
+/// Computed once per [`JetstreamClient`], so that building the subject
+/// of a request only has to append and validate the final tokens.
+#[derive(Debug, Clone)]
+struct ApiPrefixes {
+    stream_info: Subject,
+    consumer_info: Subject,
+    consumer_msg_next: Subject,
+}
+
 /// A Jetstream API error
 #[derive(Debug, Deserialize, thiserror::Error)]
 #[error("jetstream error status={status}")]
@@ -55,12 +86,22 @@ pub enum JetstreamError2 {
     Subject(#[source] SubjectValidateError),
     #[error("client closed")]
     ClientClosed(#[source] ClientClosedError),
+    #[error("publish failure")]
+    Publish(#[source] PublishError),
     #[error("client request failure")]
     ResponseError(#[source] ResponseError),
     #[error("JSON deserialization")]
     Json(#[source] serde_json::Error),
     #[error("bad response code")]
     Status(#[source] JetstreamError),
+    #[error("stream name already in use")]
+    StreamNameInUse(#[source] JetstreamError),
+    #[error("consumer name already in use")]
+    ConsumerNameInUse(#[source] JetstreamError),
+    #[error("could not create consumer")]
+    CouldNotCreateConsumer(#[source] JetstreamError),
+    #[error("not a push consumer")]
+    NotPushConsumer,
 }
 
 impl JetstreamClient {
@@ -86,9 +127,11 @@ impl JetstreamClient {
     /// Create a Jetstream client using the provided API `prefix`
     #[must_use]
     pub fn new_with_prefix(client: Client, prefix: Subject) -> Self {
+        let api_prefixes = ApiPrefixes::new(&prefix);
         Self {
             client,
             prefix,
+            api_prefixes,
             request_timeout: DEFAULT_REQUEST_TIMEOUT,
         }
     }
@@ -98,6 +141,14 @@ impl JetstreamClient {
         Streams::new(self.clone())
     }
 
+    /// List the names of the streams present within this client's Jetstream context
+    ///
+    /// Cheaper than [`JetstreamClient::streams`] when the configuration
+    /// and state of the streams aren't needed.
+    pub fn stream_names(&self) -> StreamNames {
+        StreamNames::new(self.clone())
+    }
+
     /// Obtain a stream present within this client's Jetstream context
     ///
     /// # Errors
@@ -105,27 +156,173 @@ impl JetstreamClient {
     /// It returns an error if the given `name` produces an invalid subject or if an error occurs
     /// while creating the stream.
     pub async fn stream(&self, name: impl Display) -> Result<Option<Stream>, JetstreamError2> {
-        let subject = format!("{}.STREAM.INFO.{}", self.prefix, name)
-            .try_into()
+        let subject = self
+            .cached_api_subject(|prefixes| &prefixes.stream_info, &[&name])
             .map_err(JetstreamError2::Subject)?;
-        let resp = self
-            .client
-            .request(subject)
-            .response_timeout(self.request_timeout)
-            .payload(Bytes::new())
-            .await
-            .map_err(JetstreamError2::ClientClosed)?;
-        let resp = resp.await.map_err(JetstreamError2::ResponseError)?;
+        match self.api_request::<Stream>(subject, Bytes::new()).await? {
+            Response::Response(stream) => Ok(Some(stream)),
+            Response::Error { error } if error.code == JetstreamErrorCode::STREAM_NOT_FOUND => {
+                Ok(None)
+            }
+            Response::Error { error } => Err(JetstreamError2::Status(error)),
+        }
+    }
 
-        if resp.status_code == Some(StatusCode::NO_RESPONDERS) {
-            return Err(JetstreamError2::ResponseError(ResponseError::NoResponders));
+    /// Publish a message to a stream, waiting for the server to acknowledge it
+    ///
+    /// The returned builder allows setting headers used for de-duplication and
+    /// optimistic concurrency control before setting the payload.
+    pub fn publish(&self, subject: Subject) -> JetstreamPublish<'_> {
+        JetstreamPublish::build(self, subject)
+    }
+
+    /// Create a new stream within this client's Jetstream context
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the name of the stream produces an invalid subject or if an error
+    /// occurs while creating the stream. [`JetstreamError2::StreamNameInUse`] is returned if a
+    /// stream with the same name but a different configuration already exists.
+    pub async fn create_stream(&self, config: &StreamConfig) -> Result<Stream, JetstreamError2> {
+        self.create_or_update_stream("CREATE", config).await
+    }
+
+    /// Update the configuration of an existing stream within this client's Jetstream context
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the name of the stream produces an invalid subject or if an error
+    /// occurs while updating the stream.
+    pub async fn update_stream(&self, config: &StreamConfig) -> Result<Stream, JetstreamError2> {
+        self.create_or_update_stream("UPDATE", config).await
+    }
+
+    async fn create_or_update_stream(
+        &self,
+        action: &str,
+        config: &StreamConfig,
+    ) -> Result<Stream, JetstreamError2> {
+        let subject = self
+            .api_subject(&["STREAM", action, &config.name])
+            .map_err(JetstreamError2::Subject)?;
+        let payload = serde_json::to_vec(config).map_err(JetstreamError2::Json)?;
+        match self.api_request::<Stream>(subject, payload.into()).await? {
+            Response::Response(stream) => Ok(stream),
+            Response::Error { error } if error.code == JetstreamErrorCode::STREAM_NAME_IN_USE => {
+                Err(JetstreamError2::StreamNameInUse(error))
+            }
+            Response::Error { error } => Err(JetstreamError2::Status(error)),
         }
+    }
 
-        let json = serde_json::from_slice::<Response<Stream>>(&resp.base.payload)
-            .map_err(JetstreamError2::Json)?;
-        match json {
-            Response::Response(stream) => Ok(Some(stream)),
-            Response::Error { error } if error.code == JetstreamErrorCode::STREAM_NOT_FOUND => {
+    /// Delete a stream present within this client's Jetstream context
+    ///
+    /// Returns `false` if the server reported that the stream couldn't be deleted.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `name` produces an invalid subject or if an error occurs
+    /// while deleting the stream.
+    pub async fn delete_stream(&self, name: impl Display) -> Result<bool, JetstreamError2> {
+        let subject = self
+            .api_subject(&["STREAM", "DELETE", &name.to_string()])
+            .map_err(JetstreamError2::Subject)?;
+        match self
+            .api_request::<DeleteStreamResponse>(subject, Bytes::new())
+            .await?
+        {
+            Response::Response(response) => Ok(response.success),
+            Response::Error { error } => Err(JetstreamError2::Status(error)),
+        }
+    }
+
+    /// Purge messages from a stream present within this client's Jetstream context
+    ///
+    /// Returns the number of purged messages.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `name` produces an invalid subject or if an error occurs
+    /// while purging the stream.
+    pub async fn purge_stream(
+        &self,
+        name: impl Display,
+        request: &PurgeRequest,
+    ) -> Result<u64, JetstreamError2> {
+        let subject = self
+            .api_subject(&["STREAM", "PURGE", &name.to_string()])
+            .map_err(JetstreamError2::Subject)?;
+        let payload = serde_json::to_vec(request).map_err(JetstreamError2::Json)?;
+        match self
+            .api_request::<PurgeStreamResponse>(subject, payload.into())
+            .await?
+        {
+            Response::Response(response) => Ok(response.purged),
+            Response::Error { error } => Err(JetstreamError2::Status(error)),
+        }
+    }
+
+    /// Get the message stored at sequence `sequence` of a stream
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `stream_name` produces an invalid subject or if an error
+    /// occurs while getting the message.
+    pub async fn get_message(
+        &self,
+        stream_name: impl Display,
+        sequence: u64,
+    ) -> Result<Option<StoredMessage>, JetstreamError2> {
+        self.do_get_message(
+            stream_name,
+            &GetMessageRequest {
+                sequence: Some(sequence),
+                last_by_subject: None,
+                next_by_subject: None,
+            },
+        )
+        .await
+    }
+
+    /// Get the last message stored for `subject` in a stream
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `stream_name` produces an invalid subject or if an error
+    /// occurs while getting the message.
+    pub async fn get_last_message_for_subject(
+        &self,
+        stream_name: impl Display,
+        subject: &Subject,
+    ) -> Result<Option<StoredMessage>, JetstreamError2> {
+        self.do_get_message(
+            stream_name,
+            &GetMessageRequest {
+                sequence: None,
+                last_by_subject: Some(subject),
+                next_by_subject: None,
+            },
+        )
+        .await
+    }
+
+    async fn do_get_message(
+        &self,
+        stream_name: impl Display,
+        request: &GetMessageRequest<'_>,
+    ) -> Result<Option<StoredMessage>, JetstreamError2> {
+        let subject = self
+            .api_subject(&["STREAM", "MSG", "GET", &stream_name.to_string()])
+            .map_err(JetstreamError2::Subject)?;
+        let payload = serde_json::to_vec(request).map_err(JetstreamError2::Json)?;
+        match self
+            .api_request::<GetMessageResponse>(subject, payload.into())
+            .await?
+        {
+            Response::Response(response) => Ok(Some(response.message)),
+            Response::Error { error }
+                if error.code == JetstreamErrorCode::STREAM_MESSAGE_NOT_FOUND =>
+            {
                 Ok(None)
             }
             Response::Error { error } => Err(JetstreamError2::Status(error)),
@@ -148,32 +345,164 @@ impl JetstreamClient {
         stream_name: impl Display,
         consumer_name: impl Display,
     ) -> Result<Option<Consumer>, JetstreamError2> {
-        let subject = format!(
-            "{}.CONSUMER.INFO.{}.{}",
-            self.prefix, stream_name, consumer_name
-        )
-        .try_into()
+        match self.consumer_info(stream_name, consumer_name).await {
+            Ok(consumer) => Ok(Some(consumer)),
+            Err(JetstreamError2::Status(error))
+                if error.code == JetstreamErrorCode::CONSUMER_NOT_FOUND =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) async fn consumer_info(
+        &self,
+        stream_name: impl Display,
+        consumer_name: impl Display,
+    ) -> Result<Consumer, JetstreamError2> {
+        let subject = self
+            .cached_api_subject(
+                |prefixes| &prefixes.consumer_info,
+                &[&stream_name, &consumer_name],
+            )
+            .map_err(JetstreamError2::Subject)?;
+        match self.api_request::<Consumer>(subject, Bytes::new()).await? {
+            Response::Response(consumer) => Ok(consumer),
+            Response::Error { error } => Err(JetstreamError2::Status(error)),
+        }
+    }
+
+    /// Create a new consumer on the stream `stream_name`
+    ///
+    /// Durable consumers are created via the `CONSUMER.DURABLE.CREATE` API,
+    /// while ephemeral ones are created via `CONSUMER.CREATE`.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `stream_name` and the name of the consumer produce an
+    /// invalid subject or if an error occurs while creating the consumer.
+    /// [`JetstreamError2::ConsumerNameInUse`] is returned if a consumer with the same name but
+    /// a different configuration already exists, while [`JetstreamError2::CouldNotCreateConsumer`]
+    /// is returned if the server rejected the configuration.
+    pub async fn create_consumer(
+        &self,
+        stream_name: impl Display,
+        config: &ConsumerConfig,
+    ) -> Result<Consumer, JetstreamError2> {
+        let stream_name = stream_name.to_string();
+        let subject = match config.durability {
+            ConsumerDurability::Durable => {
+                self.api_subject(&["CONSUMER", "DURABLE", "CREATE", &stream_name, &config.name])
+            }
+            ConsumerDurability::Ephemeral => {
+                self.api_subject(&["CONSUMER", "CREATE", &stream_name])
+            }
+        }
         .map_err(JetstreamError2::Subject)?;
-        let resp = self
-            .client
-            .request(subject)
-            .response_timeout(self.request_timeout)
-            .payload(Bytes::new())
-            .await
-            .map_err(JetstreamError2::ClientClosed)?;
-        let resp = resp.await.map_err(JetstreamError2::ResponseError)?;
+        let payload = serde_json::to_vec(&CreateConsumerRequest {
+            stream_name,
+            config,
+        })
+        .map_err(JetstreamError2::Json)?;
+        self.do_create_consumer(subject, payload.into()).await
+    }
 
-        if resp.status_code == Some(StatusCode::NO_RESPONDERS) {
-            return Err(JetstreamError2::ResponseError(ResponseError::NoResponders));
+    async fn do_create_consumer(
+        &self,
+        subject: Subject,
+        payload: Bytes,
+    ) -> Result<Consumer, JetstreamError2> {
+        consumer_create_response(self.api_request::<Consumer>(subject, payload).await?)
+    }
+
+    /// Delete a consumer from the stream `stream_name`
+    ///
+    /// Returns `false` if the server reported that the consumer couldn't be deleted.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `stream_name` and `consumer_name` produce an invalid
+    /// subject or if an error occurs while deleting the consumer.
+    pub async fn delete_consumer(
+        &self,
+        stream_name: impl Display,
+        consumer_name: impl Display,
+    ) -> Result<bool, JetstreamError2> {
+        let subject = self
+            .api_subject(&[
+                "CONSUMER",
+                "DELETE",
+                &stream_name.to_string(),
+                &consumer_name.to_string(),
+            ])
+            .map_err(JetstreamError2::Subject)?;
+        match self
+            .api_request::<DeleteConsumerResponse>(subject, Bytes::new())
+            .await?
+        {
+            Response::Response(response) => Ok(response.success),
+            Response::Error { error } => Err(JetstreamError2::Status(error)),
         }
+    }
 
-        let json = serde_json::from_slice::<Response<Consumer>>(&resp.base.payload)
+    /// Pause the consumer `consumer_name` of the stream `stream_name` until `until`
+    ///
+    /// A paused consumer doesn't deliver messages, while still being able to
+    /// receive acknowledgements. Requires NATS Server 2.11 or later.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `stream_name` and `consumer_name` produce an invalid
+    /// subject or if an error occurs while pausing the consumer.
+    pub async fn pause_consumer(
+        &self,
+        stream_name: impl Display,
+        consumer_name: impl Display,
+        until: DateTime<Utc>,
+    ) -> Result<ConsumerPause, JetstreamError2> {
+        self.set_consumer_pause(stream_name, consumer_name, Some(until))
+            .await
+    }
+
+    /// Resume the paused consumer `consumer_name` of the stream `stream_name`
+    ///
+    /// Requires NATS Server 2.11 or later.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `stream_name` and `consumer_name` produce an invalid
+    /// subject or if an error occurs while resuming the consumer.
+    pub async fn resume_consumer(
+        &self,
+        stream_name: impl Display,
+        consumer_name: impl Display,
+    ) -> Result<ConsumerPause, JetstreamError2> {
+        self.set_consumer_pause(stream_name, consumer_name, None)
+            .await
+    }
+
+    async fn set_consumer_pause(
+        &self,
+        stream_name: impl Display,
+        consumer_name: impl Display,
+        pause_until: Option<DateTime<Utc>>,
+    ) -> Result<ConsumerPause, JetstreamError2> {
+        let subject = self
+            .api_subject(&[
+                "CONSUMER",
+                "PAUSE",
+                &stream_name.to_string(),
+                &consumer_name.to_string(),
+            ])
+            .map_err(JetstreamError2::Subject)?;
+        let payload = serde_json::to_vec(&PauseConsumerRequest { pause_until })
             .map_err(JetstreamError2::Json)?;
-        match json {
-            Response::Response(stream) => Ok(Some(stream)),
-            Response::Error { error } if error.code == JetstreamErrorCode::CONSUMER_NOT_FOUND => {
-                Ok(None)
-            }
+        match self
+            .api_request::<ConsumerPause>(subject, payload.into())
+            .await?
+        {
+            Response::Response(response) => Ok(response),
             Response::Error { error } => Err(JetstreamError2::Status(error)),
         }
     }
@@ -202,8 +531,119 @@ impl JetstreamClient {
         ConsumerStream::new(consumer, self.clone(), expires, max_msgs)
     }
 
-    pub(crate) fn subject_for_request(&self, endpoint: &Subject) -> Subject {
-        Subject::from_dangerous_value(format!("{}.{}", self.prefix, endpoint).into())
+    /// Subscribe to the messages delivered by the push `consumer`
+    ///
+    /// Subscribes to the `deliver_subject` of the consumer, joining
+    /// its `deliver_group` as the queue group if one is configured.
+    ///
+    /// # Errors
+    ///
+    /// It returns [`JetstreamError2::NotPushConsumer`] if `consumer` is a pull consumer,
+    /// or an error if the client has been closed.
+    pub async fn subscribe_push(
+        &self,
+        consumer: &Consumer,
+    ) -> Result<Subscription, JetstreamError2> {
+        let ConsumerSpecificConfig::Push {
+            deliver_subject,
+            deliver_group,
+        } = &consumer.config.specs
+        else {
+            return Err(JetstreamError2::NotPushConsumer);
+        };
+
+        self.client
+            .subscribe(deliver_subject.clone(), deliver_group.clone())
+            .await
+            .map_err(JetstreamError2::ClientClosed)
+    }
+
+    /// Create an ephemeral push consumer and subscribe to its messages, resuming it after reconnects
+    ///
+    /// The consumer gets a name chosen by the client, overriding the `durability`
+    /// and `name` of `config`. After every reconnect it is replaced by a new consumer
+    /// delivering from the message following the last one received by the subscription,
+    /// so that the messages stored while the client was disconnected aren't lost.
+    /// Messages delivered by both consumers are only yielded once.
+    /// The consumer is deleted once the subscription is dropped.
+    ///
+    /// # Errors
+    ///
+    /// It returns [`JetstreamError2::NotPushConsumer`] if `config` is for a pull consumer,
+    /// or an error if the given `stream_name` produces an invalid subject or if an error
+    /// occurs while creating the consumer.
+    pub async fn subscribe_push_resumable(
+        &self,
+        stream_name: impl Display,
+        config: ConsumerConfig,
+    ) -> Result<Subscription, JetstreamError2> {
+        let ConsumerSpecificConfig::Push {
+            deliver_subject,
+            deliver_group,
+        } = &config.specs
+        else {
+            return Err(JetstreamError2::NotPushConsumer);
+        };
+        let (deliver_subject, deliver_group) = (deliver_subject.clone(), deliver_group.clone());
+
+        let resume = ConsumerResume::new(self.prefix.clone(), stream_name.to_string(), config);
+        let (subject, payload) = resume.create_request()?;
+        let subscription = self
+            .client
+            .subscribe_resumable(deliver_subject, deliver_group, resume)
+            .await
+            .map_err(JetstreamError2::ClientClosed)?;
+        self.do_create_consumer(subject, payload).await?;
+        Ok(subscription)
+    }
+
+    /// Make a request to the Jetstream API endpoint `subject`, decoding its JSON response
+    async fn api_request<T: DeserializeOwned>(
+        &self,
+        subject: Subject,
+        payload: Bytes,
+    ) -> Result<Response<T>, JetstreamError2> {
+        let resp = self
+            .client
+            .request(subject)
+            .response_timeout(self.request_timeout)
+            .payload(payload)
+            .await
+            .map_err(JetstreamError2::Publish)?;
+        let resp = resp.await.map_err(JetstreamError2::ResponseError)?;
+
+        if resp.status_code == Some(StatusCode::NO_RESPONDERS) {
+            return Err(JetstreamError2::ResponseError(ResponseError::NoResponders));
+        }
+
+        serde_json::from_slice(&resp.base.payload).map_err(JetstreamError2::Json)
+    }
+
+    /// Build the subject of the Jetstream API endpoint made of `tokens`
+    pub(crate) fn api_subject(&self, tokens: &[&str]) -> Result<Subject, SubjectValidateError> {
+        self.prefix.join_tokens(tokens)
+    }
+
+    /// Build the subject of the `CONSUMER.MSG.NEXT` endpoint for the given consumer
+    pub(crate) fn consumer_msg_next_subject(
+        &self,
+        stream_name: &str,
+        consumer_name: &str,
+    ) -> Result<Subject, SubjectValidateError> {
+        self.cached_api_subject(
+            |prefixes| &prefixes.consumer_msg_next,
+            &[&stream_name, &consumer_name],
+        )
+    }
+
+    /// Build a subject by appending `tokens` to one of the cached endpoint subjects
+    fn cached_api_subject(
+        &self,
+        endpoint: impl FnOnce(&ApiPrefixes) -> &Subject,
+        tokens: &[&dyn Display],
+    ) -> Result<Subject, SubjectValidateError> {
+        let prefixes = self.api_prefixes.as_ref().map_err(Clone::clone)?;
+        endpoint(prefixes).join_display(tokens)
     }
 
     /// Get a reference to the inner NATS Core client
@@ -218,12 +658,43 @@ impl JetstreamClient {
     }
 }
 
+impl ApiPrefixes {
+    fn new(prefix: &Subject) -> Result<Self, SubjectValidateError> {
+        Ok(Self {
+            stream_info: prefix.join_tokens(&["STREAM", "INFO"])?,
+            consumer_info: prefix.join_tokens(&["CONSUMER", "INFO"])?,
+            consumer_msg_next: prefix.join_tokens(&["CONSUMER", "MSG", "NEXT"])?,
+        })
+    }
+}
+
+impl JetstreamError {
+    /// The HTTP-like status code of the error
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The Jetstream specific error code
+    #[must_use]
+    pub fn code(&self) -> JetstreamErrorCode {
+        self.code
+    }
+
+    /// The human readable description of the error
+    #[must_use]
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
 impl JetstreamErrorCode {
     pub const NOT_ENABLED: Self = Self(10076);
     pub const NOT_ENABLED_FOR_ACCOUNT: Self = Self(10039);
     pub const BAD_REQUEST: Self = Self(10003);
 
     pub const STREAM_NOT_FOUND: Self = Self(10059);
+    pub const STREAM_NOT_MATCH: Self = Self(10060);
     pub const STREAM_NAME_IN_USE: Self = Self(10058);
     pub const STREAM_MESSAGE_NOT_FOUND: Self = Self(10037);
     pub const STREAM_WRONG_LAST_SEQUENCE: Self = Self(10071);
@@ -248,3 +719,413 @@ impl From<JetstreamErrorCode> for u16 {
         value.0
     }
 }
+
+/// Map the response to a consumer creation request to the created consumer
+fn consumer_create_response(response: Response<Consumer>) -> Result<Consumer, JetstreamError2> {
+    match response {
+        Response::Response(consumer) => Ok(consumer),
+        Response::Error { error } if error.code == JetstreamErrorCode::CONSUMER_NAME_IN_USE => {
+            Err(JetstreamError2::ConsumerNameInUse(error))
+        }
+        Response::Error { error }
+            if error.code == JetstreamErrorCode::COULD_NOT_CREATE_CONSUMER =>
+        {
+            Err(JetstreamError2::CouldNotCreateConsumer(error))
+        }
+        Response::Error { error } => Err(JetstreamError2::Status(error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, num::NonZeroU32, time::Duration};
+
+    use bytes::Bytes;
+    use chrono::{DateTime, Utc};
+    use watermelon_proto::{QueueGroup, Subject};
+
+    use super::{
+        AckPolicy, Consumer, ConsumerConfig, ConsumerDurability, ConsumerSpecificConfig,
+        ConsumerStorage, DeliverPolicy, DiscardPolicy, JetstreamClient, JetstreamError2,
+        JetstreamErrorCode, PurgeRequest, ReplayPolicy, Storage, StreamConfig,
+    };
+    use crate::{client::tests::response, core::Client, handler::HandlerCommand};
+
+    fn config(durability: ConsumerDurability) -> ConsumerConfig {
+        ConsumerConfig {
+            durability,
+            name: "processor".to_owned(),
+            description: String::new(),
+            deliver_policy: DeliverPolicy::All,
+            ack_policy: AckPolicy::None,
+            max_deliver: None,
+            backoff: Vec::new(),
+            filter_subjects: Vec::new(),
+            replay_policy: ReplayPolicy::Instant,
+            rate_limit: None,
+            flow_control: None,
+            idle_heartbeat: Duration::ZERO,
+            headers_only: false,
+            specs: ConsumerSpecificConfig::Pull {
+                max_waiting: None,
+                max_request_batch: None,
+                max_request_expires: Duration::ZERO,
+                max_request_max_bytes: None,
+            },
+            inactive_threshold: Duration::ZERO,
+            replicas: None,
+            storage: ConsumerStorage::Disk,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn api_subjects() {
+        let (client, _handler) = Client::test(8);
+        let jetstream = JetstreamClient::new_with_domain(client, "hub").unwrap();
+
+        assert_eq!(
+            "$JS.hub.API.STREAM.INFO.orders",
+            jetstream
+                .cached_api_subject(|prefixes| &prefixes.stream_info, &[&"orders"])
+                .unwrap()
+                .as_str()
+        );
+        assert_eq!(
+            "$JS.hub.API.CONSUMER.MSG.NEXT.orders.processor",
+            jetstream
+                .consumer_msg_next_subject("orders", "processor")
+                .unwrap()
+                .as_str()
+        );
+        assert!(jetstream
+            .consumer_msg_next_subject("orders", "proc essor")
+            .is_err());
+
+        let (client, _handler) = Client::test(8);
+        let jetstream = JetstreamClient::new_with_prefix(client, Subject::from_static("$JS.>"));
+        assert!(jetstream
+            .consumer_msg_next_subject("orders", "processor")
+            .is_err());
+    }
+
+    fn stream_config() -> StreamConfig {
+        StreamConfig {
+            name: "orders".to_owned(),
+            subjects: vec!["orders.>".to_owned()],
+            max_consumers: None,
+            max_messages: None,
+            max_bytes: None,
+            max_age: Duration::ZERO,
+            max_messages_per_subject: None,
+            max_message_size: None,
+            discard_policy: DiscardPolicy::Old,
+            storage: Storage::File,
+            replicas: NonZeroU32::MIN,
+            duplicate_window: Duration::from_secs(120),
+            compression: None,
+            allow_direct: false,
+            mirror_direct: false,
+            sealed: false,
+            allow_delete: true,
+            allow_purge: true,
+            allow_rollup_hdrs: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn create_and_update_stream() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+        let config = stream_config();
+
+        for (create, expected_subject) in [
+            (true, "$JS.API.STREAM.CREATE.orders"),
+            (false, "$JS.API.STREAM.UPDATE.orders"),
+        ] {
+            let stream = async {
+                if create {
+                    jetstream.create_stream(&config).await
+                } else {
+                    jetstream.update_stream(&config).await
+                }
+            };
+            let (result, ()) = tokio::join!(stream, async {
+                let Some(HandlerCommand::RequestMultiplexed {
+                    subject,
+                    payload: request,
+                    reply,
+                    ..
+                }) = handler.receiver.recv().await
+                else {
+                    unreachable!()
+                };
+                assert_eq!(expected_subject, subject.as_str());
+                let request = serde_json::from_slice::<serde_json::Value>(&request).unwrap();
+                assert_eq!("orders", request["name"]);
+                assert_eq!(false, request["deny_purge"]);
+
+                let stream = serde_json::json!({
+                    "type": "io.nats.jetstream.api.v1.stream_create_response",
+                    "config": request,
+                    "created": "2024-01-01T00:00:00Z",
+                    "state": {
+                        "messages": 0,
+                        "bytes": 0,
+                        "first_seq": 0,
+                        "first_ts": "0001-01-01T00:00:00Z",
+                        "last_seq": 0,
+                        "last_ts": "0001-01-01T00:00:00Z",
+                        "consumer_count": 0
+                    }
+                });
+                reply
+                    .send(response(subject, serde_json::to_vec(&stream).unwrap()))
+                    .unwrap();
+            });
+            let stream = result.unwrap();
+            assert_eq!("orders", stream.config.name);
+            assert_eq!(0, stream.state.messages);
+        }
+
+        let (result, ()) = tokio::join!(jetstream.create_stream(&config), async {
+            let Some(HandlerCommand::RequestMultiplexed { subject, reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            let payload = br#"{"type":"io.nats.jetstream.api.v1.stream_create_response","error":{"code":400,"err_code":10058,"description":"stream name already in use with a different configuration"}}"#;
+            reply
+                .send(response(subject, Bytes::from_static(payload)))
+                .unwrap();
+        });
+        assert!(matches!(result, Err(JetstreamError2::StreamNameInUse(_))));
+    }
+
+    #[tokio::test]
+    async fn delete_and_purge_stream() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let (result, ()) = tokio::join!(jetstream.delete_stream("orders"), async {
+            let Some(HandlerCommand::RequestMultiplexed { subject, reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            assert_eq!("$JS.API.STREAM.DELETE.orders", subject.as_str());
+            let payload =
+                br#"{"type":"io.nats.jetstream.api.v1.stream_delete_response","success":true}"#;
+            reply
+                .send(response(subject, Bytes::from_static(payload)))
+                .unwrap();
+        });
+        assert!(result.unwrap());
+
+        let (result, ()) = tokio::join!(jetstream.delete_stream("orders"), async {
+            let Some(HandlerCommand::RequestMultiplexed { subject, reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            let payload = br#"{"type":"io.nats.jetstream.api.v1.stream_delete_response","error":{"code":404,"err_code":10059,"description":"stream not found"}}"#;
+            reply
+                .send(response(subject, Bytes::from_static(payload)))
+                .unwrap();
+        });
+        let Err(JetstreamError2::Status(err)) = result else {
+            unreachable!()
+        };
+        assert_eq!(JetstreamErrorCode::STREAM_NOT_FOUND, err.code());
+
+        let request = PurgeRequest {
+            filter: Some(Subject::from_static("orders.eu")),
+            sequence: None,
+            keep: Some(1),
+        };
+        let (result, ()) = tokio::join!(jetstream.purge_stream("orders", &request), async {
+            let Some(HandlerCommand::RequestMultiplexed {
+                subject,
+                payload: request,
+                reply,
+                ..
+            }) = handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            assert_eq!("$JS.API.STREAM.PURGE.orders", subject.as_str());
+            let request = serde_json::from_slice::<serde_json::Value>(&request).unwrap();
+            assert_eq!(
+                serde_json::json!({"filter": "orders.eu", "keep": 1}),
+                request
+            );
+
+            let payload = br#"{"type":"io.nats.jetstream.api.v1.stream_purge_response","success":true,"purged":5}"#;
+            reply
+                .send(response(subject, Bytes::from_static(payload)))
+                .unwrap();
+        });
+        assert_eq!(5, result.unwrap());
+    }
+
+    #[tokio::test]
+    async fn pause_consumer() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let until = "2030-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        for (pause_until, payload) in [
+            (
+                Some(until),
+                &br#"{"type":"io.nats.jetstream.api.v1.consumer_pause_response","paused":true,"pause_until":"2030-01-01T00:00:00Z","pause_remaining":60000000000}"#[..],
+            ),
+            (
+                None,
+                br#"{"type":"io.nats.jetstream.api.v1.consumer_pause_response","paused":false,"pause_until":"0001-01-01T00:00:00Z"}"#,
+            ),
+        ] {
+            let pause = async {
+                match pause_until {
+                    Some(until) => jetstream.pause_consumer("orders", "processor", until).await,
+                    None => jetstream.resume_consumer("orders", "processor").await,
+                }
+            };
+            let (result, ()) = tokio::join!(pause, async {
+                let Some(HandlerCommand::RequestMultiplexed {
+                    subject,
+                    payload: request,
+                    reply,
+                    ..
+                }) = handler.receiver.recv().await
+                else {
+                    unreachable!()
+                };
+                assert_eq!("$JS.API.CONSUMER.PAUSE.orders.processor", subject.as_str());
+                let request = serde_json::from_slice::<serde_json::Value>(&request).unwrap();
+                match pause_until {
+                    Some(_) => assert_eq!("2030-01-01T00:00:00Z", request["pause_until"]),
+                    None => assert_eq!(serde_json::json!({}), request),
+                }
+
+                reply
+                    .send(response(subject, Bytes::from_static(payload)))
+                    .unwrap();
+            });
+
+            let pause = result.unwrap();
+            assert_eq!(pause_until.is_some(), pause.paused);
+            assert_eq!(pause_until, pause.pause_until);
+            assert_eq!(
+                if pause_until.is_some() {
+                    Duration::from_secs(60)
+                } else {
+                    Duration::ZERO
+                },
+                pause.pause_remaining
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_consumer() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let mut consumer = Consumer {
+            stream_name: "orders".to_owned(),
+            config: config(ConsumerDurability::Durable),
+            created_at: Utc::now(),
+        };
+        let mut refreshed = Consumer {
+            stream_name: "orders".to_owned(),
+            config: config(ConsumerDurability::Durable),
+            created_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+        };
+        refreshed.config.description = "refreshed".to_owned();
+        refreshed.config.max_deliver = Some(5);
+
+        let (result, ()) = tokio::join!(consumer.refresh(&jetstream), async {
+            let Some(HandlerCommand::RequestMultiplexed { subject, reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            assert_eq!("$JS.API.CONSUMER.INFO.orders.processor", subject.as_str());
+
+            let mut payload = serde_json::to_value(&refreshed).unwrap();
+            payload["type"] = "io.nats.jetstream.api.v1.consumer_info_response".into();
+            reply
+                .send(response(subject, serde_json::to_vec(&payload).unwrap()))
+                .unwrap();
+        });
+        result.unwrap();
+        assert_eq!("refreshed", consumer.config.description);
+        assert_eq!(Some(5), consumer.config.max_deliver);
+        assert_eq!(refreshed.created_at, consumer.created_at);
+    }
+
+    #[tokio::test]
+    async fn create_consumer() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        for (durability, expected_subject) in [
+            (
+                ConsumerDurability::Durable,
+                "$JS.API.CONSUMER.DURABLE.CREATE.orders.processor",
+            ),
+            (
+                ConsumerDurability::Ephemeral,
+                "$JS.API.CONSUMER.CREATE.orders",
+            ),
+        ] {
+            let config = config(durability);
+            let (result, ()) = tokio::join!(jetstream.create_consumer("orders", &config), async {
+                let Some(HandlerCommand::RequestMultiplexed { subject, reply, .. }) =
+                    handler.receiver.recv().await
+                else {
+                    unreachable!()
+                };
+                assert_eq!(expected_subject, subject.as_str());
+
+                let payload = br#"{"type":"io.nats.jetstream.api.v1.consumer_create_response","error":{"code":400,"err_code":10148,"description":"consumer name already in use"}}"#;
+                reply
+                    .send(response(subject, Bytes::from_static(payload)))
+                    .unwrap();
+            });
+            assert!(matches!(result, Err(JetstreamError2::ConsumerNameInUse(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_push() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let mut consumer = Consumer {
+            stream_name: "orders".to_owned(),
+            config: config(ConsumerDurability::Durable),
+            created_at: Utc::now(),
+        };
+        assert!(matches!(
+            jetstream.subscribe_push(&consumer).await,
+            Err(JetstreamError2::NotPushConsumer)
+        ));
+
+        consumer.config.specs = ConsumerSpecificConfig::Push {
+            deliver_subject: Subject::from_static("deliver.orders"),
+            deliver_group: Some(QueueGroup::from_static("workers")),
+        };
+        let _subscription = jetstream.subscribe_push(&consumer).await.unwrap();
+        let Some(HandlerCommand::Subscribe {
+            subject,
+            queue_group,
+            ..
+        }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        assert_eq!("deliver.orders", subject.as_str());
+        assert_eq!(Some("workers"), queue_group.as_deref());
+    }
+}
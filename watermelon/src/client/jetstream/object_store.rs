@@ -0,0 +1,668 @@
+use std::{
+    fmt::{self, Debug, Display},
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{BufMut as _, Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use data_encoding::BASE64URL;
+use futures_core::{future::BoxFuture, FusedStream, Stream};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt as _, ReadBuf};
+use watermelon_proto::Subject;
+
+use crate::client::{
+    jetstream::resources::GetMessageRequest, nuid, DeliverPolicy, JetstreamClient, JetstreamError2,
+    OrderedConfig, OrderedConsumer, PurgeRequest, Rollup,
+};
+
+const CHUNK_SIZE: usize = 128 * 1024;
+const DIGEST_PREFIX: &str = "SHA-256=";
+
+/// An [`ObjectInfo`] together with the stream sequence it is stored at
+type SequencedInfo = (u64, ObjectInfo);
+
+/// A Jetstream Object Store bucket
+///
+/// Obtained from [`JetstreamClient::object_store`].
+///
+/// Each bucket is backed by the `OBJ_{bucket}` stream. Objects are split into
+/// chunks stored under the `$O.{bucket}.C.{nuid}` subject, while their
+/// [`ObjectInfo`] is stored under `$O.{bucket}.M.{name}`, with the name
+/// base64url encoded.
+#[derive(Debug, Clone)]
+pub struct ObjectStore {
+    client: JetstreamClient,
+    bucket: String,
+    stream_name: String,
+}
+
+/// The metadata of an object stored in an [`ObjectStore`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub bucket: String,
+    /// The unique identifier of the chunks of this object
+    pub nuid: String,
+    /// The size of the object, in bytes
+    pub size: u64,
+    /// The time at which the object was last modified
+    #[serde(rename = "mtime")]
+    pub modified: DateTime<Utc>,
+    /// The number of chunks the object is split into
+    pub chunks: u64,
+    /// The SHA-256 digest of the object, in the `SHA-256={base64url}` format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deleted: bool,
+}
+
+/// A reader of the content of an object
+///
+/// Obtained from [`ObjectStore::get`].
+///
+/// The digest of the content is verified once all of it has been read,
+/// returning an [`io::ErrorKind::InvalidData`] error if it doesn't match.
+pub struct ObjectReader {
+    info: ObjectInfo,
+    consumer: Option<OrderedConsumer>,
+    chunk: Bytes,
+    remaining_chunks: u64,
+    hasher: Sha256,
+}
+
+/// A stream of the objects stored in an [`ObjectStore`]
+///
+/// Obtained from [`ObjectStore::list`]. Deleted objects are skipped.
+#[must_use = "streams do nothing unless polled"]
+pub struct ObjectList {
+    store: ObjectStore,
+    sequence: u64,
+    fetch: Option<BoxFuture<'static, Result<Option<SequencedInfo>, JetstreamError2>>>,
+    exhausted: bool,
+}
+
+/// An error encountered while putting an object into an [`ObjectStore`]
+#[derive(Debug, thiserror::Error)]
+pub enum ObjectPutError {
+    #[error("failed to read the object")]
+    Io(#[source] io::Error),
+    #[error("jetstream error")]
+    Jetstream(#[source] JetstreamError2),
+}
+
+impl JetstreamClient {
+    /// Obtain a handle to the Object Store `bucket`
+    ///
+    /// The bucket is not checked for existence.
+    pub fn object_store(&self, bucket: impl Display) -> ObjectStore {
+        let bucket = bucket.to_string();
+        ObjectStore {
+            client: self.clone(),
+            stream_name: format!("OBJ_{bucket}"),
+            bucket,
+        }
+    }
+}
+
+impl ObjectStore {
+    /// The name of this bucket
+    #[must_use]
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// Get the metadata of the object `name`
+    ///
+    /// Returns `None` if the object doesn't exist or if it has been deleted.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if an error occurs while getting the metadata.
+    pub async fn info(&self, name: impl Display) -> Result<Option<ObjectInfo>, JetstreamError2> {
+        let info = self.meta(&name.to_string()).await?;
+        Ok(info.filter(|info| !info.deleted))
+    }
+
+    /// Store the content of `reader` as the object `name`
+    ///
+    /// Any previous object with the same name is replaced.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if reading from `reader` fails or if an error occurs
+    /// while storing the object.
+    pub async fn put<R>(
+        &self,
+        name: impl Display,
+        mut reader: R,
+    ) -> Result<ObjectInfo, ObjectPutError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let name = name.to_string();
+        let previous = self.meta(&name).await.map_err(ObjectPutError::Jetstream)?;
+
+        let nuid = nuid::as_str(&nuid::next()).to_owned();
+        let chunk_subject = self
+            .chunk_subject(&nuid)
+            .map_err(ObjectPutError::Jetstream)?;
+
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        let mut chunks = 0;
+        let mut buf = BytesMut::with_capacity(CHUNK_SIZE);
+        loop {
+            let eof = loop {
+                let limit = CHUNK_SIZE - buf.len();
+                match reader.read_buf(&mut (&mut buf).limit(limit)).await {
+                    Ok(0) => break true,
+                    Ok(_) if buf.len() == CHUNK_SIZE => break false,
+                    Ok(_) => {}
+                    Err(err) => {
+                        self.purge_chunks(&nuid).await;
+                        return Err(ObjectPutError::Io(err));
+                    }
+                }
+            };
+
+            if !buf.is_empty() {
+                let chunk = buf.split().freeze();
+                hasher.update(&chunk);
+                size += chunk.len() as u64;
+                chunks += 1;
+
+                if let Err(err) = self
+                    .client
+                    .publish(chunk_subject.clone())
+                    .payload(chunk)
+                    .await
+                {
+                    self.purge_chunks(&nuid).await;
+                    return Err(ObjectPutError::Jetstream(err));
+                }
+            }
+
+            if eof {
+                break;
+            }
+        }
+
+        let info = ObjectInfo {
+            name,
+            description: None,
+            bucket: self.bucket.clone(),
+            nuid,
+            size,
+            modified: Utc::now(),
+            chunks,
+            digest: Some(format!(
+                "{DIGEST_PREFIX}{}",
+                BASE64URL.encode(&hasher.finalize())
+            )),
+            deleted: false,
+        };
+        if let Err(err) = self.publish_meta(&info).await {
+            self.purge_chunks(&info.nuid).await;
+            return Err(ObjectPutError::Jetstream(err));
+        }
+
+        if let Some(previous) = previous {
+            if !previous.deleted {
+                self.purge_chunks(&previous.nuid).await;
+            }
+        }
+        Ok(info)
+    }
+
+    /// Get the content of the object `name`
+    ///
+    /// Returns `None` if the object doesn't exist or if it has been deleted.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if an error occurs while getting the metadata
+    /// or while creating the consumer delivering the chunks.
+    pub async fn get(&self, name: impl Display) -> Result<Option<ObjectReader>, JetstreamError2> {
+        let Some(info) = self.info(name).await? else {
+            return Ok(None);
+        };
+
+        let consumer = if info.chunks > 0 {
+            let config = OrderedConfig::new()
+                .filter_subjects(vec![self.chunk_subject(&info.nuid)?])
+                .deliver_policy(DeliverPolicy::All);
+            Some(
+                self.client
+                    .ordered_consumer(&self.stream_name, config)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Some(ObjectReader {
+            remaining_chunks: info.chunks,
+            info,
+            consumer,
+            chunk: Bytes::new(),
+            hasher: Sha256::new(),
+        }))
+    }
+
+    /// Delete the object `name`
+    ///
+    /// The metadata is replaced by a delete marker and the chunks are purged.
+    /// Deleting an object that doesn't exist does nothing.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if an error occurs while getting the metadata,
+    /// publishing the delete marker or purging the chunks.
+    pub async fn delete(&self, name: impl Display) -> Result<(), JetstreamError2> {
+        let Some(mut info) = self.info(name).await? else {
+            return Ok(());
+        };
+
+        let nuid = info.nuid.clone();
+        info.size = 0;
+        info.chunks = 0;
+        info.digest = None;
+        info.modified = Utc::now();
+        info.deleted = true;
+        self.publish_meta(&info).await?;
+
+        self.client
+            .purge_stream(
+                &self.stream_name,
+                &PurgeRequest {
+                    filter: Some(self.chunk_subject(&nuid)?),
+                    ..PurgeRequest::default()
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// List the objects stored in this bucket
+    pub fn list(&self) -> ObjectList {
+        ObjectList {
+            store: self.clone(),
+            sequence: 1,
+            fetch: None,
+            exhausted: false,
+        }
+    }
+
+    async fn meta(&self, name: &str) -> Result<Option<ObjectInfo>, JetstreamError2> {
+        let subject = self.meta_subject(name)?;
+        let Some(message) = self
+            .client
+            .get_last_message_for_subject(&self.stream_name, &subject)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        serde_json::from_slice(&message.payload)
+            .map(Some)
+            .map_err(JetstreamError2::Json)
+    }
+
+    /// Get the first metadata stored at or after `sequence`, together with its sequence
+    async fn next_meta(&self, sequence: u64) -> Result<Option<SequencedInfo>, JetstreamError2> {
+        let subject = self.meta_subject_prefix()?;
+        let Some(message) = self
+            .client
+            .do_get_message(
+                &self.stream_name,
+                &GetMessageRequest {
+                    sequence: Some(sequence),
+                    last_by_subject: None,
+                    next_by_subject: Some(&subject),
+                },
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let info = serde_json::from_slice(&message.payload).map_err(JetstreamError2::Json)?;
+        Ok(Some((message.sequence, info)))
+    }
+
+    async fn publish_meta(&self, info: &ObjectInfo) -> Result<(), JetstreamError2> {
+        let payload = serde_json::to_vec(info).map_err(JetstreamError2::Json)?;
+        self.client
+            .publish(self.meta_subject(&info.name)?)
+            .rollup(Rollup::Subject)
+            .payload(payload.into())
+            .await?;
+        Ok(())
+    }
+
+    /// Purge the chunks of an object, ignoring any error
+    async fn purge_chunks(&self, nuid: &str) {
+        let Ok(filter) = self.chunk_subject(nuid) else {
+            return;
+        };
+        let _ = self
+            .client
+            .purge_stream(
+                &self.stream_name,
+                &PurgeRequest {
+                    filter: Some(filter),
+                    ..PurgeRequest::default()
+                },
+            )
+            .await;
+    }
+
+    fn chunk_subject(&self, nuid: &str) -> Result<Subject, JetstreamError2> {
+        Subject::from_tokens(["$O", &self.bucket, "C", nuid]).map_err(JetstreamError2::Subject)
+    }
+
+    fn meta_subject(&self, name: &str) -> Result<Subject, JetstreamError2> {
+        let name = BASE64URL.encode(name.as_bytes());
+        Subject::from_tokens(["$O", &self.bucket, "M", &name]).map_err(JetstreamError2::Subject)
+    }
+
+    fn meta_subject_prefix(&self) -> Result<Subject, JetstreamError2> {
+        Subject::from_tokens(["$O", &self.bucket, "M", ">"]).map_err(JetstreamError2::Subject)
+    }
+}
+
+impl ObjectReader {
+    /// The metadata of the object being read
+    #[must_use]
+    pub fn info(&self) -> &ObjectInfo {
+        &self.info
+    }
+
+    fn verify_digest(&mut self) -> io::Result<()> {
+        let digest = format!(
+            "{DIGEST_PREFIX}{}",
+            BASE64URL.encode(&self.hasher.finalize_reset())
+        );
+        if self
+            .info
+            .digest
+            .as_deref()
+            .is_some_and(|expected| expected != digest)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "object digest mismatch",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl AsyncRead for ObjectReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        while this.chunk.is_empty() {
+            let Some(consumer) = &mut this.consumer else {
+                return Poll::Ready(Ok(()));
+            };
+            if this.remaining_chunks == 0 {
+                this.consumer = None;
+                return Poll::Ready(this.verify_digest());
+            }
+
+            match Pin::new(consumer).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(message))) => {
+                    this.remaining_chunks -= 1;
+                    this.hasher.update(&message.base.payload);
+                    this.chunk = message.base.payload;
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(io::Error::other(err))),
+                Poll::Ready(None) => {
+                    return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+                }
+            }
+        }
+
+        let len = this.chunk.len().min(buf.remaining());
+        buf.put_slice(&this.chunk.split_to(len));
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Debug for ObjectReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjectReader")
+            .field("info", &self.info)
+            .field("remaining_chunks", &self.remaining_chunks)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for ObjectList {
+    type Item = Result<ObjectInfo, JetstreamError2>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.exhausted {
+                return Poll::Ready(None);
+            }
+
+            let fetch = this.fetch.get_or_insert_with(|| {
+                let store = this.store.clone();
+                let sequence = this.sequence;
+                Box::pin(async move { store.next_meta(sequence).await })
+            });
+
+            match Pin::new(fetch).poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(Some((sequence, info)))) => {
+                    this.fetch = None;
+                    this.sequence = sequence + 1;
+                    if !info.deleted {
+                        return Poll::Ready(Some(Ok(info)));
+                    }
+                }
+                Poll::Ready(Ok(None)) => {
+                    this.fetch = None;
+                    this.exhausted = true;
+                }
+                Poll::Ready(Err(err)) => {
+                    this.fetch = None;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+impl Debug for ObjectList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjectList")
+            .field("store", &self.store)
+            .field("sequence", &self.sequence)
+            .field("exhausted", &self.exhausted)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FusedStream for ObjectList {
+    fn is_terminated(&self) -> bool {
+        self.exhausted
+    }
+}
+
+#[expect(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "required by serde's skip_serializing_if"
+)]
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[cfg(test)]
+mod tests {
+    use data_encoding::BASE64;
+    use futures_util::StreamExt as _;
+    use serde_json::json;
+    use tokio::sync::oneshot;
+    use watermelon_proto::{
+        headers::{HeaderMap, HeaderName, HeaderValue},
+        ServerMessage, Subject,
+    };
+
+    use super::ObjectInfo;
+    use crate::{
+        client::{
+            tests::{response, TestHandler},
+            JetstreamClient,
+        },
+        core::Client,
+        handler::HandlerCommand,
+    };
+
+    const NOT_FOUND: &str = r#"{"type":"io.nats.jetstream.api.v1.stream_msg_get_response","error":{"code":404,"err_code":10037,"description":"no message found"}}"#;
+
+    async fn next_request(
+        handler: &mut TestHandler,
+    ) -> (
+        Subject,
+        HeaderMap,
+        serde_json::Value,
+        oneshot::Sender<ServerMessage>,
+    ) {
+        let Some(HandlerCommand::RequestMultiplexed {
+            subject,
+            headers,
+            payload,
+            reply,
+            ..
+        }) = handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        let payload = serde_json::from_slice(&payload).unwrap_or_else(|_| {
+            serde_json::Value::String(String::from_utf8(payload.to_vec()).unwrap())
+        });
+        (subject, headers, payload, reply)
+    }
+
+    fn respond(reply: oneshot::Sender<ServerMessage>, subject: Subject, payload: String) {
+        reply.send(response(subject, payload)).unwrap();
+    }
+
+    fn stored_info(sequence: u64, info: &ObjectInfo) -> String {
+        json!({
+            "message": {
+                "subject": format!("$O.files.M.{}", info.name),
+                "seq": sequence,
+                "time": "2024-01-01T00:00:00Z",
+                "data": BASE64.encode(&serde_json::to_vec(info).unwrap()),
+            }
+        })
+        .to_string()
+    }
+
+    fn info(name: &str, deleted: bool) -> ObjectInfo {
+        ObjectInfo {
+            name: name.to_owned(),
+            description: None,
+            bucket: "files".to_owned(),
+            nuid: "abcd".to_owned(),
+            size: 5,
+            modified: "2024-01-01T00:00:00Z".parse().unwrap(),
+            chunks: 1,
+            digest: None,
+            deleted,
+        }
+    }
+
+    #[tokio::test]
+    async fn put() {
+        let (client, mut handler) = Client::test(8);
+        let store = JetstreamClient::new(client).object_store("files");
+
+        let (info, ()) = tokio::join!(store.put("docs/readme.md", &b"hello"[..]), async {
+            let (subject, _headers, request, reply) = next_request(&mut handler).await;
+            assert_eq!("$JS.API.STREAM.MSG.GET.OBJ_files", subject.as_str());
+            assert_eq!(
+                "$O.files.M.ZG9jcy9yZWFkbWUubWQ=",
+                request["last_by_subj"].as_str().unwrap()
+            );
+            respond(reply, subject, NOT_FOUND.to_owned());
+
+            let (subject, _headers, chunk, reply) = next_request(&mut handler).await;
+            assert!(subject.starts_with("$O.files.C."));
+            assert_eq!("hello", chunk.as_str().unwrap());
+            respond(
+                reply,
+                subject,
+                r#"{"stream":"OBJ_files","seq":1}"#.to_owned(),
+            );
+
+            let (subject, headers, meta, reply) = next_request(&mut handler).await;
+            assert_eq!("$O.files.M.ZG9jcy9yZWFkbWUubWQ=", subject.as_str());
+            assert_eq!(
+                Some(&HeaderValue::from_static("sub")),
+                headers.get(&HeaderName::ROLLUP)
+            );
+            assert_eq!("docs/readme.md", meta["name"]);
+            assert_eq!(5, meta["size"]);
+            assert_eq!(1, meta["chunks"]);
+            assert_eq!(
+                "SHA-256=LPJNul-wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=",
+                meta["digest"]
+            );
+            assert!(meta.get("deleted").is_none());
+            respond(
+                reply,
+                subject,
+                r#"{"stream":"OBJ_files","seq":2}"#.to_owned(),
+            );
+        });
+
+        let info = info.unwrap();
+        assert_eq!(5, info.size);
+        assert_eq!(1, info.chunks);
+        assert_eq!(22, info.nuid.len());
+    }
+
+    #[tokio::test]
+    async fn list() {
+        let (client, mut handler) = Client::test(8);
+        let store = JetstreamClient::new(client).object_store("files");
+
+        let (objects, ()) = tokio::join!(store.list().collect::<Vec<_>>(), async {
+            for (expected_sequence, response) in [
+                (1, stored_info(3, &info("a.txt", false))),
+                (4, stored_info(5, &info("b.txt", true))),
+                (6, NOT_FOUND.to_owned()),
+            ] {
+                let (subject, _headers, request, reply) = next_request(&mut handler).await;
+                assert_eq!("$JS.API.STREAM.MSG.GET.OBJ_files", subject.as_str());
+                assert_eq!(expected_sequence, request["seq"]);
+                assert_eq!("$O.files.M.>", request["next_by_subj"]);
+                respond(reply, subject, response);
+            }
+        });
+
+        let objects = objects.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!([info("a.txt", false)].as_slice(), objects.as_slice());
+    }
+}
@@ -0,0 +1,607 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Display},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures_core::{future::BoxFuture, FusedStream, Stream};
+use tokio::time::{sleep, Instant, Sleep};
+use watermelon_proto::{
+    error::ServerError, headers::HeaderName, ServerMessage, StatusCode, Subject,
+};
+
+use crate::{
+    client::{
+        backoff::ReconnectBackoff, AckPolicy, ConsumerConfig, ConsumerDurability,
+        ConsumerSpecificConfig, ConsumerStorage, DeliverPolicy, JetstreamClient, JetstreamError2,
+        JetstreamMessageMetadata, ReplayPolicy,
+    },
+    subscription::Subscription,
+};
+
+const LAST_CONSUMER_SEQUENCE_HEADER: &str = "Nats-Last-Consumer";
+const CONSUMER_STALLED_HEADER: &str = "Nats-Consumer-Stalled";
+const MISSED_HEARTBEATS: u32 = 3;
+const RESET_BACKOFF_MIN: Duration = Duration::from_millis(100);
+const RESET_BACKOFF_MAX: Duration = Duration::from_secs(10);
+const RESET_BACKOFF_JITTER: f64 = 0.2;
+
+/// Configuration for [`JetstreamClient::ordered_consumer`]
+#[derive(Debug, Clone)]
+pub struct OrderedConfig {
+    filter_subjects: Vec<Subject>,
+    deliver_policy: DeliverPolicy,
+    headers_only: bool,
+    idle_heartbeat: Duration,
+    max_reset_attempts: u32,
+}
+
+/// An ordered, self-healing, stream of messages from a Jetstream stream
+///
+/// Obtained from [`JetstreamClient::ordered_consumer`].
+///
+/// Messages are delivered by an ephemeral push consumer, in stream order and
+/// without gaps. If a message goes missing, or if the server stops sending
+/// heartbeats, the consumer is recreated starting from the stream sequence
+/// following the last message that has been yielded, and the previous
+/// consumer is deleted.
+///
+/// Failed attempts at recreating the consumer are retried with an exponential
+/// backoff. The stream ends after [`OrderedConfig::max_reset_attempts`]
+/// consecutive failures.
+#[must_use = "streams do nothing unless polled"]
+pub struct OrderedConsumer {
+    client: JetstreamClient,
+    stream_name: String,
+    config: OrderedConfig,
+    subscription: Option<Subscription>,
+    consumer_name: Option<String>,
+    consumer_sequence: u64,
+    stream_sequence: Option<u64>,
+    heartbeat_timeout: Pin<Box<Sleep>>,
+    resetting: Option<BoxFuture<'static, Result<(Subscription, String), JetstreamError2>>>,
+    failed_resets: u32,
+    terminated: bool,
+}
+
+/// An error encountered while consuming an [`OrderedConsumer`]
+#[derive(Debug, thiserror::Error)]
+pub enum OrderedConsumerError {
+    #[error("an error returned by the server")]
+    ServerError(#[source] ServerError),
+    #[error("message is missing the Jetstream metadata")]
+    InvalidMetadata(ServerMessage),
+    /// Recreating the consumer after a gap or a missed heartbeat failed
+    ///
+    /// The consumer is recreated again, after a backoff, the next time the stream
+    /// is polled, unless [`OrderedConfig::max_reset_attempts`] has been reached.
+    #[error("failed to recreate the consumer")]
+    Reset(#[source] JetstreamError2),
+}
+
+impl OrderedConfig {
+    /// Construct a new configuration with the default values
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            filter_subjects: Vec::new(),
+            deliver_policy: DeliverPolicy::All,
+            headers_only: false,
+            idle_heartbeat: Duration::from_secs(5),
+            max_reset_attempts: 10,
+        }
+    }
+
+    /// Only deliver the messages matching one of `filter_subjects`
+    ///
+    /// Default: every message of the stream.
+    #[must_use]
+    pub fn filter_subjects(mut self, filter_subjects: Vec<Subject>) -> Self {
+        self.filter_subjects = filter_subjects;
+        self
+    }
+
+    /// Define where in the stream delivery starts
+    ///
+    /// Only used when first creating the consumer. Recreated consumers
+    /// always start after the last message that has been yielded.
+    ///
+    /// Default: [`DeliverPolicy::All`].
+    #[must_use]
+    pub fn deliver_policy(mut self, deliver_policy: DeliverPolicy) -> Self {
+        self.deliver_policy = deliver_policy;
+        self
+    }
+
+    /// Deliver only the headers of the messages, without the payload
+    ///
+    /// Default: `false`.
+    #[must_use]
+    pub fn headers_only(mut self, headers_only: bool) -> Self {
+        self.headers_only = headers_only;
+        self
+    }
+
+    /// Define the interval at which the server sends heartbeats while idle
+    ///
+    /// The consumer is recreated after missing three heartbeats in a row.
+    ///
+    /// Default: 5 seconds.
+    #[must_use]
+    pub fn idle_heartbeat(mut self, idle_heartbeat: Duration) -> Self {
+        self.idle_heartbeat = idle_heartbeat;
+        self
+    }
+
+    /// Define how many consecutive failed attempts at recreating the consumer end the stream
+    ///
+    /// Default: 10.
+    #[must_use]
+    pub fn max_reset_attempts(mut self, max_reset_attempts: u32) -> Self {
+        self.max_reset_attempts = max_reset_attempts;
+        self
+    }
+}
+
+impl Default for OrderedConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JetstreamClient {
+    /// Consume the stream `stream_name` in order via an ephemeral push consumer
+    ///
+    /// See [`OrderedConsumer`].
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the given `stream_name` produces an invalid subject or if an error
+    /// occurs while creating the consumer.
+    pub async fn ordered_consumer(
+        &self,
+        stream_name: impl Display,
+        config: OrderedConfig,
+    ) -> Result<OrderedConsumer, JetstreamError2> {
+        let stream_name = stream_name.to_string();
+        let (subscription, consumer_name) = subscribe_ordered(
+            self.clone(),
+            stream_name.clone(),
+            config.clone(),
+            config.deliver_policy,
+        )
+        .await?;
+
+        Ok(OrderedConsumer {
+            client: self.clone(),
+            stream_name,
+            heartbeat_timeout: Box::pin(sleep(config.idle_heartbeat * MISSED_HEARTBEATS)),
+            config,
+            subscription: Some(subscription),
+            consumer_name: Some(consumer_name),
+            consumer_sequence: 0,
+            stream_sequence: None,
+            resetting: None,
+            failed_resets: 0,
+            terminated: false,
+        })
+    }
+}
+
+async fn subscribe_ordered(
+    client: JetstreamClient,
+    stream_name: String,
+    config: OrderedConfig,
+    deliver_policy: DeliverPolicy,
+) -> Result<(Subscription, String), JetstreamError2> {
+    let deliver_subject = client.client().new_inbox();
+
+    let subscription = client
+        .client()
+        .subscribe(deliver_subject.clone(), None)
+        .await
+        .map_err(JetstreamError2::ClientClosed)?;
+
+    let consumer_config = ConsumerConfig {
+        durability: ConsumerDurability::Ephemeral,
+        name: String::new(),
+        description: String::new(),
+        deliver_policy,
+        ack_policy: AckPolicy::None,
+        max_deliver: Some(1),
+        backoff: Vec::new(),
+        filter_subjects: config.filter_subjects,
+        replay_policy: ReplayPolicy::Instant,
+        rate_limit: None,
+        flow_control: Some(true),
+        idle_heartbeat: config.idle_heartbeat,
+        headers_only: config.headers_only,
+        specs: ConsumerSpecificConfig::Push {
+            deliver_subject,
+            deliver_group: None,
+        },
+        inactive_threshold: Duration::ZERO,
+        replicas: None,
+        storage: ConsumerStorage::Memory,
+        metadata: BTreeMap::new(),
+    };
+    let consumer = client
+        .create_consumer(stream_name, &consumer_config)
+        .await?;
+
+    Ok((subscription, consumer.config.name))
+}
+
+impl OrderedConsumer {
+    /// Drop the current consumer and start creating a new one after the last yielded message
+    fn reset(&mut self) {
+        let deliver_policy = match self.stream_sequence {
+            Some(stream_sequence) => DeliverPolicy::StartSequence {
+                sequence: stream_sequence + 1,
+            },
+            None => self.config.deliver_policy,
+        };
+        let delay = self.failed_resets.checked_sub(1).map(|attempt| {
+            ReconnectBackoff::new(RESET_BACKOFF_MIN, RESET_BACKOFF_MAX, RESET_BACKOFF_JITTER)
+                .delay(attempt)
+        });
+
+        self.subscription = None;
+        self.consumer_sequence = 0;
+
+        let client = self.client.clone();
+        let stream_name = self.stream_name.clone();
+        let config = self.config.clone();
+        let prev_consumer_name = self.consumer_name.take();
+        self.resetting = Some(Box::pin(async move {
+            if let Some(delay) = delay {
+                sleep(delay).await;
+            }
+            if let Some(prev_consumer_name) = prev_consumer_name {
+                // The server would eventually remove it for being inactive anyway
+                let _ = client
+                    .delete_consumer(&stream_name, prev_consumer_name)
+                    .await;
+            }
+
+            subscribe_ordered(client, stream_name, config, deliver_policy).await
+        }));
+    }
+
+    fn reset_heartbeat_timeout(&mut self) {
+        let heartbeat_timeout = self.config.idle_heartbeat * MISSED_HEARTBEATS;
+        self.heartbeat_timeout
+            .as_mut()
+            .reset(Instant::now() + heartbeat_timeout);
+    }
+
+    /// Answer flow control requests and stalled consumer heartbeats
+    fn handle_control_message(&self, message: &ServerMessage) {
+        let reply_subject = message.base.reply_subject.clone().or_else(|| {
+            message
+                .base
+                .headers
+                .get(&HeaderName::from_static(CONSUMER_STALLED_HEADER))
+                .and_then(|subject| Subject::try_from(subject.as_str().to_owned()).ok())
+        });
+        if let Some(reply_subject) = reply_subject {
+            let _ = self
+                .client
+                .client()
+                .publish(reply_subject)
+                .payload(Bytes::new())
+                .try_publish();
+        }
+    }
+}
+
+impl Stream for OrderedConsumer {
+    type Item = Result<ServerMessage, OrderedConsumerError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.terminated {
+            return Poll::Ready(None);
+        }
+        if this.subscription.is_none() && this.resetting.is_none() {
+            // The previous attempt at recreating the consumer failed
+            this.reset();
+        }
+        if let Some(resetting) = &mut this.resetting {
+            match resetting.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok((subscription, consumer_name))) => {
+                    this.resetting = None;
+                    this.subscription = Some(subscription);
+                    this.consumer_name = Some(consumer_name);
+                    this.failed_resets = 0;
+                    this.reset_heartbeat_timeout();
+                }
+                Poll::Ready(Err(err)) => {
+                    this.resetting = None;
+                    this.failed_resets += 1;
+                    this.terminated = this.failed_resets >= this.config.max_reset_attempts;
+                    return Poll::Ready(Some(Err(OrderedConsumerError::Reset(err))));
+                }
+            }
+        }
+
+        loop {
+            let Some(subscription) = &mut this.subscription else {
+                unreachable!()
+            };
+            let message = match Pin::new(subscription).poll_next(cx) {
+                Poll::Pending => {
+                    if this.heartbeat_timeout.as_mut().poll(cx).is_ready() {
+                        this.reset();
+                        cx.waker().wake_by_ref();
+                    }
+                    return Poll::Pending;
+                }
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(err))) => {
+                    return Poll::Ready(Some(Err(OrderedConsumerError::ServerError(err))))
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+            };
+            this.reset_heartbeat_timeout();
+
+            if message.status_code == Some(StatusCode::IDLE_HEARTBEAT) {
+                let last_consumer_sequence = message
+                    .base
+                    .headers
+                    .get(&HeaderName::from_static(LAST_CONSUMER_SEQUENCE_HEADER))
+                    .and_then(|sequence| sequence.as_str().parse::<u64>().ok());
+                if last_consumer_sequence.is_some_and(|sequence| sequence != this.consumer_sequence)
+                {
+                    // Messages were sent after the last one we received
+                    this.reset();
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+
+                this.handle_control_message(&message);
+                continue;
+            }
+            if message.status_code.is_some() {
+                continue;
+            }
+
+            let Some(metadata) = message
+                .base
+                .reply_subject
+                .as_ref()
+                .and_then(JetstreamMessageMetadata::from_reply_subject)
+            else {
+                return Poll::Ready(Some(Err(OrderedConsumerError::InvalidMetadata(message))));
+            };
+            if metadata.consumer_sequence != this.consumer_sequence + 1 {
+                this.reset();
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            this.consumer_sequence = metadata.consumer_sequence;
+            this.stream_sequence = Some(metadata.stream_sequence);
+            return Poll::Ready(Some(Ok(message)));
+        }
+    }
+}
+
+impl Debug for OrderedConsumer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrderedConsumer")
+            .field("stream_name", &self.stream_name)
+            .field("config", &self.config)
+            .field("subscription", &self.subscription)
+            .field("consumer_name", &self.consumer_name)
+            .field("consumer_sequence", &self.consumer_sequence)
+            .field("stream_sequence", &self.stream_sequence)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FusedStream for OrderedConsumer {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+            || self
+                .subscription
+                .as_ref()
+                .is_some_and(FusedStream::is_terminated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{pin::pin, time::Duration};
+
+    use bytes::Bytes;
+    use futures_core::FusedStream as _;
+    use futures_util::StreamExt as _;
+    use watermelon_proto::{Subject, SubscriptionId};
+
+    use super::{OrderedConfig, OrderedConsumerError};
+    use crate::{
+        client::{
+            tests::{accept_consumer, ack_subject, message, response, TestHandler},
+            JetstreamClient,
+        },
+        core::Client,
+        handler::HandlerCommand,
+    };
+
+    /// Answer the request deleting the previous consumer
+    async fn accept_delete(handler: &mut TestHandler) {
+        let Some(HandlerCommand::Unsubscribe { .. }) = handler.receiver.recv().await else {
+            unreachable!()
+        };
+        let Some(HandlerCommand::RequestMultiplexed { subject, reply, .. }) =
+            handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        assert_eq!("$JS.API.CONSUMER.DELETE.orders.ephemeral", subject.as_str());
+        reply
+            .send(response(subject, Bytes::from_static(b"{\"success\":true}")))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reset_on_gap() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let (consumer, (messages, _, config)) = tokio::join!(
+            jetstream.ordered_consumer("orders", OrderedConfig::new()),
+            accept_consumer(&mut handler, "orders")
+        );
+        let mut consumer = pin!(consumer.unwrap());
+        assert_eq!(None, config["opt_start_seq"].as_u64());
+
+        messages
+            .try_send(Ok(message(
+                SubscriptionId::MIN,
+                Subject::from_static("orders.created"),
+                Some(ack_subject("orders", "ephemeral", 10, 1)),
+                Bytes::from_static(b"order"),
+            )))
+            .unwrap();
+        messages
+            .try_send(Ok(message(
+                SubscriptionId::MIN,
+                Subject::from_static("orders.created"),
+                Some(ack_subject("orders", "ephemeral", 11, 2)),
+                Bytes::from_static(b"order"),
+            )))
+            .unwrap();
+        messages
+            .try_send(Ok(message(
+                SubscriptionId::MIN,
+                Subject::from_static("orders.created"),
+                Some(ack_subject("orders", "ephemeral", 13, 4)),
+                Bytes::from_static(b"order"),
+            )))
+            .unwrap();
+        for _ in 0..2 {
+            consumer.next().await.unwrap().unwrap();
+        }
+
+        // The third message skipped consumer sequence 3
+        let (message, (_messages, start_sequence)) = tokio::join!(consumer.next(), async {
+            accept_delete(&mut handler).await;
+            let (messages, _, config) = accept_consumer(&mut handler, "orders").await;
+            // The new consumer starts counting from 1 again
+            messages
+                .try_send(Ok(message(
+                    SubscriptionId::MIN,
+                    Subject::from_static("orders.created"),
+                    Some(ack_subject("orders", "ephemeral", 12, 1)),
+                    Bytes::from_static(b"order"),
+                )))
+                .unwrap();
+            (messages, config["opt_start_seq"].as_u64())
+        });
+        assert_eq!(Some(12), start_sequence);
+        assert_eq!(
+            "$JS.ACK.orders.ephemeral.1.12.1.1700000000123456789.0",
+            message
+                .unwrap()
+                .unwrap()
+                .base
+                .reply_subject
+                .unwrap()
+                .as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn reset_on_missed_heartbeats() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let config = OrderedConfig::new().idle_heartbeat(Duration::from_millis(10));
+        let (consumer, (_messages, _, _)) = tokio::join!(
+            jetstream.ordered_consumer("orders", config),
+            accept_consumer(&mut handler, "orders")
+        );
+        let mut consumer = pin!(consumer.unwrap());
+
+        let (message, (_messages, start_sequence)) = tokio::join!(consumer.next(), async {
+            accept_delete(&mut handler).await;
+            let (messages, _, config) = accept_consumer(&mut handler, "orders").await;
+            messages
+                .try_send(Ok(message(
+                    SubscriptionId::MIN,
+                    Subject::from_static("orders.created"),
+                    Some(ack_subject("orders", "ephemeral", 1, 1)),
+                    Bytes::from_static(b"order"),
+                )))
+                .unwrap();
+            (messages, config["opt_start_seq"].as_u64())
+        });
+        // Nothing was yielded before, so the original deliver policy is used
+        assert_eq!(None, start_sequence);
+        message.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn fuse_after_failed_resets() {
+        let (client, mut handler) = Client::test(8);
+        let jetstream = JetstreamClient::new(client);
+
+        let config = OrderedConfig::new().max_reset_attempts(2);
+        let (consumer, (messages, _, _)) = tokio::join!(
+            jetstream.ordered_consumer("orders", config),
+            accept_consumer(&mut handler, "orders")
+        );
+        let mut consumer = pin!(consumer.unwrap());
+
+        messages
+            .try_send(Ok(message(
+                SubscriptionId::MIN,
+                Subject::from_static("orders.created"),
+                Some(ack_subject("orders", "ephemeral", 11, 2)),
+                Bytes::from_static(b"order"),
+            )))
+            .unwrap();
+        let (result, ()) = tokio::join!(consumer.next(), async {
+            accept_delete(&mut handler).await;
+            let Some(HandlerCommand::Subscribe { .. }) = handler.receiver.recv().await else {
+                unreachable!()
+            };
+            let Some(HandlerCommand::RequestMultiplexed { reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            drop(reply);
+        });
+        assert!(matches!(result, Some(Err(OrderedConsumerError::Reset(_)))));
+        assert!(!consumer.is_terminated());
+
+        // Retried after a backoff, without deleting the consumer again since none was created
+        let (result, ()) = tokio::join!(consumer.next(), async {
+            loop {
+                match handler.receiver.recv().await.unwrap() {
+                    HandlerCommand::Subscribe { .. } => break,
+                    HandlerCommand::RequestMultiplexed { .. } => unreachable!(),
+                    _ => {}
+                }
+            }
+            let Some(HandlerCommand::RequestMultiplexed { reply, .. }) =
+                handler.receiver.recv().await
+            else {
+                unreachable!()
+            };
+            drop(reply);
+        });
+        assert!(matches!(result, Some(Err(OrderedConsumerError::Reset(_)))));
+        assert!(consumer.is_terminated());
+        assert!(consumer.next().await.is_none());
+    }
+}
@@ -8,7 +8,11 @@ use chrono::{DateTime, Utc};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use watermelon_proto::{QueueGroup, Subject};
 
-use super::{duration, duration_vec, nullable_number, option_nonzero};
+use crate::client::{JetstreamClient, JetstreamError2};
+
+use super::{duration, duration_vec, nullable_datetime, nullable_number, option_nonzero};
+
+const DEFAULT_ACK_WAIT: Duration = Duration::from_secs(30);
 
 /// A Jetstream consumer
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,6 +23,21 @@ pub struct Consumer {
     pub created_at: DateTime<Utc>,
 }
 
+impl Consumer {
+    /// Fetch the latest configuration of this consumer from the server
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the consumer no longer exists
+    /// or if an error occurs while fetching it.
+    pub async fn refresh(&mut self, client: &JetstreamClient) -> Result<(), JetstreamError2> {
+        *self = client
+            .consumer_info(&self.stream_name, &self.config.name)
+            .await?;
+        Ok(())
+    }
+}
+
 /// A Jetstream consumer configuration
 #[derive(Debug)]
 pub struct ConsumerConfig {
@@ -34,6 +53,12 @@ pub struct ConsumerConfig {
     pub rate_limit: Option<NonZeroU64>,
     pub flow_control: Option<bool>,
     pub idle_heartbeat: Duration,
+    /// Deliver only the headers of the messages, without the payload
+    ///
+    /// The size of the omitted payload is available via
+    /// [`ServerMessage::declared_payload_size`].
+    ///
+    /// [`ServerMessage::declared_payload_size`]: watermelon_proto::ServerMessage::declared_payload_size
     pub headers_only: bool,
 
     pub specs: ConsumerSpecificConfig,
@@ -45,6 +70,72 @@ pub struct ConsumerConfig {
     pub metadata: BTreeMap<String, String>,
 }
 
+/// A builder for [`ConsumerConfig`]
+///
+/// Obtained from [`ConsumerConfig::builder`].
+#[derive(Debug)]
+pub struct ConsumerConfigBuilder {
+    durability: ConsumerDurability,
+    name: String,
+    description: String,
+    deliver_policy: DeliverPolicy,
+    ack_policy: AckPolicy,
+    max_deliver: Option<u32>,
+    backoff: Vec<Duration>,
+    filter_subjects: Vec<Subject>,
+    replay_policy: ReplayPolicy,
+    rate_limit: Option<NonZeroU64>,
+    flow_control: Option<bool>,
+    idle_heartbeat: Duration,
+    headers_only: bool,
+    inactive_threshold: Duration,
+    replicas: Option<NonZeroU32>,
+    storage: ConsumerStorage,
+    metadata: BTreeMap<String, String>,
+}
+
+/// An error encountered while building a [`ConsumerConfig`]
+#[derive(Debug, thiserror::Error)]
+pub enum ConsumerConfigError {
+    #[error("durable consumers must have a name")]
+    EmptyDurableName,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CreateConsumerRequest<'a> {
+    pub(crate) stream_name: String,
+    pub(crate) config: &'a ConsumerConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct DeleteConsumerResponse {
+    pub(crate) success: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct PauseConsumerRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) pause_until: Option<DateTime<Utc>>,
+}
+
+/// The pause state of a consumer
+///
+/// Returned by [`JetstreamClient::pause_consumer`] and [`JetstreamClient::resume_consumer`].
+///
+/// [`JetstreamClient::pause_consumer`]: crate::jetstream::JetstreamClient::pause_consumer
+/// [`JetstreamClient::resume_consumer`]: crate::jetstream::JetstreamClient::resume_consumer
+#[derive(Debug, Deserialize)]
+pub struct ConsumerPause {
+    /// Whether the consumer is paused
+    pub paused: bool,
+    /// The time at which the consumer resumes delivering messages
+    #[serde(default, with = "nullable_datetime")]
+    pub pause_until: Option<DateTime<Utc>>,
+    /// The time left until the consumer resumes delivering messages
+    #[serde(default, with = "duration")]
+    pub pause_remaining: Duration,
+}
+
 /// Pull or Push configuration parameters for a consumer
 #[derive(Debug)]
 pub enum ConsumerSpecificConfig {
@@ -203,6 +294,259 @@ struct RawConsumerConfig {
     metadata: BTreeMap<String, String>,
 }
 
+impl ConsumerConfig {
+    /// Build a new consumer configuration named `name`
+    ///
+    /// The consumer is ephemeral, acknowledges messages explicitly
+    /// and delivers every message in the stream at full speed, unless
+    /// configured otherwise. The configuration is completed via
+    /// [`ConsumerConfigBuilder::pull`] or [`ConsumerConfigBuilder::push`].
+    #[must_use]
+    pub fn builder(name: impl Into<String>) -> ConsumerConfigBuilder {
+        ConsumerConfigBuilder {
+            durability: ConsumerDurability::Ephemeral,
+            name: name.into(),
+            description: String::new(),
+            deliver_policy: DeliverPolicy::All,
+            ack_policy: AckPolicy::Explicit {
+                wait: DEFAULT_ACK_WAIT,
+                max_pending: None,
+            },
+            max_deliver: None,
+            backoff: Vec::new(),
+            filter_subjects: Vec::new(),
+            replay_policy: ReplayPolicy::Instant,
+            rate_limit: None,
+            flow_control: None,
+            idle_heartbeat: Duration::ZERO,
+            headers_only: false,
+            inactive_threshold: Duration::ZERO,
+            replicas: None,
+            storage: ConsumerStorage::Disk,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+impl ConsumerConfigBuilder {
+    /// Make the consumer durable, persisting it until explicitly deleted
+    ///
+    /// Default: ephemeral.
+    #[must_use]
+    pub fn durable(mut self) -> Self {
+        self.durability = ConsumerDurability::Durable;
+        self
+    }
+
+    /// Define the description of the consumer
+    ///
+    /// Default: empty.
+    #[must_use]
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Define from where in the stream the consumer starts delivering messages
+    ///
+    /// Default: [`DeliverPolicy::All`].
+    #[must_use]
+    pub fn deliver_policy(mut self, deliver_policy: DeliverPolicy) -> Self {
+        self.deliver_policy = deliver_policy;
+        self
+    }
+
+    /// Define how messages delivered by the consumer have to be acknowledged
+    ///
+    /// Default: [`AckPolicy::Explicit`] with a 30 seconds wait.
+    #[must_use]
+    pub fn ack_policy(mut self, ack_policy: AckPolicy) -> Self {
+        self.ack_policy = ack_policy;
+        self
+    }
+
+    /// Define the maximum number of times a message is delivered
+    ///
+    /// Default: unlimited.
+    #[must_use]
+    pub fn max_deliver(mut self, max_deliver: u32) -> Self {
+        self.max_deliver = Some(max_deliver);
+        self
+    }
+
+    /// Define the delays between redeliveries of unacknowledged messages
+    ///
+    /// Default: the ack wait of the [`AckPolicy`].
+    #[must_use]
+    pub fn backoff(mut self, backoff: Vec<Duration>) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Only deliver messages matching any of `filter_subjects`
+    ///
+    /// Default: every message in the stream.
+    #[must_use]
+    pub fn filter_subjects(mut self, filter_subjects: Vec<Subject>) -> Self {
+        self.filter_subjects = filter_subjects;
+        self
+    }
+
+    /// Define the rate at which messages are delivered
+    ///
+    /// Default: [`ReplayPolicy::Instant`].
+    #[must_use]
+    pub fn replay_policy(mut self, replay_policy: ReplayPolicy) -> Self {
+        self.replay_policy = replay_policy;
+        self
+    }
+
+    /// Limit the delivery rate of the consumer to `bits_per_second`
+    ///
+    /// Default: unlimited.
+    #[must_use]
+    pub fn rate_limit(mut self, bits_per_second: NonZeroU64) -> Self {
+        self.rate_limit = Some(bits_per_second);
+        self
+    }
+
+    /// Enable flow control for push consumers
+    ///
+    /// Default: the server default.
+    #[must_use]
+    pub fn flow_control(mut self, flow_control: bool) -> Self {
+        self.flow_control = Some(flow_control);
+        self
+    }
+
+    /// Have the server send heartbeats when the consumer is idle
+    ///
+    /// Default: disabled.
+    #[must_use]
+    pub fn idle_heartbeat(mut self, idle_heartbeat: Duration) -> Self {
+        self.idle_heartbeat = idle_heartbeat;
+        self
+    }
+
+    /// Deliver only the headers of the messages, without the payload
+    ///
+    /// Default: `false`.
+    #[must_use]
+    pub fn headers_only(mut self, headers_only: bool) -> Self {
+        self.headers_only = headers_only;
+        self
+    }
+
+    /// Define after how long without activity the consumer is deleted
+    ///
+    /// Default: the server default.
+    #[must_use]
+    pub fn inactive_threshold(mut self, inactive_threshold: Duration) -> Self {
+        self.inactive_threshold = inactive_threshold;
+        self
+    }
+
+    /// Define the number of replicas of the consumer
+    ///
+    /// Default: the number of replicas of the stream.
+    #[must_use]
+    pub fn replicas(mut self, replicas: NonZeroU32) -> Self {
+        self.replicas = Some(replicas);
+        self
+    }
+
+    /// Define whether the consumer state is kept on disk or in memory
+    ///
+    /// Default: [`ConsumerStorage::Disk`].
+    #[must_use]
+    pub fn storage(mut self, storage: ConsumerStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Attach arbitrary metadata to the consumer
+    ///
+    /// Default: empty.
+    #[must_use]
+    pub fn metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Build the configuration of a pull consumer
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the consumer is durable but has an empty name.
+    pub fn pull(self) -> Result<ConsumerConfig, ConsumerConfigError> {
+        self.build(ConsumerSpecificConfig::Pull {
+            max_waiting: None,
+            max_request_batch: None,
+            max_request_expires: Duration::ZERO,
+            max_request_max_bytes: None,
+        })
+    }
+
+    /// Build the configuration of a push consumer delivering messages to `deliver_subject`
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the consumer is durable but has an empty name.
+    pub fn push(self, deliver_subject: Subject) -> Result<ConsumerConfig, ConsumerConfigError> {
+        self.build(ConsumerSpecificConfig::Push {
+            deliver_subject,
+            deliver_group: None,
+        })
+    }
+
+    fn build(self, specs: ConsumerSpecificConfig) -> Result<ConsumerConfig, ConsumerConfigError> {
+        let Self {
+            durability,
+            name,
+            description,
+            deliver_policy,
+            ack_policy,
+            max_deliver,
+            backoff,
+            filter_subjects,
+            replay_policy,
+            rate_limit,
+            flow_control,
+            idle_heartbeat,
+            headers_only,
+            inactive_threshold,
+            replicas,
+            storage,
+            metadata,
+        } = self;
+
+        if matches!(durability, ConsumerDurability::Durable) && name.is_empty() {
+            return Err(ConsumerConfigError::EmptyDurableName);
+        }
+
+        Ok(ConsumerConfig {
+            durability,
+            name,
+            description,
+            deliver_policy,
+            ack_policy,
+            max_deliver,
+            backoff,
+            filter_subjects,
+            replay_policy,
+            rate_limit,
+            flow_control,
+            idle_heartbeat,
+            headers_only,
+            specs,
+            inactive_threshold,
+            replicas,
+            storage,
+            metadata,
+        })
+    }
+}
+
 impl Serialize for ConsumerConfig {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let (name, durable_name) = match self.durability {
@@ -398,3 +742,54 @@ impl Default for AckPolicy {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use watermelon_proto::Subject;
+
+    use super::{
+        AckPolicy, ConsumerConfig, ConsumerConfigError, ConsumerDurability, ConsumerSpecificConfig,
+    };
+
+    #[test]
+    fn builder() {
+        let config = ConsumerConfig::builder("processor")
+            .durable()
+            .max_deliver(5)
+            .pull()
+            .unwrap();
+        assert!(matches!(config.durability, ConsumerDurability::Durable));
+        assert_eq!("processor", config.name);
+        assert_eq!(Some(5), config.max_deliver);
+        assert!(matches!(
+            config.ack_policy,
+            AckPolicy::Explicit {
+                wait,
+                max_pending: None
+            } if wait == Duration::from_secs(30)
+        ));
+        assert!(matches!(config.specs, ConsumerSpecificConfig::Pull { .. }));
+
+        let config = ConsumerConfig::builder("")
+            .push(Subject::from_static("deliver.here"))
+            .unwrap();
+        assert!(matches!(config.durability, ConsumerDurability::Ephemeral));
+        assert!(matches!(
+            config.specs,
+            ConsumerSpecificConfig::Push {
+                deliver_subject,
+                deliver_group: None
+            } if deliver_subject.as_str() == "deliver.here"
+        ));
+    }
+
+    #[test]
+    fn durable_without_name() {
+        assert!(matches!(
+            ConsumerConfig::builder("").durable().pull(),
+            Err(ConsumerConfigError::EmptyDurableName)
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+use watermelon_proto::{
+    headers::HeaderMap,
+    proto::{decode_headers, error::DecoderError},
+    Subject,
+};
+
+/// A message stored in a Jetstream stream
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawStoredMessage")]
+pub struct StoredMessage {
+    pub subject: Subject,
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub headers: HeaderMap,
+    pub payload: Bytes,
+}
+
+/// The metadata of a message delivered by a Jetstream consumer
+///
+/// Parsed from the reply subject of the message, which is used to acknowledge it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JetstreamMessageMetadata {
+    /// The Jetstream domain, if the server is configured with one
+    pub domain: Option<String>,
+    /// The name of the stream the message is stored in
+    pub stream: String,
+    /// The name of the consumer that delivered the message
+    pub consumer: String,
+    /// The number of times the message has been delivered, including this one
+    pub delivered: u64,
+    /// The sequence of the message in the stream
+    pub stream_sequence: u64,
+    /// The sequence of the delivery in the consumer
+    pub consumer_sequence: u64,
+    /// The time at which the message was stored in the stream
+    pub timestamp: DateTime<Utc>,
+    /// The number of messages still pending for the consumer
+    pub pending: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawStoredMessage {
+    subject: Subject,
+    #[serde(rename = "seq")]
+    sequence: u64,
+    #[serde(rename = "time")]
+    timestamp: DateTime<Utc>,
+    #[serde(default, rename = "hdrs")]
+    headers: Option<String>,
+    #[serde(default, rename = "data")]
+    payload: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum StoredMessageDecodeError {
+    #[error("invalid base64 encoding")]
+    Base64(#[source] data_encoding::DecodeError),
+    #[error("invalid headers")]
+    Headers(#[source] DecoderError),
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct GetMessageRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "seq")]
+    pub(crate) sequence: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "last_by_subj")]
+    pub(crate) last_by_subject: Option<&'a Subject>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "next_by_subj")]
+    pub(crate) next_by_subject: Option<&'a Subject>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct GetMessageResponse {
+    pub(crate) message: StoredMessage,
+}
+
+impl JetstreamMessageMetadata {
+    /// Parse the metadata out of the reply subject of a message delivered by a consumer
+    ///
+    /// Both the `$JS.ACK.<stream>.<consumer>.<delivered>.<stream seq>.<consumer seq>.<timestamp>.<pending>`
+    /// format and the newer format, which also includes the domain and the account hash, are supported.
+    ///
+    /// Returns `None` if `reply_subject` isn't a Jetstream ack subject.
+    #[must_use]
+    pub fn from_reply_subject(reply_subject: &Subject) -> Option<Self> {
+        let tokens = reply_subject.tokens().collect::<Vec<_>>();
+        // $JS.ACK.<stream>.<consumer>.<delivered>.<stream seq>.<consumer seq>.<timestamp>.<pending>
+        // $JS.ACK.<domain>.<account hash>.<stream>.<consumer>.<delivered>.<stream seq>.<consumer seq>.<timestamp>.<pending>[.<token>]
+        let (domain, rest) = match tokens.as_slice() {
+            ["$JS", "ACK", rest @ ..] if rest.len() == 7 => (None, rest),
+            ["$JS", "ACK", domain, _account_hash, rest @ ..] if rest.len() >= 7 => {
+                (Some(*domain).filter(|&domain| domain != "_"), &rest[..7])
+            }
+            _ => return None,
+        };
+        let [stream, consumer, delivered, stream_sequence, consumer_sequence, timestamp, pending] =
+            rest
+        else {
+            return None;
+        };
+
+        Some(Self {
+            domain: domain.map(ToOwned::to_owned),
+            stream: (*stream).to_owned(),
+            consumer: (*consumer).to_owned(),
+            delivered: delivered.parse().ok()?,
+            stream_sequence: stream_sequence.parse().ok()?,
+            consumer_sequence: consumer_sequence.parse().ok()?,
+            timestamp: DateTime::from_timestamp_nanos(timestamp.parse().ok()?),
+            pending: pending.parse().ok()?,
+        })
+    }
+}
+
+impl TryFrom<RawStoredMessage> for StoredMessage {
+    type Error = StoredMessageDecodeError;
+
+    fn try_from(value: RawStoredMessage) -> Result<Self, Self::Error> {
+        let headers = match value.headers {
+            Some(headers) if !headers.is_empty() => {
+                let headers = BASE64
+                    .decode(headers.as_bytes())
+                    .map_err(StoredMessageDecodeError::Base64)?;
+                let (_status_code, headers) = decode_headers(Bytes::from(headers))
+                    .map_err(StoredMessageDecodeError::Headers)?;
+                headers
+            }
+            Some(_) | None => HeaderMap::new(),
+        };
+        let payload = match value.payload {
+            Some(payload) => BASE64
+                .decode(payload.as_bytes())
+                .map_err(StoredMessageDecodeError::Base64)?
+                .into(),
+            None => Bytes::new(),
+        };
+
+        Ok(Self {
+            subject: value.subject,
+            sequence: value.sequence,
+            timestamp: value.timestamp,
+            headers,
+            payload,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use chrono::DateTime;
+    use watermelon_proto::{
+        headers::{HeaderName, HeaderValue},
+        Subject,
+    };
+
+    use super::{GetMessageResponse, JetstreamMessageMetadata};
+
+    #[test]
+    fn deserialize_stored_message() {
+        let json = r#"{"message":{"subject":"orders.eu.created","seq":42,"hdrs":"TkFUUy8xLjANCk5hdHMtTXNnLUlkOiBhYmNkDQoNCg==","data":"aGVsbG8=","time":"2024-01-01T12:00:00.5Z"}}"#;
+        let message = serde_json::from_str::<GetMessageResponse>(json)
+            .unwrap()
+            .message;
+
+        assert_eq!(Subject::from_static("orders.eu.created"), message.subject);
+        assert_eq!(42, message.sequence);
+        assert_eq!(
+            Some(&HeaderValue::from_static("abcd")),
+            message.headers.get(&HeaderName::from_static("Nats-Msg-Id"))
+        );
+        assert_eq!(Bytes::from_static(b"hello"), message.payload);
+    }
+
+    #[test]
+    fn deserialize_stored_message_without_headers() {
+        let json =
+            r#"{"message":{"subject":"orders.eu.created","seq":1,"time":"2024-01-01T12:00:00Z"}}"#;
+        let message = serde_json::from_str::<GetMessageResponse>(json)
+            .unwrap()
+            .message;
+
+        assert!(message.headers.is_empty());
+        assert!(message.payload.is_empty());
+    }
+
+    #[test]
+    fn metadata_from_reply_subject() {
+        let v1 = Subject::from_static("$JS.ACK.orders.worker.3.42.7.1700000000123456789.10");
+        assert_eq!(
+            Some(JetstreamMessageMetadata {
+                domain: None,
+                stream: "orders".to_owned(),
+                consumer: "worker".to_owned(),
+                delivered: 3,
+                stream_sequence: 42,
+                consumer_sequence: 7,
+                timestamp: DateTime::from_timestamp_nanos(1_700_000_000_123_456_789),
+                pending: 10,
+            }),
+            JetstreamMessageMetadata::from_reply_subject(&v1)
+        );
+
+        let v2 = Subject::from_static(
+            "$JS.ACK.hub.ACCHASH.orders.worker.3.42.7.1700000000123456789.10.xyz",
+        );
+        let metadata = JetstreamMessageMetadata::from_reply_subject(&v2).unwrap();
+        assert_eq!(Some("hub"), metadata.domain.as_deref());
+        assert_eq!("orders", metadata.stream);
+        assert_eq!(42, metadata.stream_sequence);
+
+        let v2_no_domain =
+            Subject::from_static("$JS.ACK._.ACCHASH.orders.worker.3.42.7.1700000000123456789.10");
+        let metadata = JetstreamMessageMetadata::from_reply_subject(&v2_no_domain).unwrap();
+        assert_eq!(None, metadata.domain);
+
+        for invalid in [
+            "_INBOX.abcd",
+            "$JS.ACK.orders.worker.3.42",
+            "$JS.ACK.orders.worker.three.42.7.1700000000123456789.10",
+        ] {
+            assert_eq!(
+                None,
+                JetstreamMessageMetadata::from_reply_subject(&Subject::from_static(invalid))
+            );
+        }
+    }
+}
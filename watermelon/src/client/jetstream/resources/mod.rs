@@ -1,16 +1,27 @@
 use serde::Deserialize;
 
 pub use self::consumer::{
-    AckPolicy, Consumer, ConsumerConfig, ConsumerDurability, ConsumerSpecificConfig,
-    ConsumerStorage, DeliverPolicy, ReplayPolicy,
+    AckPolicy, Consumer, ConsumerConfig, ConsumerConfigBuilder, ConsumerConfigError,
+    ConsumerDurability, ConsumerPause, ConsumerSpecificConfig, ConsumerStorage, DeliverPolicy,
+    ReplayPolicy,
 };
+pub(crate) use self::consumer::{
+    CreateConsumerRequest, DeleteConsumerResponse, PauseConsumerRequest,
+};
+pub(crate) use self::message::{GetMessageRequest, GetMessageResponse};
+pub use self::message::{JetstreamMessageMetadata, StoredMessage};
+pub use self::publish::PubAck;
 pub use self::stream::{
-    Compression, DiscardPolicy, RetentionPolicy, Storage, Stream, StreamConfig, StreamState,
+    Compression, DiscardPolicy, PurgeRequest, RetentionPolicy, Storage, Stream, StreamConfig,
+    StreamState,
 };
+pub(crate) use self::stream::{DeleteStreamResponse, PurgeStreamResponse};
 
 use super::JetstreamError;
 
 mod consumer;
+mod message;
+mod publish;
 mod stream;
 
 #[derive(Debug, Deserialize)]
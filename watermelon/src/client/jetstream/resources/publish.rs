@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+/// The acknowledgement of a message published to a Jetstream stream
+///
+/// Obtained from [`JetstreamClient::publish`].
+///
+/// [`JetstreamClient::publish`]: crate::jetstream::JetstreamClient::publish
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PubAck {
+    /// The name of the stream the message was stored in
+    pub stream: String,
+    /// The sequence the message was stored at
+    #[serde(rename = "seq")]
+    pub sequence: u64,
+    /// Whether the message was a duplicate of one published within the duplicate window
+    #[serde(default)]
+    pub duplicate: bool,
+    /// The Jetstream domain of the stream
+    #[serde(default)]
+    pub domain: Option<String>,
+}
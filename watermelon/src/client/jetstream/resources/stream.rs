@@ -2,6 +2,7 @@ use std::{num::NonZeroU32, time::Duration};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use watermelon_proto::Subject;
 
 use super::{compression, duration, nullable_datetime, nullable_number, opposite_bool};
 
@@ -11,21 +12,34 @@ pub struct Stream {
     pub config: StreamConfig,
     #[serde(rename = "created")]
     pub created_at: DateTime<Utc>,
+    pub state: StreamState,
     // TODO: `cluster`
 }
 
 /// The state of the stream
 #[derive(Debug, Deserialize)]
 pub struct StreamState {
+    /// The number of messages stored in the stream
     pub messages: u64,
+    /// The number of bytes stored in the stream
     pub bytes: u64,
+    /// The sequence of the first message stored in the stream
+    #[serde(rename = "first_seq")]
     pub first_sequence: u64,
+    /// The timestamp of the first message stored in the stream, if any
     #[serde(with = "nullable_datetime", rename = "first_ts")]
     pub first_sequence_timestamp: Option<DateTime<Utc>>,
+    /// The sequence of the last message stored in the stream
+    #[serde(rename = "last_seq")]
     pub last_sequence: u64,
+    /// The timestamp of the last message stored in the stream, if any
     #[serde(with = "nullable_datetime", rename = "last_ts")]
     pub last_sequence_timestamp: Option<DateTime<Utc>>,
+    /// The number of consumers of the stream
     pub consumer_count: u32,
+    /// The number of distinct subjects messages are stored under
+    #[serde(default)]
+    pub num_subjects: u64,
 }
 
 /// A Jetstream stream configuration
@@ -69,6 +83,32 @@ pub struct StreamConfig {
     // TODO: `consumer_limits` https://github.com/nats-io/nats-server/blob/e25d973a8f389ce3aa415e4bcdfba1f7d0834f7f/server/stream.go#L99
 }
 
+/// A request to purge messages from a stream
+///
+/// The default value purges every message in the stream.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PurgeRequest {
+    /// Only purge messages matching this subject
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Subject>,
+    /// Purge all messages up to, but not including, this sequence
+    #[serde(skip_serializing_if = "Option::is_none", rename = "seq")]
+    pub sequence: Option<u64>,
+    /// Keep this many of the most recent messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct DeleteStreamResponse {
+    pub(crate) success: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PurgeStreamResponse {
+    pub(crate) purged: u64,
+}
+
 /// A streams retention policy
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -99,3 +139,94 @@ pub enum Storage {
 pub enum Compression {
     S2,
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone as _, Utc};
+
+    use super::{Stream, StreamState};
+
+    #[test]
+    fn deserialize_stream_info() {
+        let payload = br#"{
+            "type": "io.nats.jetstream.api.v1.stream_info_response",
+            "total": 0,
+            "offset": 0,
+            "limit": 0,
+            "config": {
+                "name": "orders",
+                "subjects": ["orders.>"],
+                "retention": "limits",
+                "max_consumers": -1,
+                "max_msgs": -1,
+                "max_bytes": -1,
+                "max_age": 0,
+                "max_msgs_per_subject": -1,
+                "max_msg_size": -1,
+                "discard": "old",
+                "storage": "file",
+                "num_replicas": 1,
+                "duplicate_window": 120000000000,
+                "compression": "none",
+                "allow_direct": false,
+                "mirror_direct": false,
+                "sealed": false,
+                "deny_delete": false,
+                "deny_purge": false,
+                "allow_rollup_hdrs": false,
+                "consumer_limits": {}
+            },
+            "created": "2024-01-01T00:00:00Z",
+            "state": {
+                "messages": 3,
+                "bytes": 189,
+                "first_seq": 4,
+                "first_ts": "2024-01-01T10:00:00Z",
+                "last_seq": 6,
+                "last_ts": "2024-01-01T10:00:02Z",
+                "num_subjects": 2,
+                "num_deleted": 0,
+                "consumer_count": 1
+            },
+            "cluster": {
+                "leader": "nats-0"
+            },
+            "ts": "2024-01-01T10:00:03Z"
+        }"#;
+        let stream = serde_json::from_slice::<Stream>(payload).unwrap();
+        assert_eq!("orders", stream.config.name);
+
+        let state = stream.state;
+        assert_eq!(3, state.messages);
+        assert_eq!(189, state.bytes);
+        assert_eq!(4, state.first_sequence);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap()),
+            state.first_sequence_timestamp
+        );
+        assert_eq!(6, state.last_sequence);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 2).unwrap()),
+            state.last_sequence_timestamp
+        );
+        assert_eq!(1, state.consumer_count);
+        assert_eq!(2, state.num_subjects);
+    }
+
+    #[test]
+    fn deserialize_empty_stream_state() {
+        let payload = br#"{
+            "messages": 0,
+            "bytes": 0,
+            "first_seq": 0,
+            "first_ts": "0001-01-01T00:00:00Z",
+            "last_seq": 0,
+            "last_ts": "0001-01-01T00:00:00Z",
+            "consumer_count": 0
+        }"#;
+        let state = serde_json::from_slice::<StreamState>(payload).unwrap();
+        assert_eq!(None, state.first_sequence_timestamp);
+        assert_eq!(None, state.last_sequence_timestamp);
+        assert_eq!(0, state.num_subjects);
+    }
+}
@@ -0,0 +1,239 @@
+use std::mem;
+
+use bytes::Bytes;
+use tokio::sync::oneshot;
+use watermelon_proto::{headers::HeaderName, ServerMessage, StatusCode, Subject};
+
+use crate::client::{
+    nuid, Consumer, ConsumerConfig, ConsumerDurability, DeliverPolicy, JetstreamError2,
+    JetstreamMessageMetadata, ResponseError,
+};
+
+use super::{
+    consumer_create_response,
+    resources::{CreateConsumerRequest, Response},
+};
+
+/// The ephemeral push consumer feeding a subscription, recreated by the handler after every reconnect
+///
+/// The handler tracks the highest stream sequence delivered to the subscription.
+/// After reconnecting it deletes the previous consumer and creates a new one
+/// starting from the following sequence, so that the messages stored
+/// while the client was disconnected aren't lost.
+#[derive(Debug)]
+pub(crate) struct ConsumerResume {
+    api_prefix: Subject,
+    stream_name: String,
+    config: ConsumerConfig,
+    last_sequence: Option<u64>,
+}
+
+impl ConsumerResume {
+    /// Track the consumer configured by `config`, giving it a new name
+    pub(crate) fn new(
+        api_prefix: Subject,
+        stream_name: String,
+        mut config: ConsumerConfig,
+    ) -> Self {
+        config.durability = ConsumerDurability::Ephemeral;
+        config.name = consumer_name();
+        Self {
+            api_prefix,
+            stream_name,
+            config,
+            last_sequence: None,
+        }
+    }
+
+    /// Record the stream sequence of `message`
+    ///
+    /// Returns `false` if `message` had already been delivered by a previous consumer.
+    pub(crate) fn record(&mut self, message: &ServerMessage) -> bool {
+        let Some(sequence) = stream_sequence(message) else {
+            // Heartbeats and flow control messages
+            return true;
+        };
+        if self
+            .last_sequence
+            .is_some_and(|last_sequence| sequence <= last_sequence)
+        {
+            return false;
+        }
+
+        self.last_sequence = Some(sequence);
+        true
+    }
+
+    /// Replace the consumer with a new one starting after the last recorded message
+    ///
+    /// Returns the requests deleting the previous consumer and creating the new one.
+    /// If the requests can't be built the previous consumer is kept.
+    pub(crate) fn resume(&mut self) -> Result<[(Subject, Bytes); 2], JetstreamError2> {
+        let delete = self.delete_request()?;
+
+        let name = mem::replace(&mut self.config.name, consumer_name());
+        let deliver_policy = self.config.deliver_policy;
+        if let Some(last_sequence) = self.last_sequence {
+            self.config.deliver_policy = DeliverPolicy::StartSequence {
+                sequence: last_sequence + 1,
+            };
+        }
+        match self.create_request() {
+            Ok(create) => Ok([delete, create]),
+            Err(err) => {
+                self.config.name = name;
+                self.config.deliver_policy = deliver_policy;
+                Err(err)
+            }
+        }
+    }
+
+    /// Build the request creating the consumer
+    pub(crate) fn create_request(&self) -> Result<(Subject, Bytes), JetstreamError2> {
+        let subject = self
+            .api_prefix
+            .join_tokens(&["CONSUMER", "CREATE", &self.stream_name, &self.config.name])
+            .map_err(JetstreamError2::Subject)?;
+        let payload = serde_json::to_vec(&CreateConsumerRequest {
+            stream_name: self.stream_name.clone(),
+            config: &self.config,
+        })
+        .map_err(JetstreamError2::Json)?;
+        Ok((subject, payload.into()))
+    }
+
+    /// Build the request deleting the consumer
+    pub(crate) fn delete_request(&self) -> Result<(Subject, Bytes), JetstreamError2> {
+        let subject = self
+            .api_prefix
+            .join_tokens(&["CONSUMER", "DELETE", &self.stream_name, &self.config.name])
+            .map_err(JetstreamError2::Subject)?;
+        Ok((subject, Bytes::new()))
+    }
+}
+
+/// Wait for the response to the request made by [`ConsumerResume::resume`] creating the new consumer
+///
+/// Returns `None` if the connection was lost before the response arrived,
+/// in which case the consumer is resumed again after reconnecting.
+pub(crate) async fn resume_response(
+    response: oneshot::Receiver<ServerMessage>,
+) -> Option<Result<Consumer, JetstreamError2>> {
+    let response = response.await.ok()?;
+    if response.status_code == Some(StatusCode::NO_RESPONDERS) {
+        return Some(Err(JetstreamError2::ResponseError(
+            ResponseError::NoResponders,
+        )));
+    }
+
+    Some(
+        serde_json::from_slice::<Response<Consumer>>(&response.base.payload)
+            .map_err(JetstreamError2::Json)
+            .and_then(consumer_create_response),
+    )
+}
+
+fn consumer_name() -> String {
+    nuid::as_str(&nuid::next()).to_owned()
+}
+
+/// The stream sequence of `message`, read from its ack subject or from its `Nats-Sequence` header
+fn stream_sequence(message: &ServerMessage) -> Option<u64> {
+    if let Some(metadata) = message
+        .base
+        .reply_subject
+        .as_ref()
+        .and_then(JetstreamMessageMetadata::from_reply_subject)
+    {
+        return Some(metadata.stream_sequence);
+    }
+
+    message
+        .base
+        .headers
+        .get(&HeaderName::SEQUENCE)
+        .and_then(|sequence| sequence.as_str().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use watermelon_proto::{
+        headers::{HeaderName, HeaderValue},
+        ServerMessage, Subject, SubscriptionId,
+    };
+
+    use super::ConsumerResume;
+    use crate::client::{
+        tests::{ack_subject, message},
+        ConsumerConfig, DeliverPolicy,
+    };
+
+    fn order(reply_subject: Option<Subject>) -> ServerMessage {
+        message(
+            SubscriptionId::MIN,
+            Subject::from_static("orders.new"),
+            reply_subject,
+            Bytes::new(),
+        )
+    }
+
+    fn resume() -> ConsumerResume {
+        let config = ConsumerConfig::builder("watcher")
+            .push(Subject::from_static("_INBOX.deliver"))
+            .unwrap();
+        ConsumerResume::new(Subject::from_static("$JS.API"), "orders".to_owned(), config)
+    }
+
+    #[test]
+    fn record() {
+        let mut resume = resume();
+
+        let ack = order(Some(ack_subject("orders", "abc", 42, 1)));
+        assert!(resume.record(&ack));
+        assert!(!resume.record(&ack));
+
+        let mut republished = order(None);
+        republished
+            .base
+            .headers
+            .insert(HeaderName::SEQUENCE, HeaderValue::from_static("43"));
+        assert!(resume.record(&republished));
+
+        let heartbeat = order(None);
+        assert!(resume.record(&heartbeat));
+        assert!(resume.record(&heartbeat));
+    }
+
+    #[test]
+    fn resume_requests() {
+        let mut resume = resume();
+        let old_name = resume.config.name.clone();
+        assert_ne!("watcher", old_name);
+
+        assert!(resume.record(&order(Some(ack_subject("orders", "abc", 42, 1)))));
+        let requests = resume.resume().unwrap();
+        let new_name = &resume.config.name;
+        assert_ne!(&old_name, new_name);
+        assert!(matches!(
+            resume.config.deliver_policy,
+            DeliverPolicy::StartSequence { sequence: 43 }
+        ));
+
+        let [(delete, delete_payload), (create, create_payload)] = requests.as_slice() else {
+            unreachable!()
+        };
+        assert_eq!(
+            format!("$JS.API.CONSUMER.DELETE.orders.{old_name}"),
+            delete.as_str()
+        );
+        assert!(delete_payload.is_empty());
+        assert_eq!(
+            format!("$JS.API.CONSUMER.CREATE.orders.{new_name}"),
+            create.as_str()
+        );
+        let request = serde_json::from_slice::<serde_json::Value>(create_payload).unwrap();
+        assert_eq!(new_name.as_str(), request["config"]["name"]);
+        assert_eq!(43, request["config"]["opt_start_seq"]);
+    }
+}
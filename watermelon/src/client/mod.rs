@@ -1,57 +1,95 @@
-use std::{fmt::Write, num::NonZeroU64, process::abort, sync::Arc, time::Duration};
+use std::{
+    io,
+    num::{NonZeroU32, NonZeroU64, NonZeroUsize},
+    pin::pin,
+    process::abort,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
 #[cfg(test)]
 use std::{
     net::{IpAddr, Ipv4Addr},
-    num::{NonZeroU16, NonZeroU32},
+    num::NonZeroU16,
 };
 
 use arc_swap::ArcSwap;
 use bytes::Bytes;
-use rand::RngCore;
+use futures_util::future::{select, Either};
 use tokio::{
+    runtime,
     sync::{
+        broadcast,
         mpsc::{self, error::TrySendError, Permit},
         oneshot,
     },
     task::JoinHandle,
-    time::{interval, MissedTickBehavior},
+    time::{sleep, timeout},
 };
 use watermelon_mini::ConnectError;
 #[cfg(test)]
 use watermelon_proto::NonStandardServerInfo;
 use watermelon_proto::{
-    headers::HeaderMap, QueueGroup, ServerAddr, ServerInfo, Subject, SubscriptionId,
+    headers::{HeaderMap, HeaderName, HeaderValue},
+    Message, MessageBase, QueueGroup, ServerAddr, ServerInfo, ServerMessage, Subject,
+    SubscriptionId,
 };
 
-pub use self::builder::{ClientBuilder, Echo};
+pub use self::builder::{ClientBuilder, Echo, OfflineBufferPolicy};
+#[cfg(feature = "opentelemetry")]
+use self::commands::insert_trace_context;
+use self::commands::publish_backpressured;
 pub use self::commands::{
-    ClientPublish, ClientRequest, DoClientPublish, DoClientRequest, DoOwnedClientPublish,
-    DoOwnedClientRequest, OwnedClientPublish, OwnedClientRequest, Publish, PublishBuilder, Request,
-    RequestBuilder, ResponseError, ResponseFut,
+    propagated_deadline, ClientPublish, ClientRequest, DoClientPublish, DoClientRequest,
+    DoOwnedClientPublish, DoOwnedClientRequest, JsonRequestError, OwnedClientPublish,
+    OwnedClientRequest, Publish, PublishBuilder, Request, RequestBuilder, RequestMany,
+    RequestManyConfig, ResponseError, ResponseFut, RetryRequestError,
 };
+pub use self::connect::{connect, ToServerAddrs};
+use self::events::events_stream;
+pub use self::events::ClientEvent;
+pub(crate) use self::jetstream::{resume_response, ConsumerResume};
 pub use self::jetstream::{
-    AckPolicy, Compression, Consumer, ConsumerBatch, ConsumerConfig, ConsumerDurability,
+    AckError, AckPolicy, AckTiming, AutoAckConsumerStream, Compression, Consumer, ConsumerBatch,
+    ConsumerConfig, ConsumerConfigBuilder, ConsumerConfigError, ConsumerDurability, ConsumerPause,
     ConsumerSpecificConfig, ConsumerStorage, ConsumerStream, ConsumerStreamError, Consumers,
-    DeliverPolicy, DiscardPolicy, JetstreamClient, JetstreamError, JetstreamError2,
-    JetstreamErrorCode, ReplayPolicy, RetentionPolicy, Storage, Stream, StreamConfig, StreamState,
-    Streams,
+    DeliverPolicy, DiscardPolicy, DoJetstreamPublish, JetstreamClient, JetstreamError,
+    JetstreamError2, JetstreamErrorCode, JetstreamMessage, JetstreamMessageMetadata,
+    JetstreamPublish, KeyValue, KvEntry, KvOperation, KvWatch, KvWatchError, OrderedConfig,
+    OrderedConsumer, OrderedConsumerError, PubAck, PurgeRequest, ReplayPolicy, RetentionPolicy,
+    Rollup, Storage, StoredMessage, Stream, StreamConfig, StreamNames, StreamState, Streams,
 };
+#[cfg(feature = "object-store")]
+pub use self::jetstream::{ObjectInfo, ObjectList, ObjectPutError, ObjectReader, ObjectStore};
+pub(crate) use self::offline_buffer::{is_publish, OfflineBuffer, OfflineBufferFull};
 pub use self::quick_info::QuickInfo;
 pub(crate) use self::quick_info::RawQuickInfo;
+use self::reply_subjects::ReplySubjects;
+pub(crate) use self::server_pool::ServerPool;
+pub use self::stats::ConnectionStats;
+pub use self::status::ServerStatus;
 #[cfg(test)]
 use self::tests::TestHandler;
 use crate::{
     atomic::{AtomicU64, Ordering},
-    core::{MultiplexedSubscription, Subscription},
+    core::{MergedSubscription, MultiplexedSubscription, Subscription},
     handler::{
         Handler, HandlerCommand, HandlerOutput, RecycledHandler, MULTIPLEXED_SUBSCRIPTION_ID,
     },
 };
 
+mod backoff;
 mod builder;
 mod commands;
+mod connect;
+mod events;
 mod jetstream;
+mod nuid;
+mod offline_buffer;
 mod quick_info;
+mod reply_subjects;
+mod server_pool;
+mod stats;
+mod status;
 #[cfg(test)]
 pub(crate) mod tests;
 
@@ -59,8 +97,8 @@ pub(crate) mod tests;
 pub(super) mod from_env;
 
 const CLIENT_OP_CHANNEL_SIZE: usize = 512;
-const SUBSCRIPTION_CHANNEL_SIZE: usize = 256;
-const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+const EVENTS_CHANNEL_SIZE: usize = 32;
+const FLUSH_ON_DROP_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// A NATS client
 ///
@@ -77,18 +115,97 @@ struct ClientInner {
     sender: mpsc::Sender<HandlerCommand>,
     info: Arc<ArcSwap<ServerInfo>>,
     quick_info: Arc<RawQuickInfo>,
-    multiplexed_subscription_prefix: Subject,
+    known_servers: Arc<ArcSwap<Vec<ServerAddr>>>,
+    events: broadcast::Sender<ClientEvent>,
+    reply_subjects: ReplySubjects,
     next_subscription_id: AtomicU64,
     inbox_prefix: Subject,
     default_response_timeout: Duration,
+    subscription_capacity: NonZeroUsize,
+    offline_buffer: Option<Arc<OfflineBuffer>>,
+    echo: Echo,
+    origin: HeaderValue,
+    own_message_filters: AtomicU64,
+    own_message_subjects: Mutex<Vec<Subject>>,
+    flush_on_drop: bool,
+    #[cfg(feature = "opentelemetry")]
+    propagate_trace_context: bool,
     handler: JoinHandle<()>,
 }
 
 /// An error encountered while trying to publish a command to a closed [`Client`]
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
-#[error("client closed")]
-pub struct ClientClosedError;
+#[error("{}", if self.reconnect_exhausted { "client closed after giving up reconnecting" } else { "client closed" })]
+pub struct ClientClosedError {
+    reconnect_exhausted: bool,
+}
+
+/// An error encountered while waiting for the [`Client`] to connect
+///
+/// Returned by [`Client::wait_connected_timeout`].
+#[derive(Debug, thiserror::Error)]
+pub enum WaitConnectedError {
+    /// The client has been closed or gave up reconnecting
+    #[error("client closed")]
+    Closed(#[source] ClientClosedError),
+    /// The client didn't connect in time
+    #[error("timed out")]
+    TimedOut,
+}
+
+/// An error encountered while flushing a [`Client`] or measuring its round-trip time
+#[derive(Debug, thiserror::Error)]
+pub enum FlushError {
+    /// The client has been closed via [`Client::close`]
+    #[error("client closed")]
+    Closed(#[source] ClientClosedError),
+    /// The connection was lost before the server acknowledged the flush
+    #[error("disconnected")]
+    Disconnected,
+    /// The server didn't acknowledge the flush in time
+    #[error("timed out")]
+    TimedOut,
+}
+
+/// An error encountered while publishing a message
+#[derive(Debug, thiserror::Error)]
+pub enum PublishError {
+    /// The encoded headers and payload are bigger than the server's `max_payload`
+    #[error("payload too large, max={max}")]
+    PayloadTooLarge { max: NonZeroU32 },
+    /// The inbox prefix of the request contains wildcards
+    #[error("invalid inbox prefix")]
+    InvalidInboxPrefix,
+    /// The client is disconnected and its offline buffer is full
+    ///
+    /// Returned when [`OfflineBufferPolicy::Reject`] is configured
+    /// via [`ClientBuilder::offline_buffer`], or by
+    /// [`Client::publish_backpressured`] when the message is dropped
+    /// from the offline buffer by one of the other policies.
+    #[error("offline buffer full")]
+    OfflineBufferFull,
+    /// The client has been closed via [`Client::close`]
+    #[error("client closed")]
+    Closed(#[source] ClientClosedError),
+}
+
+/// A received message that can be responded to via [`Client::respond`]
+pub trait Respondable {
+    /// The subject the response has to be published to
+    fn reply_subject(&self) -> Option<&Subject>;
+}
+
+/// An error encountered while responding to a message via [`Client::respond`]
+#[derive(Debug, thiserror::Error)]
+pub enum RespondError {
+    /// The message doesn't have a reply subject to send the response to
+    #[error("message has no reply subject")]
+    MissingReplySubject,
+    /// The response couldn't be published
+    #[error("publish error")]
+    Publish(#[source] PublishError),
+}
 
 #[derive(Debug, thiserror::Error)]
 #[error("try command error")]
@@ -96,11 +213,41 @@ pub enum TryCommandError {
     /// The client's internal buffer is currently full
     #[error("buffer full")]
     BufferFull,
+    /// The encoded headers and payload are bigger than the server's `max_payload`
+    #[error("payload too large, max={max}")]
+    PayloadTooLarge { max: NonZeroU32 },
+    /// The inbox prefix of the request contains wildcards
+    #[error("invalid inbox prefix")]
+    InvalidInboxPrefix,
+    /// The client is disconnected and its offline buffer is full
+    ///
+    /// Only returned when [`OfflineBufferPolicy::Reject`] is configured
+    /// via [`ClientBuilder::offline_buffer`].
+    #[error("offline buffer full")]
+    OfflineBufferFull,
     /// The client has been closed via [`Client::close`]
     #[error("client closed")]
     Closed(#[source] ClientClosedError),
 }
 
+impl Respondable for Message {
+    fn reply_subject(&self) -> Option<&Subject> {
+        self.reply.as_ref()
+    }
+}
+
+impl Respondable for MessageBase {
+    fn reply_subject(&self) -> Option<&Subject> {
+        self.reply_subject.as_ref()
+    }
+}
+
+impl Respondable for ServerMessage {
+    fn reply_subject(&self) -> Option<&Subject> {
+        self.base.reply_subject.as_ref()
+    }
+}
+
 impl Client {
     /// Construct a new client
     #[must_use]
@@ -108,49 +255,151 @@ impl Client {
         ClientBuilder::new()
     }
 
+    #[expect(clippy::too_many_lines)]
     pub(super) async fn connect(
-        addr: ServerAddr,
+        addrs: Vec<ServerAddr>,
         builder: ClientBuilder,
     ) -> Result<Self, ConnectError> {
+        let servers = ServerPool::new(addrs, builder.randomize_servers).ok_or_else(|| {
+            ConnectError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no server addresses",
+            ))
+        })?;
         let (sender, receiver) = mpsc::channel(CLIENT_OP_CHANNEL_SIZE);
 
         let quick_info = Arc::new(RawQuickInfo::new());
-        let handle = RecycledHandler::new(receiver, Arc::clone(&quick_info), &builder);
-        let handle = Handler::connect(&addr, &builder, handle)
-            .await
-            .map_err(|(err, _recycle)| err)?;
+        let (events, _) = broadcast::channel(EVENTS_CHANNEL_SIZE);
+        let offline_buffer = builder
+            .offline_buffer
+            .map(|(capacity, policy)| Arc::new(OfflineBuffer::new(capacity, policy)));
+        let mut recycle = RecycledHandler::new(
+            receiver,
+            Arc::clone(&quick_info),
+            events.clone(),
+            servers,
+            offline_buffer.clone(),
+            &builder,
+        );
+        let known_servers = Arc::clone(recycle.known_servers());
+
+        // Try every seed server once before giving up
+        let mut remaining_attempts = recycle.servers_len();
+        let handle = loop {
+            match Handler::connect(&builder, recycle).await {
+                Ok(handle) => break handle,
+                Err((err, prev_recycle)) => {
+                    remaining_attempts -= 1;
+                    if remaining_attempts == 0 {
+                        return Err(err);
+                    }
+
+                    recycle = prev_recycle;
+                }
+            }
+        };
         let info = handle.info().clone();
         let multiplexed_subscription_prefix = handle.multiplexed_subscription_prefix().clone();
         let inbox_prefix = builder.inbox_prefix.clone();
         let default_response_timeout = builder.default_response_timeout;
+        let request_inbox_pool_size = builder.request_inbox_pool_size;
+        let subscription_capacity = builder.subscription_capacity;
+        let echo = builder.echo;
+        let flush_on_drop = builder.flush_on_drop;
+        #[cfg(feature = "opentelemetry")]
+        let propagate_trace_context = builder.propagate_trace_context;
 
+        let handler_events = events.clone();
+        let handler_quick_info = Arc::clone(&quick_info);
         let handler = tokio::spawn(async move {
             let mut handle = handle;
+            let events = handler_events;
+            let quick_info = handler_quick_info;
 
             loop {
-                match (&mut handle).await {
-                    HandlerOutput::ServerError | HandlerOutput::Disconnected => {
+                let output = (&mut handle).await;
+                match output {
+                    HandlerOutput::ServerError(_)
+                    | HandlerOutput::Disconnected
+                    | HandlerOutput::LameDuck => {
+                        if matches!(output, HandlerOutput::LameDuck) {
+                            handle.write_pending().await;
+                        }
                         let mut recycle = handle.recycle().await;
 
-                        let mut interval = interval(RECONNECT_DELAY);
-                        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
-
-                        loop {
-                            interval.tick().await;
+                        let mut attempt = 0;
+                        let mut reconnects = 0_usize;
+                        let mut persistent_error = matches!(
+                            &output,
+                            HandlerOutput::ServerError(error) if error.is_persistent()
+                        );
+                        'reconnect: loop {
+                            // Go through every known server before backing off
+                            for _ in 0..recycle.servers_len() {
+                                if builder
+                                    .max_reconnects
+                                    .is_some_and(|max_reconnects| reconnects >= max_reconnects)
+                                {
+                                    trace_event!(
+                                        warn,
+                                        "giving up reconnecting, closing the client",
+                                        reconnects = %reconnects,
+                                    );
+                                    // Set before dropping the handler, so that failing
+                                    // commands observe the reason
+                                    quick_info.store_is_reconnect_exhausted(true);
+                                    let discarded_messages = recycle.discard_offline_buffer();
+                                    let _ = events.send(ClientEvent::ReconnectExhausted {
+                                        discarded_messages,
+                                    });
+                                    return;
+                                }
+                                reconnects += 1;
 
-                            match Handler::connect(&addr, &builder, recycle).await {
-                                Ok(new_handle) => {
-                                    handle = new_handle;
-                                    break;
+                                match Handler::connect(&builder, recycle).await {
+                                    Ok(new_handle) => {
+                                        handle = new_handle;
+                                        break 'reconnect;
+                                    }
+                                    Err((err, prev_recycle)) => {
+                                        trace_event!(
+                                            debug,
+                                            "reconnect attempt failed",
+                                            attempt = %attempt,
+                                        );
+                                        persistent_error |= is_persistent_error(&err);
+                                        let _ = events.send(ClientEvent::ReconnectFailed {
+                                            error: Arc::new(err),
+                                        });
+                                        recycle = prev_recycle;
+                                    }
                                 }
-                                Err((_err, prev_recycle)) => recycle = prev_recycle,
                             }
+
+                            // The server is going to reject the same configuration again,
+                            // so retrying quickly would only hammer it
+                            let delay = builder.reconnect_backoff.delay(if persistent_error {
+                                u32::MAX
+                            } else {
+                                attempt
+                            });
+                            trace_event!(
+                                info,
+                                "failed to reconnect to any server, backing off",
+                                delay = ?delay,
+                            );
+                            sleep(delay).await;
+                            attempt = attempt.saturating_add(1);
+                            persistent_error = false;
                         }
                     }
                     HandlerOutput::UnexpectedState => {
                         // Retry and hope for the best
                     }
-                    HandlerOutput::Closed => break,
+                    HandlerOutput::Closed => {
+                        trace_event!(debug, "client closed");
+                        break;
+                    }
                 }
             }
         });
@@ -160,10 +409,24 @@ impl Client {
                 info,
                 sender,
                 quick_info,
-                multiplexed_subscription_prefix,
+                known_servers,
+                events,
+                reply_subjects: ReplySubjects::new(
+                    multiplexed_subscription_prefix,
+                    request_inbox_pool_size,
+                ),
                 next_subscription_id: AtomicU64::new(u64::from(MULTIPLEXED_SUBSCRIPTION_ID) + 1),
                 inbox_prefix,
                 default_response_timeout,
+                subscription_capacity,
+                offline_buffer,
+                echo,
+                origin: create_origin(),
+                own_message_filters: AtomicU64::new(0),
+                own_message_subjects: Mutex::new(Vec::new()),
+                flush_on_drop,
+                #[cfg(feature = "opentelemetry")]
+                propagate_trace_context,
                 handler,
             }),
         })
@@ -171,7 +434,14 @@ impl Client {
 
     #[cfg(test)]
     pub(crate) fn test(client_to_handler_chan_size: usize) -> (Self, TestHandler) {
-        let builder = Self::builder();
+        Self::test_with_builder(Self::builder(), client_to_handler_chan_size)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn test_with_builder(
+        builder: ClientBuilder,
+        client_to_handler_chan_size: usize,
+    ) -> (Self, TestHandler) {
         let (sender, receiver) = mpsc::channel(client_to_handler_chan_size);
         let info = Arc::new(ArcSwap::new(Arc::from(ServerInfo {
             id: "1234".to_owned(),
@@ -202,6 +472,7 @@ impl Client {
             non_standard: NonStandardServerInfo::default(),
         })));
         let quick_info = Arc::new(RawQuickInfo::new());
+        let (events, _) = broadcast::channel(EVENTS_CHANNEL_SIZE);
         let multiplexed_subscription_prefix = create_inbox_subject(&builder.inbox_prefix);
 
         let this = Self {
@@ -209,10 +480,26 @@ impl Client {
                 sender,
                 info: Arc::clone(&info),
                 quick_info: Arc::clone(&quick_info),
-                multiplexed_subscription_prefix,
+                known_servers: Arc::new(ArcSwap::from_pointee(Vec::new())),
+                events,
+                reply_subjects: ReplySubjects::new(
+                    multiplexed_subscription_prefix,
+                    builder.request_inbox_pool_size,
+                ),
                 next_subscription_id: AtomicU64::new(1),
                 inbox_prefix: builder.inbox_prefix,
                 default_response_timeout: builder.default_response_timeout,
+                subscription_capacity: builder.subscription_capacity,
+                offline_buffer: builder
+                    .offline_buffer
+                    .map(|(capacity, policy)| Arc::new(OfflineBuffer::new(capacity, policy))),
+                echo: Echo::Allow,
+                origin: create_origin(),
+                own_message_filters: AtomicU64::new(0),
+                own_message_subjects: Mutex::new(Vec::new()),
+                flush_on_drop: builder.flush_on_drop,
+                #[cfg(feature = "opentelemetry")]
+                propagate_trace_context: builder.propagate_trace_context,
                 handler: tokio::spawn(async move {}),
             }),
         };
@@ -229,7 +516,7 @@ impl Client {
     /// Consider calling [`Publish::client`] instead if you already have
     /// a [`Publish`] instance.
     #[must_use]
-    pub fn publish(&self, subject: Subject) -> ClientPublish {
+    pub fn publish(&self, subject: Subject) -> ClientPublish<'_> {
         ClientPublish::build(self, subject)
     }
 
@@ -238,10 +525,30 @@ impl Client {
     /// Consider calling [`Request::client`] instead if you already have
     /// a [`Request`] instance.
     #[must_use]
-    pub fn request(&self, subject: Subject) -> ClientRequest {
+    pub fn request(&self, subject: Subject) -> ClientRequest<'_> {
         ClientRequest::build(self, subject)
     }
 
+    /// Publish `publish`, waiting for it to be enqueued into the connection
+    ///
+    /// Awaiting a [`DoClientPublish`] only waits for space in the channel
+    /// to the task driving the connection, while the connection's write buffer
+    /// may keep growing if the socket can't keep up. This instead returns once
+    /// the message has been moved into the write buffer, which only happens
+    /// while the buffer has room for more commands, keeping memory bounded for
+    /// large or bursty publishers.
+    ///
+    /// It doesn't wait for the message to be written to the socket,
+    /// nor for the server to receive it. See [`Client::flush`] for that.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the message is bigger than the server's `max_payload`
+    /// or if the client has been closed.
+    pub async fn publish_backpressured(&self, publish: Publish) -> Result<(), PublishError> {
+        publish_backpressured(self, publish).await
+    }
+
     /// Publish a new message to the NATS server, taking ownership of this client
     ///
     /// When possible consider using [`Client::publish`] instead.
@@ -264,6 +571,47 @@ impl Client {
         OwnedClientRequest::build(self, subject)
     }
 
+    /// Publish `payload` to the reply subject of the received message `to`
+    ///
+    /// This is the responding side of [`Client::request`].
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if `to` has no reply subject or if publishing the response fails.
+    pub async fn respond<M>(&self, to: &M, payload: Bytes) -> Result<(), RespondError>
+    where
+        M: Respondable + ?Sized,
+    {
+        let reply_subject = to
+            .reply_subject()
+            .cloned()
+            .ok_or(RespondError::MissingReplySubject)?;
+        self.publish(reply_subject)
+            .payload(payload)
+            .await
+            .map_err(RespondError::Publish)
+    }
+
+    /// Publish a new request and collect every response received on a dedicated inbox
+    ///
+    /// Useful for scatter-gather patterns, where multiple subscribers
+    /// respond to the same request. The returned [`RequestMany`] stream yields
+    /// responses until one of the limits defined by `config` is reached or
+    /// the server reports that there are no responders.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the message is bigger than the server's
+    /// `max_payload` or if the client has been closed.
+    pub async fn request_many(
+        &self,
+        subject: Subject,
+        payload: Bytes,
+        config: RequestManyConfig,
+    ) -> Result<RequestMany, PublishError> {
+        RequestMany::publish(self, subject, payload, config).await
+    }
+
     /// Subscribe to the given filter subject
     ///
     /// Create a new subscription with the NATS server and ask for all
@@ -276,6 +624,7 @@ impl Client {
     ///
     /// If the client was built with [`Echo::Allow`], then messages
     /// published by this same client may be received by this subscription.
+    /// See [`Subscription::ignore_own_messages`] for filtering them out.
     ///
     /// # Errors
     ///
@@ -284,15 +633,88 @@ impl Client {
         &self,
         filter_subject: Subject,
         queue_group: Option<QueueGroup>,
+    ) -> Result<Subscription, ClientClosedError> {
+        self.subscribe_with_capacity(
+            filter_subject,
+            queue_group,
+            self.inner.subscription_capacity,
+        )
+        .await
+    }
+
+    /// Subscribe to the given filter subject, buffering up to `capacity` messages
+    ///
+    /// Like [`Client::subscribe`], but overrides the capacity configured
+    /// via [`ClientBuilder::subscription_capacity`].
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the connection with the client is closed.
+    pub async fn subscribe_with_capacity(
+        &self,
+        filter_subject: Subject,
+        queue_group: Option<QueueGroup>,
+        capacity: NonZeroUsize,
     ) -> Result<Subscription, ClientClosedError> {
         let permit = self
             .inner
             .sender
             .reserve()
             .await
-            .map_err(|_| ClientClosedError)?;
+            .map_err(|_| self.closed_error())?;
 
-        Ok(self.do_subscribe(permit, filter_subject, queue_group))
+        Ok(self.do_subscribe(permit, filter_subject, queue_group, capacity, None))
+    }
+
+    /// Subscribe to the messages delivered by the consumer tracked by `resume`
+    ///
+    /// The handler recreates the consumer after every reconnect and deletes it
+    /// once the subscription goes away.
+    pub(crate) async fn subscribe_resumable(
+        &self,
+        filter_subject: Subject,
+        queue_group: Option<QueueGroup>,
+        resume: ConsumerResume,
+    ) -> Result<Subscription, ClientClosedError> {
+        let permit = self
+            .inner
+            .sender
+            .reserve()
+            .await
+            .map_err(|_| self.closed_error())?;
+
+        Ok(self.do_subscribe(
+            permit,
+            filter_subject,
+            queue_group,
+            self.inner.subscription_capacity,
+            Some(Box::new(resume)),
+        ))
+    }
+
+    /// Subscribe to every one of the given filter subjects, merging them into a single stream
+    ///
+    /// Every filter subject gets its own [`Subscription`], as if [`Client::subscribe`]
+    /// had been called for each one of them. The returned [`MergedSubscription`] polls
+    /// them in round-robin order.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the connection with the client is closed.
+    pub async fn subscribe_many(
+        &self,
+        filter_subjects: Vec<Subject>,
+        queue_group: Option<QueueGroup>,
+    ) -> Result<MergedSubscription, ClientClosedError> {
+        let mut subscriptions = Vec::with_capacity(filter_subjects.len());
+        for filter_subject in filter_subjects {
+            let subscription = self
+                .subscribe(filter_subject.clone(), queue_group.clone())
+                .await?;
+            subscriptions.push((filter_subject, subscription));
+        }
+
+        Ok(MergedSubscription::new(subscriptions))
     }
 
     pub(crate) fn try_subscribe(
@@ -306,7 +728,13 @@ impl Client {
             .try_reserve()
             .map_err(|_| TryCommandError::BufferFull)?;
 
-        Ok(self.do_subscribe(permit, filter_subject, queue_group))
+        Ok(self.do_subscribe(
+            permit,
+            filter_subject,
+            queue_group,
+            self.inner.subscription_capacity,
+            None,
+        ))
     }
 
     fn do_subscribe(
@@ -314,6 +742,8 @@ impl Client {
         permit: Permit<'_, HandlerCommand>,
         filter_subject: Subject,
         queue_group: Option<QueueGroup>,
+        capacity: NonZeroUsize,
+        resume: Option<Box<ConsumerResume>>,
     ) -> Subscription {
         let id = self
             .inner
@@ -323,15 +753,18 @@ impl Client {
         if id == SubscriptionId::MAX {
             abort();
         }
-        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_CHANNEL_SIZE);
+        let (sender, receiver) = mpsc::channel(capacity.get());
+        let dropped_messages = Arc::new(AtomicU64::new(0));
 
         permit.send(HandlerCommand::Subscribe {
             id,
-            subject: filter_subject,
+            subject: filter_subject.clone(),
             queue_group,
             messages: sender,
+            dropped_messages: Arc::clone(&dropped_messages),
+            resume,
         });
-        Subscription::new(id, self.clone(), receiver)
+        Subscription::new(id, filter_subject, self.clone(), receiver, dropped_messages)
     }
 
     pub(super) async fn multiplexed_request(
@@ -345,7 +778,7 @@ impl Client {
             .sender
             .reserve()
             .await
-            .map_err(|_| ClientClosedError)?;
+            .map_err(|_| self.closed_error())?;
 
         Ok(self.do_multiplexed_request(permit, subject, headers, payload))
     }
@@ -374,7 +807,9 @@ impl Client {
     ) -> MultiplexedSubscription {
         let (sender, receiver) = oneshot::channel();
 
-        let reply_subject = create_inbox_subject(&self.inner.multiplexed_subscription_prefix);
+        let reply_subject = self.inner.reply_subjects.next();
+        let mut headers = headers;
+        self.tag_outgoing(&subject, &mut headers);
 
         permit.send(HandlerCommand::RequestMultiplexed {
             subject,
@@ -401,14 +836,145 @@ impl Client {
         self.inner.quick_info.get()
     }
 
-    pub(crate) fn create_inbox_subject(&self) -> Subject {
+    /// Get a snapshot of the server the client is connected to and of the connection health
+    #[must_use]
+    pub fn status(&self) -> ServerStatus {
+        ServerStatus {
+            info: self.server_info(),
+            quick_info: self.quick_info(),
+        }
+    }
+
+    /// Wait until the client is connected to the NATS server
+    ///
+    /// Resolves immediately if the client is currently connected, otherwise
+    /// waits for the client to reconnect.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client has been closed or if it gave up
+    /// reconnecting, see [`ClientBuilder::max_reconnects`].
+    pub async fn wait_connected(&self) -> Result<(), ClientClosedError> {
+        if self.inner.sender.is_closed() {
+            return Err(self.closed_error());
+        }
+
+        let connected = pin!(self.inner.quick_info.wait_connected());
+        let closed = pin!(self.inner.sender.closed());
+        match select(connected, closed).await {
+            Either::Left((true, _)) => Ok(()),
+            Either::Left((false, _)) | Either::Right(((), _)) => Err(self.closed_error()),
+        }
+    }
+
+    /// Like [`Client::wait_connected`], but gives up after `duration`
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client has been closed, if it gave up
+    /// reconnecting or if it doesn't connect within `duration`.
+    pub async fn wait_connected_timeout(
+        &self,
+        duration: Duration,
+    ) -> Result<(), WaitConnectedError> {
+        match timeout(duration, self.wait_connected()).await {
+            Ok(result) => result.map_err(WaitConnectedError::Closed),
+            Err(_) => Err(WaitConnectedError::TimedOut),
+        }
+    }
+
+    /// Get the servers the client knows about
+    ///
+    /// Contains the seed servers the client was connected with, followed
+    /// by the servers advertised by the cluster via the `connect_urls`
+    /// of the [`ServerInfo`]. The list is updated as the server sends
+    /// new topology information and is used to pick the server to
+    /// reconnect to.
+    #[must_use]
+    pub fn known_servers(&self) -> Vec<ServerAddr> {
+        Vec::clone(&self.inner.known_servers.load())
+    }
+
+    /// Subscribe to connection lifecycle events
+    ///
+    /// The returned stream only yields events that happen after this method
+    /// is called. Events are dropped if the stream isn't polled often enough
+    /// to keep up with them.
+    pub fn events(&self) -> impl futures_core::Stream<Item = ClientEvent> {
+        events_stream(self.inner.events.subscribe())
+    }
+
+    /// Generate a new unique inbox subject
+    ///
+    /// The subject is made of the inbox prefix configured via
+    /// [`ClientBuilder::inbox_prefix`] followed by a random token.
+    /// It can be used to implement custom request/reply patterns, where the
+    /// caller manages the subscription receiving the replies, instead of
+    /// relying on [`Client::request`].
+    ///
+    /// ```no_run
+    /// # async fn example(client: watermelon::core::Client) {
+    /// use futures_util::StreamExt as _;
+    /// use watermelon::proto::Subject;
+    ///
+    /// let inbox = client.new_inbox();
+    /// let mut replies = client.subscribe(inbox.clone(), None).await.unwrap();
+    ///
+    /// client
+    ///     .publish(Subject::from_static("service.echo"))
+    ///     .reply_subject(Some(inbox))
+    ///     .payload_static(b"hello")
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let reply = replies.next().await.unwrap().unwrap();
+    /// println!("received {:?}", reply.base.payload);
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn new_inbox(&self) -> Subject {
         create_inbox_subject(&self.inner.inbox_prefix)
     }
 
+    /// Push the publish `command` into the offline buffer if the client is disconnected
+    ///
+    /// `command` is given back if it has to be sent to the handler.
+    pub(crate) fn buffer_offline(
+        &self,
+        command: HandlerCommand,
+    ) -> Result<Option<HandlerCommand>, OfflineBufferFull> {
+        match &self.inner.offline_buffer {
+            // Nothing would ever replay the message
+            Some(_) if self.inner.quick_info.get().is_reconnect_exhausted => Ok(Some(command)),
+            Some(offline_buffer) => offline_buffer.push(command),
+            None => Ok(Some(command)),
+        }
+    }
+
+    /// Whether a backpressured publish that was never enqueued has been dropped from the offline buffer
+    ///
+    /// Otherwise it's been dropped because the client was closed.
+    pub(crate) fn dropped_offline(&self) -> bool {
+        self.inner.offline_buffer.is_some()
+            && !self.inner.quick_info.get().is_reconnect_exhausted
+            && !self.inner.sender.is_closed()
+    }
+
+    /// The error returned by commands that can't be sent because the client is closed
+    pub(crate) fn closed_error(&self) -> ClientClosedError {
+        ClientClosedError {
+            reconnect_exhausted: self.inner.quick_info.get().is_reconnect_exhausted,
+        }
+    }
+
     pub(crate) fn default_response_timeout(&self) -> Duration {
         self.inner.default_response_timeout
     }
 
+    pub(crate) fn recycle_reply_subject(&self, reply_subject: Subject) {
+        self.inner.reply_subjects.recycle(reply_subject);
+    }
+
     pub(crate) fn lazy_unsubscribe_multiplexed(&self, reply_subject: Subject) {
         if self
             .try_enqueue_command(HandlerCommand::UnsubscribeMultiplexed { reply_subject })
@@ -440,6 +1006,102 @@ impl Client {
         self.inner.quick_info.store_is_failed_unsubscribe(true);
     }
 
+    /// Tag an outbound message to `subject` as originating from this client
+    ///
+    /// The tag is only added if a [`Subscription`] ignoring its own messages
+    /// matches `subject`, so that publishing stays free of overhead otherwise.
+    fn tag_origin(&self, subject: &Subject, headers: &mut HeaderMap) {
+        if matches!(self.inner.echo, Echo::Allow)
+            && self.inner.own_message_filters.load(Ordering::Acquire) > 0
+            && self.inner.info.load().supports_headers
+            && self
+                .inner
+                .own_message_subjects
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .any(|filter_subject| subject.matches(filter_subject))
+        {
+            headers.insert(origin_header_name(), self.inner.origin.clone());
+        }
+    }
+
+    /// Add the headers attached by the client to every outgoing message
+    ///
+    /// Besides the origin tag, the context of the current span is propagated if enabled
+    /// via `ClientBuilder::propagate_trace_context`, unless the message already
+    /// carries a trace context.
+    pub(crate) fn tag_outgoing(&self, subject: &Subject, headers: &mut HeaderMap) {
+        self.tag_origin(subject, headers);
+        #[cfg(feature = "opentelemetry")]
+        if self.inner.propagate_trace_context && self.inner.info.load().supports_headers {
+            inject_trace_context(headers);
+        }
+    }
+
+    /// Remove the tag added by [`Client::tag_origin`] from a received message
+    ///
+    /// Returns `true` if the message was tagged by this client.
+    pub(crate) fn strip_origin(&self, headers: &mut HeaderMap) -> bool {
+        if headers.is_empty() {
+            return false;
+        }
+
+        // Only touch the headers if the tag is there, since removing
+        // anything drops the raw header block the message was received with
+        let origin_header_name = origin_header_name();
+        let Some(origin) = headers.get(&origin_header_name) else {
+            return false;
+        };
+        let is_own_message = *origin == self.inner.origin;
+        headers.remove(&origin_header_name);
+        is_own_message
+    }
+
+    pub(crate) fn add_own_message_filter(&self, filter_subject: &Subject) {
+        self.inner
+            .own_message_subjects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(filter_subject.clone());
+        self.inner
+            .own_message_filters
+            .fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub(crate) fn remove_own_message_filter(&self, filter_subject: &Subject) {
+        let mut own_message_subjects = self
+            .inner
+            .own_message_subjects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(i) = own_message_subjects
+            .iter()
+            .position(|subject| subject == filter_subject)
+        {
+            own_message_subjects.swap_remove(i);
+        }
+        drop(own_message_subjects);
+        self.inner
+            .own_message_filters
+            .fetch_sub(1, Ordering::AcqRel);
+    }
+
+    /// Check that a message fits within the `max_payload` advertised by the server
+    pub(crate) fn check_payload_size(
+        &self,
+        headers: &HeaderMap,
+        payload: &Bytes,
+    ) -> Result<(), NonZeroU32> {
+        let max = self.inner.info.load().max_payload;
+        let len = headers.encoded_len().saturating_add(payload.len());
+        if usize::try_from(max.get()).is_ok_and(|max| len > max) {
+            Err(max)
+        } else {
+            Ok(())
+        }
+    }
+
     pub(super) async fn enqueue_command(
         &self,
         cmd: HandlerCommand,
@@ -448,14 +1110,159 @@ impl Client {
             .sender
             .send(cmd)
             .await
-            .map_err(|_| ClientClosedError)
+            .map_err(|_| self.closed_error())
     }
 
     pub(super) fn try_enqueue_command(&self, cmd: HandlerCommand) -> Result<(), TryCommandError> {
         self.inner
             .sender
             .try_send(cmd)
-            .map_err(TryCommandError::from_try_send_error)
+            .map_err(|err| TryCommandError::from_try_send_error(err, self))
+    }
+
+    /// Wait for the server to process every command sent so far
+    ///
+    /// Sends a `PING` after every command that was previously enqueued
+    /// and waits for the server to reply with the matching `PONG`.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client is closed or if the connection
+    /// is lost before the `PONG` is received.
+    pub async fn flush(&self) -> Result<(), FlushError> {
+        let (sender, receiver) = oneshot::channel();
+        self.enqueue_command(HandlerCommand::Flush(sender))
+            .await
+            .map_err(FlushError::Closed)?;
+
+        receiver.await.map_err(|_| FlushError::Disconnected)
+    }
+
+    /// Like [`Client::flush`], but gives up after `duration`
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client is closed, if the connection
+    /// is lost or if the `PONG` isn't received within `duration`.
+    pub async fn flush_timeout(&self, duration: Duration) -> Result<(), FlushError> {
+        timeout(duration, self.flush())
+            .await
+            .map_err(|_| FlushError::TimedOut)?
+    }
+
+    /// Measure the round-trip time to the NATS server
+    ///
+    /// Sends a `PING` and measures the time until the matching `PONG` is received.
+    /// `PONG`s are matched with `PING`s in FIFO order, so concurrent calls, and
+    /// calls to [`Client::flush`], are answered in the order they were made.
+    /// The measurement also includes the time spent writing any command
+    /// that was enqueued before the `PING`.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client is closed or if the connection
+    /// is lost before the `PONG` is received.
+    pub async fn rtt(&self) -> Result<Duration, FlushError> {
+        let (sender, receiver) = oneshot::channel();
+        self.enqueue_command(HandlerCommand::Rtt(sender))
+            .await
+            .map_err(FlushError::Closed)?;
+
+        receiver.await.map_err(|_| FlushError::Disconnected)
+    }
+
+    /// Get statistics about the current connection to the NATS server
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client is closed.
+    pub async fn connection_stats(&self) -> Result<ConnectionStats, ClientClosedError> {
+        let (sender, receiver) = oneshot::channel();
+        self.enqueue_command(HandlerCommand::Stats(sender)).await?;
+
+        receiver.await.map_err(|_| self.closed_error())
+    }
+
+    /// Get the number of requests made via [`Client::request`] still waiting for a response
+    ///
+    /// See [`ConnectionStats::in_flight_requests`].
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client is closed.
+    pub async fn in_flight_requests(&self) -> Result<usize, ClientClosedError> {
+        self.connection_stats()
+            .await
+            .map(|stats| stats.in_flight_requests())
+    }
+
+    /// Cancel every request made via [`Client::request`] still waiting for a response
+    ///
+    /// The pending [`ResponseFut`]s resolve with [`ResponseError::SubscriptionClosed`].
+    /// This is useful for promptly releasing the tasks awaiting responses
+    /// during a shutdown. Requests using a dedicated subscription, like those
+    /// with an inbox prefix override, aren't affected.
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client is closed.
+    pub async fn cancel_all_requests(&self) -> Result<(), ClientClosedError> {
+        self.enqueue_command(HandlerCommand::CancelMultiplexed)
+            .await
+    }
+
+    /// Send a raw protocol operation to the NATS server
+    ///
+    /// This is an escape hatch for operations that aren't modeled by the rest of the API.
+    /// The operation is written as-is, bypassing any bookkeeping done by the client, so:
+    ///
+    /// * subscribing via [`ClientOp::Subscribe`] doesn't register a [`Subscription`], causing
+    ///   the messages of the subscription to be discarded and the subscription not to be
+    ///   resumed after a reconnect;
+    /// * unsubscribing via [`ClientOp::Unsubscribe`] an `id` owned by a [`Subscription`]
+    ///   silently stops it from receiving messages;
+    /// * sending [`ClientOp::Connect`] may change the options the connection was negotiated
+    ///   with, in ways the client is unaware of;
+    /// * operations aren't replayed after a reconnect.
+    ///
+    /// [`ClientOp::Ping`] is the only operation whose reply is tracked, as the `PONG` sent back
+    /// by the server is discarded.
+    ///
+    /// [`ClientOp::Subscribe`]: watermelon_proto::proto::ClientOp::Subscribe
+    /// [`ClientOp::Unsubscribe`]: watermelon_proto::proto::ClientOp::Unsubscribe
+    /// [`ClientOp::Connect`]: watermelon_proto::proto::ClientOp::Connect
+    /// [`ClientOp::Ping`]: watermelon_proto::proto::ClientOp::Ping
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client is closed.
+    #[cfg(feature = "dangerous-raw-ops")]
+    pub async fn send_raw(
+        &self,
+        op: watermelon_proto::proto::ClientOp,
+    ) -> Result<(), ClientClosedError> {
+        self.enqueue_command(HandlerCommand::Raw { op }).await
+    }
+
+    /// Gracefully close this client, letting every [`Subscription`] receive its in-flight messages
+    ///
+    /// Unsubscribes every active [`Subscription`] and waits for the server to deliver
+    /// any message it had already routed to them. The `Stream` implementation of every
+    /// [`Subscription`] terminates after yielding the remaining messages.
+    /// The client is then closed via [`Client::close`].
+    pub async fn drain(&self) {
+        let (sender, receiver) = oneshot::channel();
+        if self
+            .enqueue_command(HandlerCommand::Drain(sender))
+            .await
+            .is_err()
+        {
+            return;
+        }
+
+        // The receiver fails if the connection is lost while draining
+        let _ = receiver.await;
+        self.close().await;
     }
 
     /// Close this client, waiting for any remaining buffered messages to be processed first
@@ -478,29 +1285,122 @@ impl Client {
 
 impl Drop for ClientInner {
     fn drop(&mut self) {
+        if self.flush_on_drop {
+            if let Ok(runtime) = runtime::Handle::try_current() {
+                let sender = self.sender.clone();
+                let handler = self.handler.abort_handle();
+                runtime.spawn(async move {
+                    let _ = timeout(FLUSH_ON_DROP_TIMEOUT, async move {
+                        let (flushed_sender, flushed) = oneshot::channel();
+                        if sender
+                            .send(HandlerCommand::Flush(flushed_sender))
+                            .await
+                            .is_ok()
+                        {
+                            let _ = flushed.await;
+                        }
+                    })
+                    .await;
+                    handler.abort();
+                });
+                return;
+            }
+        }
+
         self.handler.abort();
     }
 }
 
+impl ClientClosedError {
+    /// Returns `true` if the client was closed because it gave up reconnecting to the server
+    ///
+    /// See [`ClientBuilder::max_reconnects`].
+    #[must_use]
+    pub fn is_reconnect_exhausted(&self) -> bool {
+        self.reconnect_exhausted
+    }
+}
+
+impl From<ClientClosedError> for PublishError {
+    fn from(err: ClientClosedError) -> Self {
+        Self::Closed(err)
+    }
+}
+
 impl TryCommandError {
     #[expect(
         clippy::needless_pass_by_value,
         reason = "this is an auxiliary conversion function"
     )]
-    pub(crate) fn from_try_send_error<T>(err: TrySendError<T>) -> Self {
+    pub(crate) fn from_try_send_error<T>(err: TrySendError<T>, client: &Client) -> Self {
         match err {
             TrySendError::Full(_) => Self::BufferFull,
-            TrySendError::Closed(_) => Self::Closed(ClientClosedError),
+            TrySendError::Closed(_) => Self::Closed(client.closed_error()),
         }
     }
 }
 
-pub(crate) fn create_inbox_subject(prefix: &Subject) -> Subject {
-    let mut suffix = [0u8; 16];
-    rand::thread_rng().fill_bytes(&mut suffix);
+fn origin_header_name() -> HeaderName {
+    HeaderName::from_static("Watermelon-Origin")
+}
+
+fn create_origin() -> HeaderValue {
+    let id = nuid::next();
+    HeaderValue::from_dangerous_value(nuid::as_str(&id).into())
+}
+
+/// Propagate the W3C Trace Context of the current `tracing` span
+///
+/// The span context is only known when spans are recorded via
+/// `tracing-opentelemetry`, otherwise nothing is inserted.
+#[cfg(feature = "opentelemetry")]
+fn inject_trace_context(headers: &mut HeaderMap) {
+    use opentelemetry::trace::TraceContextExt as _;
+    use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+    if headers.get(&HeaderName::TRACEPARENT).is_some() {
+        return;
+    }
+
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return;
+    }
+
+    let traceparent = format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    );
+    let tracestate = HeaderValue::try_from(span_context.trace_state().header())
+        .ok()
+        .filter(|tracestate| !tracestate.is_empty());
+    insert_trace_context(
+        headers,
+        HeaderValue::from_dangerous_value(traceparent.into()),
+        tracestate,
+    );
+}
+
+/// Returns whether `err` was caused by a server error which is going to occur again
+fn is_persistent_error(err: &ConnectError) -> bool {
+    matches!(
+        err,
+        ConnectError::Connect(watermelon_net::error::ConnectError::ServerError(err))
+            if err.is_persistent()
+    )
+}
+
+pub fn create_inbox_subject(prefix: &Subject) -> Subject {
+    let suffix = nuid::next();
 
-    let mut subject = String::with_capacity(prefix.len() + ".".len() + (suffix.len() * 2));
-    write!(&mut subject, "{}.{:x}", prefix, u128::from_ne_bytes(suffix)).unwrap();
+    let mut subject = String::with_capacity(prefix.len() + ".".len() + suffix.len());
+    subject.push_str(prefix);
+    subject.push('.');
+    subject.push_str(nuid::as_str(&suffix));
 
     Subject::from_dangerous_value(subject.into())
 }
@@ -0,0 +1,113 @@
+use std::{cell::RefCell, str};
+
+use rand::{Rng as _, RngCore as _};
+
+const DIGITS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const BASE: u64 = DIGITS.len() as u64;
+const PREFIX_LEN: usize = 12;
+const SEQUENCE_LEN: usize = 10;
+/// `BASE.pow(SEQUENCE_LEN)`
+const MAX_SEQUENCE: u64 = 839_299_365_868_340_224;
+const MIN_INCREMENT: u64 = 33;
+const MAX_INCREMENT: u64 = 333;
+
+/// The length of a NUID
+pub(crate) const NUID_LEN: usize = PREFIX_LEN + SEQUENCE_LEN;
+
+thread_local! {
+    static NUID: RefCell<Nuid> = RefCell::new(Nuid::new());
+}
+
+/// Generate a new NUID
+///
+/// NUIDs are the 22 characters long base62 unique identifiers used across
+/// the NATS ecosystem, made of a random prefix followed by a sequence
+/// which is incremented by a random amount every time.
+pub(crate) fn next() -> [u8; NUID_LEN] {
+    NUID.with(|nuid| nuid.borrow_mut().next())
+}
+
+/// View a NUID obtained from [`next`] as a string
+pub(crate) fn as_str(nuid: &[u8; NUID_LEN]) -> &str {
+    str::from_utf8(nuid).expect("NUIDs are ASCII")
+}
+
+#[derive(Debug)]
+struct Nuid {
+    prefix: [u8; PREFIX_LEN],
+    sequence: u64,
+    increment: u64,
+}
+
+impl Nuid {
+    fn new() -> Self {
+        let mut this = Self {
+            prefix: [0; PREFIX_LEN],
+            sequence: 0,
+            increment: 0,
+        };
+        this.randomize();
+        this
+    }
+
+    fn next(&mut self) -> [u8; NUID_LEN] {
+        self.sequence += self.increment;
+        if self.sequence >= MAX_SEQUENCE {
+            self.randomize();
+        }
+
+        let mut nuid = [0; NUID_LEN];
+        nuid[..PREFIX_LEN].copy_from_slice(&self.prefix);
+
+        let mut sequence = self.sequence;
+        for digit in nuid[PREFIX_LEN..].iter_mut().rev() {
+            *digit = DIGITS[usize::try_from(sequence % BASE).unwrap()];
+            sequence /= BASE;
+        }
+        nuid
+    }
+
+    fn randomize(&mut self) {
+        let mut rng = rand::thread_rng();
+
+        let mut random = [0; PREFIX_LEN];
+        rng.fill_bytes(&mut random);
+        for (digit, random) in self.prefix.iter_mut().zip(random) {
+            *digit = DIGITS[usize::from(random) % DIGITS.len()];
+        }
+
+        self.sequence = rng.gen_range(0..MAX_SEQUENCE);
+        self.increment = rng.gen_range(MIN_INCREMENT..MAX_INCREMENT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{Nuid, BASE, DIGITS, MAX_SEQUENCE, NUID_LEN, SEQUENCE_LEN};
+
+    #[test]
+    fn max_sequence() {
+        assert_eq!(BASE.pow(u32::try_from(SEQUENCE_LEN).unwrap()), MAX_SEQUENCE);
+    }
+
+    #[test]
+    fn unique() {
+        let nuids = (0..10_000).map(|_| super::next()).collect::<BTreeSet<_>>();
+        assert_eq!(10_000, nuids.len());
+        assert!(nuids.iter().flatten().all(|digit| DIGITS.contains(digit)));
+    }
+
+    #[test]
+    fn rollover() {
+        let mut nuid = Nuid::new();
+        let prefix = nuid.prefix;
+        nuid.sequence = MAX_SEQUENCE - 1;
+
+        let next = nuid.next();
+        assert_eq!(NUID_LEN, next.len());
+        assert_ne!(prefix, nuid.prefix);
+        assert!(nuid.sequence < MAX_SEQUENCE);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::VecDeque,
+    mem,
+    num::NonZeroUsize,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{core::OfflineBufferPolicy, handler::HandlerCommand};
+
+/// The publishes made while the client is disconnected
+///
+/// Shared between the [`Client`], which pushes publishes into it while
+/// disconnected, and the handler, which moves the publishes it didn't get
+/// to write into it when disconnecting and replays them after reconnecting.
+/// The connection state is tracked under the same lock as the publishes,
+/// so that a publish can't be buffered after the handler has already
+/// taken the buffered publishes for replaying.
+///
+/// Only [`HandlerCommand::Publish`] and [`HandlerCommand::PublishBackpressured`]
+/// are ever buffered.
+///
+/// [`Client`]: crate::core::Client
+#[derive(Debug)]
+pub(crate) struct OfflineBuffer {
+    capacity: NonZeroUsize,
+    policy: OfflineBufferPolicy,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    is_connected: bool,
+    commands: VecDeque<HandlerCommand>,
+    /// How many of the `commands` at the front were moved in by the handler
+    handler_commands: usize,
+}
+
+/// The offline buffer is full and configured with [`OfflineBufferPolicy::Reject`]
+#[derive(Debug)]
+pub(crate) struct OfflineBufferFull;
+
+impl OfflineBuffer {
+    pub(crate) fn new(capacity: NonZeroUsize, policy: OfflineBufferPolicy) -> Self {
+        Self {
+            capacity,
+            policy,
+            state: Mutex::new(State {
+                is_connected: false,
+                commands: VecDeque::new(),
+                handler_commands: 0,
+            }),
+        }
+    }
+
+    /// Buffer the publish `command` if the client is disconnected
+    ///
+    /// `command` is given back if the client is connected,
+    /// in which case it has to be sent to the handler.
+    pub(crate) fn push(
+        &self,
+        command: HandlerCommand,
+    ) -> Result<Option<HandlerCommand>, OfflineBufferFull> {
+        debug_assert!(is_publish(&command));
+
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.is_connected {
+            return Ok(Some(command));
+        }
+
+        if state.commands.len() >= self.capacity.get() {
+            match self.policy {
+                OfflineBufferPolicy::DropOldest => {
+                    trace_event!(debug, "offline buffer full, dropping the oldest message");
+                    state.commands.pop_front();
+                    state.handler_commands = state.handler_commands.saturating_sub(1);
+                }
+                OfflineBufferPolicy::DropNewest => {
+                    trace_event!(debug, "offline buffer full, dropping the newest message");
+                    return Ok(None);
+                }
+                OfflineBufferPolicy::Reject => return Err(OfflineBufferFull),
+            }
+        }
+
+        state.commands.push_back(command);
+        Ok(None)
+    }
+
+    /// Mark the client as connected, taking the publishes to be replayed
+    ///
+    /// `pending` are the publishes that reached the handler after it disconnected.
+    /// They were made while the client was still connected, so they are replayed
+    /// after the ones the handler moved in when disconnecting but before the ones
+    /// the client buffered.
+    pub(crate) fn connected(
+        &self,
+        pending: impl IntoIterator<Item = HandlerCommand>,
+    ) -> VecDeque<HandlerCommand> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.is_connected = true;
+        let handler_commands = mem::take(&mut state.handler_commands);
+        let mut commands = mem::take(&mut state.commands);
+        let mut buffered = commands.split_off(handler_commands);
+        commands.extend(pending);
+        commands.append(&mut buffered);
+        commands
+    }
+
+    /// Mark the client as disconnected, buffering the publishes made from now on
+    ///
+    /// `pending` are the publishes the handler didn't get to write to the
+    /// connection. They are buffered ahead of the publishes made from now on,
+    /// and since they have already been accepted they are kept even past
+    /// the capacity of the buffer.
+    pub(crate) fn disconnected(&self, pending: impl IntoIterator<Item = HandlerCommand>) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        debug_assert!(state.commands.is_empty());
+        state.is_connected = false;
+        state.commands.extend(pending);
+        state.handler_commands = state.commands.len();
+    }
+
+    /// Drop every buffered publish, returning how many were dropped
+    pub(crate) fn clear(&self) -> usize {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.handler_commands = 0;
+        mem::take(&mut state.commands).len()
+    }
+}
+
+/// Whether `command` is a publish which can go into the [`OfflineBuffer`]
+pub(crate) fn is_publish(command: &HandlerCommand) -> bool {
+    matches!(
+        command,
+        HandlerCommand::Publish { .. } | HandlerCommand::PublishBackpressured { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use bytes::Bytes;
+    use claims::{assert_matches, assert_none};
+    use watermelon_proto::{headers::HeaderMap, MessageBase, Subject};
+
+    use super::{OfflineBuffer, OfflineBufferFull};
+    use crate::{core::OfflineBufferPolicy, handler::HandlerCommand};
+
+    fn message(payload: &'static str) -> HandlerCommand {
+        HandlerCommand::Publish {
+            message: MessageBase {
+                subject: Subject::from_static("foo"),
+                reply_subject: None,
+                headers: HeaderMap::new(),
+                payload: Bytes::from_static(payload.as_bytes()),
+            },
+        }
+    }
+
+    fn payload(command: HandlerCommand) -> Bytes {
+        let HandlerCommand::Publish { message } = command else {
+            unreachable!()
+        };
+        message.payload
+    }
+
+    fn payloads(buffer: &OfflineBuffer, pending: Vec<HandlerCommand>) -> Vec<Bytes> {
+        buffer.connected(pending).into_iter().map(payload).collect()
+    }
+
+    #[test]
+    fn policies() {
+        let capacity = NonZeroUsize::new(2).unwrap();
+
+        let buffer = OfflineBuffer::new(capacity, OfflineBufferPolicy::DropOldest);
+        for payload in ["1", "2", "3"] {
+            assert_none!(buffer.push(message(payload)).unwrap());
+        }
+        assert_eq!(payloads(&buffer, Vec::new()), ["2", "3"]);
+
+        let buffer = OfflineBuffer::new(capacity, OfflineBufferPolicy::DropNewest);
+        for payload in ["1", "2", "3"] {
+            assert_none!(buffer.push(message(payload)).unwrap());
+        }
+        assert_eq!(payloads(&buffer, Vec::new()), ["1", "2"]);
+
+        let buffer = OfflineBuffer::new(capacity, OfflineBufferPolicy::Reject);
+        assert_none!(buffer.push(message("1")).unwrap());
+        assert_none!(buffer.push(message("2")).unwrap());
+        assert_matches!(buffer.push(message("3")), Err(OfflineBufferFull));
+        assert_eq!(payloads(&buffer, Vec::new()), ["1", "2"]);
+    }
+
+    #[test]
+    fn connected() {
+        let buffer = OfflineBuffer::new(NonZeroUsize::new(2).unwrap(), OfflineBufferPolicy::Reject);
+        assert!(buffer.connected(Vec::new()).is_empty());
+
+        let passthrough = buffer.push(message("1")).unwrap();
+        assert_eq!(payload(passthrough.unwrap()), "1");
+
+        buffer.disconnected(Vec::new());
+        assert_none!(buffer.push(message("2")).unwrap());
+        assert_eq!(payloads(&buffer, Vec::new()), ["2"]);
+    }
+
+    #[test]
+    fn pending() {
+        let buffer = OfflineBuffer::new(NonZeroUsize::new(2).unwrap(), OfflineBufferPolicy::Reject);
+        assert!(buffer.connected(Vec::new()).is_empty());
+
+        // Publishes the handler didn't get to write are kept past the capacity
+        buffer.disconnected(vec![message("1"), message("2")]);
+        assert_matches!(buffer.push(message("4")), Err(OfflineBufferFull));
+        assert_eq!(payloads(&buffer, vec![message("3")]), ["1", "2", "3"]);
+
+        let buffer = OfflineBuffer::new(
+            NonZeroUsize::new(2).unwrap(),
+            OfflineBufferPolicy::DropOldest,
+        );
+        buffer.disconnected(vec![message("1"), message("2")]);
+        assert_none!(buffer.push(message("4")).unwrap());
+        assert_eq!(payloads(&buffer, vec![message("3")]), ["2", "3", "4"]);
+    }
+}
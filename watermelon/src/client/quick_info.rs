@@ -1,13 +1,22 @@
-use crate::atomic::{AtomicU32, Ordering};
+use tokio::sync::Notify;
+
+use crate::atomic::{AtomicU32, AtomicU64, Ordering};
 
 const IS_CONNECTED: u32 = 1 << 0;
 #[cfg(feature = "non-standard-zstd")]
 const IS_ZSTD_COMPRESSED: u32 = 1 << 1;
 const IS_LAMEDUCK: u32 = 1 << 2;
+const IS_TLS: u32 = 1 << 3;
+const IS_RECONNECT_EXHAUSTED: u32 = 1 << 4;
 const IS_FAILED_UNSUBSCRIBE: u32 = 1 << 31;
 
 #[derive(Debug)]
-pub(crate) struct RawQuickInfo(AtomicU32);
+pub(crate) struct RawQuickInfo {
+    flags: AtomicU32,
+    dropped_messages: AtomicU64,
+    connections: AtomicU64,
+    connected: Notify,
+}
 
 /// Client information
 ///
@@ -21,41 +30,132 @@ pub struct QuickInfo {
     #[cfg(feature = "non-standard-zstd")]
     pub(crate) is_zstd_compressed: bool,
     pub(crate) is_lameduck: bool,
+    pub(crate) is_tls: bool,
+    pub(crate) is_reconnect_exhausted: bool,
     pub(crate) is_failed_unsubscribe: bool,
+    pub(crate) dropped_messages: u64,
 }
 
 impl RawQuickInfo {
     pub(crate) fn new() -> Self {
-        Self(AtomicU32::new(
-            QuickInfo {
-                is_connected: false,
-                #[cfg(feature = "non-standard-zstd")]
-                is_zstd_compressed: false,
-                is_lameduck: false,
-                is_failed_unsubscribe: false,
-            }
-            .encode(),
-        ))
+        Self {
+            flags: AtomicU32::new(
+                QuickInfo {
+                    is_connected: false,
+                    #[cfg(feature = "non-standard-zstd")]
+                    is_zstd_compressed: false,
+                    is_lameduck: false,
+                    is_tls: false,
+                    is_reconnect_exhausted: false,
+                    is_failed_unsubscribe: false,
+                    dropped_messages: 0,
+                }
+                .encode(),
+            ),
+            dropped_messages: AtomicU64::new(0),
+            connections: AtomicU64::new(0),
+            connected: Notify::new(),
+        }
     }
 
     pub(crate) fn get(&self) -> QuickInfo {
-        QuickInfo::decode(self.0.load(Ordering::Acquire))
+        QuickInfo::decode(
+            self.flags.load(Ordering::Acquire),
+            self.dropped_messages.load(Ordering::Acquire),
+        )
     }
 
+    /// Store the flags returned by `f`
+    ///
+    /// [`QuickInfo::dropped_messages`] is a counter and isn't affected by this.
     pub(crate) fn store<F>(&self, mut f: F)
     where
         F: FnMut(QuickInfo) -> QuickInfo,
     {
         let prev_params = self.get();
-        self.0.store(f(prev_params).encode(), Ordering::Release);
+        let params = f(prev_params);
+        self.flags.store(params.encode(), Ordering::Release);
+        if params.is_connected {
+            if !prev_params.is_connected {
+                self.connections.fetch_add(1, Ordering::AcqRel);
+            }
+            self.connected.notify_waiters();
+        }
+    }
+
+    /// The number of times the connected flag went from unset to set
+    ///
+    /// Unlike [`ClientEvent::Connected`] this can't be missed by lagging
+    /// behind, so it's used to detect reconnects.
+    ///
+    /// [`ClientEvent::Connected`]: crate::core::ClientEvent::Connected
+    pub(crate) fn connections(&self) -> u64 {
+        self.connections.load(Ordering::Acquire)
+    }
+
+    /// Wait until the client has connected after the `connections`-th connection
+    ///
+    /// Returns the new value of [`RawQuickInfo::connections`].
+    pub(crate) async fn wait_reconnected(&self, connections: u64) -> u64 {
+        let notified = self.connected.notified();
+        tokio::pin!(notified);
+        loop {
+            notified.as_mut().enable();
+            let current = self.connections();
+            if current != connections {
+                return current;
+            }
+
+            notified.as_mut().await;
+            notified.set(self.connected.notified());
+        }
+    }
+
+    /// Wait until either the connected or the reconnect exhausted flag is set
+    ///
+    /// Returns `false` if the client gave up reconnecting.
+    pub(crate) async fn wait_connected(&self) -> bool {
+        let notified = self.connected.notified();
+        tokio::pin!(notified);
+        loop {
+            // Register the waiter before checking the flags, so that a
+            // concurrent connection isn't missed
+            notified.as_mut().enable();
+            let quick_info = self.get();
+            if quick_info.is_connected {
+                return true;
+            }
+            if quick_info.is_reconnect_exhausted {
+                return false;
+            }
+
+            notified.as_mut().await;
+            notified.set(self.connected.notified());
+        }
+    }
+
+    pub(crate) fn increment_dropped_messages(&self) {
+        self.dropped_messages.fetch_add(1, Ordering::AcqRel);
     }
 
     pub(crate) fn store_is_connected(&self, val: bool) {
-        self.store_bit(IS_CONNECTED, val);
+        let was_connected = self.store_bit(IS_CONNECTED, val);
+        if val {
+            if !was_connected {
+                self.connections.fetch_add(1, Ordering::AcqRel);
+            }
+            self.connected.notify_waiters();
+        }
     }
     pub(crate) fn store_is_lameduck(&self, val: bool) {
         self.store_bit(IS_LAMEDUCK, val);
     }
+    pub(crate) fn store_is_reconnect_exhausted(&self, val: bool) {
+        self.store_bit(IS_RECONNECT_EXHAUSTED, val);
+        if val {
+            self.connected.notify_waiters();
+        }
+    }
     pub(crate) fn store_is_failed_unsubscribe(&self, val: bool) {
         self.store_bit(IS_FAILED_UNSUBSCRIBE, val);
     }
@@ -65,14 +165,15 @@ impl RawQuickInfo {
         reason = "we want this to be inlined inside the store_* functions"
     )]
     #[inline(always)]
-    fn store_bit(&self, mask: u32, val: bool) {
+    fn store_bit(&self, mask: u32, val: bool) -> bool {
         debug_assert_eq!(mask.count_ones(), 1);
 
-        if val {
-            self.0.fetch_or(mask, Ordering::AcqRel);
+        let prev = if val {
+            self.flags.fetch_or(mask, Ordering::AcqRel)
         } else {
-            self.0.fetch_and(!mask, Ordering::AcqRel);
-        }
+            self.flags.fetch_and(!mask, Ordering::AcqRel)
+        };
+        (prev & mask) != 0
     }
 }
 
@@ -96,6 +197,40 @@ impl QuickInfo {
         self.is_lameduck
     }
 
+    /// Returns `true` if the client connection is TLS encrypted
+    ///
+    /// Useful when connecting to a [`Protocol::PossiblyPlain`] address,
+    /// where the connection is only upgraded to TLS if the server requires it.
+    ///
+    /// [`Protocol::PossiblyPlain`]: crate::proto::Protocol::PossiblyPlain
+    #[must_use]
+    pub fn is_tls(&self) -> bool {
+        self.is_tls
+    }
+
+    /// Returns `true` if the client gave up reconnecting to the server
+    ///
+    /// See [`ClientBuilder::max_reconnects`].
+    ///
+    /// [`ClientBuilder::max_reconnects`]: crate::core::ClientBuilder::max_reconnects
+    #[must_use]
+    pub fn is_reconnect_exhausted(&self) -> bool {
+        self.is_reconnect_exhausted
+    }
+
+    /// The number of messages that were dropped because a [`Subscription`] wasn't being polled fast enough
+    ///
+    /// This counts messages received from the NATS server that couldn't fit
+    /// in the buffer of the [`Subscription`] they were meant for.
+    /// See [`ClientBuilder::subscription_capacity`] for how to size the buffer.
+    ///
+    /// [`Subscription`]: crate::core::Subscription
+    /// [`ClientBuilder::subscription_capacity`]: crate::core::ClientBuilder::subscription_capacity
+    #[must_use]
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages
+    }
+
     fn encode(self) -> u32 {
         let mut val = 0;
 
@@ -112,6 +247,14 @@ impl QuickInfo {
             val |= IS_LAMEDUCK;
         }
 
+        if self.is_tls {
+            val |= IS_TLS;
+        }
+
+        if self.is_reconnect_exhausted {
+            val |= IS_RECONNECT_EXHAUSTED;
+        }
+
         if self.is_failed_unsubscribe {
             val |= IS_FAILED_UNSUBSCRIBE;
         }
@@ -119,13 +262,16 @@ impl QuickInfo {
         val
     }
 
-    fn decode(val: u32) -> Self {
+    fn decode(val: u32, dropped_messages: u64) -> Self {
         Self {
             is_connected: (val & IS_CONNECTED) != 0,
             #[cfg(feature = "non-standard-zstd")]
             is_zstd_compressed: (val & IS_ZSTD_COMPRESSED) != 0,
             is_lameduck: (val & IS_LAMEDUCK) != 0,
+            is_tls: (val & IS_TLS) != 0,
+            is_reconnect_exhausted: (val & IS_RECONNECT_EXHAUSTED) != 0,
             is_failed_unsubscribe: (val & IS_FAILED_UNSUBSCRIBE) != 0,
+            dropped_messages,
         }
     }
 }
@@ -142,7 +288,10 @@ mod tests {
             #[cfg(feature = "non-standard-zstd")]
             is_zstd_compressed: false,
             is_lameduck: false,
+            is_tls: false,
+            is_reconnect_exhausted: false,
             is_failed_unsubscribe: false,
+            dropped_messages: 0,
         };
 
         for is_connected in [false, true] {
@@ -153,13 +302,34 @@ mod tests {
                 quick_info.store_is_lameduck(is_lameduck);
                 expected.is_lameduck = is_lameduck;
 
-                for is_failed_unsubscribe in [false, true] {
-                    quick_info.store_is_failed_unsubscribe(is_failed_unsubscribe);
-                    expected.is_failed_unsubscribe = is_failed_unsubscribe;
+                for is_reconnect_exhausted in [false, true] {
+                    quick_info.store_is_reconnect_exhausted(is_reconnect_exhausted);
+                    expected.is_reconnect_exhausted = is_reconnect_exhausted;
+
+                    for is_failed_unsubscribe in [false, true] {
+                        quick_info.store_is_failed_unsubscribe(is_failed_unsubscribe);
+                        expected.is_failed_unsubscribe = is_failed_unsubscribe;
 
-                    assert_eq!(expected, quick_info.get());
+                        assert_eq!(expected, quick_info.get());
+                    }
                 }
             }
         }
+
+        quick_info.increment_dropped_messages();
+        quick_info.increment_dropped_messages();
+        expected.dropped_messages = 2;
+        assert_eq!(expected, quick_info.get());
+
+        quick_info.store_is_connected(false);
+        expected.is_connected = false;
+        assert_eq!(expected, quick_info.get());
+
+        quick_info.store(|quick_info| QuickInfo {
+            is_tls: true,
+            ..quick_info
+        });
+        expected.is_tls = true;
+        assert_eq!(expected, quick_info.get());
     }
 }
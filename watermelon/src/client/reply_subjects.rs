@@ -0,0 +1,104 @@
+use std::sync::{Mutex, PoisonError};
+
+use watermelon_proto::Subject;
+
+use super::nuid;
+
+/// Generator of the reply subjects used by multiplexed requests
+///
+/// Reply subjects are made of the random multiplexed subscription prefix
+/// followed by a NUID, so that they can't be guessed by other clients.
+/// Subjects of requests that received their response can optionally be
+/// kept in a pool and handed out again, avoiding an allocation per request.
+#[derive(Debug)]
+pub(crate) struct ReplySubjects {
+    prefix: Subject,
+    pool: Mutex<Vec<Subject>>,
+    pool_capacity: usize,
+}
+
+impl ReplySubjects {
+    pub(crate) fn new(prefix: Subject, pool_capacity: usize) -> Self {
+        Self {
+            prefix,
+            pool: Mutex::new(Vec::with_capacity(pool_capacity)),
+            pool_capacity,
+        }
+    }
+
+    pub(crate) fn next(&self) -> Subject {
+        if self.pool_capacity > 0 {
+            if let Some(reply_subject) = self
+                .pool
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .pop()
+            {
+                return reply_subject;
+            }
+        }
+
+        let suffix = nuid::next();
+        let mut subject = String::with_capacity(self.prefix.len() + ".".len() + suffix.len());
+        subject.push_str(&self.prefix);
+        subject.push('.');
+        subject.push_str(nuid::as_str(&suffix));
+        Subject::from_dangerous_value(subject.into())
+    }
+
+    /// Give back the reply subject of a request that received its response
+    ///
+    /// Subjects of requests that were cancelled must never be recycled,
+    /// since the response may still arrive after the subject is reused.
+    pub(crate) fn recycle(&self, reply_subject: Subject) {
+        if self.pool_capacity == 0 {
+            return;
+        }
+
+        let mut pool = self.pool.lock().unwrap_or_else(PoisonError::into_inner);
+        if pool.len() < self.pool_capacity {
+            pool.push(reply_subject);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use watermelon_proto::Subject;
+
+    use super::ReplySubjects;
+    use crate::client::nuid::NUID_LEN;
+
+    #[test]
+    fn unique() {
+        let reply_subjects = ReplySubjects::new(Subject::from_static("_INBOX.abcd"), 0);
+        let first = reply_subjects.next();
+        let second = reply_subjects.next();
+        for subject in [&first, &second] {
+            let suffix = subject.strip_prefix("_INBOX.abcd.").unwrap();
+            assert_eq!(NUID_LEN, suffix.len());
+        }
+        assert_ne!(first, second);
+
+        reply_subjects.recycle(first.clone());
+        let third = reply_subjects.next();
+        assert_ne!(first, third);
+        assert_ne!(second, third);
+    }
+
+    #[test]
+    fn pooled() {
+        let reply_subjects = ReplySubjects::new(Subject::from_static("_INBOX.abcd"), 1);
+        let first = reply_subjects.next();
+        let second = reply_subjects.next();
+
+        reply_subjects.recycle(first.clone());
+        // The pool is full
+        reply_subjects.recycle(second.clone());
+
+        assert_eq!(first, reply_subjects.next());
+        let third = reply_subjects.next();
+        assert_ne!(first, third);
+        assert_ne!(second, third);
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use rand::seq::SliceRandom;
+use watermelon_proto::{ServerAddr, ServerInfo};
+
+/// The set of servers the client can (re)connect to
+///
+/// Contains the seed servers configured by the user, followed by
+/// the servers discovered via the `connect_urls` advertised by the cluster.
+#[derive(Debug)]
+pub(crate) struct ServerPool {
+    // INVARIANT: `!servers.is_empty()`
+    servers: Vec<ServerAddr>,
+    next: usize,
+    randomize: bool,
+    known: Arc<ArcSwap<Vec<ServerAddr>>>,
+}
+
+impl ServerPool {
+    pub(crate) fn new(mut seeds: Vec<ServerAddr>, randomize: bool) -> Option<Self> {
+        if seeds.is_empty() {
+            return None;
+        }
+
+        if randomize {
+            seeds.shuffle(&mut rand::thread_rng());
+        }
+
+        Some(Self {
+            known: Arc::new(ArcSwap::from_pointee(seeds.clone())),
+            servers: seeds,
+            next: 0,
+            randomize,
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    /// A shared, always up to date, snapshot of the servers in the pool
+    pub(crate) fn known(&self) -> &Arc<ArcSwap<Vec<ServerAddr>>> {
+        &self.known
+    }
+
+    /// Get the next server to try to connect to, in round-robin order
+    pub(crate) fn next_server(&mut self) -> &ServerAddr {
+        let i = self.next % self.servers.len();
+        self.next = i + 1;
+        &self.servers[i]
+    }
+
+    /// Add the servers advertised by `info`, which was received by connecting to `addr`
+    pub(crate) fn add_discovered(&mut self, addr: &ServerAddr, info: &ServerInfo) {
+        let mut new_servers = info
+            .connect_addrs(addr)
+            .into_iter()
+            .filter(|discovered| {
+                !self.servers.iter().any(|server| {
+                    server.host() == discovered.host() && server.port() == discovered.port()
+                })
+            })
+            .collect::<Vec<_>>();
+        if new_servers.is_empty() {
+            return;
+        }
+
+        if self.randomize {
+            new_servers.shuffle(&mut rand::thread_rng());
+        }
+        self.servers.extend(new_servers);
+        self.known.store(Arc::new(self.servers.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use watermelon_proto::{ServerAddr, ServerInfo};
+
+    use super::ServerPool;
+
+    fn info(connect_urls: &[&str]) -> ServerInfo {
+        serde_json::from_value(serde_json::json!({
+            "server_id": "1234",
+            "server_name": "watermelon-test",
+            "version": "2.10.17",
+            "go": "go1.22.5",
+            "host": "127.0.0.1",
+            "port": 4222,
+            "headers": true,
+            "max_payload": 1_048_576,
+            "proto": 1,
+            "connect_urls": connect_urls,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn round_robin() {
+        let seeds = ["nats://127.0.0.1:4222", "nats://127.0.0.1:4223"]
+            .map(|addr| addr.parse::<ServerAddr>().unwrap())
+            .to_vec();
+        let mut pool = ServerPool::new(seeds.clone(), false).unwrap();
+        let known = Arc::clone(pool.known());
+        assert_eq!(seeds, **known.load());
+
+        assert_eq!(&seeds[0], pool.next_server());
+        assert_eq!(&seeds[1], pool.next_server());
+        assert_eq!(&seeds[0], pool.next_server());
+
+        pool.add_discovered(&seeds[0], &info(&["127.0.0.1:4223", "127.0.0.1:4224"]));
+        assert_eq!(3, pool.len());
+        assert_eq!(3, known.load().len());
+        assert_eq!(&seeds[1], pool.next_server());
+        assert_eq!(
+            &"nats://127.0.0.1:4224".parse::<ServerAddr>().unwrap(),
+            pool.next_server()
+        );
+        assert_eq!(&seeds[0], pool.next_server());
+    }
+
+    #[test]
+    fn empty() {
+        assert!(ServerPool::new(Vec::new(), true).is_none());
+    }
+}
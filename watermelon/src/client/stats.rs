@@ -0,0 +1,102 @@
+/// Statistics about the connection to the NATS server
+///
+/// Obtained from [`Client::connection_stats`]. A growing amount of buffered
+/// data is a sign that the client or the server aren't keeping up,
+/// which may eventually lead the server to close the connection
+/// with a slow consumer error.
+///
+/// The write counters start from zero every time the client (re)connects.
+/// They can be used to tune [`ClientBuilder::flush_interval`], by comparing
+/// the average amount of data written per flush across different intervals:
+///
+/// ```no_run
+/// # async fn example(client: watermelon::core::Client) {
+/// let stats = client.connection_stats().await.unwrap();
+/// println!(
+///     "written {} bytes in {} flushes, {:?} bytes per flush",
+///     stats.written_bytes(),
+///     stats.flushes(),
+///     stats.average_bytes_per_flush(),
+/// );
+/// # }
+/// ```
+///
+/// [`Client::connection_stats`]: crate::core::Client::connection_stats
+/// [`ClientBuilder::flush_interval`]: crate::core::ClientBuilder::flush_interval
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub(crate) buffered_write_bytes: usize,
+    pub(crate) buffered_read_bytes: usize,
+    pub(crate) pending_pings: u8,
+    pub(crate) in_flight_commands: usize,
+    pub(crate) in_flight_requests: usize,
+    pub(crate) written_bytes: u64,
+    pub(crate) flushes: u64,
+}
+
+impl ConnectionStats {
+    /// The number of bytes that have been enqueued but not written to the socket yet
+    ///
+    /// This is always `0` for websocket connections.
+    #[must_use]
+    pub fn buffered_write_bytes(&self) -> usize {
+        self.buffered_write_bytes
+    }
+
+    /// The number of bytes that have been read from the socket but not processed yet
+    #[must_use]
+    pub fn buffered_read_bytes(&self) -> usize {
+        self.buffered_read_bytes
+    }
+
+    /// The number of keep-alive `PING`s that haven't been answered by the server yet
+    #[must_use]
+    pub fn pending_pings(&self) -> u8 {
+        self.pending_pings
+    }
+
+    /// The number of commands awaiting an acknowledgement from the server
+    ///
+    /// This is always `0` if [`ClientBuilder::verbose`] is disabled.
+    ///
+    /// [`ClientBuilder::verbose`]: crate::core::ClientBuilder::verbose
+    #[must_use]
+    pub fn in_flight_commands(&self) -> usize {
+        self.in_flight_commands
+    }
+
+    /// The number of requests made via [`Client::request`] still waiting for a response
+    ///
+    /// Requests made with a custom reply subject, or an inbox prefix override,
+    /// use a dedicated subscription and aren't counted.
+    ///
+    /// [`Client::request`]: crate::core::Client::request
+    #[must_use]
+    pub fn in_flight_requests(&self) -> usize {
+        self.in_flight_requests
+    }
+
+    /// The number of bytes written to the socket since the connection was established
+    ///
+    /// This is always `0` for websocket connections.
+    #[must_use]
+    pub fn written_bytes(&self) -> u64 {
+        self.written_bytes
+    }
+
+    /// The number of times the socket has been flushed since the connection was established
+    ///
+    /// This is always `0` for websocket connections.
+    #[must_use]
+    pub fn flushes(&self) -> u64 {
+        self.flushes
+    }
+
+    /// The average number of bytes written to the socket between two flushes
+    ///
+    /// Returns `None` if the socket hasn't been flushed yet.
+    #[must_use]
+    pub fn average_bytes_per_flush(&self) -> Option<u64> {
+        self.written_bytes.checked_div(self.flushes)
+    }
+}
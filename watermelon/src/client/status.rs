@@ -0,0 +1,83 @@
+use std::{num::NonZeroU32, sync::Arc};
+
+use watermelon_proto::ServerInfo;
+
+use super::QuickInfo;
+
+/// A snapshot of the server the client is connected to and of the connection health
+///
+/// Obtained from [`Client::status`]. It combines the most commonly needed
+/// parts of [`Client::server_info`] and [`Client::quick_info`].
+/// The round-trip time to the server can be measured via [`Client::rtt`].
+///
+/// [`Client::status`]: crate::core::Client::status
+/// [`Client::server_info`]: crate::core::Client::server_info
+/// [`Client::quick_info`]: crate::core::Client::quick_info
+/// [`Client::rtt`]: crate::core::Client::rtt
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub(crate) info: Arc<ServerInfo>,
+    pub(crate) quick_info: QuickInfo,
+}
+
+impl ServerStatus {
+    /// The name of the server
+    #[must_use]
+    pub fn server_name(&self) -> &str {
+        &self.info.name
+    }
+
+    /// The version of the NATS server
+    #[must_use]
+    pub fn version(&self) -> &str {
+        &self.info.version
+    }
+
+    /// The version of Go the NATS server was built with
+    #[must_use]
+    pub fn go_version(&self) -> &str {
+        &self.info.go_version
+    }
+
+    /// The name of the cluster the server belongs to, if any
+    #[must_use]
+    pub fn cluster_name(&self) -> Option<&str> {
+        self.info.cluster_name.as_deref()
+    }
+
+    /// Returns `true` if the server has Jetstream enabled
+    #[must_use]
+    pub fn supports_jetstream(&self) -> bool {
+        self.info.supports_jetstream
+    }
+
+    /// The maximum size of the headers and payload of a message accepted by the server
+    #[must_use]
+    pub fn max_payload(&self) -> NonZeroU32 {
+        self.info.max_payload
+    }
+
+    /// The id assigned by the server to this client connection
+    #[must_use]
+    pub fn client_id(&self) -> Option<u64> {
+        self.info.client_id
+    }
+
+    /// Returns `true` if the client is currently connected to the NATS server
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.quick_info.is_connected()
+    }
+
+    /// Returns `true` if the server is in Lame Duck Mode
+    #[must_use]
+    pub fn is_lameduck(&self) -> bool {
+        self.quick_info.is_lameduck()
+    }
+
+    /// Returns `true` if the client connection is TLS encrypted
+    #[must_use]
+    pub fn is_tls(&self) -> bool {
+        self.quick_info.is_tls()
+    }
+}
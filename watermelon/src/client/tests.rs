@@ -1,10 +1,26 @@
-use std::sync::Arc;
+use std::{num::NonZeroUsize, pin::pin, sync::Arc, time::Duration};
 
 use arc_swap::ArcSwap;
-use tokio::sync::mpsc;
-use watermelon_proto::ServerInfo;
+use bytes::Bytes;
+use futures_util::{FutureExt as _, StreamExt as _};
+use tokio::{
+    io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, oneshot},
+};
+use watermelon_proto::{
+    error::ServerError, headers::HeaderMap, MessageBase, ServerAddr, ServerInfo, ServerMessage,
+    StatusCode, Subject, SubscriptionId,
+};
 
-use crate::{client::RawQuickInfo, handler::HandlerCommand};
+use crate::{
+    client::{
+        ConsumerConfig, JetstreamClient, JetstreamError2, PublishError, RawQuickInfo,
+        ResponseError, WaitConnectedError,
+    },
+    core::{publish::Publish, Client, ClientEvent, OfflineBufferPolicy},
+    handler::HandlerCommand,
+};
 
 #[derive(Debug)]
 pub(crate) struct TestHandler {
@@ -12,3 +28,923 @@ pub(crate) struct TestHandler {
     pub(crate) _info: Arc<ArcSwap<ServerInfo>>,
     pub(crate) quick_info: Arc<RawQuickInfo>,
 }
+
+/// Build the response to a request made to `subject`, carrying `payload`
+pub(crate) fn response(subject: Subject, payload: impl Into<Bytes>) -> ServerMessage {
+    ServerMessage {
+        status_code: Some(StatusCode::OK),
+        subscription_id: SubscriptionId::MIN,
+        base: MessageBase {
+            subject,
+            reply_subject: None,
+            headers: HeaderMap::new(),
+            payload: payload.into(),
+        },
+    }
+}
+
+/// Build a response carrying `status_code` and `payload`
+pub(crate) fn status_response(status_code: StatusCode, payload: &'static [u8]) -> ServerMessage {
+    let mut response = response(
+        Subject::from_static("_INBOX.abcd"),
+        Bytes::from_static(payload),
+    );
+    response.status_code = Some(status_code);
+    response
+}
+
+/// Build a message delivered to the subscription `subscription_id`
+pub(crate) fn message(
+    subscription_id: SubscriptionId,
+    subject: Subject,
+    reply_subject: Option<Subject>,
+    payload: impl Into<Bytes>,
+) -> ServerMessage {
+    ServerMessage {
+        status_code: None,
+        subscription_id,
+        base: MessageBase {
+            subject,
+            reply_subject,
+            headers: HeaderMap::new(),
+            payload: payload.into(),
+        },
+    }
+}
+
+/// Build the ack subject of the message `stream_sequence` delivered by `consumer` of `stream`
+pub(crate) fn ack_subject(
+    stream: &str,
+    consumer: &str,
+    stream_sequence: u64,
+    consumer_sequence: u64,
+) -> Subject {
+    format!(
+        "$JS.ACK.{stream}.{consumer}.1.{stream_sequence}.{consumer_sequence}.1700000000123456789.0"
+    )
+    .try_into()
+    .unwrap()
+}
+
+/// Build the response to the creation of a consumer of `stream_name` configured by `config`
+///
+/// `config` is the configuration sent by the client, completed with the
+/// fields which are always returned by the server.
+pub(crate) fn consumer_created(stream_name: &str, mut config: serde_json::Value) -> Vec<u8> {
+    if config["name"].is_null() || config["name"] == "" {
+        config["name"] = "ephemeral".into();
+    }
+    for field in ["max_deliver", "max_ack_pending"] {
+        if config[field].is_null() {
+            config[field] = (-1).into();
+        }
+    }
+
+    serde_json::to_vec(&serde_json::json!({
+        "stream_name": stream_name,
+        "config": config,
+        "created": "2024-01-01T00:00:00Z",
+    }))
+    .unwrap()
+}
+
+/// Answer the next consumer creation request received by `handler`
+///
+/// Returns the sender of the messages delivered to the consumer,
+/// the subject of the request and the requested configuration.
+pub(crate) async fn accept_consumer(
+    handler: &mut TestHandler,
+    stream_name: &str,
+) -> (
+    mpsc::Sender<Result<ServerMessage, ServerError>>,
+    Subject,
+    serde_json::Value,
+) {
+    let Some(HandlerCommand::Subscribe { messages, .. }) = handler.receiver.recv().await else {
+        unreachable!()
+    };
+    let Some(HandlerCommand::RequestMultiplexed {
+        subject,
+        reply_subject,
+        payload,
+        reply,
+        ..
+    }) = handler.receiver.recv().await
+    else {
+        unreachable!()
+    };
+    let request = serde_json::from_slice::<serde_json::Value>(&payload).unwrap();
+    let config = request["config"].clone();
+    reply
+        .send(response(
+            reply_subject,
+            consumer_created(stream_name, config.clone()),
+        ))
+        .unwrap();
+    (messages, subject, config)
+}
+
+pub(crate) const INFO: &[u8] = b"INFO {\"server_id\":\"id\",\"server_name\":\"name\",\"version\":\"2.10.0\",\"go\":\"go1.22\",\"host\":\"127.0.0.1\",\"port\":4222,\"headers\":true,\"max_payload\":1048576,\"proto\":1}\r\n";
+
+/// Bind a loopback listener acting as a NATS server
+pub(crate) async fn listen() -> (TcpListener, ServerAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = format!("nats://{}", listener.local_addr().unwrap())
+        .parse()
+        .unwrap();
+    (listener, addr)
+}
+
+/// Accept a client on `listener` and go through the `CONNECT` handshake
+pub(crate) async fn handshake(listener: &TcpListener) -> BufReader<TcpStream> {
+    handshake_with_info(listener, INFO).await
+}
+
+/// Like [`handshake`], but sending `info` as the `INFO` line
+pub(crate) async fn handshake_with_info(
+    listener: &TcpListener,
+    info: &[u8],
+) -> BufReader<TcpStream> {
+    let (socket, _) = listener.accept().await.unwrap();
+    let mut socket = BufReader::new(socket);
+    socket.write_all(info).await.unwrap();
+
+    assert!(read_line(&mut socket).await.starts_with("CONNECT "));
+    assert_eq!("PING\r\n", read_line(&mut socket).await);
+    socket.write_all(b"PONG\r\n").await.unwrap();
+    socket
+}
+
+/// Read the next line written by the client, including the trailing `\r\n`
+pub(crate) async fn read_line(socket: &mut BufReader<TcpStream>) -> String {
+    let mut line = String::new();
+    socket.read_line(&mut line).await.unwrap();
+    line
+}
+
+#[tokio::test]
+async fn flush_on_drop() {
+    let (client, mut handler) = Client::test(8);
+    drop(client);
+    assert!(handler.receiver.recv().await.is_none());
+
+    let (client, mut handler) = Client::test_with_builder(Client::builder().flush_on_drop(true), 8);
+    drop(client);
+    let Some(HandlerCommand::Flush(flushed)) = handler.receiver.recv().await else {
+        unreachable!()
+    };
+    flushed.send(()).unwrap();
+    assert!(handler.receiver.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn publish_backpressured() {
+    let (client, mut handler) = Client::test(8);
+
+    let publish = Publish::builder(Subject::from_static("foo")).payload(Bytes::from_static(b"bar"));
+    let mut published = pin!(client.publish_backpressured(publish.clone()));
+    assert!(published.as_mut().now_or_never().is_none());
+
+    let Some(HandlerCommand::PublishBackpressured { message, enqueued }) =
+        handler.receiver.recv().await
+    else {
+        unreachable!()
+    };
+    assert_eq!("foo", message.subject.as_str());
+    // Still waiting for the handler to enqueue the message into the connection
+    assert!(published.as_mut().now_or_never().is_none());
+    enqueued.send(()).unwrap();
+    published.await.unwrap();
+
+    let (published, ()) = tokio::join!(client.publish_backpressured(publish), async {
+        let Some(HandlerCommand::PublishBackpressured { enqueued, .. }) =
+            handler.receiver.recv().await
+        else {
+            unreachable!()
+        };
+        // The connection was dropped before enqueueing the message
+        drop(enqueued);
+    });
+    assert!(matches!(published, Err(PublishError::Closed(_))));
+}
+
+#[tokio::test]
+async fn wait_connected() {
+    let (client, handler) = Client::test(8);
+    assert!(matches!(
+        client
+            .wait_connected_timeout(Duration::from_millis(10))
+            .await,
+        Err(WaitConnectedError::TimedOut)
+    ));
+
+    let mut connected = pin!(client.wait_connected());
+    assert!(connected.as_mut().now_or_never().is_none());
+    handler.quick_info.store_is_connected(true);
+    connected.await.unwrap();
+    client
+        .wait_connected_timeout(Duration::from_millis(10))
+        .await
+        .unwrap();
+
+    // Waiters are woken up when the client gives up reconnecting
+    handler.quick_info.store_is_connected(false);
+    let mut connected = pin!(client.wait_connected());
+    assert!(connected.as_mut().now_or_never().is_none());
+    handler.quick_info.store_is_reconnect_exhausted(true);
+    assert!(connected.await.unwrap_err().is_reconnect_exhausted());
+
+    // Waiters are woken up when the client is closed
+    let (client, handler) = Client::test(8);
+    let mut connected = pin!(client.wait_connected());
+    assert!(connected.as_mut().now_or_never().is_none());
+    drop(handler);
+    assert!(!connected.await.unwrap_err().is_reconnect_exhausted());
+    assert!(matches!(
+        client
+            .wait_connected_timeout(Duration::from_millis(10))
+            .await,
+        Err(WaitConnectedError::Closed(_))
+    ));
+}
+
+#[tokio::test]
+async fn new_inbox() {
+    let (client, _handler) = Client::test_with_builder(
+        Client::builder().inbox_prefix(Subject::from_static("_CUSTOM")),
+        8,
+    );
+
+    let inbox = client.new_inbox();
+    assert!(inbox.starts_with("_CUSTOM."));
+    assert_eq!(2, inbox.split('.').count());
+    assert_ne!(inbox, client.new_inbox());
+}
+
+#[tokio::test]
+async fn status() {
+    let (client, handler) = Client::test(8);
+
+    let status = client.status();
+    assert_eq!("watermelon-test", status.server_name());
+    assert_eq!("2.10.17", status.version());
+    assert_eq!(None, status.cluster_name());
+    assert!(status.supports_jetstream());
+    assert_eq!(Some(1), status.client_id());
+    assert!(!status.is_connected());
+
+    handler.quick_info.store_is_connected(true);
+    assert!(client.status().is_connected());
+}
+
+#[tokio::test]
+async fn reconnect_exhausted() {
+    let (client, handler) = Client::test_with_builder(
+        Client::builder()
+            .offline_buffer(NonZeroUsize::new(8).unwrap(), OfflineBufferPolicy::Reject),
+        8,
+    );
+    handler.quick_info.store_is_reconnect_exhausted(true);
+    drop(handler);
+
+    assert!(client.quick_info().is_reconnect_exhausted());
+    let Err(PublishError::Closed(err)) = client
+        .publish(Subject::from_static("foo"))
+        .payload(Bytes::from_static(b"bar"))
+        .await
+    else {
+        unreachable!()
+    };
+    assert!(err.is_reconnect_exhausted());
+    assert_eq!(
+        "client closed after giving up reconnecting",
+        err.to_string()
+    );
+
+    let err = client.connection_stats().await.unwrap_err();
+    assert!(err.is_reconnect_exhausted());
+}
+
+#[tokio::test]
+async fn max_reconnects() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let socket = handshake(&listener).await;
+        // Every reconnect attempt gets refused
+        drop(listener);
+        socket
+    });
+
+    let client = Client::builder()
+        .verbose(false)
+        .offline_buffer(NonZeroUsize::new(8).unwrap(), OfflineBufferPolicy::Reject)
+        .reconnect_backoff(Duration::from_millis(50), Duration::from_millis(50), 0.0)
+        .max_reconnects(Some(3))
+        .connect([addr])
+        .await
+        .unwrap();
+    let mut events = pin!(client.events());
+    drop(server.await.unwrap());
+    while !matches!(events.next().await, Some(ClientEvent::Disconnected)) {}
+
+    for payload in [b"1", b"2"] {
+        client
+            .publish(Subject::from_static("foo"))
+            .payload(Bytes::from_static(payload))
+            .await
+            .unwrap();
+    }
+
+    let mut failed_reconnects = 0;
+    loop {
+        match events.next().await.unwrap() {
+            ClientEvent::ReconnectFailed { .. } => failed_reconnects += 1,
+            ClientEvent::ReconnectExhausted { discarded_messages } => {
+                assert_eq!(2, discarded_messages);
+                break;
+            }
+            event => unreachable!("{event:?}"),
+        }
+    }
+    assert_eq!(3, failed_reconnects);
+    assert!(client.quick_info().is_reconnect_exhausted());
+}
+
+#[tokio::test]
+async fn persistent_server_error_backoff() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        socket
+            .write_all(b"-ERR 'Authorization Violation'\r\n")
+            .await
+            .unwrap();
+
+        // The reconnect attempt gets rejected the same way
+        let (reconnect, _) = listener.accept().await.unwrap();
+        let mut reconnect = BufReader::new(reconnect);
+        reconnect.write_all(INFO).await.unwrap();
+        assert!(read_line(&mut reconnect).await.starts_with("CONNECT "));
+        assert_eq!("PING\r\n", read_line(&mut reconnect).await);
+        reconnect
+            .write_all(b"-ERR 'Authorization Violation'\r\n")
+            .await
+            .unwrap();
+
+        // The client backs off for `max` instead of `min`
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), listener.accept())
+                .await
+                .is_err()
+        );
+    });
+
+    let _client = Client::builder()
+        .reconnect_backoff(Duration::from_millis(10), Duration::from_secs(60), 0.0)
+        .connect([addr])
+        .await
+        .unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn fatal_server_error_reconnects() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        socket
+            .write_all(b"-ERR 'Authorization Violation'\r\n")
+            .await
+            .unwrap();
+
+        handshake(&listener).await
+    });
+
+    let _client = Client::builder()
+        .verbose(false)
+        .reconnect_backoff(Duration::from_millis(10), Duration::from_millis(10), 0.0)
+        .connect([addr])
+        .await
+        .unwrap();
+    let _socket = tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn drain() {
+    let (listener, addr) = listen().await;
+    let (drain_started, drain_started_receiver) = oneshot::channel();
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        let sub = read_line(&mut socket).await;
+        assert!(sub.starts_with("SUB orders "));
+        let orders_id = sub.trim_end().rsplit(' ').next().unwrap().to_owned();
+
+        assert_eq!(
+            format!("UNSUB {orders_id}\r\n"),
+            read_line(&mut socket).await
+        );
+        assert_eq!("PING\r\n", read_line(&mut socket).await);
+        drain_started.send(()).unwrap();
+
+        // A subscription created while draining
+        let sub = read_line(&mut socket).await;
+        assert!(sub.starts_with("SUB payments "));
+        let payments_id = sub.trim_end().rsplit(' ').next().unwrap().to_owned();
+
+        socket
+            .write_all(
+                format!(
+                    "MSG orders {orders_id} 8\r\nin-fligh\r\nPONG\r\nMSG payments {payments_id} 7\r\npayment\r\n"
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        socket
+    });
+
+    let client = Client::builder().connect([addr]).await.unwrap();
+    let mut orders = client
+        .subscribe(Subject::from_static("orders"), None)
+        .await
+        .unwrap();
+    let (mut payments, ()) = tokio::join!(
+        async {
+            drain_started_receiver.await.unwrap();
+            client
+                .subscribe(Subject::from_static("payments"), None)
+                .await
+                .unwrap()
+        },
+        client.drain()
+    );
+
+    let message = orders.next().await.unwrap().unwrap();
+    assert_eq!(b"in-fligh".as_slice(), message.base.payload);
+    assert!(orders.next().await.is_none());
+
+    // Still delivered after the drain completed
+    let message = payments.next().await.unwrap().unwrap();
+    assert_eq!(b"payment".as_slice(), message.base.payload);
+    assert!(payments.next().await.is_none());
+
+    drop(server.await.unwrap());
+}
+
+#[tokio::test]
+async fn connection_stats() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        assert_eq!("PUB foo 3\r\n", read_line(&mut socket).await);
+        assert_eq!("bar\r\n", read_line(&mut socket).await);
+        assert_eq!("PING\r\n", read_line(&mut socket).await);
+        socket.write_all(b"PONG\r\n").await.unwrap();
+        socket
+    });
+
+    let client = Client::builder()
+        .verbose(false)
+        .connect([addr])
+        .await
+        .unwrap();
+    let stats = client.connection_stats().await.unwrap();
+    assert_eq!(0, stats.written_bytes());
+    assert_eq!(0, stats.flushes());
+    assert_eq!(None, stats.average_bytes_per_flush());
+
+    client
+        .publish(Subject::from_static("foo"))
+        .payload(Bytes::from_static(b"bar"))
+        .await
+        .unwrap();
+    client.flush().await.unwrap();
+
+    let stats = client.connection_stats().await.unwrap();
+    assert_eq!(
+        ("PUB foo 3\r\nbar\r\n".len() + "PING\r\n".len()) as u64,
+        stats.written_bytes()
+    );
+    assert_eq!(1, stats.flushes());
+    assert_eq!(Some(stats.written_bytes()), stats.average_bytes_per_flush());
+
+    let _socket = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn reset_decoder_once() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        socket.write_all(b"BOGUS\r\n").await.unwrap();
+        // The client resets the decoder and keeps the connection. Bytes which reached
+        // it together with the invalid command are discarded, so ping until it answers.
+        loop {
+            socket.write_all(b"PING\r\n").await.unwrap();
+            if let Ok(line) =
+                tokio::time::timeout(Duration::from_millis(100), read_line(&mut socket)).await
+            {
+                assert_eq!("PONG\r\n", line);
+                break;
+            }
+        }
+
+        // The second failure makes the client give up on the connection
+        socket.write_all(b"BOGUS\r\n").await.unwrap();
+        loop {
+            match read_line(&mut socket).await.as_str() {
+                "PONG\r\n" => {}
+                line => {
+                    assert_eq!("", line);
+                    break;
+                }
+            }
+        }
+
+        handshake(&listener).await
+    });
+
+    let client = Client::builder().connect([addr]).await.unwrap();
+    let _socket = server.await.unwrap();
+    client
+        .wait_connected_timeout(Duration::from_secs(5))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn offline_buffer_order() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        assert_eq!("PUB foo 1\r\n", read_line(&mut socket).await);
+        assert_eq!("1\r\n", read_line(&mut socket).await);
+        drop(socket);
+
+        let mut socket = handshake(&listener).await;
+        for payload in ["2", "3"] {
+            assert_eq!("PUB foo 1\r\n", read_line(&mut socket).await);
+            assert_eq!(format!("{payload}\r\n"), read_line(&mut socket).await);
+        }
+        socket
+    });
+
+    let client = Client::builder()
+        .verbose(false)
+        .offline_buffer(NonZeroUsize::new(8).unwrap(), OfflineBufferPolicy::Reject)
+        .connect([addr])
+        .await
+        .unwrap();
+    let mut events = pin!(client.events());
+    client
+        .publish(Subject::from_static("foo"))
+        .payload(Bytes::from_static(b"1"))
+        .await
+        .unwrap();
+    while !matches!(events.next().await, Some(ClientEvent::Disconnected)) {}
+
+    client
+        .publish(Subject::from_static("foo"))
+        .payload(Bytes::from_static(b"2"))
+        .await
+        .unwrap();
+    // Only resolves once replayed after reconnecting
+    client
+        .publish_backpressured(
+            Publish::builder(Subject::from_static("foo")).payload(Bytes::from_static(b"3")),
+        )
+        .await
+        .unwrap();
+
+    let _socket = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn offline_subscribe_before_publish() {
+    let (listener, addr) = listen().await;
+    let (reconnect_tx, reconnect_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        drop(handshake(&listener).await);
+
+        // Keep the client disconnected until it has subscribed and published
+        reconnect_rx.await.unwrap();
+        let mut socket = handshake(&listener).await;
+        assert!(read_line(&mut socket).await.starts_with("SUB foo "));
+        assert_eq!("PUB foo 1\r\n", read_line(&mut socket).await);
+        assert_eq!("1\r\n", read_line(&mut socket).await);
+        socket
+    });
+
+    let client = Client::builder()
+        .verbose(false)
+        .offline_buffer(NonZeroUsize::new(8).unwrap(), OfflineBufferPolicy::Reject)
+        .connect([addr])
+        .await
+        .unwrap();
+    let mut events = pin!(client.events());
+    while !matches!(events.next().await, Some(ClientEvent::Disconnected)) {}
+
+    let _subscription = client
+        .subscribe(Subject::from_static("foo"), None)
+        .await
+        .unwrap();
+    client
+        .publish(Subject::from_static("foo"))
+        .payload(Bytes::from_static(b"1"))
+        .await
+        .unwrap();
+    reconnect_tx.send(()).unwrap();
+
+    let _socket = tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn cancel_all_requests() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        assert!(read_line(&mut socket).await.starts_with("SUB _INBOX."));
+        assert!(read_line(&mut socket).await.starts_with("PUB ping _INBOX."));
+        assert_eq!("\r\n", read_line(&mut socket).await);
+        socket
+    });
+
+    let client = Client::builder()
+        .verbose(false)
+        .connect([addr])
+        .await
+        .unwrap();
+    let response_fut = client
+        .request(Subject::from_static("ping"))
+        .payload(Bytes::new())
+        .await
+        .unwrap();
+    assert_eq!(1, client.in_flight_requests().await.unwrap());
+
+    client.cancel_all_requests().await.unwrap();
+    assert_eq!(0, client.in_flight_requests().await.unwrap());
+    assert!(matches!(
+        response_fut.await,
+        Err(ResponseError::SubscriptionClosed)
+    ));
+
+    let _socket = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn retain_raw_headers() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        let sub = read_line(&mut socket).await;
+        assert!(sub.starts_with("SUB foo "));
+        let id = sub.trim_end().rsplit(' ').next().unwrap().to_owned();
+
+        socket
+            .write_all(format!("HMSG foo {id} 18 21\r\nNATS/1.0\r\nA: 1\r\n\r\nbar\r\n").as_bytes())
+            .await
+            .unwrap();
+        socket
+    });
+
+    let client = Client::builder()
+        .verbose(false)
+        .retain_raw_headers(true)
+        .connect([addr])
+        .await
+        .unwrap();
+    let mut subscription = client
+        .subscribe(Subject::from_static("foo"), None)
+        .await
+        .unwrap();
+
+    let message = subscription.next().await.unwrap().unwrap();
+    assert_eq!(b"bar".as_slice(), message.base.payload);
+    assert_eq!(
+        Some(b"NATS/1.0\r\nA: 1\r\n\r\n".as_slice()),
+        message.base.headers.raw().map(AsRef::as_ref)
+    );
+
+    let _socket = server.await.unwrap();
+}
+
+/// Read a `PUB` or `HPUB` written by the client, returning its subject, reply subject and payload
+///
+/// The reply subject is empty if the message doesn't have one.
+pub(crate) async fn read_publish(socket: &mut BufReader<TcpStream>) -> (String, String, Vec<u8>) {
+    let line = read_line(socket).await;
+    let (subject, reply_subject, headers_len, total_len) =
+        match line.trim_end().split(' ').collect::<Vec<_>>().as_slice() {
+            ["PUB", subject, total_len] => (*subject, "", 0, total_len.parse::<usize>().unwrap()),
+            ["PUB", subject, reply_subject, total_len] => (
+                *subject,
+                *reply_subject,
+                0,
+                total_len.parse::<usize>().unwrap(),
+            ),
+            ["HPUB", subject, headers_len, total_len] => (
+                *subject,
+                "",
+                headers_len.parse::<usize>().unwrap(),
+                total_len.parse::<usize>().unwrap(),
+            ),
+            ["HPUB", subject, reply_subject, headers_len, total_len] => (
+                *subject,
+                *reply_subject,
+                headers_len.parse::<usize>().unwrap(),
+                total_len.parse::<usize>().unwrap(),
+            ),
+            _ => unreachable!("{line:?}"),
+        };
+
+    let mut body = vec![0; total_len + "\r\n".len()];
+    socket.read_exact(&mut body).await.unwrap();
+    body.truncate(total_len);
+    body.drain(..headers_len);
+    (subject.to_owned(), reply_subject.to_owned(), body)
+}
+
+#[tokio::test]
+async fn resume_consumer_after_reconnect() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        let sub = read_line(&mut socket).await;
+        assert!(sub.starts_with("SUB _INBOX.deliver "));
+        let id = sub.trim_end().rsplit(' ').next().unwrap().to_owned();
+        let mux = read_line(&mut socket).await;
+        let mux_id = mux.trim_end().rsplit(' ').next().unwrap().to_owned();
+
+        let (subject, reply_subject, payload) = read_publish(&mut socket).await;
+        let old_name = subject
+            .strip_prefix("$JS.API.CONSUMER.CREATE.orders.")
+            .unwrap()
+            .to_owned();
+        let request = serde_json::from_slice::<serde_json::Value>(&payload).unwrap();
+        assert_eq!(old_name, request["config"]["name"]);
+        let consumer = consumer_created("orders", request["config"].clone());
+        socket
+            .write_all(format!("MSG {reply_subject} {mux_id} {}\r\n", consumer.len()).as_bytes())
+            .await
+            .unwrap();
+        socket.write_all(&consumer).await.unwrap();
+        socket
+            .write_all(
+                format!(
+                    "\r\nMSG _INBOX.deliver {id} $JS.ACK.orders.{old_name}.1.42.1.1700000000123456789.0 2\r\n42\r\n"
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        drop(socket);
+
+        // After reconnecting the consumer is replaced by one starting after the last message
+        let mut socket = handshake(&listener).await;
+        assert_eq!(
+            format!("SUB _INBOX.deliver {id}\r\n"),
+            read_line(&mut socket).await
+        );
+        assert_eq!(mux, read_line(&mut socket).await);
+        let (subject, _, payload) = read_publish(&mut socket).await;
+        assert_eq!(
+            format!("$JS.API.CONSUMER.DELETE.orders.{old_name}"),
+            subject
+        );
+        assert!(payload.is_empty());
+        let (subject, _, payload) = read_publish(&mut socket).await;
+        let new_name = subject
+            .strip_prefix("$JS.API.CONSUMER.CREATE.orders.")
+            .unwrap()
+            .to_owned();
+        assert_ne!(old_name, new_name);
+        let request = serde_json::from_slice::<serde_json::Value>(&payload).unwrap();
+        assert_eq!(43, request["config"]["opt_start_seq"]);
+
+        // Messages already delivered by the previous consumer are skipped
+        for sequence in [42, 43] {
+            socket
+                .write_all(
+                    format!(
+                        "MSG _INBOX.deliver {id} $JS.ACK.orders.{new_name}.1.{sequence}.1.1700000000123456789.0 2\r\n{sequence}\r\n"
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+        }
+
+        // Dropping the subscription deletes the consumer
+        let (subject, _, _) = read_publish(&mut socket).await;
+        assert_eq!(
+            format!("$JS.API.CONSUMER.DELETE.orders.{new_name}"),
+            subject
+        );
+        assert_eq!(format!("UNSUB {id}\r\n"), read_line(&mut socket).await);
+        socket
+    });
+
+    let client = Client::builder()
+        .reconnect_backoff(Duration::from_millis(10), Duration::from_millis(10), 0.0)
+        .connect([addr])
+        .await
+        .unwrap();
+    let config = ConsumerConfig::builder("ignored")
+        .push(Subject::from_static("_INBOX.deliver"))
+        .unwrap();
+    let mut subscription = JetstreamClient::new(client.clone())
+        .subscribe_push_resumable("orders", config)
+        .await
+        .unwrap();
+
+    let message = subscription.next().await.unwrap().unwrap();
+    assert_eq!(b"42".as_slice(), message.base.payload);
+    let message = subscription.next().await.unwrap().unwrap();
+    assert_eq!(b"43".as_slice(), message.base.payload);
+    drop(subscription);
+
+    let _socket = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn report_rejected_consumer_resume() {
+    let (listener, addr) = listen().await;
+    let server = tokio::spawn(async move {
+        let mut socket = handshake(&listener).await;
+        let sub = read_line(&mut socket).await;
+        let id = sub.trim_end().rsplit(' ').next().unwrap().to_owned();
+        let mux = read_line(&mut socket).await;
+        let mux_id = mux.trim_end().rsplit(' ').next().unwrap().to_owned();
+        let (_, reply_subject, payload) = read_publish(&mut socket).await;
+        let request = serde_json::from_slice::<serde_json::Value>(&payload).unwrap();
+        let consumer = consumer_created("orders", request["config"].clone());
+        socket
+            .write_all(format!("MSG {reply_subject} {mux_id} {}\r\n", consumer.len()).as_bytes())
+            .await
+            .unwrap();
+        socket.write_all(&consumer).await.unwrap();
+        socket.write_all(b"\r\n").await.unwrap();
+        drop(socket);
+
+        let mut socket = handshake(&listener).await;
+        assert_eq!(
+            format!("SUB _INBOX.deliver {id}\r\n"),
+            read_line(&mut socket).await
+        );
+        assert_eq!(mux, read_line(&mut socket).await);
+        let (subject, _, _) = read_publish(&mut socket).await;
+        assert!(subject.starts_with("$JS.API.CONSUMER.DELETE.orders."));
+        let (subject, reply_subject, _) = read_publish(&mut socket).await;
+        assert!(subject.starts_with("$JS.API.CONSUMER.CREATE.orders."));
+        let error =
+            br#"{"error":{"code":500,"err_code":10012,"description":"insufficient resources"}}"#;
+        socket
+            .write_all(format!("MSG {reply_subject} {mux_id} {}\r\n", error.len()).as_bytes())
+            .await
+            .unwrap();
+        socket.write_all(error).await.unwrap();
+        socket.write_all(b"\r\n").await.unwrap();
+        socket
+    });
+
+    let client = Client::builder()
+        .reconnect_backoff(Duration::from_millis(10), Duration::from_millis(10), 0.0)
+        .connect([addr])
+        .await
+        .unwrap();
+    let mut events = pin!(client.events());
+    let config = ConsumerConfig::builder("ignored")
+        .push(Subject::from_static("_INBOX.deliver"))
+        .unwrap();
+    let subscription = JetstreamClient::new(client.clone())
+        .subscribe_push_resumable("orders", config)
+        .await
+        .unwrap();
+
+    let error = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(ClientEvent::ConsumerResumeFailed {
+                subscription_id,
+                error,
+            }) = events.next().await
+            {
+                assert_eq!(subscription.id, subscription_id);
+                return error;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert!(matches!(
+        *error,
+        JetstreamError2::CouldNotCreateConsumer(ref error) if error.description() == "insufficient resources"
+    ));
+
+    let _socket = server.await.unwrap();
+}
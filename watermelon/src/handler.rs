@@ -1,6 +1,7 @@
 use std::{
     collections::{BTreeMap, VecDeque},
     future::Future,
+    iter, mem,
     num::NonZeroU64,
     ops::ControlFlow,
     pin::Pin,
@@ -14,15 +15,22 @@ use bytes::Bytes;
 use tokio::{
     net::TcpStream,
     sync::{
+        broadcast,
         mpsc::{self, error::TrySendError},
         oneshot,
     },
     time::{self, Instant, Sleep},
 };
 use watermelon_mini::{
-    easy_connect, ConnectError, ConnectFlags, ConnectionCompression, ConnectionSecurity,
+    connect_with_tls_config, easy_connect, ConnectError, ConnectFlags, ConnectionCompression,
+    ConnectionSecurity,
+};
+#[cfg(feature = "websocket")]
+use watermelon_net::error::WebsocketReadError;
+use watermelon_net::{
+    error::{ConnectionReadError, StreamingReadError},
+    Connection,
 };
-use watermelon_net::Connection;
 use watermelon_proto::{
     error::ServerError,
     headers::HeaderMap,
@@ -30,7 +38,11 @@ use watermelon_proto::{
     MessageBase, QueueGroup, ServerAddr, ServerInfo, ServerMessage, Subject, SubscriptionId,
 };
 
-use crate::client::{create_inbox_subject, QuickInfo, RawQuickInfo};
+use crate::atomic::{AtomicU64, Ordering};
+use crate::client::{
+    create_inbox_subject, is_publish, resume_response, ClientEvent, ConnectionStats,
+    ConsumerResume, JetstreamError2, OfflineBuffer, QuickInfo, RawQuickInfo, ServerPool,
+};
 use crate::core::{ClientBuilder, Echo};
 
 pub(crate) const MULTIPLEXED_SUBSCRIPTION_ID: SubscriptionId = SubscriptionId::MIN;
@@ -38,6 +50,7 @@ const PING_INTERVAL: Duration = Duration::from_secs(10);
 const RECV_BUF: usize = 16;
 
 #[derive(Debug)]
+#[expect(clippy::struct_excessive_bools)]
 pub(crate) struct Handler {
     conn: Connection<
         ConnectionCompression<ConnectionSecurity<TcpStream>>,
@@ -45,16 +58,25 @@ pub(crate) struct Handler {
     >,
     info: Arc<ArcSwap<ServerInfo>>,
     quick_info: Arc<RawQuickInfo>,
+    events: broadcast::Sender<ClientEvent>,
+    servers: ServerPool,
+    offline_buffer: Option<Arc<OfflineBuffer>>,
+    addr: ServerAddr,
     delayed_flusher: Option<DelayedFlusher>,
     flushing: bool,
+    written_bytes: u64,
+    flushes: u64,
     shutting_down: bool,
+    reconnect_on_lameduck: bool,
+    reset_decoder: bool,
 
     ping_interval: Pin<Box<Sleep>>,
     pending_pings: u8,
+    pong_waiters: VecDeque<PongWaiter>,
 
     commands: mpsc::Receiver<HandlerCommand>,
     recv_buf: Vec<HandlerCommand>,
-    in_flight_commands: VecDeque<InFlightCommand>,
+    in_flight_commands: InFlightCommands,
 
     multiplexed_subscription_prefix: Subject,
     multiplexed_subscriptions: Option<BTreeMap<Subject, oneshot::Sender<ServerMessage>>>,
@@ -70,10 +92,33 @@ struct DelayedFlusher {
     delay: Pin<Box<Option<Sleep>>>,
 }
 
+#[derive(Debug)]
+enum PongWaiter {
+    KeepAlive,
+    Flush(oneshot::Sender<()>),
+    Drain {
+        sender: oneshot::Sender<()>,
+        subscription_ids: Vec<SubscriptionId>,
+    },
+    Rtt {
+        sender: oneshot::Sender<Duration>,
+        sent_at: Instant,
+    },
+    #[cfg(feature = "dangerous-raw-ops")]
+    Raw,
+}
+
 #[derive(Debug)]
 pub(crate) struct RecycledHandler {
     commands: mpsc::Receiver<HandlerCommand>,
     quick_info: Arc<RawQuickInfo>,
+    events: broadcast::Sender<ClientEvent>,
+    servers: ServerPool,
+    offline_buffer: Option<Arc<OfflineBuffer>>,
+    /// The `INFO` shared with the client, `None` until the first connection is established
+    info: Option<Arc<ArcSwap<ServerInfo>>>,
+    /// Commands received while disconnecting, to be handled after reconnecting
+    pending_commands: Vec<HandlerCommand>,
 
     multiplexed_subscription_prefix: Subject,
     subscriptions: BTreeMap<SubscriptionId, Subscription>,
@@ -88,6 +133,19 @@ struct Subscription {
     messages: mpsc::Sender<Result<ServerMessage, ServerError>>,
     remaining: Option<NonZeroU64>,
     failed_subscribe: bool,
+    dropped_messages: Arc<AtomicU64>,
+    is_slow_consumer: bool,
+    resume: Option<Box<ConsumerResume>>,
+}
+
+impl Subscription {
+    /// Count a message that didn't fit in the channel
+    ///
+    /// Returns `true` if the subscription just became a slow consumer.
+    fn record_dropped_message(&mut self) -> bool {
+        self.dropped_messages.fetch_add(1, Ordering::AcqRel);
+        !mem::replace(&mut self.is_slow_consumer, true)
+    }
 }
 
 #[derive(Debug)]
@@ -95,6 +153,10 @@ pub(crate) enum HandlerCommand {
     Publish {
         message: MessageBase,
     },
+    PublishBackpressured {
+        message: MessageBase,
+        enqueued: oneshot::Sender<()>,
+    },
     RequestMultiplexed {
         subject: Subject,
         reply_subject: Subject,
@@ -105,17 +167,28 @@ pub(crate) enum HandlerCommand {
     UnsubscribeMultiplexed {
         reply_subject: Subject,
     },
+    CancelMultiplexed,
     Subscribe {
         id: SubscriptionId,
         subject: Subject,
         queue_group: Option<QueueGroup>,
         messages: mpsc::Sender<Result<ServerMessage, ServerError>>,
+        dropped_messages: Arc<AtomicU64>,
+        resume: Option<Box<ConsumerResume>>,
     },
     Unsubscribe {
         id: SubscriptionId,
         max_messages: Option<NonZeroU64>,
     },
+    Flush(oneshot::Sender<()>),
+    Rtt(oneshot::Sender<Duration>),
+    Stats(oneshot::Sender<ConnectionStats>),
+    Drain(oneshot::Sender<()>),
     Close(oneshot::Sender<()>),
+    #[cfg(feature = "dangerous-raw-ops")]
+    Raw {
+        op: ClientOp,
+    },
 }
 
 #[derive(Debug)]
@@ -124,30 +197,102 @@ pub(crate) enum InFlightCommand {
     Subscribe { id: SubscriptionId },
 }
 
+/// The commands awaiting a `+OK` or `-ERR` from the server
+///
+/// Nothing is tracked when verbose mode is off, since the server
+/// doesn't acknowledge successful commands.
+#[derive(Debug)]
+struct InFlightCommands {
+    commands: VecDeque<InFlightCommand>,
+    verbose: bool,
+}
+
+impl InFlightCommands {
+    fn new(verbose: bool) -> Self {
+        Self {
+            commands: VecDeque::new(),
+            verbose,
+        }
+    }
+
+    fn push_back(&mut self, command: InFlightCommand) {
+        if self.verbose {
+            self.commands.push_back(command);
+        }
+    }
+
+    fn pop_front(&mut self) -> Option<InFlightCommand> {
+        self.commands.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.commands.len()
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum HandlerOutput {
-    ServerError,
+    ServerError(ServerError),
     UnexpectedState,
     Disconnected,
+    LameDuck,
     Closed,
 }
 
 impl Handler {
+    #[expect(clippy::too_many_lines)]
     pub(crate) async fn connect(
-        addr: &ServerAddr,
         builder: &ClientBuilder,
-        recycle: RecycledHandler,
+        mut recycle: RecycledHandler,
     ) -> Result<Self, (ConnectError, RecycledHandler)> {
+        let addr = recycle.servers.next_server().clone();
+
         let mut flags = ConnectFlags::default();
         flags.echo = matches!(builder.echo, Echo::Allow);
+        flags.verbose = builder.verbose;
+        flags.pedantic = builder.pedantic;
+        flags.tcp = builder.tcp_options.clone();
+        flags.max_head_len = builder.max_head_len;
+        flags.retain_raw_headers = builder.retain_raw_headers;
+        flags.write_flatten_threshold = builder.write_flatten_threshold;
+        flags.timeout = builder.connect_timeout;
+        #[cfg(feature = "websocket-deflate")]
+        {
+            flags.websocket_deflate = builder.websocket_deflate;
+        }
         #[cfg(feature = "non-standard-zstd")]
         {
             flags.zstd = builder.non_standard_zstd;
         }
 
-        let (mut conn, info) = match easy_connect(addr, builder.auth_method.as_ref(), flags).await {
+        let auth = builder.auth_method.as_ref();
+        let connect = async {
+            match &builder.tls_config {
+                Some(tls_config) => {
+                    connect_with_tls_config(&addr, auth, flags, Arc::clone(tls_config)).await
+                }
+                None => easy_connect(&addr, auth, flags).await,
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let connect = tracing::Instrument::instrument(
+            connect,
+            tracing::info_span!("connect", server = %addr.redacted_display()),
+        );
+        let (mut conn, info) = match connect.await {
             Ok(items) => items,
-            Err(err) => return Err((err, recycle)),
+            Err(err) => {
+                trace_event!(warn, "failed to connect", server = %addr.redacted_display(), error = ?err);
+                return Err((err, recycle));
+            }
+        };
+        recycle.servers.add_discovered(&addr, &info);
+        let info = match recycle.info.take() {
+            Some(shared_info) => {
+                shared_info.store(Arc::from(info));
+                shared_info
+            }
+            None => Arc::new(ArcSwap::new(Arc::from(info))),
         };
 
         #[cfg(feature = "non-standard-zstd")]
@@ -156,16 +301,27 @@ impl Handler {
         } else {
             false
         };
+        let is_tls = match &conn {
+            Connection::Streaming(streaming) => streaming.socket().get_ref().is_tls(),
+            #[cfg(feature = "websocket")]
+            Connection::Websocket(websocket) => websocket.socket().is_tls(),
+            #[cfg(not(feature = "websocket"))]
+            Connection::Websocket(_) => unreachable!(),
+        };
         recycle.quick_info.store(|quick_info| QuickInfo {
             is_connected: true,
             #[cfg(feature = "non-standard-zstd")]
             is_zstd_compressed,
             is_lameduck: false,
+            is_tls,
             ..quick_info
         });
+        let _ = recycle.events.send(ClientEvent::Connected);
+        trace_event!(info, "connected", server = %addr.redacted_display(), tls = %is_tls);
 
-        let mut in_flight_commands = VecDeque::new();
-        for (&id, subscription) in &recycle.subscriptions {
+        let mut in_flight_commands = InFlightCommands::new(builder.verbose);
+        let mut resume_requests = Vec::new();
+        for (&id, subscription) in &mut recycle.subscriptions {
             in_flight_commands.push_back(InFlightCommand::Subscribe { id });
             conn.enqueue_write_op(&ClientOp::Subscribe {
                 id,
@@ -179,6 +335,11 @@ impl Handler {
                     max_messages: Some(remaining),
                 });
             }
+
+            if let Some(resume) = &mut subscription.resume {
+                // Messages stored while disconnected weren't delivered to the previous consumer
+                resume_requests.push((id, resume.resume()));
+            }
         }
 
         let delayed_flusher = if builder.flush_interval.is_zero() {
@@ -190,15 +351,25 @@ impl Handler {
             })
         };
 
-        Ok(Self {
+        let mut pending_commands = recycle.pending_commands;
+        let mut this = Self {
             conn,
-            info: Arc::new(ArcSwap::new(Arc::from(info))),
+            info,
             quick_info: recycle.quick_info,
+            events: recycle.events,
+            servers: recycle.servers,
+            offline_buffer: recycle.offline_buffer,
+            addr,
             delayed_flusher,
             flushing: false,
+            written_bytes: 0,
+            flushes: 0,
             shutting_down: false,
+            reconnect_on_lameduck: builder.reconnect_on_lameduck,
+            reset_decoder: false,
             ping_interval: Box::pin(time::sleep(PING_INTERVAL)),
             pending_pings: 0,
+            pong_waiters: VecDeque::new(),
             commands: recycle.commands,
             recv_buf: Vec::with_capacity(RECV_BUF),
             in_flight_commands,
@@ -206,16 +377,57 @@ impl Handler {
             multiplexed_subscription_prefix: recycle.multiplexed_subscription_prefix,
             multiplexed_subscriptions: None,
             awaiting_close: recycle.awaiting_close,
-        })
+        };
+
+        let replay = this.offline_buffer.clone().map(|offline_buffer| {
+            let late_publishes = drain_publishes(&mut this.commands, &mut pending_commands);
+            offline_buffer.connected(late_publishes)
+        });
+        for (subscription_id, requests) in resume_requests {
+            this.resume_consumer(subscription_id, requests);
+        }
+        // Subscriptions made while disconnected go out before the publishes
+        // made while disconnected, which might be expecting them
+        for command in pending_commands {
+            this.handle_command(command);
+        }
+        for command in replay.into_iter().flatten() {
+            this.handle_command(command);
+        }
+        Ok(this)
+    }
+
+    /// Write out and flush every buffered command
+    ///
+    /// Used before leaving a server that is still reachable, like one in lame duck mode.
+    pub(crate) async fn write_pending(&mut self) {
+        if let Err(err) = self.conn.write_and_flush().await {
+            trace_event!(warn, "failed to write pending commands", error = ?err);
+        }
     }
 
     pub(crate) async fn recycle(mut self) -> RecycledHandler {
         self.quick_info.store_is_connected(false);
+        let mut pending_commands = Vec::new();
+        if let Some(offline_buffer) = &self.offline_buffer {
+            // Buffer the publishes that didn't make it to the connection,
+            // ahead of the ones made from now on
+            let publishes =
+                drain_publishes(&mut self.commands, &mut pending_commands).collect::<Vec<_>>();
+            offline_buffer.disconnected(publishes);
+        }
+        let _ = self.events.send(ClientEvent::Disconnected);
+        trace_event!(info, "disconnected", server = %self.addr.redacted_display());
         let _ = self.conn.shutdown().await;
 
         RecycledHandler {
             commands: self.commands,
             quick_info: self.quick_info,
+            events: self.events,
+            servers: self.servers,
+            offline_buffer: self.offline_buffer,
+            info: Some(self.info),
+            pending_commands,
             subscriptions: self.subscriptions,
             multiplexed_subscription_prefix: self.multiplexed_subscription_prefix,
             awaiting_close: self.awaiting_close,
@@ -230,6 +442,121 @@ impl Handler {
         &self.multiplexed_subscription_prefix
     }
 
+    /// Make a request, returning the receiver of its response
+    fn request(&mut self, subject: Subject, payload: Bytes) -> oneshot::Receiver<ServerMessage> {
+        let (reply, response) = oneshot::channel();
+        self.handle_command(HandlerCommand::RequestMultiplexed {
+            subject,
+            reply_subject: create_inbox_subject(&self.multiplexed_subscription_prefix),
+            headers: HeaderMap::new(),
+            payload,
+            reply,
+        });
+        response
+    }
+
+    /// Make a request whose response is going to be ignored
+    fn fire_request(&mut self, subject: Subject, payload: Bytes) {
+        drop(self.request(subject, payload));
+    }
+
+    /// Replace the consumer of the subscription `subscription_id` using the `requests`
+    /// built by [`ConsumerResume::resume`]
+    ///
+    /// A failure to create the new consumer is reported via [`ClientEvent::ConsumerResumeFailed`].
+    fn resume_consumer(
+        &mut self,
+        subscription_id: SubscriptionId,
+        requests: Result<[(Subject, Bytes); 2], JetstreamError2>,
+    ) {
+        let events = self.events.clone();
+        let report = move |error| {
+            trace_event!(warn, "could not resume consumer", subscription_id = %subscription_id);
+            let _ = events.send(ClientEvent::ConsumerResumeFailed {
+                subscription_id,
+                error: Arc::new(error),
+            });
+        };
+
+        match requests {
+            Ok([(delete_subject, delete_payload), (create_subject, create_payload)]) => {
+                self.fire_request(delete_subject, delete_payload);
+                let response = self.request(create_subject, create_payload);
+                tokio::spawn(async move {
+                    if let Some(Err(error)) = resume_response(response).await {
+                        report(error);
+                    }
+                });
+            }
+            Err(error) => report(error),
+        }
+    }
+
+    /// Forget the subscription `id`, deleting the consumer it was resuming
+    fn remove_subscription(&mut self, id: SubscriptionId) -> Option<Subscription> {
+        let mut subscription = self.subscriptions.remove(&id)?;
+        if let Some(resume) = subscription.resume.take() {
+            if let Ok((subject, payload)) = resume.delete_request() {
+                self.fire_request(subject, payload);
+            }
+        }
+        Some(subscription)
+    }
+
+    fn handle_message(&mut self, message: ServerMessage) {
+        let subscription_id = message.subscription_id;
+
+        if let Some(subscription) = self.subscriptions.get_mut(&subscription_id) {
+            if subscription
+                .resume
+                .as_mut()
+                .is_some_and(|resume| !resume.record(&message))
+            {
+                // Already delivered by the consumer used before reconnecting
+            } else {
+                match subscription.messages.try_send(Ok(message)) {
+                    Ok(()) => {
+                        subscription.is_slow_consumer = false;
+                    }
+                    Err(TrySendError::Full(_)) => {
+                        // The subscription isn't keeping up, the message is lost
+                        self.quick_info.increment_dropped_messages();
+                        if subscription.record_dropped_message() {
+                            trace_event!(
+                                warn,
+                                "slow consumer, dropping messages",
+                                subscription_id = %subscription_id,
+                            );
+                            let _ = self
+                                .events
+                                .send(ClientEvent::SlowConsumer { subscription_id });
+                        }
+                    }
+                    Err(TrySendError::Closed(_)) => {
+                        self.in_flight_commands
+                            .push_back(InFlightCommand::Unimportant);
+                        self.conn.enqueue_write_op(&ClientOp::Unsubscribe {
+                            id: subscription_id,
+                            max_messages: None,
+                        });
+                        return;
+                    }
+                }
+            }
+
+            if let Some(remaining) = &mut subscription.remaining {
+                match NonZeroU64::new(remaining.get() - 1) {
+                    Some(new_remaining) => *remaining = new_remaining,
+                    None => {
+                        self.remove_subscription(subscription_id);
+                    }
+                }
+            }
+        } else {
+            // 🤷
+        }
+    }
+
     fn handle_server_op(&mut self, server_op: ServerOp) -> ControlFlow<HandlerOutput, ()> {
         match server_op {
             ServerOp::Message { message }
@@ -245,44 +572,10 @@ impl Handler {
                     // 🤷
                 }
             }
-            ServerOp::Message { message } => {
-                let subscription_id = message.subscription_id;
-
-                if let Some(subscription) = self.subscriptions.get_mut(&subscription_id) {
-                    match subscription.messages.try_send(Ok(message)) {
-                        Ok(()) => {}
-                        #[expect(
-                            clippy::match_same_arms,
-                            reason = "the case still needs to be implemented"
-                        )]
-                        Err(TrySendError::Full(_)) => {
-                            // TODO
-                        }
-                        Err(TrySendError::Closed(_)) => {
-                            self.in_flight_commands
-                                .push_back(InFlightCommand::Unimportant);
-                            self.conn.enqueue_write_op(&ClientOp::Unsubscribe {
-                                id: subscription_id,
-                                max_messages: None,
-                            });
-                            return ControlFlow::Continue(());
-                        }
-                    }
-
-                    if let Some(remaining) = &mut subscription.remaining {
-                        match NonZeroU64::new(remaining.get() - 1) {
-                            Some(new_remaining) => *remaining = new_remaining,
-                            None => {
-                                self.subscriptions.remove(&subscription_id);
-                            }
-                        }
-                    }
-                } else {
-                    // 🤷
-                }
-            }
+            ServerOp::Message { message } => self.handle_message(message),
             ServerOp::Success => {
                 let Some(in_flight_command) = self.in_flight_commands.pop_front() else {
+                    trace_event!(error, "received an unexpected +OK");
                     return ControlFlow::Break(HandlerOutput::UnexpectedState);
                 };
 
@@ -293,8 +586,15 @@ impl Handler {
                 }
             }
             ServerOp::Error { error } if error.is_fatal() == Some(false) => {
+                trace_event!(warn, "server error", error = %error);
                 let Some(in_flight_command) = self.in_flight_commands.pop_front() else {
-                    return ControlFlow::Break(HandlerOutput::UnexpectedState);
+                    if self.in_flight_commands.verbose {
+                        trace_event!(error, "received an unexpected -ERR");
+                        return ControlFlow::Break(HandlerOutput::UnexpectedState);
+                    }
+
+                    // Without verbose mode the error can't be traced back to the command that caused it
+                    return ControlFlow::Continue(());
                 };
 
                 match in_flight_command {
@@ -302,7 +602,7 @@ impl Handler {
                         // Nothing to do
                     }
                     InFlightCommand::Subscribe { id } => {
-                        if let Some(mut subscription) = self.subscriptions.remove(&id) {
+                        if let Some(mut subscription) = self.remove_subscription(id) {
                             match subscription.messages.try_send(Err(error)) {
                                 Ok(()) | Err(TrySendError::Closed(_)) => {
                                     // Nothing to do
@@ -320,16 +620,51 @@ impl Handler {
                     }
                 }
             }
-            ServerOp::Error { error: _ } => return ControlFlow::Break(HandlerOutput::ServerError),
+            ServerOp::Error { error } => {
+                trace_event!(error, "fatal server error", error = %error);
+                return ControlFlow::Break(HandlerOutput::ServerError(error));
+            }
             ServerOp::Ping => {
                 self.conn.enqueue_write_op(&ClientOp::Pong);
             }
-            ServerOp::Pong => {
-                self.pending_pings = self.pending_pings.saturating_sub(1);
-            }
+            ServerOp::Pong => match self.pong_waiters.pop_front() {
+                Some(PongWaiter::KeepAlive) | None => {
+                    self.pending_pings = self.pending_pings.saturating_sub(1);
+                }
+                Some(PongWaiter::Flush(sender)) => {
+                    let _ = sender.send(());
+                }
+                Some(PongWaiter::Drain {
+                    sender,
+                    subscription_ids,
+                }) => {
+                    // Every message published before the server received our
+                    // `UNSUB`s has now been delivered. Subscriptions created
+                    // after the drain started are left untouched
+                    for id in subscription_ids {
+                        self.remove_subscription(id);
+                    }
+                    let _ = sender.send(());
+                }
+                Some(PongWaiter::Rtt { sender, sent_at }) => {
+                    let _ = sender.send(sent_at.elapsed());
+                }
+                #[cfg(feature = "dangerous-raw-ops")]
+                Some(PongWaiter::Raw) => {}
+            },
             ServerOp::Info { info } => {
+                let entered_lameduck = info.lame_duck_mode && !self.quick_info.get().is_lameduck;
+                if entered_lameduck {
+                    trace_event!(warn, "server entered lame duck mode", server = %self.addr.redacted_display());
+                    let _ = self.events.send(ClientEvent::LameDuck);
+                }
                 self.quick_info.store_is_lameduck(info.lame_duck_mode);
+                self.servers.add_discovered(&self.addr, &info);
                 self.info.store(Arc::from(info));
+
+                if entered_lameduck && self.reconnect_on_lameduck && self.servers.len() > 1 {
+                    return ControlFlow::Break(HandlerOutput::LameDuck);
+                }
             }
         }
 
@@ -348,8 +683,10 @@ impl Handler {
 
             self.conn.enqueue_write_op(&ClientOp::Ping);
             self.pending_pings += 1;
+            self.pong_waiters.push_back(PongWaiter::KeepAlive);
             Ok(())
         } else {
+            trace_event!(warn, "server stopped responding to keep-alive pings");
             Err(HandlerOutput::Disconnected)
         }
     }
@@ -378,7 +715,7 @@ impl Handler {
                 id: closed_subscription_id,
                 max_messages: None,
             });
-            self.subscriptions.remove(&closed_subscription_id);
+            self.remove_subscription(closed_subscription_id);
         }
     }
 
@@ -414,10 +751,23 @@ impl Future for Handler {
             match this.conn.poll_read_next(cx) {
                 Poll::Pending => break,
                 Poll::Ready(Ok(server_op)) => {
-                    this.handle_server_op(server_op);
+                    if let ControlFlow::Break(output) = this.handle_server_op(server_op) {
+                        return Poll::Ready(output);
+                    }
                     handled_server_op = true;
                 }
-                Poll::Ready(Err(_err)) => return Poll::Ready(HandlerOutput::Disconnected),
+                Poll::Ready(Err(err)) if !this.reset_decoder && is_decoder_error(&err) => {
+                    // Discard the bytes which couldn't be framed and give the decoder
+                    // a single chance to resynchronize on the next control line.
+                    // Failing again means the stream is beyond recovery.
+                    trace_event!(warn, "failed to decode from the connection, resetting the decoder", error = ?err);
+                    this.reset_decoder = true;
+                    this.conn.reset_decoder();
+                }
+                Poll::Ready(Err(err)) => {
+                    trace_event!(warn, "failed to read from the connection", error = ?err);
+                    return Poll::Ready(HandlerOutput::Disconnected);
+                }
             }
         }
         if handled_server_op {
@@ -431,8 +781,12 @@ impl Future for Handler {
                     if streaming.may_write() {
                         match streaming.poll_write_next(cx) {
                             Poll::Pending => true,
-                            Poll::Ready(Ok(_n)) => false,
-                            Poll::Ready(Err(_err)) => {
+                            Poll::Ready(Ok(n)) => {
+                                this.written_bytes += n as u64;
+                                false
+                            }
+                            Poll::Ready(Err(err)) => {
+                                trace_event!(warn, "failed to write to the connection", error = ?err);
                                 return Poll::Ready(HandlerOutput::Disconnected);
                             }
                         }
@@ -503,7 +857,6 @@ impl Future for Handler {
                 }
                 (_, false) => {
                     // At least the write waker must be registered
-                    continue;
                 }
             }
         }
@@ -523,8 +876,16 @@ impl Future for Handler {
             if can_flush {
                 match this.conn.poll_flush(cx) {
                     Poll::Pending => {}
-                    Poll::Ready(Ok(())) => this.flushing = false,
-                    Poll::Ready(Err(_err)) => return Poll::Ready(HandlerOutput::Disconnected),
+                    Poll::Ready(Ok(())) => {
+                        this.flushing = false;
+                        if matches!(this.conn, Connection::Streaming(_)) {
+                            this.flushes += 1;
+                        }
+                    }
+                    Poll::Ready(Err(err)) => {
+                        trace_event!(warn, "failed to flush the connection", error = ?err);
+                        return Poll::Ready(HandlerOutput::Disconnected);
+                    }
                 }
             }
         }
@@ -544,12 +905,6 @@ enum ReceiveOutcome {
 }
 
 impl Handler {
-    // TODO: refactor this, a view into Handler is needed in order to split `recv_buf` from the
-    // rest.
-    #[expect(
-        clippy::too_many_lines,
-        reason = "not good, but a non trivial refactor is needed"
-    )]
     fn receive_command(&mut self, cx: &mut Context<'_>) -> ReceiveOutcome {
         while self.conn.may_enqueue_more_ops() {
             debug_assert!(self.recv_buf.is_empty());
@@ -560,117 +915,11 @@ impl Handler {
             {
                 Poll::Pending => return ReceiveOutcome::NoMoreCommands,
                 Poll::Ready(1..) => {
-                    for cmd in self.recv_buf.drain(..) {
-                        match cmd {
-                            HandlerCommand::Publish { message } => {
-                                self.in_flight_commands
-                                    .push_back(InFlightCommand::Unimportant);
-                                self.conn.enqueue_write_op(&ClientOp::Publish { message });
-                            }
-                            HandlerCommand::RequestMultiplexed {
-                                subject,
-                                reply_subject,
-                                headers,
-                                payload,
-                                reply,
-                            } => {
-                                debug_assert!(reply_subject
-                                    .starts_with(&*self.multiplexed_subscription_prefix));
-
-                                let multiplexed_subscriptions =
-                                    if let Some(multiplexed_subscriptions) =
-                                        &mut self.multiplexed_subscriptions
-                                    {
-                                        multiplexed_subscriptions
-                                    } else {
-                                        init_multiplexed_subscriptions(
-                                            &mut self.in_flight_commands,
-                                            &mut self.conn,
-                                            &self.multiplexed_subscription_prefix,
-                                            &mut self.multiplexed_subscriptions,
-                                        )
-                                    };
-
-                                self.in_flight_commands
-                                    .push_back(InFlightCommand::Unimportant);
-                                multiplexed_subscriptions.insert(reply_subject.clone(), reply);
-
-                                let message = MessageBase {
-                                    subject,
-                                    reply_subject: Some(reply_subject),
-                                    headers,
-                                    payload,
-                                };
-                                self.conn.enqueue_write_op(&ClientOp::Publish { message });
-                            }
-                            HandlerCommand::UnsubscribeMultiplexed { reply_subject } => {
-                                debug_assert!(reply_subject
-                                    .starts_with(&*self.multiplexed_subscription_prefix));
-
-                                if let Some(multiplexed_subscriptions) =
-                                    &mut self.multiplexed_subscriptions
-                                {
-                                    let _ = multiplexed_subscriptions.remove(&reply_subject);
-                                }
-                            }
-                            HandlerCommand::Subscribe {
-                                id,
-                                subject,
-                                queue_group,
-                                messages,
-                            } => {
-                                self.subscriptions.insert(
-                                    id,
-                                    Subscription {
-                                        subject: subject.clone(),
-                                        queue_group: queue_group.clone(),
-                                        messages,
-                                        remaining: None,
-                                        failed_subscribe: false,
-                                    },
-                                );
-                                self.in_flight_commands
-                                    .push_back(InFlightCommand::Subscribe { id });
-                                self.conn.enqueue_write_op(&ClientOp::Subscribe {
-                                    id,
-                                    subject,
-                                    queue_group,
-                                });
-                            }
-                            HandlerCommand::Unsubscribe {
-                                id,
-                                max_messages: Some(max_messages),
-                            } => {
-                                if let Some(subscription) = self.subscriptions.get_mut(&id) {
-                                    subscription.remaining = Some(max_messages);
-                                    self.in_flight_commands
-                                        .push_back(InFlightCommand::Unimportant);
-                                    self.conn.enqueue_write_op(&ClientOp::Unsubscribe {
-                                        id,
-                                        max_messages: Some(max_messages),
-                                    });
-                                }
-                            }
-                            HandlerCommand::Unsubscribe {
-                                id,
-                                max_messages: None,
-                            } => {
-                                if self.subscriptions.remove(&id).is_some() {
-                                    self.in_flight_commands
-                                        .push_back(InFlightCommand::Unimportant);
-                                    self.conn.enqueue_write_op(&ClientOp::Unsubscribe {
-                                        id,
-                                        max_messages: None,
-                                    });
-                                }
-                            }
-                            HandlerCommand::Close(sender) => {
-                                self.shutting_down = true;
-                                self.awaiting_close.push(sender);
-                                self.commands.close();
-                            }
-                        }
+                    let mut recv_buf = mem::take(&mut self.recv_buf);
+                    for cmd in recv_buf.drain(..) {
+                        self.handle_command(cmd);
                     }
+                    self.recv_buf = recv_buf;
                 }
                 Poll::Ready(0) => self.shutting_down = true,
             }
@@ -678,27 +927,237 @@ impl Handler {
 
         ReceiveOutcome::NoMoreSpace
     }
+
+    #[expect(
+        clippy::too_many_lines,
+        reason = "every command is handled in a single place"
+    )]
+    fn handle_command(&mut self, cmd: HandlerCommand) {
+        match cmd {
+            HandlerCommand::Publish { message } => {
+                self.in_flight_commands
+                    .push_back(InFlightCommand::Unimportant);
+                self.conn.enqueue_write_op(&ClientOp::Publish { message });
+            }
+            HandlerCommand::PublishBackpressured { message, enqueued } => {
+                self.in_flight_commands
+                    .push_back(InFlightCommand::Unimportant);
+                self.conn.enqueue_write_op(&ClientOp::Publish { message });
+                let _ = enqueued.send(());
+            }
+            HandlerCommand::RequestMultiplexed {
+                subject,
+                reply_subject,
+                headers,
+                payload,
+                reply,
+            } => {
+                debug_assert!(reply_subject.starts_with(&*self.multiplexed_subscription_prefix));
+
+                let multiplexed_subscriptions =
+                    if let Some(multiplexed_subscriptions) = &mut self.multiplexed_subscriptions {
+                        multiplexed_subscriptions
+                    } else {
+                        init_multiplexed_subscriptions(
+                            &mut self.in_flight_commands,
+                            &mut self.conn,
+                            &self.multiplexed_subscription_prefix,
+                            &mut self.multiplexed_subscriptions,
+                        )
+                    };
+
+                self.in_flight_commands
+                    .push_back(InFlightCommand::Unimportant);
+                multiplexed_subscriptions.insert(reply_subject.clone(), reply);
+
+                let message = MessageBase {
+                    subject,
+                    reply_subject: Some(reply_subject),
+                    headers,
+                    payload,
+                };
+                self.conn.enqueue_write_op(&ClientOp::Publish { message });
+            }
+            HandlerCommand::UnsubscribeMultiplexed { reply_subject } => {
+                debug_assert!(reply_subject.starts_with(&*self.multiplexed_subscription_prefix));
+
+                if let Some(multiplexed_subscriptions) = &mut self.multiplexed_subscriptions {
+                    let _ = multiplexed_subscriptions.remove(&reply_subject);
+                }
+            }
+            HandlerCommand::CancelMultiplexed => {
+                // Dropping the senders resolves the pending requests
+                if let Some(multiplexed_subscriptions) = &mut self.multiplexed_subscriptions {
+                    multiplexed_subscriptions.clear();
+                }
+            }
+            HandlerCommand::Subscribe {
+                id,
+                subject,
+                queue_group,
+                messages,
+                dropped_messages,
+                resume,
+            } => {
+                self.subscriptions.insert(
+                    id,
+                    Subscription {
+                        subject: subject.clone(),
+                        queue_group: queue_group.clone(),
+                        messages,
+                        remaining: None,
+                        failed_subscribe: false,
+                        dropped_messages,
+                        is_slow_consumer: false,
+                        resume,
+                    },
+                );
+                self.in_flight_commands
+                    .push_back(InFlightCommand::Subscribe { id });
+                self.conn.enqueue_write_op(&ClientOp::Subscribe {
+                    id,
+                    subject,
+                    queue_group,
+                });
+            }
+            HandlerCommand::Unsubscribe {
+                id,
+                max_messages: Some(max_messages),
+            } => {
+                if let Some(subscription) = self.subscriptions.get_mut(&id) {
+                    subscription.remaining = Some(max_messages);
+                    self.in_flight_commands
+                        .push_back(InFlightCommand::Unimportant);
+                    self.conn.enqueue_write_op(&ClientOp::Unsubscribe {
+                        id,
+                        max_messages: Some(max_messages),
+                    });
+                }
+            }
+            HandlerCommand::Unsubscribe {
+                id,
+                max_messages: None,
+            } => {
+                if self.remove_subscription(id).is_some() {
+                    self.in_flight_commands
+                        .push_back(InFlightCommand::Unimportant);
+                    self.conn.enqueue_write_op(&ClientOp::Unsubscribe {
+                        id,
+                        max_messages: None,
+                    });
+                }
+            }
+            HandlerCommand::Flush(sender) => {
+                self.conn.enqueue_write_op(&ClientOp::Ping);
+                self.pong_waiters.push_back(PongWaiter::Flush(sender));
+            }
+            HandlerCommand::Rtt(sender) => {
+                self.conn.enqueue_write_op(&ClientOp::Ping);
+                self.pong_waiters.push_back(PongWaiter::Rtt {
+                    sender,
+                    sent_at: Instant::now(),
+                });
+            }
+            HandlerCommand::Stats(sender) => {
+                let _ = sender.send(ConnectionStats {
+                    buffered_write_bytes: self.conn.buffered_write_bytes(),
+                    buffered_read_bytes: self.conn.buffered_read_bytes(),
+                    pending_pings: self.pending_pings,
+                    in_flight_commands: self.in_flight_commands.len(),
+                    in_flight_requests: self
+                        .multiplexed_subscriptions
+                        .as_ref()
+                        .map_or(0, BTreeMap::len),
+                    written_bytes: self.written_bytes,
+                    flushes: self.flushes,
+                });
+            }
+            HandlerCommand::Drain(sender) => {
+                let subscription_ids = self.subscriptions.keys().copied().collect::<Vec<_>>();
+                for &id in &subscription_ids {
+                    self.in_flight_commands
+                        .push_back(InFlightCommand::Unimportant);
+                    self.conn.enqueue_write_op(&ClientOp::Unsubscribe {
+                        id,
+                        max_messages: None,
+                    });
+                }
+
+                self.conn.enqueue_write_op(&ClientOp::Ping);
+                self.pong_waiters.push_back(PongWaiter::Drain {
+                    sender,
+                    subscription_ids,
+                });
+            }
+            HandlerCommand::Close(sender) => {
+                self.shutting_down = true;
+                self.awaiting_close.push(sender);
+                self.commands.close();
+            }
+            #[cfg(feature = "dangerous-raw-ops")]
+            HandlerCommand::Raw { op } => {
+                match op {
+                    ClientOp::Connect { .. }
+                    | ClientOp::Publish { .. }
+                    | ClientOp::Subscribe { .. }
+                    | ClientOp::Unsubscribe { .. } => {
+                        self.in_flight_commands
+                            .push_back(InFlightCommand::Unimportant);
+                    }
+                    ClientOp::Ping => self.pong_waiters.push_back(PongWaiter::Raw),
+                    ClientOp::Pong => {}
+                }
+                self.conn.enqueue_write_op(&op);
+            }
+        }
+    }
 }
 
 impl RecycledHandler {
     pub(crate) fn new(
         commands: mpsc::Receiver<HandlerCommand>,
         quick_info: Arc<RawQuickInfo>,
+        events: broadcast::Sender<ClientEvent>,
+        servers: ServerPool,
+        offline_buffer: Option<Arc<OfflineBuffer>>,
         builder: &ClientBuilder,
     ) -> Self {
         Self {
             commands,
             quick_info,
+            events,
+            servers,
+            offline_buffer,
+            info: None,
+            pending_commands: Vec::new(),
             subscriptions: BTreeMap::new(),
             multiplexed_subscription_prefix: create_inbox_subject(&builder.inbox_prefix),
             awaiting_close: Vec::new(),
         }
     }
+
+    /// Drop the publishes waiting in the offline buffer, returning how many were dropped
+    ///
+    /// Used when giving up reconnecting, since nothing would ever replay them.
+    pub(crate) fn discard_offline_buffer(&self) -> usize {
+        self.offline_buffer
+            .as_ref()
+            .map_or(0, |offline_buffer| offline_buffer.clear())
+    }
+
+    /// The number of servers the client can try to connect to
+    pub(crate) fn servers_len(&self) -> usize {
+        self.servers.len()
+    }
+
+    pub(crate) fn known_servers(&self) -> &Arc<ArcSwap<Vec<ServerAddr>>> {
+        self.servers.known()
+    }
 }
 
 #[cold]
 fn init_multiplexed_subscriptions<'a>(
-    in_flight_commands: &mut VecDeque<InFlightCommand>,
+    in_flight_commands: &mut InFlightCommands,
     conn: &mut Connection<
         ConnectionCompression<ConnectionSecurity<TcpStream>>,
         ConnectionSecurity<TcpStream>,
@@ -719,3 +1178,28 @@ fn init_multiplexed_subscriptions<'a>(
 
     multiplexed_subscriptions.insert(BTreeMap::new())
 }
+
+/// Receive the commands already sent to the handler without waiting
+///
+/// Publishes are yielded, while every other command is pushed into `other`.
+fn drain_publishes<'a>(
+    commands: &'a mut mpsc::Receiver<HandlerCommand>,
+    other: &'a mut Vec<HandlerCommand>,
+) -> impl Iterator<Item = HandlerCommand> + 'a {
+    iter::from_fn(move || loop {
+        let command = commands.try_recv().ok()?;
+        if is_publish(&command) {
+            return Some(command);
+        }
+        other.push(command);
+    })
+}
+
+fn is_decoder_error(err: &ConnectionReadError) -> bool {
+    match err {
+        ConnectionReadError::Streaming(StreamingReadError::Decoder(_)) => true,
+        #[cfg(feature = "websocket")]
+        ConnectionReadError::Websocket(WebsocketReadError::Decoder(_)) => true,
+        _ => false,
+    }
+}
@@ -1,27 +1,60 @@
 pub use watermelon_proto as proto;
 
+/// Emit a `tracing` event, or evaluate nothing if the `tracing` feature is disabled
+///
+/// Fields are written as `name = %value` for [`Display`] and `name = ?value` for [`Debug`].
+///
+/// [`Display`]: std::fmt::Display
+/// [`Debug`]: std::fmt::Debug
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($level:ident, $message:literal $(, $key:ident = $sigil:tt $value:expr)* $(,)?) => {
+        ::tracing::$level!($($key = $sigil $value,)* $message)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($level:ident, $message:literal $(, $key:ident = $sigil:tt $value:expr)* $(,)?) => {{
+        $(let _ = &$value;)*
+    }};
+}
+
 mod atomic;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod client;
 mod handler;
+mod merged_subscription;
 mod multiplexed_subscription;
 mod subscription;
 #[cfg(test)]
 pub(crate) mod tests;
 
+pub use self::client::connect;
+// Exposed for the benchmarks
+#[doc(hidden)]
+pub use self::client::create_inbox_subject;
+
 pub mod core {
     //! NATS Core functionality implementation
 
-    pub use crate::client::{Client, ClientBuilder, Echo, QuickInfo};
+    pub use crate::client::{
+        Client, ClientBuilder, ClientEvent, ConnectionStats, Echo, OfflineBufferPolicy, QuickInfo,
+        Respondable, ServerStatus, ToServerAddrs,
+    };
+    pub use crate::merged_subscription::MergedSubscription;
     pub(crate) use crate::multiplexed_subscription::MultiplexedSubscription;
     pub use crate::subscription::Subscription;
-    pub use watermelon_mini::AuthenticationMethod;
+    pub use watermelon_mini::{AuthenticationMethod, TlsClientCert};
+    pub use watermelon_net::{IpPreference, Resolver, SocketOptions, SystemResolver};
 
     pub mod publish {
         //! Utilities for publishing messages
 
         pub use crate::client::{
-            ClientPublish, DoClientPublish, DoOwnedClientPublish, OwnedClientPublish, Publish,
-            PublishBuilder,
+            propagated_deadline, ClientPublish, DoClientPublish, DoOwnedClientPublish,
+            OwnedClientPublish, Publish, PublishBuilder,
         };
     }
 
@@ -30,14 +63,18 @@ pub mod core {
 
         pub use crate::client::{
             ClientRequest, DoClientRequest, DoOwnedClientRequest, OwnedClientRequest, Request,
-            RequestBuilder, ResponseFut,
+            RequestBuilder, RequestMany, RequestManyConfig, ResponseFut,
         };
     }
 
     pub mod error {
         //! NATS Core specific errors
 
-        pub use crate::client::{ClientClosedError, ResponseError, TryCommandError};
+        pub use crate::client::{
+            ClientClosedError, FlushError, JsonRequestError, PublishError, RespondError,
+            ResponseError, RetryRequestError, TryCommandError, WaitConnectedError,
+        };
+        pub use watermelon_mini::{ConnectError, TlsClientCertError};
     }
 }
 
@@ -47,15 +84,36 @@ pub mod jetstream {
     //! Relies on NATS Core to communicate with the NATS server
 
     pub use crate::client::{
-        AckPolicy, Compression, Consumer, ConsumerBatch, ConsumerConfig, ConsumerDurability,
+        AckPolicy, AckTiming, AutoAckConsumerStream, Compression, Consumer, ConsumerBatch,
+        ConsumerConfig, ConsumerConfigBuilder, ConsumerDurability, ConsumerPause,
         ConsumerSpecificConfig, ConsumerStorage, ConsumerStream, ConsumerStreamError, Consumers,
-        DeliverPolicy, DiscardPolicy, JetstreamClient, ReplayPolicy, RetentionPolicy, Storage,
-        Stream, StreamConfig, StreamState, Streams,
+        DeliverPolicy, DiscardPolicy, DoJetstreamPublish, JetstreamClient, JetstreamMessage,
+        JetstreamMessageMetadata, JetstreamPublish, OrderedConfig, OrderedConsumer, PubAck,
+        PurgeRequest, ReplayPolicy, RetentionPolicy, Rollup, Storage, StoredMessage, Stream,
+        StreamConfig, StreamNames, StreamState, Streams,
     };
 
+    pub mod kv {
+        //! NATS Jetstream Key-Value store
+
+        pub use crate::client::{KeyValue, KvEntry, KvOperation, KvWatch, KvWatchError};
+    }
+
+    #[cfg(feature = "object-store")]
+    pub mod object_store {
+        //! NATS Jetstream Object Store
+
+        pub use crate::client::{
+            ObjectInfo, ObjectList, ObjectPutError, ObjectReader, ObjectStore,
+        };
+    }
+
     pub mod error {
         //! NATS Jetstream specific errors
 
-        pub use crate::client::{JetstreamError, JetstreamError2, JetstreamErrorCode};
+        pub use crate::client::{
+            AckError, ConsumerConfigError, JetstreamError, JetstreamError2, JetstreamErrorCode,
+            OrderedConsumerError,
+        };
     }
 }
@@ -0,0 +1,169 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::{FusedStream, Stream};
+use watermelon_proto::{error::ServerError, ServerMessage, Subject};
+
+use crate::core::{error::ClientClosedError, Subscription};
+
+/// A fan-in of multiple NATS subscriptions
+///
+/// Yields the messages received by every underlying [`Subscription`],
+/// together with the filter subject of the subscription that received them.
+/// The subscriptions are polled in round-robin order, so that a busy
+/// subscription can't starve the others.
+///
+/// The stream terminates once every underlying subscription has terminated.
+///
+/// Obtained from [`Client::subscribe_many`].
+///
+/// [`Client::subscribe_many`]: crate::core::Client::subscribe_many
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct MergedSubscription {
+    subscriptions: Vec<(Subject, Subscription)>,
+    next: usize,
+}
+
+impl MergedSubscription {
+    pub(crate) fn new(subscriptions: Vec<(Subject, Subscription)>) -> Self {
+        Self {
+            subscriptions,
+            next: 0,
+        }
+    }
+
+    /// The filter subjects of the subscriptions that haven't terminated yet
+    pub fn filter_subjects(&self) -> impl Iterator<Item = &Subject> {
+        self.subscriptions.iter().map(|(subject, _)| subject)
+    }
+
+    /// Immediately close every underlying subscription
+    ///
+    /// See [`Subscription::close`].
+    ///
+    /// # Errors
+    ///
+    /// It returns an error if the client is closed.
+    pub async fn close(&mut self) -> Result<(), ClientClosedError> {
+        for (_, subscription) in &mut self.subscriptions {
+            subscription.close().await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Stream for MergedSubscription {
+    type Item = (Subject, Result<ServerMessage, ServerError>);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let mut polled = 0;
+        while polled < this.subscriptions.len() {
+            let i = this.next % this.subscriptions.len();
+            let (subject, subscription) = &mut this.subscriptions[i];
+            match Pin::new(subscription).poll_next(cx) {
+                Poll::Ready(Some(msg)) => {
+                    this.next = i + 1;
+                    return Poll::Ready(Some((subject.clone(), msg)));
+                }
+                Poll::Ready(None) => {
+                    this.subscriptions.remove(i);
+                    this.next = i;
+                }
+                Poll::Pending => {
+                    this.next = i + 1;
+                    polled += 1;
+                }
+            }
+        }
+
+        if this.subscriptions.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl FusedStream for MergedSubscription {
+    fn is_terminated(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures_util::{FutureExt as _, StreamExt as _};
+    use watermelon_proto::{headers::HeaderMap, MessageBase, ServerMessage, Subject};
+
+    use crate::{core::Client, handler::HandlerCommand};
+
+    #[tokio::test]
+    async fn round_robin() {
+        let (client, mut handler) = Client::test(4);
+
+        let mut merged = client
+            .subscribe_many(
+                vec![
+                    Subject::from_static("orders.>"),
+                    Subject::from_static("users.>"),
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut senders = Vec::new();
+        for expected in ["orders.>", "users.>"] {
+            let HandlerCommand::Subscribe {
+                id,
+                subject,
+                messages,
+                ..
+            } = handler.receiver.try_recv().unwrap()
+            else {
+                unreachable!()
+            };
+            assert_eq!(Subject::from_static(expected), subject);
+            senders.push((id, messages));
+        }
+
+        for (id, messages) in &senders {
+            for _ in 0..2 {
+                messages
+                    .try_send(Ok(ServerMessage {
+                        status_code: None,
+                        subscription_id: *id,
+                        base: MessageBase {
+                            subject: Subject::from_static("a.b"),
+                            reply_subject: None,
+                            headers: HeaderMap::new(),
+                            payload: Bytes::new(),
+                        },
+                    }))
+                    .unwrap();
+            }
+        }
+
+        let mut filters = Vec::new();
+        for _ in 0..4 {
+            let (filter, msg) = merged.next().await.unwrap();
+            msg.unwrap();
+            filters.push(filter);
+        }
+        assert_eq!(
+            ["orders.>", "users.>", "orders.>", "users.>"].map(Subject::from_static),
+            filters.as_slice()
+        );
+        assert!(merged.next().now_or_never().is_none());
+
+        drop(senders);
+        assert!(merged.next().await.is_none());
+    }
+}
@@ -48,9 +48,18 @@ impl Future for MultiplexedSubscription {
 
         match Pin::new(&mut subscription.receiver).poll(cx) {
             Poll::Pending => Poll::Pending,
-            Poll::Ready(result) => {
-                self.subscription = None;
-                Poll::Ready(result.map_err(|_| ClientClosedError))
+            Poll::Ready(Ok(message)) => {
+                let Inner {
+                    reply_subject,
+                    receiver: _,
+                    client,
+                } = self.subscription.take().unwrap();
+                client.recycle_reply_subject(reply_subject);
+                Poll::Ready(Ok(message))
+            }
+            Poll::Ready(Err(_)) => {
+                let Inner { client, .. } = self.subscription.take().unwrap();
+                Poll::Ready(Err(client.closed_error()))
             }
         }
     }
@@ -1,14 +1,18 @@
 use std::{
     num::NonZeroU64,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
 use futures_core::{FusedStream, Stream};
 use tokio::sync::mpsc;
-use watermelon_proto::{error::ServerError, ServerMessage, SubscriptionId};
+use watermelon_proto::{error::ServerError, ServerMessage, Subject, SubscriptionId};
 
-use crate::core::{error::ClientClosedError, Client};
+use crate::{
+    atomic::{AtomicU64, Ordering},
+    core::{error::ClientClosedError, Client},
+};
 
 const BATCH_RECEIVE_SIZE: usize = 16;
 
@@ -27,10 +31,15 @@ const BATCH_RECEIVE_SIZE: usize = 16;
 #[derive(Debug)]
 pub struct Subscription {
     pub(crate) id: SubscriptionId,
+    subject: Subject,
     client: Client,
     receiver: mpsc::Receiver<Result<ServerMessage, ServerError>>,
     receiver_queue: Vec<Result<ServerMessage, ServerError>>,
     status: SubscriptionStatus,
+    delivered: u64,
+    max_messages: Option<NonZeroU64>,
+    dropped_messages: Arc<AtomicU64>,
+    ignore_own_messages: bool,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -42,18 +51,81 @@ enum SubscriptionStatus {
 impl Subscription {
     pub(crate) fn new(
         id: SubscriptionId,
+        subject: Subject,
         client: Client,
         receiver: mpsc::Receiver<Result<ServerMessage, ServerError>>,
+        dropped_messages: Arc<AtomicU64>,
     ) -> Self {
         Self {
             id,
+            subject,
             client,
             receiver,
             receiver_queue: Vec::with_capacity(BATCH_RECEIVE_SIZE),
             status: SubscriptionStatus::Subscribed,
+            delivered: 0,
+            max_messages: None,
+            dropped_messages,
+            ignore_own_messages: false,
         }
     }
 
+    /// The number of messages that were dropped because this subscription wasn't polled fast enough
+    ///
+    /// A [`ClientEvent::SlowConsumer`] event is emitted when the subscription starts
+    /// dropping messages.
+    ///
+    /// [`ClientEvent::SlowConsumer`]: crate::core::ClientEvent::SlowConsumer
+    #[must_use]
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages.load(Ordering::Acquire)
+    }
+
+    /// The number of messages yielded by the [`Stream`] implementation so far
+    ///
+    /// Errors are not counted.
+    #[must_use]
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Returns `true` if the limit set via [`Subscription::close_after`] has been reached
+    ///
+    /// Only the messages that have been yielded by the [`Stream`] implementation,
+    /// as reported by [`Subscription::delivered`], are taken into account.
+    /// Always returns `false` if [`Subscription::close_after`] hasn't been called.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.max_messages
+            .is_some_and(|max_messages| self.delivered >= max_messages.get())
+    }
+
+    /// Drop messages published by this same client
+    ///
+    /// Only useful when the client was built with [`Echo::Allow`], in which
+    /// case the NATS Server echoes back messages published by this client
+    /// to all of its matching subscriptions. This allows keeping echo enabled
+    /// for some subscriptions while ignoring it for others.
+    ///
+    /// While at least one subscription ignores its own messages, the client
+    /// tags every outbound message matching the subject of such subscriptions
+    /// with a client-unique header, which is then used to recognize echoed
+    /// messages and is removed before messages are yielded. Messages published
+    /// before this method is called are not tagged and will therefore still be delivered.
+    ///
+    /// Default: `false`.
+    ///
+    /// [`Echo::Allow`]: crate::core::Echo::Allow
+    pub fn ignore_own_messages(&mut self, ignore_own_messages: bool) {
+        match (self.ignore_own_messages, ignore_own_messages) {
+            (false, true) => self.client.add_own_message_filter(&self.subject),
+            (true, false) => self.client.remove_own_message_filter(&self.subject),
+            (false, false) | (true, true) => {}
+        }
+
+        self.ignore_own_messages = ignore_own_messages;
+    }
+
     /// Immediately close the subscription
     ///
     /// The `Stream` implementation will continue to yield any remaining
@@ -110,6 +182,7 @@ impl Subscription {
             }
             (SubscriptionStatus::Subscribed, false) => {
                 self.client.unsubscribe(self.id, Some(max_messages)).await?;
+                self.max_messages = Some(max_messages);
             }
             (SubscriptionStatus::Unsubscribed, _) => {}
         }
@@ -124,25 +197,36 @@ impl Stream for Subscription {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
 
-        if let Some(msg) = this.receiver_queue.pop() {
-            return Poll::Ready(Some(msg));
-        }
-
-        match Pin::new(&mut this.receiver).poll_recv_many(
-            cx,
-            &mut this.receiver_queue,
-            BATCH_RECEIVE_SIZE,
-        ) {
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(n @ 1..) => {
-                debug_assert_eq!(n, this.receiver_queue.len());
-                this.receiver_queue.reverse();
-                Poll::Ready(this.receiver_queue.pop())
-            }
-            Poll::Ready(0) => {
-                this.status = SubscriptionStatus::Unsubscribed;
-                Poll::Ready(None)
+        loop {
+            let mut msg = if let Some(msg) = this.receiver_queue.pop() {
+                msg
+            } else {
+                match Pin::new(&mut this.receiver).poll_recv_many(
+                    cx,
+                    &mut this.receiver_queue,
+                    BATCH_RECEIVE_SIZE,
+                ) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(n @ 1..) => {
+                        debug_assert_eq!(n, this.receiver_queue.len());
+                        this.receiver_queue.reverse();
+                        this.receiver_queue.pop().unwrap()
+                    }
+                    Poll::Ready(0) => {
+                        this.status = SubscriptionStatus::Unsubscribed;
+                        return Poll::Ready(None);
+                    }
+                }
+            };
+
+            if let Ok(msg) = &mut msg {
+                if this.client.strip_origin(&mut msg.base.headers) && this.ignore_own_messages {
+                    continue;
+                }
+
+                this.delivered += 1;
             }
+            return Poll::Ready(Some(msg));
         }
     }
 
@@ -159,6 +243,8 @@ impl FusedStream for Subscription {
 
 impl Drop for Subscription {
     fn drop(&mut self) {
+        self.ignore_own_messages(false);
+
         if matches!(self.status, SubscriptionStatus::Unsubscribed) || self.receiver.is_closed() {
             return;
         }
@@ -171,6 +257,7 @@ impl Drop for Subscription {
 mod tests {
     use std::{
         future::Future,
+        num::{NonZeroU64, NonZeroUsize},
         pin::pin,
         task::{Context, Poll},
     };
@@ -180,10 +267,12 @@ mod tests {
     use futures_util::{task::noop_waker_ref, StreamExt};
     use tokio::sync::mpsc::error::TryRecvError;
     use watermelon_proto::{
-        headers::HeaderMap, MessageBase, ServerMessage, StatusCode, Subject, SubscriptionId,
+        error::ServerError,
+        headers::{HeaderMap, HeaderName, HeaderValue},
+        MessageBase, ServerMessage, StatusCode, Subject, SubscriptionId,
     };
 
-    use crate::{core::Client, handler::HandlerCommand};
+    use crate::{atomic::Ordering, core::Client, handler::HandlerCommand};
 
     #[tokio::test]
     async fn subscribe() {
@@ -200,6 +289,8 @@ mod tests {
             subject,
             queue_group,
             messages,
+            dropped_messages: _,
+            resume: None,
         } = subscribe_command
         else {
             unreachable!()
@@ -295,6 +386,187 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn delivered() {
+        let (client, mut handler) = Client::test(1);
+
+        let mut subscription = client
+            .subscribe(Subject::from_static("abcd.>"), None)
+            .await
+            .unwrap();
+        let HandlerCommand::Subscribe { messages, .. } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+
+        subscription
+            .close_after(NonZeroU64::new(2).unwrap())
+            .await
+            .unwrap();
+        assert_matches!(
+            handler.receiver.try_recv().unwrap(),
+            HandlerCommand::Unsubscribe {
+                max_messages: Some(_),
+                ..
+            }
+        );
+
+        let msg = ServerMessage {
+            status_code: Some(StatusCode::OK),
+            subscription_id: SubscriptionId::from(1),
+            base: MessageBase {
+                subject: Subject::from_static("abcd.1"),
+                reply_subject: None,
+                headers: HeaderMap::new(),
+                payload: Bytes::from_static(b"test"),
+            },
+        };
+        messages.try_send(Ok(msg.clone())).unwrap();
+        messages
+            .try_send(Err(ServerError::SlowConsumer {
+                raw_message: "Slow Consumer".into(),
+            }))
+            .unwrap();
+        messages.try_send(Ok(msg)).unwrap();
+
+        assert_eq!(0, subscription.delivered());
+        assert!(subscription.next().await.unwrap().is_ok());
+        assert_eq!(1, subscription.delivered());
+        assert!(!subscription.is_complete());
+        assert!(subscription.next().await.unwrap().is_err());
+        assert_eq!(1, subscription.delivered());
+        assert!(subscription.next().await.unwrap().is_ok());
+        assert_eq!(2, subscription.delivered());
+        assert!(subscription.is_complete());
+    }
+
+    #[tokio::test]
+    async fn subscribe_with_capacity() {
+        let (client, mut handler) = Client::test(2);
+
+        let _subscription = client
+            .subscribe(Subject::from_static("abcd.>"), None)
+            .await
+            .unwrap();
+        let HandlerCommand::Subscribe { messages, .. } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(256, messages.max_capacity());
+
+        let subscription = client
+            .subscribe_with_capacity(
+                Subject::from_static("abcd.>"),
+                None,
+                NonZeroUsize::new(4).unwrap(),
+            )
+            .await
+            .unwrap();
+        let HandlerCommand::Subscribe {
+            messages,
+            dropped_messages,
+            ..
+        } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(4, messages.max_capacity());
+
+        // The handler reports dropped messages through the shared counter
+        assert_eq!(0, subscription.dropped_messages());
+        dropped_messages.fetch_add(3, Ordering::AcqRel);
+        assert_eq!(3, subscription.dropped_messages());
+    }
+
+    #[tokio::test]
+    async fn ignore_own_messages() {
+        let (client, mut handler) = Client::test(4);
+
+        let mut subscription = client
+            .subscribe(Subject::from_static("abcd.>"), None)
+            .await
+            .unwrap();
+        let HandlerCommand::Subscribe { messages, .. } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+
+        // Publishes aren't tagged until a subscription asks for it
+        client
+            .publish(Subject::from_static("abcd.1"))
+            .payload(Bytes::from_static(b"untagged"))
+            .await
+            .unwrap();
+        let HandlerCommand::Publish { message: untagged } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        assert!(untagged.headers.is_empty());
+
+        subscription.ignore_own_messages(true);
+        client
+            .publish(Subject::from_static("abcd.2"))
+            .payload(Bytes::from_static(b"tagged"))
+            .await
+            .unwrap();
+        let HandlerCommand::Publish { message: tagged } = handler.receiver.try_recv().unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(1, tagged.headers.len());
+
+        // Subjects not matching the subscription aren't tagged
+        client
+            .publish(Subject::from_static("efgh.1"))
+            .payload(Bytes::new())
+            .await
+            .unwrap();
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert!(message.headers.is_empty());
+
+        // Tagged by another client
+        let mut foreign = tagged.clone();
+        foreign.payload = Bytes::from_static(b"foreign");
+        foreign.headers.insert(
+            HeaderName::from_static("Watermelon-Origin"),
+            HeaderValue::from_static("other"),
+        );
+
+        for base in [tagged, untagged, foreign] {
+            messages
+                .try_send(Ok(ServerMessage {
+                    status_code: None,
+                    subscription_id: SubscriptionId::from(1),
+                    base,
+                }))
+                .unwrap();
+        }
+
+        let msg = subscription.next().await.unwrap().unwrap();
+        assert_eq!(Bytes::from_static(b"untagged"), msg.base.payload);
+        assert_eq!(1, subscription.delivered());
+
+        // The tag is removed before messages are yielded
+        let msg = subscription.next().await.unwrap().unwrap();
+        assert_eq!(Bytes::from_static(b"foreign"), msg.base.payload);
+        assert!(msg.base.headers.is_empty());
+
+        // Dropping the subscription stops the tagging
+        drop(subscription);
+        let _ = handler.receiver.try_recv().unwrap();
+        client
+            .publish(Subject::from_static("abcd.3"))
+            .payload(Bytes::new())
+            .await
+            .unwrap();
+        let HandlerCommand::Publish { message } = handler.receiver.try_recv().unwrap() else {
+            unreachable!()
+        };
+        assert!(message.headers.is_empty());
+    }
+
     #[tokio::test]
     async fn drop_unsubscribe() {
         let (client, mut handler) = Client::test(1);
@@ -310,6 +582,8 @@ mod tests {
             subject,
             queue_group,
             messages: _,
+            dropped_messages: _,
+            resume: None,
         } = subscribe_command
         else {
             unreachable!()